@@ -0,0 +1,135 @@
+use crate::summary::{percentile, MetricsSummary, RequestSummary};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// Latency budget of a named request (or custom metric), unset percentiles are not checked
+#[derive(Deserialize, Default, Clone)]
+pub struct Budget {
+    pub max_p50_ms: Option<usize>,
+    pub max_p95_ms: Option<usize>,
+    pub max_p99_ms: Option<usize>,
+}
+
+// Result of a budget check of a named request
+pub enum BudgetResult {
+    Pass,
+    // exceeded budgets, e.g. "p95 612ms > 500ms"
+    Fail(Vec<String>),
+    // request was not executed in the run
+    NoData,
+}
+
+// Per request latency budgets, loaded from JSON file configured by LATENCY_BUDGETS_FILE env variable:
+// { "post-create": { "max_p95_ms": 300 }, "user-search": { "max_p50_ms": 100, "max_p95_ms": 400 } }
+pub struct Budgets {
+    pub budgets: BTreeMap<String, Budget>,
+}
+
+impl Budgets {
+    // None if LATENCY_BUDGETS_FILE is not set
+    pub fn from_env() -> Result<Option<Self>, String> {
+        match std::env::var("LATENCY_BUDGETS_FILE") {
+            Ok(path) if !path.trim().is_empty() => Self::read(path.trim()).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn read(path: &str) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        let budgets = serde_json::from_str(&data).map_err(|e| format!("{path}: {e}"))?;
+        Ok(Budgets { budgets })
+    }
+
+    // Budget results by request name, requests are looked up in requests and then in custom metrics
+    pub fn check(&self, metrics: &MetricsSummary) -> Vec<(String, BudgetResult)> {
+        self.budgets
+            .iter()
+            .map(|(name, budget)| {
+                let request = metrics.requests.get(name).or(metrics.custom.get(name));
+                (name.clone(), check_budget(budget, request))
+            })
+            .collect()
+    }
+
+    // Prints pass/fail table, returns failures (as SLA violations)
+    pub fn print(&self, metrics: &MetricsSummary) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        println!("Latency budgets:");
+        println!(
+            "{:<40} {:>14} {:>14} {:>14} {:>8}",
+            "Name", "p50 (ms)", "p95 (ms)", "p99 (ms)", "Result"
+        );
+        for (name, result) in self.check(metrics) {
+            let budget = &self.budgets[&name];
+            let times = metrics
+                .requests
+                .get(&name)
+                .or(metrics.custom.get(&name))
+                .map(|r| &r.times);
+            let result = match result {
+                BudgetResult::Pass => "PASS",
+                BudgetResult::NoData => "NO DATA",
+                BudgetResult::Fail(failures) => {
+                    for failure in failures {
+                        violations.push(format!("{name}: {failure} (latency budget)"));
+                    }
+                    "FAIL"
+                }
+            };
+            println!(
+                "{:<40} {:>14} {:>14} {:>14} {:>8}",
+                name,
+                format_cell(times, 0.5, budget.max_p50_ms),
+                format_cell(times, 0.95, budget.max_p95_ms),
+                format_cell(times, 0.99, budget.max_p99_ms),
+                result
+            );
+        }
+
+        violations
+    }
+}
+
+fn check_budget(budget: &Budget, request: Option<&RequestSummary>) -> BudgetResult {
+    let Some(request) = request.filter(|r| !r.times.is_empty()) else {
+        return BudgetResult::NoData;
+    };
+
+    let failures: Vec<String> = [
+        ("p50", 0.5, budget.max_p50_ms),
+        ("p95", 0.95, budget.max_p95_ms),
+        ("p99", 0.99, budget.max_p99_ms),
+    ]
+    .into_iter()
+    .filter_map(|(label, p, max)| {
+        let max = max?;
+        let value = percentile(&request.times, p)?;
+        (value > max).then(|| format!("{label} {value}ms > {max}ms"))
+    })
+    .collect();
+
+    if failures.is_empty() {
+        BudgetResult::Pass
+    } else {
+        BudgetResult::Fail(failures)
+    }
+}
+
+// "actual / budget", "-" for unset budget
+fn format_cell(
+    times: Option<&BTreeMap<usize, usize>>,
+    percentile_value: f64,
+    max: Option<usize>,
+) -> String {
+    match max {
+        Some(max) => {
+            let actual = times
+                .and_then(|t| percentile(t, percentile_value))
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            format!("{actual} / {max}")
+        }
+        None => "-".to_string(),
+    }
+}
@@ -0,0 +1,24 @@
+use crate::summary::RequestSummary;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Metrics measured by scenarios across multiple requests (e.g. delivery latency),
+// Goose 0.17 collects only response times of single requests
+static CUSTOM_METRICS: Mutex<BTreeMap<String, RequestSummary>> = Mutex::new(BTreeMap::new());
+
+pub fn record(name: &str, time: Duration) {
+    let mut metrics = CUSTOM_METRICS.lock().unwrap();
+    let metric = metrics.entry(name.to_string()).or_default();
+    *metric.times.entry(time.as_millis() as usize).or_insert(0) += 1;
+    metric.success_count += 1;
+}
+
+pub fn record_failure(name: &str) {
+    let mut metrics = CUSTOM_METRICS.lock().unwrap();
+    metrics.entry(name.to_string()).or_default().fail_count += 1;
+}
+
+pub fn snapshot() -> BTreeMap<String, RequestSummary> {
+    CUSTOM_METRICS.lock().unwrap().clone()
+}
@@ -71,6 +71,8 @@ pub fn rand_like_type() -> crate::domain::common::LikeType {
         crate::domain::common::LikeType::Love,
         crate::domain::common::LikeType::Insightful,
         crate::domain::common::LikeType::Dislike,
+        crate::domain::common::LikeType::Celebrate,
+        crate::domain::common::LikeType::Funny,
     ];
     types.choose(&mut rand::thread_rng()).unwrap().to_owned()
 }
@@ -0,0 +1,65 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Mode of benchmark run, configured by BENCHMARK_MODE env variable
+pub enum Mode {
+    // load is generated, metrics are checked by this process
+    Standalone,
+    // load is generated (after START_AT), metrics are written to METRICS_FILE for the manager
+    Worker {
+        worker_id: String,
+        metrics_file: String,
+    },
+    // metrics of workers (METRICS_FILES) are merged and checked, no load is generated
+    Manager {
+        metrics_files: Vec<String>,
+    },
+}
+
+impl Mode {
+    pub fn from_env() -> Result<Self, String> {
+        let mode = std::env::var("BENCHMARK_MODE").unwrap_or_default();
+        match mode.to_lowercase().as_str() {
+            "" | "standalone" => Ok(Mode::Standalone),
+            "worker" => {
+                let worker_id = std::env::var("WORKER_ID")
+                    .map_err(|_| "WORKER_ID is required in worker mode".to_string())?;
+                let metrics_file = std::env::var("METRICS_FILE")
+                    .unwrap_or_else(|_| format!("metrics-{worker_id}.json"));
+                Ok(Mode::Worker {
+                    worker_id,
+                    metrics_file,
+                })
+            }
+            "manager" => {
+                let metrics_files: Vec<String> = std::env::var("METRICS_FILES")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|f| f.trim().to_string())
+                    .filter(|f| !f.is_empty())
+                    .collect();
+                if metrics_files.is_empty() {
+                    Err("METRICS_FILES is required in manager mode".to_string())
+                } else {
+                    Ok(Mode::Manager { metrics_files })
+                }
+            }
+            _ => Err(format!("Unknown BENCHMARK_MODE: {mode}")),
+        }
+    }
+}
+
+// Wait until coordinated start time START_AT (unix timestamp in seconds), if set
+pub async fn wait_for_start() -> Result<(), String> {
+    if let Ok(start_at) = std::env::var("START_AT") {
+        let start_at: u64 = start_at
+            .parse()
+            .map_err(|_| format!("Invalid START_AT: {start_at}"))?;
+        let start_at = UNIX_EPOCH + Duration::from_secs(start_at);
+
+        if let Ok(wait) = start_at.duration_since(SystemTime::now()) {
+            println!("Waiting {}s for coordinated start", wait.as_secs());
+            tokio::time::sleep(wait).await;
+        }
+    }
+    Ok(())
+}
@@ -15,6 +15,8 @@ pub mod common {
         Love,
         Insightful,
         Dislike,
+        Celebrate,
+        Funny,
     }
 
     #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -33,6 +35,9 @@ pub mod common {
     #[serde(rename_all = "kebab-case")]
     pub struct ErrDetail {
         pub message: String,
+        // error kind (e.g. not-found), only in errors of user, post and chat agents
+        #[serde(default)]
+        pub code: Option<String>,
     }
 
     #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -45,6 +50,15 @@ pub mod common {
     #[serde(rename_all = "kebab-case")]
     pub struct CreatePost {
         pub content: String,
+        pub location: Option<Location>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct Location {
+        pub name: String,
+        pub lat: f64,
+        pub lon: f64,
     }
 
     #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -79,6 +93,13 @@ pub mod common {
     pub struct AddMessage {
         pub user_id: String,
         pub content: String,
+        pub kind: Option<MessageKind>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum MessageKind {
+        Text,
     }
 }
 
@@ -108,6 +129,49 @@ pub mod social_net {
         pub updated_at: String,
     }
 
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct PostSummary {
+        pub post_id: String,
+        pub content: String,
+        pub created_by: String,
+        pub like_count: u32,
+        pub comment_count: u32,
+        pub created_at: String,
+        pub updated_at: String,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct PostSummariesView {
+        pub posts: Vec<PostSummary>,
+        pub next_cursor: Option<String>,
+        pub degraded: bool,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct MessagePreview {
+        pub message_id: String,
+        pub created_by: String,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct ChatRef {
+        pub chat_id: String,
+        pub last_message: Option<MessagePreview>,
+        pub message_count: u32,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct UserChatsUpdates {
+        pub user_id: String,
+        pub chats: Vec<ChatRef>,
+        pub cursor: String,
+    }
+
     #[derive(Clone, Debug, Serialize, Deserialize)]
     #[serde(rename_all = "kebab-case")]
     pub struct Chat {
@@ -112,7 +112,7 @@ impl GooseRequestExt for GooseUser {
     }
 }
 
-fn get_headers() -> HeaderMap {
+pub(crate) fn get_headers() -> HeaderMap {
     let mut headers = HeaderMap::new();
     headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
     headers.insert(ACCEPT, "application/json".parse().unwrap());
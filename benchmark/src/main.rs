@@ -1,11 +1,37 @@
+mod budgets;
+mod custom_metrics;
 mod data;
+mod distributed;
 mod domain;
 mod goose_ext;
+mod sla;
+mod summary;
+mod warm_up;
 
 use crate::goose_ext::GooseRequestExt;
 use goose::prelude::*;
 use std::time::Duration;
 
+// page size and max number of pages read in paginated read scenarios
+const POSTS_PAGE_SIZE: u32 = 10;
+const POSTS_PAGES_MAX_COUNT: usize = 3;
+
+// number of nested replies added in one iteration of deep comment threads scenario
+const COMMENT_THREAD_DEPTH: usize = 20;
+// max number of comments of post (COMMENTS_MAX_COUNT of post agent),
+// new post is created when next thread would exceed it
+const POST_COMMENTS_MAX_COUNT: usize = 2000;
+const COMMENTS_PAGE_SIZE: u32 = 20;
+
+// number of participants (including creator) of large group chats, at most max participants of chat
+const GROUP_CHAT_MIN_PARTICIPANTS: usize = 30;
+const GROUP_CHAT_MAX_PARTICIPANTS: usize = 60;
+// number of messages added in a burst to large group chat
+const GROUP_CHAT_MESSAGE_COUNT: usize = 20;
+// max wait for delivery of the last message to all participants
+const GROUP_CHAT_DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+const GROUP_CHAT_DELIVERY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
 #[tokio::main]
 async fn main() -> Result<(), GooseError> {
     let custom_host = match std::env::var("HOST") {
@@ -13,7 +39,49 @@ async fn main() -> Result<(), GooseError> {
         Err(_) => "".to_string(),
     };
 
-    GooseAttack::initialize()?
+    let sla = sla::Sla::from_env();
+
+    let budgets = match budgets::Budgets::from_env() {
+        Ok(budgets) => budgets,
+        Err(e) => {
+            eprintln!("Reading latency budgets failed: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let mode = distributed::Mode::from_env().map_err(|e| GooseError::InvalidOption {
+        option: "BENCHMARK_MODE".to_string(),
+        value: std::env::var("BENCHMARK_MODE").unwrap_or_default(),
+        detail: e,
+    })?;
+
+    if let distributed::Mode::Manager { metrics_files } = &mode {
+        let mut metrics = summary::MetricsSummary::default();
+        for file in metrics_files {
+            match summary::MetricsSummary::read(file) {
+                Ok(worker_metrics) => metrics.merge(worker_metrics),
+                Err(e) => {
+                    eprintln!("Reading worker metrics failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        metrics.print();
+        check_sla(&sla, budgets.as_ref(), &metrics);
+        return Ok(());
+    }
+
+    // warm-up is not measured, it runs before coordinated start of load generation
+    if warm_up::is_enabled() {
+        warm_up::run(custom_host.as_str()).await;
+    }
+
+    if let Err(e) = distributed::wait_for_start().await {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+
+    let metrics = GooseAttack::initialize()?
         .register_scenario(
             scenario!("Get User Data")
                 .set_wait_time(Duration::from_secs(1), Duration::from_secs(5))?
@@ -44,18 +112,70 @@ async fn main() -> Result<(), GooseError> {
                 .set_wait_time(Duration::from_secs(5), Duration::from_secs(15))?
                 .register_transaction(transaction!(create_post_comments_and_likes)),
         )
+        .register_scenario(
+            scenario!("Create Post and Read Posts Pages")
+                .set_wait_time(Duration::from_secs(5), Duration::from_secs(15))?
+                .register_transaction(transaction!(create_post_and_read_posts_pages)),
+        )
+        .register_scenario(
+            scenario!("Deep Comment Threads")
+                .set_wait_time(Duration::from_secs(5), Duration::from_secs(15))?
+                .register_transaction(transaction!(add_deep_comment_thread)),
+        )
         .register_scenario(
             scenario!("Create Chat, Messages and Likes")
                 .set_wait_time(Duration::from_secs(5), Duration::from_secs(15))?
                 .register_transaction(transaction!(create_chat_messages_and_likes)),
         )
+        .register_scenario(
+            scenario!("Large Group Chats")
+                .set_wait_time(Duration::from_secs(10), Duration::from_secs(30))?
+                .register_transaction(transaction!(create_group_chat_and_message_burst)),
+        )
         .set_default(GooseDefault::Host, custom_host.as_str())?
         .execute()
         .await?;
 
+    match mode {
+        distributed::Mode::Worker {
+            worker_id,
+            metrics_file,
+        } => {
+            // SLA is checked by the manager, on merged metrics of all workers
+            let metrics = summary::MetricsSummary::from_metrics(&worker_id, &metrics);
+            if let Err(e) = metrics.write(&metrics_file) {
+                eprintln!("Writing worker metrics failed: {e}");
+                std::process::exit(1);
+            }
+        }
+        _ => {
+            let metrics = summary::MetricsSummary::from_metrics("local", &metrics);
+            metrics.print_custom();
+            check_sla(&sla, budgets.as_ref(), &metrics);
+        }
+    }
+
     Ok(())
 }
 
+fn check_sla(
+    sla: &sla::Sla,
+    budgets: Option<&budgets::Budgets>,
+    metrics: &summary::MetricsSummary,
+) {
+    let mut violations = sla.check(metrics);
+    if let Some(budgets) = budgets {
+        violations.extend(budgets.print(metrics));
+    }
+    if !violations.is_empty() {
+        eprintln!("SLA violated:");
+        for violation in violations {
+            eprintln!("  {violation}");
+        }
+        std::process::exit(1);
+    }
+}
+
 async fn get_user_data(user: &mut GooseUser) -> TransactionResult {
     let user_id = data::rand_user_id();
 
@@ -130,6 +250,7 @@ async fn create_post_comments_and_likes(user: &mut GooseUser) -> TransactionResu
     // 1. Create Post
     let create_post = domain::common::CreatePost {
         content: data::rand_post_content(),
+        location: None,
     };
     let response = user
         .post_request(
@@ -205,6 +326,136 @@ async fn create_post_comments_and_likes(user: &mut GooseUser) -> TransactionResu
     Ok(())
 }
 
+async fn create_post_and_read_posts_pages(user: &mut GooseUser) -> TransactionResult {
+    use crate::goose_ext::GooseResponseExt;
+
+    let user_id = data::rand_user_id();
+
+    // 1. Create Post
+    let create_post = domain::common::CreatePost {
+        content: data::rand_post_content(),
+        location: None,
+    };
+    let _response = user
+        .post_request(
+            "post-create",
+            format!("/v1/social-net/users/{user_id}/posts").as_str(),
+            &create_post,
+        )
+        .await?;
+
+    // 2. Read post summaries, page by page
+    let mut cursor = String::new();
+    for _ in 0..POSTS_PAGES_MAX_COUNT {
+        let response = user
+            .get_request(
+                "user-post-summaries-get",
+                format!(
+                    "/v1/social-net/users/{user_id}/posts/summaries?cursor={cursor}&limit={POSTS_PAGE_SIZE}"
+                )
+                .as_str(),
+            )
+            .await?;
+
+        let page: domain::common::OkResult<domain::social_net::PostSummariesView> =
+            response.json().await?;
+        match page.ok.next_cursor {
+            Some(next_cursor) => cursor = next_cursor,
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+// Post of goose user growing comment threads, kept in session data
+struct CommentThreadsPost {
+    post_id: String,
+    comment_count: usize,
+}
+
+async fn add_deep_comment_thread(user: &mut GooseUser) -> TransactionResult {
+    use crate::goose_ext::GooseResponseExt;
+
+    // 1. Create Post, when there is none or comments of post would exceed max count
+    let current = user
+        .get_session_data::<CommentThreadsPost>()
+        .filter(|p| p.comment_count + COMMENT_THREAD_DEPTH <= POST_COMMENTS_MAX_COUNT)
+        .map(|p| (p.post_id.clone(), p.comment_count));
+    let (post_id, mut comment_count) = match current {
+        Some(current) => current,
+        None => {
+            let user_id = data::rand_user_id();
+            let create_post = domain::common::CreatePost {
+                content: data::rand_post_content(),
+                location: None,
+            };
+            let response = user
+                .post_request(
+                    "post-create",
+                    format!("/v1/social-net/users/{user_id}/posts").as_str(),
+                    &create_post,
+                )
+                .await?;
+
+            let post_created_res: domain::common::OkResult<domain::common::PostCreated> =
+                response.json().await?;
+            (post_created_res.ok.post_id, 0)
+        }
+    };
+
+    // 2. Add thread of replies to replies
+    let mut parent_comment_id = None;
+    for _ in 0..COMMENT_THREAD_DEPTH {
+        let create_comment = domain::common::CreateComment {
+            content: data::rand_comment_content(),
+            user_id: data::rand_user_id(),
+            parent_comment_id: parent_comment_id.clone(),
+        };
+
+        let response = user
+            .post_request(
+                "comment-thread-add",
+                format!("/v1/social-net/posts/{post_id}/comments").as_str(),
+                &create_comment,
+            )
+            .await?;
+
+        let comment_id_res: domain::common::OkResult<String> = response.json().await?;
+        parent_comment_id = Some(comment_id_res.ok);
+        comment_count += 1;
+    }
+
+    // 3. Remove deepest reply
+    if let Some(comment_id) = parent_comment_id {
+        let _response = user
+            .delete_request(
+                "comment-thread-remove",
+                format!("/v1/social-net/posts/{post_id}/comments/{comment_id}").as_str(),
+            )
+            .await?;
+        comment_count -= 1;
+    }
+
+    // 4. Read first comments page
+    let _response = user
+        .get_request(
+            "comments-page-get",
+            format!(
+                "/v1/social-net/posts/{post_id}/comments/page?cursor=&limit={COMMENTS_PAGE_SIZE}"
+            )
+            .as_str(),
+        )
+        .await?;
+
+    user.set_session_data(CommentThreadsPost {
+        post_id,
+        comment_count,
+    });
+
+    Ok(())
+}
+
 async fn create_chat_messages_and_likes(user: &mut GooseUser) -> TransactionResult {
     use crate::goose_ext::GooseResponseExt;
     use rand::Rng;
@@ -241,6 +492,7 @@ async fn create_chat_messages_and_likes(user: &mut GooseUser) -> TransactionResu
             let add_message = domain::common::AddMessage {
                 user_id: p_id.clone(),
                 content: data::rand_message_content(),
+                kind: None,
             };
 
             let response = user
@@ -289,3 +541,101 @@ async fn create_chat_messages_and_likes(user: &mut GooseUser) -> TransactionResu
 
     Ok(())
 }
+
+async fn create_group_chat_and_message_burst(user: &mut GooseUser) -> TransactionResult {
+    use crate::goose_ext::GooseResponseExt;
+    use rand::prelude::SliceRandom;
+    use rand::Rng;
+
+    let participant_count =
+        rand::thread_rng().gen_range(GROUP_CHAT_MIN_PARTICIPANTS..=GROUP_CHAT_MAX_PARTICIPANTS);
+    let mut participants = data::rand_user_ids(participant_count);
+    let creator_id = participants.pop().unwrap();
+
+    // updates of chats of participants are read since chat creation (with margin for clock skew)
+    let since = (chrono::Utc::now() - chrono::Duration::minutes(1))
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+    // 1. Create Chat
+    let create_chat = domain::common::CreateChat {
+        participants: participants.clone(),
+    };
+    let response = user
+        .post_request(
+            "group-chat-create",
+            format!("/v1/social-net/users/{creator_id}/chats").as_str(),
+            &create_chat,
+        )
+        .await?;
+
+    let chat_created_res: domain::common::OkResult<domain::common::ChatCreated> =
+        response.json().await?;
+    let chat_id = chat_created_res.ok.chat_id;
+
+    let mut all_participants = participants;
+    all_participants.push(creator_id);
+
+    // 2. Add burst of messages from random participants
+    let mut last_message_id = String::new();
+    for _ in 0..GROUP_CHAT_MESSAGE_COUNT {
+        let add_message = domain::common::AddMessage {
+            user_id: all_participants
+                .choose(&mut rand::thread_rng())
+                .unwrap()
+                .clone(),
+            content: data::rand_message_content(),
+            kind: None,
+        };
+        let response = user
+            .post_request(
+                "group-chat-message-add",
+                format!("/v1/social-net/chats/{chat_id}/messages").as_str(),
+                &add_message,
+            )
+            .await?;
+
+        let message_id_res: domain::common::OkResult<String> = response.json().await?;
+        last_message_id = message_id_res.ok;
+    }
+    let added_at = std::time::Instant::now();
+
+    // 3. Wait until the last message is delivered to chats of all participants
+    let mut pending = all_participants;
+    while !pending.is_empty() && added_at.elapsed() < GROUP_CHAT_DELIVERY_TIMEOUT {
+        let mut not_delivered = Vec::new();
+        for participant_id in pending {
+            let response = user
+                .get_request(
+                    "group-chat-updates-get",
+                    format!("/v1/social-net/users/{participant_id}/chats/updates?since={since}")
+                        .as_str(),
+                )
+                .await?;
+
+            let updates: domain::common::OkResult<domain::social_net::UserChatsUpdates> =
+                response.json().await?;
+            let delivered = updates.ok.chats.iter().any(|c| {
+                c.chat_id == chat_id
+                    && c.last_message
+                        .as_ref()
+                        .is_some_and(|m| m.message_id == last_message_id)
+            });
+            if !delivered {
+                not_delivered.push(participant_id);
+            }
+        }
+        pending = not_delivered;
+        if !pending.is_empty() {
+            tokio::time::sleep(GROUP_CHAT_DELIVERY_POLL_INTERVAL).await;
+        }
+    }
+
+    // time between add of the last message and its delivery to the last participant
+    if pending.is_empty() {
+        custom_metrics::record("group-chat-delivery-last-participant", added_at.elapsed());
+    } else {
+        custom_metrics::record_failure("group-chat-delivery-last-participant");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use crate::summary::{percentile, MetricsSummary};
+
+// default max p95 response time in milliseconds
+const DEFAULT_MAX_P95_MS: usize = 500;
+
+// default max error rate in percents
+const DEFAULT_MAX_ERROR_RATE: f64 = 1.0;
+
+fn env_var<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+// SLA thresholds, checked against aggregated and per request metrics
+pub struct Sla {
+    pub max_p95_ms: usize,
+    pub max_error_rate: f64,
+}
+
+impl Sla {
+    pub fn from_env() -> Self {
+        Sla {
+            max_p95_ms: env_var("SLA_MAX_P95_MS", DEFAULT_MAX_P95_MS),
+            max_error_rate: env_var("SLA_MAX_ERROR_RATE", DEFAULT_MAX_ERROR_RATE),
+        }
+    }
+
+    // Violations of thresholds, empty if all are satisfied
+    pub fn check(&self, metrics: &MetricsSummary) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        for (name, request) in metrics.requests.iter() {
+            if let Some(p95) = percentile(&request.times, 0.95) {
+                if p95 > self.max_p95_ms {
+                    violations.push(format!("{name}: p95 {p95}ms > {}ms", self.max_p95_ms));
+                }
+            }
+        }
+
+        let aggregated = metrics.aggregated();
+
+        if let Some(p95) = percentile(&aggregated.times, 0.95) {
+            if p95 > self.max_p95_ms {
+                violations.push(format!("aggregated: p95 {p95}ms > {}ms", self.max_p95_ms));
+            }
+        }
+
+        let count = aggregated.success_count + aggregated.fail_count;
+        if count > 0 {
+            let error_rate = aggregated.fail_count as f64 * 100.0 / count as f64;
+            if error_rate > self.max_error_rate {
+                violations.push(format!(
+                    "aggregated: error rate {error_rate:.2}% > {}%",
+                    self.max_error_rate
+                ));
+            }
+        }
+
+        violations
+    }
+}
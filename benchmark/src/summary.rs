@@ -0,0 +1,142 @@
+use goose::metrics::GooseMetrics;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Response times and counts of a request
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct RequestSummary {
+    // response time (ms) -> count
+    pub times: BTreeMap<usize, usize>,
+    pub success_count: usize,
+    pub fail_count: usize,
+}
+
+impl RequestSummary {
+    fn merge(&mut self, other: RequestSummary) {
+        for (time, count) in other.times {
+            *self.times.entry(time).or_insert(0) += count;
+        }
+        self.success_count += other.success_count;
+        self.fail_count += other.fail_count;
+    }
+}
+
+// Serializable metrics of one or more (merged) benchmark runs
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct MetricsSummary {
+    // ids of workers which generated the metrics
+    pub workers: Vec<String>,
+    pub requests: BTreeMap<String, RequestSummary>,
+    // metrics measured by scenarios, see custom_metrics (not checked by SLA)
+    #[serde(default)]
+    pub custom: BTreeMap<String, RequestSummary>,
+}
+
+impl MetricsSummary {
+    pub fn from_metrics(worker_id: &str, metrics: &GooseMetrics) -> Self {
+        let requests = metrics
+            .requests
+            .iter()
+            .map(|(name, request)| {
+                (
+                    name.clone(),
+                    RequestSummary {
+                        times: request.raw_data.times.clone(),
+                        success_count: request.success_count,
+                        fail_count: request.fail_count,
+                    },
+                )
+            })
+            .collect();
+
+        MetricsSummary {
+            workers: vec![worker_id.to_string()],
+            requests,
+            custom: crate::custom_metrics::snapshot(),
+        }
+    }
+
+    pub fn merge(&mut self, other: MetricsSummary) {
+        self.workers.extend(other.workers);
+        for (name, request) in other.requests {
+            self.requests.entry(name).or_default().merge(request);
+        }
+        for (name, metric) in other.custom {
+            self.custom.entry(name).or_default().merge(metric);
+        }
+    }
+
+    // All requests merged together
+    pub fn aggregated(&self) -> RequestSummary {
+        let mut result = RequestSummary::default();
+        for request in self.requests.values() {
+            result.merge(request.clone());
+        }
+        result
+    }
+
+    pub fn read(path: &str) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        serde_json::from_str(&data).map_err(|e| format!("{path}: {e}"))
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        let data = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, data).map_err(|e| format!("{path}: {e}"))
+    }
+
+    pub fn print(&self) {
+        println!("Merged metrics of workers: {}", self.workers.join(", "));
+        let aggregated_name = "Aggregated".to_string();
+        let aggregated = self.aggregated();
+        print_table(
+            self.requests
+                .iter()
+                .chain(std::iter::once((&aggregated_name, &aggregated))),
+        );
+        self.print_custom();
+    }
+
+    pub fn print_custom(&self) {
+        if !self.custom.is_empty() {
+            println!("Custom metrics:");
+            print_table(self.custom.iter());
+        }
+    }
+}
+
+fn print_table<'a>(rows: impl Iterator<Item = (&'a String, &'a RequestSummary)>) {
+    println!(
+        "{:<40} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "Name", "Requests", "Fails", "p50 (ms)", "p95 (ms)", "Max (ms)"
+    );
+    for (name, request) in rows {
+        println!(
+            "{:<40} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            name,
+            request.success_count + request.fail_count,
+            request.fail_count,
+            percentile(&request.times, 0.5).unwrap_or(0),
+            percentile(&request.times, 0.95).unwrap_or(0),
+            request.times.keys().last().copied().unwrap_or(0)
+        );
+    }
+}
+
+// Response time (ms) for given percentile (0.0 - 1.0), None if there are no requests
+pub fn percentile(times: &BTreeMap<usize, usize>, percentile: f64) -> Option<usize> {
+    let count: usize = times.values().sum();
+    if count == 0 {
+        return None;
+    }
+
+    let target = ((count as f64) * percentile).ceil() as usize;
+    let mut seen = 0;
+    for (time, time_count) in times.iter() {
+        seen += time_count;
+        if seen >= target {
+            return Some(*time);
+        }
+    }
+    times.keys().last().copied()
+}
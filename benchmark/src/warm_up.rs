@@ -0,0 +1,137 @@
+use crate::{data, domain};
+use serde::Serialize;
+
+// Warm-up is enabled by WARM_UP env variable (true or 1)
+pub fn is_enabled() -> bool {
+    std::env::var("WARM_UP")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Connect {
+    user_id: String,
+    connection_type: String,
+}
+
+// Client of warm-up requests, failures are counted, warm-up continues
+struct WarmUpClient {
+    client: reqwest::Client,
+    host: String,
+    request_count: usize,
+    fail_count: usize,
+}
+
+impl WarmUpClient {
+    async fn post<T: Serialize>(&mut self, path: &str, json: &T) -> Option<reqwest::Response> {
+        let request = self
+            .client
+            .post(format!("{}{path}", self.host))
+            .headers(crate::goose_ext::get_headers())
+            .json(json);
+        self.send(request).await
+    }
+
+    async fn put<T: Serialize>(&mut self, path: &str, json: &T) -> Option<reqwest::Response> {
+        let request = self
+            .client
+            .put(format!("{}{path}", self.host))
+            .headers(crate::goose_ext::get_headers())
+            .json(json);
+        self.send(request).await
+    }
+
+    async fn send(&mut self, request: reqwest::RequestBuilder) -> Option<reqwest::Response> {
+        self.request_count += 1;
+        match request.send().await {
+            Ok(response) if response.status().is_success() => Some(response),
+            _ => {
+                self.fail_count += 1;
+                None
+            }
+        }
+    }
+}
+
+// Pre-create users, connections, posts and chats of benchmark users before measurement,
+// so agents are instantiated and metrics are not skewed by cold starts
+pub async fn run(host: &str) {
+    if host.is_empty() {
+        println!("Warm-up skipped, HOST is not set");
+        return;
+    }
+    let mut client = WarmUpClient {
+        client: reqwest::Client::new(),
+        host: host.trim_end_matches('/').to_string(),
+        request_count: 0,
+        fail_count: 0,
+    };
+    let user_ids = data::get_user_ids();
+    println!("Warming up {} users", user_ids.len());
+
+    for (i, user_id) in user_ids.iter().enumerate() {
+        let next_user_id = &user_ids[(i + 1) % user_ids.len()];
+
+        // 1. Users and connections (following is connected without request acceptance)
+        let connect = Connect {
+            user_id: next_user_id.clone(),
+            connection_type: "following".to_string(),
+        };
+        client
+            .put(
+                format!("/v1/social-net/users/{user_id}/connections").as_str(),
+                &connect,
+            )
+            .await;
+
+        // 2. Posts
+        let create_post = domain::common::CreatePost {
+            content: data::rand_post_content(),
+            location: None,
+        };
+        client
+            .post(
+                format!("/v1/social-net/users/{user_id}/posts").as_str(),
+                &create_post,
+            )
+            .await;
+
+        // 3. Chats
+        let create_chat = domain::common::CreateChat {
+            participants: vec![next_user_id.clone()],
+        };
+        let chat_created = match client
+            .post(
+                format!("/v1/social-net/users/{user_id}/chats").as_str(),
+                &create_chat,
+            )
+            .await
+        {
+            Some(response) => response
+                .json::<domain::common::OkResult<domain::common::ChatCreated>>()
+                .await
+                .ok(),
+            None => None,
+        };
+        if let Some(chat_created) = chat_created {
+            let chat_id = chat_created.ok.chat_id;
+            let add_message = domain::common::AddMessage {
+                user_id: user_id.clone(),
+                content: data::rand_message_content(),
+                kind: None,
+            };
+            client
+                .post(
+                    format!("/v1/social-net/chats/{chat_id}/messages").as_str(),
+                    &add_message,
+                )
+                .await;
+        }
+    }
+
+    println!(
+        "Warm-up finished, requests: {}, fails: {}",
+        client.request_count, client.fail_count
+    );
+}
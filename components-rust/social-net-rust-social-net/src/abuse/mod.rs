@@ -0,0 +1,320 @@
+use crate::common::{clock, trace};
+use crate::moderation::ModerationAgentClient;
+use crate::trace_println;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+// window and number of likes or connects in the window flagged as mass action
+const VELOCITY_WINDOW_SECONDS: i64 = 600;
+const MASS_LIKES_THRESHOLD: usize = 100;
+const MASS_CONNECTS_THRESHOLD: usize = 50;
+
+// window and number of identical comments in the window flagged as repeated comments
+const REPEATED_COMMENTS_WINDOW_SECONDS: i64 = 3600;
+const REPEATED_COMMENTS_THRESHOLD: usize = 5;
+
+// the same abuse of user is flagged again only after cooldown
+const FLAG_COOLDOWN_HOURS: i64 = 24;
+
+// max number of flags kept per user
+const FLAGS_MAX_COUNT: usize = 20;
+
+// reporter of reports created in moderation queue for flagged users
+pub const ABUSE_DETECTION_REPORTER_ID: &str = "abuse-detection";
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum AbuseEventKind {
+    Like,
+    Connect,
+    Comment,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum AbuseKind {
+    MassLikes,
+    MassConnects,
+    RepeatedComments,
+}
+
+impl Display for AbuseKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AbuseKind::MassLikes => write!(f, "mass likes"),
+            AbuseKind::MassConnects => write!(f, "mass connects"),
+            AbuseKind::RepeatedComments => write!(f, "repeated identical comments"),
+        }
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct AbuseFlag {
+    pub kind: AbuseKind,
+    // number of actions in detection window
+    pub count: u32,
+    pub flagged_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl AbuseFlag {
+    fn reason(&self) -> String {
+        format!(
+            "Automatically flagged: {} ({} in window)",
+            self.kind, self.count
+        )
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct CommentEvent {
+    pub fingerprint: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Recent actions of user, in sliding windows of detection rules
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct AbuseDetector {
+    pub user_id: String,
+    pub likes: Vec<chrono::DateTime<chrono::Utc>>,
+    pub connects: Vec<chrono::DateTime<chrono::Utc>>,
+    pub comments: Vec<CommentEvent>,
+    // newest last
+    pub flags: Vec<AbuseFlag>,
+}
+
+impl AbuseDetector {
+    fn new(user_id: String) -> Self {
+        AbuseDetector {
+            user_id,
+            likes: vec![],
+            connects: vec![],
+            comments: vec![],
+            flags: vec![],
+        }
+    }
+
+    // Record action of user, returns new flag if action makes velocity anomalous
+    fn record(
+        &mut self,
+        kind: AbuseEventKind,
+        fingerprint: Option<String>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Option<AbuseFlag> {
+        let window_start = now - chrono::Duration::seconds(VELOCITY_WINDOW_SECONDS);
+        let (abuse_kind, count) = match kind {
+            AbuseEventKind::Like => {
+                self.likes.retain(|t| *t > window_start);
+                self.likes.push(now);
+                (AbuseKind::MassLikes, self.likes.len())
+            }
+            AbuseEventKind::Connect => {
+                self.connects.retain(|t| *t > window_start);
+                self.connects.push(now);
+                (AbuseKind::MassConnects, self.connects.len())
+            }
+            AbuseEventKind::Comment => {
+                let window_start =
+                    now - chrono::Duration::seconds(REPEATED_COMMENTS_WINDOW_SECONDS);
+                self.comments.retain(|c| c.created_at > window_start);
+                let fingerprint = fingerprint?;
+                let count = self
+                    .comments
+                    .iter()
+                    .filter(|c| c.fingerprint == fingerprint)
+                    .count()
+                    + 1;
+                self.comments.push(CommentEvent {
+                    fingerprint,
+                    created_at: now,
+                });
+                (AbuseKind::RepeatedComments, count)
+            }
+        };
+
+        let threshold = match abuse_kind {
+            AbuseKind::MassLikes => MASS_LIKES_THRESHOLD,
+            AbuseKind::MassConnects => MASS_CONNECTS_THRESHOLD,
+            AbuseKind::RepeatedComments => REPEATED_COMMENTS_THRESHOLD,
+        };
+        let cooldown_start = now - chrono::Duration::hours(FLAG_COOLDOWN_HOURS);
+        let recently_flagged = self
+            .flags
+            .iter()
+            .any(|f| f.kind == abuse_kind && f.flagged_at > cooldown_start);
+
+        if count >= threshold && !recently_flagged {
+            let flag = AbuseFlag {
+                kind: abuse_kind,
+                count: count as u32,
+                flagged_at: now,
+            };
+            self.flags.push(flag.clone());
+            let excess = self.flags.len().saturating_sub(FLAGS_MAX_COUNT);
+            self.flags.drain(..excess);
+            Some(flag)
+        } else {
+            None
+        }
+    }
+}
+
+// Fingerprint of content, equal for contents differing only in case and whitespace
+pub fn content_fingerprint(content: &str) -> String {
+    let normalized = content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    format!("{:x}", md5::compute(normalized))
+}
+
+#[agent_definition]
+trait AbuseDetectionAgent {
+    fn new(id: String) -> Self;
+
+    fn get_detector(&self) -> Option<AbuseDetector>;
+
+    // Record action of user, anomalous velocity is flagged and reported to moderation queue
+    fn record_event(
+        &mut self,
+        kind: AbuseEventKind,
+        fingerprint: Option<String>,
+        correlation_id: Option<String>,
+    );
+}
+
+struct AbuseDetectionAgentImpl {
+    _id: String,
+    state: Option<AbuseDetector>,
+}
+
+#[agent_implementation]
+impl AbuseDetectionAgent for AbuseDetectionAgentImpl {
+    fn new(id: String) -> Self {
+        AbuseDetectionAgentImpl {
+            _id: id,
+            state: None,
+        }
+    }
+
+    fn get_detector(&self) -> Option<AbuseDetector> {
+        self.state.clone()
+    }
+
+    fn record_event(
+        &mut self,
+        kind: AbuseEventKind,
+        fingerprint: Option<String>,
+        correlation_id: Option<String>,
+    ) {
+        trace::init(correlation_id);
+        let state = self
+            .state
+            .get_or_insert(AbuseDetector::new(self._id.clone()));
+        if let Some(flag) = state.record(kind, fingerprint, clock::now()) {
+            trace_println!(
+                "abuse flagged - user id: {}, kind: {}, count: {}",
+                state.user_id,
+                flag.kind,
+                flag.count
+            );
+            ModerationAgentClient::get().trigger_report_user(
+                ABUSE_DETECTION_REPORTER_ID.to_string(),
+                state.user_id.clone(),
+                flag.reason(),
+                trace::propagate(),
+            );
+        }
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<AbuseDetector> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+// Record action of user for abuse detection (from write paths), comment content is fingerprinted
+pub fn record_abuse_event(user_id: &str, kind: AbuseEventKind, content: Option<&str>) {
+    AbuseDetectionAgentClient::get(user_id.to_string()).trigger_record_event(
+        kind,
+        content.map(content_fingerprint),
+        trace::propagate(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    fn start() -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    #[test]
+    fn test_mass_likes_flagged_once() {
+        let mut detector = AbuseDetector::new("u1".to_string());
+        let now = start();
+        for i in 0..MASS_LIKES_THRESHOLD - 1 {
+            let at = now + chrono::Duration::seconds(i as i64);
+            assert!(detector.record(AbuseEventKind::Like, None, at).is_none());
+        }
+        let at = now + chrono::Duration::seconds(MASS_LIKES_THRESHOLD as i64);
+        let flag = detector.record(AbuseEventKind::Like, None, at).unwrap();
+        assert_eq!(flag.kind, AbuseKind::MassLikes);
+        assert_eq!(flag.count, MASS_LIKES_THRESHOLD as u32);
+
+        // cooldown
+        assert!(detector.record(AbuseEventKind::Like, None, at).is_none());
+        assert_eq!(detector.flags.len(), 1);
+    }
+
+    #[test]
+    fn test_slow_connects_not_flagged() {
+        let mut detector = AbuseDetector::new("u1".to_string());
+        let now = start();
+        for i in 0..MASS_CONNECTS_THRESHOLD * 2 {
+            // one connect per minute, at most 10 in window
+            let at = now + chrono::Duration::minutes(i as i64);
+            assert!(detector.record(AbuseEventKind::Connect, None, at).is_none());
+        }
+        assert!(detector.connects.len() <= 10);
+    }
+
+    #[test]
+    fn test_repeated_comments_flagged() {
+        let mut detector = AbuseDetector::new("u1".to_string());
+        let now = start();
+        let spam = content_fingerprint("Buy  cheap followers");
+        let other = content_fingerprint("Nice post");
+        for _ in 0..REPEATED_COMMENTS_THRESHOLD - 1 {
+            assert!(detector
+                .record(AbuseEventKind::Comment, Some(spam.clone()), now)
+                .is_none());
+            assert!(detector
+                .record(AbuseEventKind::Comment, Some(other.clone()), now)
+                .is_none());
+        }
+        let flag = detector
+            .record(
+                AbuseEventKind::Comment,
+                Some(content_fingerprint("buy cheap FOLLOWERS ")),
+                now,
+            )
+            .unwrap();
+        assert_eq!(flag.kind, AbuseKind::RepeatedComments);
+        assert_eq!(flag.count, REPEATED_COMMENTS_THRESHOLD as u32);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut detector = AbuseDetector::new("u1".to_string());
+        detector.record(AbuseEventKind::Like, Some("fp1".to_string()), start());
+        detector.record(AbuseEventKind::Comment, None, start());
+        snapshot::assert_round_trip(&Some(detector));
+    }
+}
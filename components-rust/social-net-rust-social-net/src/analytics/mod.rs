@@ -0,0 +1,531 @@
+use crate::common::hll::HyperLogLog;
+use crate::common::{clock, trace, SocialNetError};
+use crate::trace_println;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+// number of days for which activity is kept (enough for MAU of the oldest kept week)
+const ACTIVITY_RETENTION_DAYS: i64 = 60;
+
+// max number of users remembered by write paths as already recorded today
+const RECORDED_USERS_CACHE_MAX_COUNT: usize = 1000;
+
+const TOP_INVITERS_DEFAULT_LIMIT: u32 = 10;
+
+const TOP_INVITERS_MAX_LIMIT: u32 = 100;
+
+const DAY_FORMAT: &str = "%Y-%m-%d";
+
+fn parse_day(day: &str) -> Result<chrono::NaiveDate, SocialNetError> {
+    chrono::NaiveDate::parse_from_str(day, DAY_FORMAT)
+        .map_err(|_| SocialNetError::ValidationFailed(format!("Invalid day: {day}")))
+}
+
+fn format_day(day: chrono::NaiveDate) -> String {
+    day.format(DAY_FORMAT).to_string()
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ActiveUsersCount {
+    pub day: String,
+    // approximate number of distinct active users on the day
+    pub daily: u64,
+    // ... in 7 days ending with the day
+    pub weekly: u64,
+    // ... in 30 days ending with the day
+    pub monthly: u64,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ActiveUsers {
+    // day (YYYY-MM-DD) -> distinct users sketch
+    pub days: BTreeMap<String, HyperLogLog>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ActiveUsers {
+    fn new() -> Self {
+        let now = clock::now();
+        ActiveUsers {
+            days: BTreeMap::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn record(&mut self, user_id: &str, now: chrono::DateTime<chrono::Utc>) {
+        let today = now.date_naive();
+        if self.days.entry(format_day(today)).or_default().add(user_id) {
+            self.updated_at = now;
+        }
+
+        let oldest = format_day(today - chrono::Duration::days(ACTIVITY_RETENTION_DAYS - 1));
+        self.days.retain(|day, _| *day >= oldest);
+    }
+
+    // Distinct users in the period of given number of days ending with the day
+    fn count(&self, day: chrono::NaiveDate, days: i64) -> u64 {
+        let from = format_day(day - chrono::Duration::days(days - 1));
+        let to = format_day(day);
+        let mut result = HyperLogLog::new();
+        for (_, hll) in self.days.range(from..=to) {
+            let _ = result.merge(hll);
+        }
+        result.count()
+    }
+
+    fn get_count(&self, day: chrono::NaiveDate) -> ActiveUsersCount {
+        ActiveUsersCount {
+            day: format_day(day),
+            daily: self.count(day, 1),
+            weekly: self.count(day, 7),
+            monthly: self.count(day, 30),
+        }
+    }
+}
+
+#[agent_definition]
+trait DauAgent {
+    fn new() -> Self;
+
+    fn record_activity(&mut self, user_id: String, correlation_id: Option<String>);
+
+    // Active users counts for the day (YYYY-MM-DD), today if not set
+    fn get_active_users(&self, day: Option<String>) -> Result<ActiveUsersCount, SocialNetError>;
+}
+
+struct DauAgentImpl {
+    state: ActiveUsers,
+}
+
+#[agent_implementation]
+impl DauAgent for DauAgentImpl {
+    fn new() -> Self {
+        DauAgentImpl {
+            state: ActiveUsers::new(),
+        }
+    }
+
+    fn record_activity(&mut self, user_id: String, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        trace_println!("record activity - user id: {user_id}");
+        self.state.record(&user_id, clock::now());
+    }
+
+    fn get_active_users(&self, day: Option<String>) -> Result<ActiveUsersCount, SocialNetError> {
+        let day = match day.filter(|d| !d.is_empty()) {
+            Some(day) => parse_day(&day)?,
+            None => clock::now().date_naive(),
+        };
+        Ok(self.state.get_count(day))
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: ActiveUsers = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct PostViewsCount {
+    pub post_id: String,
+    pub views: u64,
+    // approximate number of distinct viewers
+    pub unique_viewers: u64,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct PostViews {
+    pub post_id: String,
+    pub views: u64,
+    // distinct viewers sketch, viewer ids are not stored
+    pub viewers: HyperLogLog,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PostViews {
+    fn new(post_id: String) -> Self {
+        let now = clock::now();
+        PostViews {
+            post_id,
+            views: 0,
+            viewers: HyperLogLog::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn record(&mut self, user_id: &str) {
+        self.views += 1;
+        self.viewers.add(user_id);
+        self.updated_at = clock::now();
+    }
+
+    fn get_count(&self) -> PostViewsCount {
+        PostViewsCount {
+            post_id: self.post_id.clone(),
+            views: self.views,
+            unique_viewers: self.viewers.count(),
+        }
+    }
+}
+
+#[agent_definition]
+trait PostViewsAgent {
+    fn new(id: String) -> Self;
+
+    fn record_view(&mut self, user_id: String, correlation_id: Option<String>);
+
+    fn get_views(&self) -> Option<PostViewsCount>;
+}
+
+struct PostViewsAgentImpl {
+    _id: String,
+    state: Option<PostViews>,
+}
+
+#[agent_implementation]
+impl PostViewsAgent for PostViewsAgentImpl {
+    fn new(id: String) -> Self {
+        PostViewsAgentImpl {
+            _id: id,
+            state: None,
+        }
+    }
+
+    fn record_view(&mut self, user_id: String, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        trace_println!("record view - user id: {user_id}");
+        self.state
+            .get_or_insert(PostViews::new(self._id.clone()))
+            .record(&user_id);
+    }
+
+    fn get_views(&self) -> Option<PostViewsCount> {
+        self.state.as_ref().map(|state| state.get_count())
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<PostViews> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SignupsCount {
+    pub day: String,
+    pub signups: u64,
+    // signups with redeemed invite code
+    pub invited: u64,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct InviterSignups {
+    pub user_id: String,
+    // number of signups with invite codes of the user
+    pub signups: u64,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct Signups {
+    // day (YYYY-MM-DD) -> signups count
+    pub days: BTreeMap<String, SignupsCount>,
+    // inviting user id -> number of invited signups
+    pub inviters: HashMap<String, u64>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Signups {
+    fn new() -> Self {
+        let now = clock::now();
+        Signups {
+            days: BTreeMap::new(),
+            inviters: HashMap::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn record(&mut self, invited_by: Option<String>, now: chrono::DateTime<chrono::Utc>) {
+        let day = format_day(now.date_naive());
+        let count = self.days.entry(day.clone()).or_insert(SignupsCount {
+            day,
+            signups: 0,
+            invited: 0,
+        });
+        count.signups += 1;
+        if let Some(invited_by) = invited_by {
+            count.invited += 1;
+            *self.inviters.entry(invited_by).or_default() += 1;
+        }
+        self.updated_at = now;
+    }
+
+    fn get_count(&self, day: chrono::NaiveDate) -> SignupsCount {
+        let day = format_day(day);
+        self.days.get(&day).cloned().unwrap_or(SignupsCount {
+            day,
+            signups: 0,
+            invited: 0,
+        })
+    }
+
+    // Users with most invited signups
+    fn top_inviters(&self, limit: usize) -> Vec<InviterSignups> {
+        let mut result: Vec<InviterSignups> = self
+            .inviters
+            .iter()
+            .map(|(user_id, signups)| InviterSignups {
+                user_id: user_id.clone(),
+                signups: *signups,
+            })
+            .collect();
+        result.sort_by(|a, b| {
+            b.signups
+                .cmp(&a.signups)
+                .then_with(|| a.user_id.cmp(&b.user_id))
+        });
+        result.truncate(limit);
+        result
+    }
+}
+
+#[agent_definition]
+trait SignupsAgent {
+    fn new() -> Self;
+
+    fn record_signup(
+        &mut self,
+        user_id: String,
+        invited_by: Option<String>,
+        correlation_id: Option<String>,
+    );
+
+    // Signups count for the day (YYYY-MM-DD), today if not set
+    fn get_signups(&self, day: Option<String>) -> Result<SignupsCount, SocialNetError>;
+
+    fn get_top_inviters(&self, limit: Option<u32>) -> Vec<InviterSignups>;
+}
+
+struct SignupsAgentImpl {
+    state: Signups,
+}
+
+#[agent_implementation]
+impl SignupsAgent for SignupsAgentImpl {
+    fn new() -> Self {
+        SignupsAgentImpl {
+            state: Signups::new(),
+        }
+    }
+
+    fn record_signup(
+        &mut self,
+        user_id: String,
+        invited_by: Option<String>,
+        correlation_id: Option<String>,
+    ) {
+        trace::init(correlation_id);
+        trace_println!(
+            "record signup - user id: {user_id}, invited by: {}",
+            invited_by.clone().unwrap_or("N/A".to_string())
+        );
+        self.state.record(invited_by, clock::now());
+    }
+
+    fn get_signups(&self, day: Option<String>) -> Result<SignupsCount, SocialNetError> {
+        let day = match day.filter(|d| !d.is_empty()) {
+            Some(day) => parse_day(&day)?,
+            None => clock::now().date_naive(),
+        };
+        Ok(self.state.get_count(day))
+    }
+
+    fn get_top_inviters(&self, limit: Option<u32>) -> Vec<InviterSignups> {
+        let limit = limit
+            .filter(|l| *l > 0)
+            .unwrap_or(TOP_INVITERS_DEFAULT_LIMIT)
+            .min(TOP_INVITERS_MAX_LIMIT);
+        self.state.top_inviters(limit as usize)
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Signups = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+// Record signup of new user, attributed to inviting user if signed up with invite code
+pub fn record_signup(user_id: &str, invited_by: Option<String>) {
+    SignupsAgentClient::get().trigger_record_signup(
+        user_id.to_string(),
+        invited_by,
+        trace::propagate(),
+    );
+}
+
+thread_local! {
+    static RECORDED_USERS_CACHE: RefCell<HashMap<String, chrono::NaiveDate>> = RefCell::new(HashMap::new());
+}
+
+// Record user activity (from write paths), sent at most once per user and day by the calling agent
+pub fn record_user_activity(user_id: &str) {
+    let today = clock::now().date_naive();
+
+    let recorded = RECORDED_USERS_CACHE.with(|c| {
+        let mut cache = c.borrow_mut();
+        if cache.get(user_id) == Some(&today) {
+            true
+        } else {
+            if cache.len() >= RECORDED_USERS_CACHE_MAX_COUNT {
+                cache.retain(|_, day| *day == today);
+            }
+            cache.insert(user_id.to_string(), today);
+            false
+        }
+    });
+
+    if !recorded {
+        DauAgentClient::get().trigger_record_activity(user_id.to_string(), trace::propagate());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    fn day(value: &str) -> chrono::DateTime<chrono::Utc> {
+        parse_day(value)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn test_active_users_count() {
+        let mut active_users = ActiveUsers::new();
+        active_users.record("u1", day("2026-01-01"));
+        active_users.record("u2", day("2026-01-01"));
+        active_users.record("u1", day("2026-01-05"));
+        active_users.record("u3", day("2026-01-20"));
+
+        let count = active_users.get_count(parse_day("2026-01-05").unwrap());
+        assert_eq!(
+            count,
+            ActiveUsersCount {
+                day: "2026-01-05".to_string(),
+                daily: 1,
+                weekly: 2,
+                monthly: 2,
+            }
+        );
+
+        let count = active_users.get_count(parse_day("2026-01-20").unwrap());
+        assert_eq!(count.daily, 1);
+        assert_eq!(count.weekly, 1);
+        assert_eq!(count.monthly, 3);
+    }
+
+    #[test]
+    fn test_active_users_retention() {
+        let mut active_users = ActiveUsers::new();
+        active_users.record("u1", day("2026-01-01"));
+        active_users.record("u2", day("2026-03-15"));
+
+        assert_eq!(active_users.days.len(), 1);
+        assert!(active_users.days.contains_key("2026-03-15"));
+    }
+
+    #[test]
+    fn test_post_views_count() {
+        let mut post_views = PostViews::new("p1".to_string());
+        post_views.record("u1");
+        post_views.record("u1");
+        post_views.record("u2");
+
+        assert_eq!(
+            post_views.get_count(),
+            PostViewsCount {
+                post_id: "p1".to_string(),
+                views: 3,
+                unique_viewers: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_signups_count() {
+        let mut signups = Signups::new();
+        signups.record(None, day("2026-01-01"));
+        signups.record(Some("u1".to_string()), day("2026-01-01"));
+        signups.record(Some("u2".to_string()), day("2026-01-02"));
+        signups.record(Some("u1".to_string()), day("2026-01-03"));
+
+        assert_eq!(
+            signups.get_count(parse_day("2026-01-01").unwrap()),
+            SignupsCount {
+                day: "2026-01-01".to_string(),
+                signups: 2,
+                invited: 1,
+            }
+        );
+        assert_eq!(
+            signups.get_count(parse_day("2026-01-10").unwrap()).signups,
+            0
+        );
+        assert_eq!(
+            signups.top_inviters(1),
+            vec![InviterSignups {
+                user_id: "u1".to_string(),
+                signups: 2,
+            }]
+        );
+        assert_eq!(signups.top_inviters(10).len(), 2);
+    }
+
+    #[test]
+    fn test_parse_day() {
+        assert!(parse_day("2026-02-30").is_err());
+        assert_eq!(format_day(parse_day("2026-02-03").unwrap()), "2026-02-03");
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut active_users = ActiveUsers::new();
+        active_users.record("u1", day("2024-01-01"));
+        active_users.record("u2", day("2024-01-02"));
+        snapshot::assert_round_trip(&active_users);
+
+        let mut post_views = PostViews::new("p1".to_string());
+        post_views.record("u1");
+        post_views.record("u2");
+        snapshot::assert_round_trip(&Some(post_views));
+
+        let mut signups = Signups::new();
+        signups.record(Some("u1".to_string()), day("2024-01-01"));
+        signups.record(None, day("2024-01-02"));
+        snapshot::assert_round_trip(&signups);
+    }
+}
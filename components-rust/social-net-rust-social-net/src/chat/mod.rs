@@ -1,18 +1,335 @@
+use crate::analytics::record_user_activity;
+use crate::common::clock;
+use crate::common::content;
+use crate::common::cursor::InvalidCursor;
+use crate::common::diagnostics::AgentDiagnostics;
+use crate::common::fetch::{join_all_with_timeout, Fetched};
+use crate::common::id;
 use crate::common::query;
-use crate::common::LikeType;
+use crate::common::trace;
+use crate::common::validation::{check_length, ContentField, ContentTooLong};
+use crate::common::{archive_cutoff, to_schedule_time, ARCHIVE_MIN_BATCH};
+use crate::common::{LikeCounts, LikeSummary, LikeType, LikersPage, SocialNetError};
+use crate::fault_injection::{inject_fault, FaultPath};
+use crate::moderation::{moderate_content, ContentRejected};
+use crate::notification::{notify_user, NotificationKind, NotificationTarget};
+use crate::operations::get_operations_config;
+use crate::trace_println;
+use crate::user::{
+    check_user_not_banned, get_user_names, record_interaction, InteractionKind, UserBanned,
+};
 use crate::user_chats::UserChatsAgentClient;
 use futures::future::join_all;
 use golem_rust::{agent_definition, agent_implementation, Schema};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 
 const MAX_CHAT_LENGTH: usize = 2000;
 
+// max number of messages before and after requested date returned by get messages around
+const MESSAGES_AROUND_MAX_CONTEXT: u32 = 100;
+
+// failed chat update deliveries are retried with exponential backoff, up to max attempts
+const DELIVERY_RETRY_BASE_DELAY_SECONDS: i64 = 5;
+const DELIVERY_RETRY_MAX_DELAY_SECONDS: i64 = 3600;
+
+// max number of characters of message preview snippet
+const MESSAGE_PREVIEW_LENGTH: usize = 100;
+
+// max length of url in message metadata
+const MESSAGE_URL_MAX_LENGTH: usize = 2048;
+
+// max size of file shared in message
+const MESSAGE_FILE_MAX_SIZE_BYTES: u64 = 100 * 1024 * 1024;
+
+// cached participant names older than this are refreshed (lazily, on chat summary read)
+const PARTICIPANT_NAMES_TTL_SECONDS: i64 = 3600;
+
+// searchable chat fields, see Chat::matches_query
+pub(crate) const CHAT_QUERY_FIELDS: &[query::QueryField] = &[
+    query::QueryField {
+        names: &["chat-id", "chatid"],
+        mode: query::FieldMatchMode::Exact,
+    },
+    query::QueryField {
+        names: &["created-by", "createdby"],
+        mode: query::FieldMatchMode::Exact,
+    },
+    query::QueryField {
+        names: &["participants"],
+        mode: query::FieldMatchMode::Exact,
+    },
+];
+
+pub(crate) const CHAT_QUERY_TERM_FIELDS: &[&str] = &["chat-id", "created-by", "messages"];
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct MessagePreview {
+    pub message_id: String,
+    pub snippet: String,
+    pub created_by: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct StickerMetadata {
+    pub pack_id: String,
+    pub sticker_id: String,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct MediaMetadata {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    pub alt_text: Option<String>,
+}
+
+impl MediaMetadata {
+    pub(crate) fn validate(&self, label: &str) -> Result<(), String> {
+        if !is_valid_url(&self.url) {
+            Err(format!("Invalid {label} url"))
+        } else if self.width == 0 || self.height == 0 {
+            Err(format!("Invalid {label} dimensions"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FileMetadata {
+    pub url: String,
+    pub name: String,
+    pub size_bytes: u64,
+    pub mime_type: Option<String>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ParticipantIssue {
+    Empty,
+    Duplicate,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct InvalidParticipant {
+    // position of participant id in request
+    pub index: u32,
+    pub participant_id: String,
+    pub issue: ParticipantIssue,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ChatError {
+    // chat, message or participant not exists
+    NotFound(String),
+    // chat with given id already exists
+    AlreadyExists(String),
+    // user is not allowed to remove content created by another user
+    Unauthorized(String),
+    // user is not allowed to write (banned user)
+    NotAllowed(String),
+    Invalid(String),
+    // rejected participant ids, with issue of each invalid id
+    InvalidParticipants(Vec<InvalidParticipant>),
+    // chat would exceed max number of participants
+    TooManyParticipants(u32),
+    // content is longer than configured max length
+    ContentTooLong(ContentTooLong),
+}
+
+impl Display for ChatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChatError::Unauthorized(user_id) => write!(f, "User not authorized: {user_id}"),
+            ChatError::NotAllowed(user_id) => write!(f, "User not allowed: {user_id}"),
+            ChatError::NotFound(message)
+            | ChatError::AlreadyExists(message)
+            | ChatError::Invalid(message) => write!(f, "{message}"),
+            ChatError::InvalidParticipants(participants) => {
+                let issues = participants
+                    .iter()
+                    .map(|p| format!("{} '{}': {:?}", p.index, p.participant_id, p.issue))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "Invalid participants: {issues}")
+            }
+            ChatError::TooManyParticipants(max) => {
+                write!(f, "Chat can have at most {max} participants")
+            }
+            ChatError::ContentTooLong(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl ChatError {
+    pub fn not_found(message: &str) -> Self {
+        ChatError::NotFound(message.to_string())
+    }
+
+    pub fn invalid(message: &str) -> Self {
+        ChatError::Invalid(message.to_string())
+    }
+}
+
+impl From<ContentTooLong> for ChatError {
+    fn from(e: ContentTooLong) -> Self {
+        ChatError::ContentTooLong(e)
+    }
+}
+
+impl From<UserBanned> for ChatError {
+    fn from(e: UserBanned) -> Self {
+        ChatError::NotAllowed(e.0)
+    }
+}
+
+// Chat errors of view agents, kinds are kept
+impl From<ChatError> for SocialNetError {
+    fn from(e: ChatError) -> Self {
+        match e {
+            ChatError::NotFound(message) => SocialNetError::NotFound(message),
+            ChatError::AlreadyExists(message) => SocialNetError::AlreadyExists(message),
+            ChatError::Unauthorized(_) => SocialNetError::Unauthorized(e.to_string()),
+            ChatError::NotAllowed(_) => SocialNetError::NotAllowed(e.to_string()),
+            ChatError::Invalid(message) => SocialNetError::ValidationFailed(message),
+            ChatError::InvalidParticipants(_) => SocialNetError::ValidationFailed(e.to_string()),
+            ChatError::TooManyParticipants(_) => SocialNetError::LimitExceeded(e.to_string()),
+            ChatError::ContentTooLong(e) => SocialNetError::ContentTooLong(e),
+        }
+    }
+}
+
+impl From<InvalidCursor> for ChatError {
+    fn from(e: InvalidCursor) -> Self {
+        ChatError::Invalid(e.to_string())
+    }
+}
+
+impl From<ContentRejected> for ChatError {
+    fn from(e: ContentRejected) -> Self {
+        ChatError::Invalid(e.to_string())
+    }
+}
+
+// Unique participant ids, all empty and duplicate ids are reported
+pub(crate) fn validate_participants(
+    participants_ids: Vec<String>,
+) -> Result<HashSet<String>, ChatError> {
+    let mut ids: HashSet<String> = HashSet::new();
+    let mut invalid: Vec<InvalidParticipant> = vec![];
+    for (index, participant_id) in participants_ids.into_iter().enumerate() {
+        let issue = if participant_id.trim().is_empty() {
+            Some(ParticipantIssue::Empty)
+        } else if ids.contains(&participant_id) {
+            Some(ParticipantIssue::Duplicate)
+        } else {
+            None
+        };
+        match issue {
+            Some(issue) => invalid.push(InvalidParticipant {
+                index: index as u32,
+                participant_id,
+                issue,
+            }),
+            None => {
+                ids.insert(participant_id);
+            }
+        }
+    }
+    if invalid.is_empty() {
+        Ok(ids)
+    } else {
+        Err(ChatError::InvalidParticipants(invalid))
+    }
+}
+
+pub(crate) fn check_participants_count(
+    count: usize,
+    max_participants: u32,
+) -> Result<(), ChatError> {
+    if count > max_participants as usize {
+        Err(ChatError::TooManyParticipants(max_participants))
+    } else {
+        Ok(())
+    }
+}
+
+// Kind of message content, content of non text messages is (optional) caption
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub enum MessageKind {
+    #[default]
+    Text,
+    Sticker(StickerMetadata),
+    Gif(MediaMetadata),
+    Image(MediaMetadata),
+    File(FileMetadata),
+    // created by the chat itself, can not be added by users
+    System,
+}
+
+pub(crate) fn is_valid_url(url: &str) -> bool {
+    url.len() <= MESSAGE_URL_MAX_LENGTH
+        && (url.starts_with("https://") || url.starts_with("http://"))
+        && !url.contains(char::is_whitespace)
+}
+
+impl MessageKind {
+    fn label(&self) -> &'static str {
+        match self {
+            MessageKind::Text => "Text",
+            MessageKind::Sticker(_) => "Sticker",
+            MessageKind::Gif(_) => "GIF",
+            MessageKind::Image(_) => "Image",
+            MessageKind::File(_) => "File",
+            MessageKind::System => "System",
+        }
+    }
+
+    // Validate kind specific metadata of message added by user
+    fn validate(&self, content: &str) -> Result<(), String> {
+        match self {
+            MessageKind::Text if content.is_empty() => Err("Message content is empty".to_string()),
+            MessageKind::Text => Ok(()),
+            MessageKind::Sticker(sticker)
+                if sticker.pack_id.trim().is_empty() || sticker.sticker_id.trim().is_empty() =>
+            {
+                Err("Sticker pack id and sticker id are required".to_string())
+            }
+            MessageKind::Sticker(_) => Ok(()),
+            MessageKind::Gif(media) | MessageKind::Image(media) => media.validate(self.label()),
+            MessageKind::File(file) => {
+                if !is_valid_url(&file.url) {
+                    Err("Invalid file url".to_string())
+                } else if file.name.trim().is_empty() {
+                    Err("File name is required".to_string())
+                } else if file.size_bytes == 0 || file.size_bytes > MESSAGE_FILE_MAX_SIZE_BYTES {
+                    Err(format!(
+                        "Invalid file size, max size: {MESSAGE_FILE_MAX_SIZE_BYTES} bytes"
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+            MessageKind::System => Err("System messages can not be added".to_string()),
+        }
+    }
+}
+
 #[derive(Schema, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub message_id: String,
+    #[serde(default)]
+    pub kind: MessageKind,
     pub content: String,
+    // markdown rendered content, set only by view agents
+    #[serde(default)]
+    pub content_html: Option<String>,
     pub likes: HashMap<String, LikeType>,
+    // user ids mentioned (@user-id) in content
+    #[serde(default)]
+    pub mentions: Vec<String>,
     pub created_by: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
@@ -20,17 +337,122 @@ pub struct Message {
 
 impl Message {
     fn new(user_id: String, content: String) -> Self {
-        let now = chrono::Utc::now();
-        let message_id = uuid::Uuid::new_v4().to_string();
+        let now = clock::now();
+        let message_id = id::new_id();
         Message {
             message_id,
+            kind: MessageKind::Text,
+            mentions: content::extract_mentions(&content),
             content,
+            content_html: None,
             likes: HashMap::new(),
             created_by: user_id,
             created_at: now,
             updated_at: now,
         }
     }
+
+    fn preview(&self) -> MessagePreview {
+        let content = self
+            .content
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let snippet = if content.is_empty() && self.kind != MessageKind::Text {
+            format!("[{}]", self.kind.label())
+        } else if content.chars().count() > MESSAGE_PREVIEW_LENGTH {
+            let mut snippet: String = content.chars().take(MESSAGE_PREVIEW_LENGTH).collect();
+            snippet.push('…');
+            snippet
+        } else {
+            content
+        };
+
+        MessagePreview {
+            message_id: self.message_id.clone(),
+            snippet,
+            created_by: self.created_by.clone(),
+            created_at: self.created_at,
+        }
+    }
+
+    fn view(&self, user_id: Option<&str>) -> MessageView {
+        MessageView {
+            message_id: self.message_id.clone(),
+            kind: self.kind.clone(),
+            content: self.content.clone(),
+            content_html: self.content_html.clone(),
+            like_counts: LikeCounts::from_likes(self.likes.values()),
+            own_like: user_id.and_then(|user_id| self.likes.get(user_id).cloned()),
+            created_by: self.created_by.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+// Message with likes counts by type instead of likes, for views
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct MessageView {
+    pub message_id: String,
+    pub kind: MessageKind,
+    pub content: String,
+    // markdown rendered content, set only by view agents
+    pub content_html: Option<String>,
+    pub like_counts: LikeCounts,
+    // like of the requesting user, if any
+    pub own_like: Option<LikeType>,
+    pub created_by: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl MessageView {
+    fn with_content_html(mut self) -> Self {
+        self.content_html = Some(content::render_markdown(&self.content));
+        self
+    }
+}
+
+// Chat with message views, for views
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ChatView {
+    pub chat_id: String,
+    pub created_by: String,
+    pub participants: HashSet<String>,
+    pub announcement_mode: bool,
+    pub messages: Vec<MessageView>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ChatView {
+    // Chat view with markdown rendered content of messages
+    pub fn with_content_html(mut self) -> Self {
+        self.messages = self
+            .messages
+            .into_iter()
+            .map(MessageView::with_content_html)
+            .collect();
+        self
+    }
+}
+
+// Chat without messages, for list rendering
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ChatSummary {
+    pub chat_id: String,
+    pub created_by: String,
+    pub participants: HashSet<String>,
+    // cached display names of participants (user id -> name), may be stale or incomplete
+    pub participant_names: HashMap<String, String>,
+    pub announcement_mode: bool,
+    pub message_count: u32,
+    pub last_message: Option<MessagePreview>,
+    // messages of other participants created after last read of the requesting user
+    pub unread_count: u32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Schema, Clone, Serialize, Deserialize)]
@@ -39,35 +461,220 @@ pub struct Chat {
     pub created_by: String,
     pub participants: HashSet<String>,
     pub messages: Vec<Message>,
+    // display names of participants, cached from user agents
+    #[serde(default)]
+    pub participant_names: HashMap<String, String>,
+    #[serde(default)]
+    pub participant_names_refreshed_at: Option<chrono::DateTime<chrono::Utc>>,
+    // number of (oldest) messages moved to chat archive agent
+    #[serde(default)]
+    pub archived_message_count: u32,
+    // announcement (broadcast) chat, only chat admin can add messages
+    #[serde(default)]
+    pub announcement_mode: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+// Messages around given date, up to context messages before and context messages since the date
+fn messages_around(
+    messages: &[Message],
+    timestamp: chrono::DateTime<chrono::Utc>,
+    context: u32,
+) -> Vec<Message> {
+    let context = context.min(MESSAGES_AROUND_MAX_CONTEXT) as usize;
+    // messages are ordered by creation
+    let index = messages.partition_point(|m| m.created_at < timestamp);
+    let start = index.saturating_sub(context);
+    let end = (index + context).min(messages.len());
+
+    messages[start..end].to_vec()
+}
+
 impl Chat {
     fn new(chat_id: String) -> Self {
-        let now = chrono::Utc::now();
+        let now = clock::now();
         Chat {
             chat_id,
             messages: vec![],
             participants: HashSet::new(),
+            participant_names: HashMap::new(),
+            participant_names_refreshed_at: None,
+            archived_message_count: 0,
+            announcement_mode: false,
             created_by: "".to_string(),
             created_at: now,
             updated_at: now,
         }
     }
 
-    fn add_message(&mut self, created_by: String, content: String) -> String {
-        let message = Message::new(created_by.clone(), content);
+    fn get_messages_around(
+        &self,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        context: u32,
+    ) -> Vec<Message> {
+        messages_around(&self.messages, timestamp, context)
+    }
+
+    // Messages around given date are (partly) archived
+    fn messages_around_archived(
+        &self,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        context: u32,
+    ) -> bool {
+        let context = context.min(MESSAGES_AROUND_MAX_CONTEXT) as usize;
+        self.archived_message_count > 0
+            && self.messages.partition_point(|m| m.created_at < timestamp) < context
+    }
+
+    fn message_count(&self) -> u32 {
+        self.messages.len() as u32 + self.archived_message_count
+    }
+
+    // Number of oldest messages not updated since cutoff, archived messages are always older than kept ones
+    fn archivable_messages_count(&self, cutoff: chrono::DateTime<chrono::Utc>) -> usize {
+        self.messages
+            .iter()
+            .take_while(|m| m.updated_at < cutoff)
+            .count()
+    }
+
+    fn take_archivable_messages(&mut self, cutoff: chrono::DateTime<chrono::Utc>) -> Vec<Message> {
+        let count = self.archivable_messages_count(cutoff);
+        self.archived_message_count += count as u32;
+        self.messages.drain(..count).collect()
+    }
+
+    pub fn last_message_preview(&self) -> Option<MessagePreview> {
+        self.messages.last().map(|m| m.preview())
+    }
+
+    // Cached participant names were not refreshed yet (or since participants change) or are expired
+    fn participant_names_stale(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.participant_names_refreshed_at
+            .is_none_or(|refreshed_at| {
+                now - refreshed_at > chrono::Duration::seconds(PARTICIPANT_NAMES_TTL_SECONDS)
+            })
+    }
+
+    fn set_participant_names(
+        &mut self,
+        names: HashMap<String, String>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) {
+        self.participant_names = names
+            .into_iter()
+            .filter(|(user_id, _)| self.participants.contains(user_id))
+            .collect();
+        self.participant_names_refreshed_at = Some(now);
+    }
+
+    fn view(&self, user_id: &str) -> ChatView {
+        ChatView {
+            chat_id: self.chat_id.clone(),
+            created_by: self.created_by.clone(),
+            participants: self.participants.clone(),
+            announcement_mode: self.announcement_mode,
+            messages: self
+                .messages
+                .iter()
+                .map(|m| m.view(Some(user_id)))
+                .collect(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    fn summary(
+        &self,
+        user_id: &str,
+        last_read_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> ChatSummary {
+        let unread_count = self
+            .messages
+            .iter()
+            .filter(|m| m.created_by != user_id && last_read_at.is_none_or(|r| m.created_at > r))
+            .count() as u32;
+
+        ChatSummary {
+            chat_id: self.chat_id.clone(),
+            created_by: self.created_by.clone(),
+            participants: self.participants.clone(),
+            participant_names: self.participant_names.clone(),
+            announcement_mode: self.announcement_mode,
+            message_count: self.message_count(),
+            last_message: self.last_message_preview(),
+            unread_count,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    fn add_message(&mut self, created_by: String, content: String, kind: MessageKind) -> String {
+        let mut message = Message::new(created_by, content);
+        message.kind = kind;
         let message_id = message.message_id.clone();
         self.updated_at = message.created_at;
         self.messages.push(message);
         message_id
     }
 
+    // Remove participant and add system message about the departure
+    fn remove_participant(&mut self, user_id: &str) -> bool {
+        if self.participants.remove(user_id) {
+            let name = self
+                .participant_names
+                .remove(user_id)
+                .unwrap_or(user_id.to_string());
+            self.add_message(
+                user_id.to_string(),
+                format!("{name} left the chat"),
+                MessageKind::System,
+            );
+            true
+        } else {
+            false
+        }
+    }
+
+    // Creator of chat acts as chat admin
+    fn is_admin(&self, user_id: &str) -> bool {
+        self.created_by == user_id
+    }
+
+    // Participants mentioned in message, without message author
+    fn get_mentioned_participants(&self, message_id: &str) -> Vec<String> {
+        self.messages
+            .iter()
+            .find(|m| m.message_id == message_id)
+            .map(|m| {
+                m.mentions
+                    .iter()
+                    .filter(|id| **id != m.created_by && self.participants.contains(*id))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Messages can be added by all users, in announcement mode only by the chat admin
+    fn can_add_message(&self, user_id: &str) -> bool {
+        !self.announcement_mode || self.is_admin(user_id)
+    }
+
+    // Message can be removed by its author or the chat admin (creator of chat)
+    fn can_remove_message(&self, message_id: &str, user_id: &str) -> bool {
+        self.is_admin(user_id)
+            || self
+                .messages
+                .iter()
+                .any(|m| m.message_id == message_id && m.created_by == user_id)
+    }
+
     fn remove_message(&mut self, message_id: String) -> bool {
         if self.messages.iter().any(|m| m.message_id == message_id) {
             self.messages.retain(|m| m.message_id != message_id);
-            self.updated_at = chrono::Utc::now();
+            self.updated_at = clock::now();
             true
         } else {
             false
@@ -87,7 +694,7 @@ impl Chat {
         {
             Some(msg) => {
                 msg.likes.insert(user_id, like_type);
-                let now = chrono::Utc::now();
+                let now = clock::now();
                 msg.updated_at = now;
                 self.updated_at = now;
                 true
@@ -105,7 +712,7 @@ impl Chat {
             Some(msg) => {
                 let removed = msg.likes.remove(&user_id).is_some();
                 if removed {
-                    let now = chrono::Utc::now();
+                    let now = clock::now();
                     msg.updated_at = now;
                     self.updated_at = now;
                 }
@@ -115,16 +722,43 @@ impl Chat {
         }
     }
 
+    fn get_message_like_counts(&self, message_id: &str) -> Option<LikeCounts> {
+        self.messages
+            .iter()
+            .find(|m| m.message_id == message_id)
+            .map(|m| LikeCounts::from_likes(m.likes.values()))
+    }
+
+    fn get_message_like_summary(
+        &self,
+        message_id: &str,
+        user_id: Option<&str>,
+    ) -> Option<LikeSummary> {
+        self.messages
+            .iter()
+            .find(|m| m.message_id == message_id)
+            .map(|m| LikeSummary::from_likes(&m.likes, user_id))
+    }
+
+    // Chat with markdown rendered content of messages
+    pub fn with_content_html(mut self) -> Self {
+        for message in self.messages.iter_mut() {
+            message.content_html = Some(content::render_markdown(&message.content));
+        }
+        self
+    }
+
     fn matches_query(&self, query: &query::Query) -> bool {
         // Check field filters first
         for (field, value) in query.field_filters.iter() {
+            let mode = query::field_mode(CHAT_QUERY_FIELDS, field);
             let matches = match field.as_str() {
-                "chat-id" | "chatid" => query::text_exact_matches(&self.chat_id, value),
-                "created-by" | "createdby" => query::text_exact_matches(&self.created_by, value),
+                "chat-id" | "chatid" => query::exact_matches(&mode, &self.chat_id, value),
+                "created-by" | "createdby" => query::exact_matches(&mode, &self.created_by, value),
                 "participants" => self
                     .participants
                     .iter()
-                    .any(|p| query::text_exact_matches(p, value)),
+                    .any(|p| query::exact_matches(&mode, p, value)),
                 _ => false, // Unknown field
             };
             if !matches {
@@ -153,32 +787,133 @@ trait ChatAgent {
 
     fn get_chat_if_match(&self, query: query::Query) -> Option<Chat>;
 
-    fn init_chat(
+    // Chat with likes counts of messages instead of likes, with own likes of user
+    fn get_chat_view(&self, user_id: String) -> Option<ChatView>;
+
+    fn get_chat_view_if_match(&self, user_id: String, query: query::Query) -> Option<ChatView>;
+
+    // Chat without messages, unread messages of user are counted since given last read
+    fn get_chat_summary(
+        &self,
+        user_id: String,
+        last_read_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Option<ChatSummary>;
+
+    fn get_chat_summary_if_match(
+        &self,
+        user_id: String,
+        last_read_at: Option<chrono::DateTime<chrono::Utc>>,
+        query: query::Query,
+    ) -> Option<ChatSummary>;
+
+    // Archived messages are included, if the date is in the archived part of chat
+    async fn get_messages_around(
+        &self,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        context: u32,
+    ) -> Option<Vec<MessageView>>;
+
+    // Chat with archived messages, oldest first
+    async fn get_chat_with_archive(&self) -> Option<Chat>;
+
+    // Move oldest messages not updated since archive threshold to chat archive agent
+    async fn archive_messages(&mut self, correlation_id: Option<String>) -> u32;
+
+    // Message likes counts by type
+    fn get_message_like_counts(&self, message_id: String) -> Option<LikeCounts>;
+
+    // Message likes counts by type, with reaction of given user
+    fn get_message_like_summary(
+        &self,
+        message_id: String,
+        user_id: Option<String>,
+    ) -> Option<LikeSummary>;
+
+    // Page of users who liked message (optionally with given type), ordered by user id
+    fn get_message_likers(
+        &self,
+        message_id: String,
+        like_type: Option<LikeType>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<LikersPage>, ChatError>;
+
+    // Participant ids must be unique and non empty, creator is added to participants
+    async fn init_chat(
         &mut self,
-        participants_ids: HashSet<String>,
+        participants_ids: Vec<String>,
         created_by: String,
         created_at: chrono::DateTime<chrono::Utc>,
-    ) -> Result<(), String>;
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
 
-    fn add_participants(&mut self, participants_ids: HashSet<String>) -> Result<(), String>;
+    // Participant ids must be unique and non empty, existing participants are skipped
+    async fn add_participants(
+        &mut self,
+        participants_ids: Vec<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
 
-    fn add_message(&mut self, user_id: String, content: String) -> Result<String, String>;
+    // Remove participant (banned user), remaining participants are notified by system message
+    fn remove_participant(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
 
-    fn remove_message(&mut self, message_id: String) -> Result<(), String>;
+    // Refresh cached participant names from user agents, if they are stale
+    async fn refresh_participant_names(&mut self, correlation_id: Option<String>);
+
+    // Message of given kind (text if none), content of non text messages is optional caption
+    async fn add_message(
+        &mut self,
+        user_id: String,
+        content: String,
+        kind: Option<MessageKind>,
+        correlation_id: Option<String>,
+    ) -> Result<String, ChatError>;
+
+    // Remove message, allowed for message author and chat admin
+    fn remove_message(
+        &mut self,
+        message_id: String,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
+
+    // Enable or disable announcement mode, allowed only for the chat admin (acting user)
+    fn set_announcement_mode(
+        &mut self,
+        enabled: bool,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
 
     fn set_message_like(
         &mut self,
         message_id: String,
         user_id: String,
         like_type: LikeType,
-    ) -> Result<(), String>;
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
 
-    fn remove_message_like(&mut self, message_id: String, user_id: String) -> Result<(), String>;
+    // Remove message like of user, allowed only for the user (acting user)
+    fn remove_message_like(
+        &mut self,
+        message_id: String,
+        user_id: String,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
+
+    // Operation counters of write methods, for admins
+    fn get_diagnostics(&self) -> AgentDiagnostics;
 }
 
 struct ChatAgentImpl {
     _id: String,
     state: Option<Chat>,
+    diagnostics: AgentDiagnostics,
 }
 
 impl ChatAgentImpl {
@@ -189,6 +924,17 @@ impl ChatAgentImpl {
     fn with_state<T>(&mut self, f: impl FnOnce(&mut Chat) -> T) -> T {
         f(self.get_state())
     }
+
+    fn refresh_participant_names_if_stale(&self) {
+        if self
+            .state
+            .as_ref()
+            .is_some_and(|chat| chat.participant_names_stale(clock::now()))
+        {
+            ChatAgentClient::get(self._id.clone())
+                .trigger_refresh_participant_names(trace::propagate());
+        }
+    }
 }
 
 #[agent_implementation]
@@ -197,6 +943,7 @@ impl ChatAgent for ChatAgentImpl {
         ChatAgentImpl {
             _id: id,
             state: None,
+            diagnostics: AgentDiagnostics::default(),
         }
     }
 
@@ -208,120 +955,401 @@ impl ChatAgent for ChatAgentImpl {
         self.state.clone().filter(|chat| chat.matches_query(&query))
     }
 
-    fn init_chat(
+    fn get_chat_view(&self, user_id: String) -> Option<ChatView> {
+        self.state.as_ref().map(|chat| chat.view(&user_id))
+    }
+
+    fn get_chat_view_if_match(&self, user_id: String, query: query::Query) -> Option<ChatView> {
+        self.state
+            .as_ref()
+            .filter(|chat| chat.matches_query(&query))
+            .map(|chat| chat.view(&user_id))
+    }
+
+    fn get_chat_summary(
+        &self,
+        user_id: String,
+        last_read_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Option<ChatSummary> {
+        self.refresh_participant_names_if_stale();
+        self.state
+            .as_ref()
+            .map(|chat| chat.summary(&user_id, last_read_at))
+    }
+
+    fn get_chat_summary_if_match(
+        &self,
+        user_id: String,
+        last_read_at: Option<chrono::DateTime<chrono::Utc>>,
+        query: query::Query,
+    ) -> Option<ChatSummary> {
+        let summary = self
+            .state
+            .as_ref()
+            .filter(|chat| chat.matches_query(&query))
+            .map(|chat| chat.summary(&user_id, last_read_at));
+        if summary.is_some() {
+            self.refresh_participant_names_if_stale();
+        }
+        summary
+    }
+
+    async fn get_messages_around(
+        &self,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        context: u32,
+    ) -> Option<Vec<MessageView>> {
+        let chat = self.state.as_ref()?;
+        let messages = if chat.messages_around_archived(timestamp, context) {
+            let mut messages = ChatArchiveAgentClient::get(self._id.clone())
+                .get_messages()
+                .await;
+            messages.extend(chat.messages.iter().cloned());
+            messages_around(&messages, timestamp, context)
+        } else {
+            chat.get_messages_around(timestamp, context)
+        };
+        Some(messages.iter().map(|m| m.view(None)).collect())
+    }
+
+    async fn get_chat_with_archive(&self) -> Option<Chat> {
+        let chat = self.state.as_ref()?;
+        if chat.archived_message_count == 0 {
+            return Some(chat.clone());
+        }
+        let mut messages = ChatArchiveAgentClient::get(self._id.clone())
+            .get_messages()
+            .await;
+        messages.extend(chat.messages.iter().cloned());
+        Some(Chat {
+            messages,
+            ..chat.clone()
+        })
+    }
+
+    async fn archive_messages(&mut self, correlation_id: Option<String>) -> u32 {
+        trace::init(correlation_id);
+        self.diagnostics.record_call("archive_messages");
+        let cutoff = archive_cutoff(clock::now());
+        let messages = match self.state.as_ref() {
+            Some(chat) => chat.messages[..chat.archivable_messages_count(cutoff)].to_vec(),
+            None => return 0,
+        };
+        if messages.is_empty() {
+            return 0;
+        }
+
+        trace_println!("archive messages - count: {}", messages.len());
+
+        // messages are removed from chat only after they are stored by archive
+        ChatArchiveAgentClient::get(self._id.clone())
+            .add_messages(messages, trace::propagate())
+            .await;
+        self.with_state(|state| state.take_archivable_messages(cutoff).len() as u32)
+    }
+
+    fn get_message_like_counts(&self, message_id: String) -> Option<LikeCounts> {
+        self.state
+            .as_ref()
+            .and_then(|chat| chat.get_message_like_counts(&message_id))
+    }
+
+    fn get_message_like_summary(
+        &self,
+        message_id: String,
+        user_id: Option<String>,
+    ) -> Option<LikeSummary> {
+        self.state
+            .as_ref()
+            .and_then(|chat| chat.get_message_like_summary(&message_id, user_id.as_deref()))
+    }
+
+    fn get_message_likers(
+        &self,
+        message_id: String,
+        like_type: Option<LikeType>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<LikersPage>, ChatError> {
+        self.state
+            .as_ref()
+            .and_then(|chat| chat.messages.iter().find(|m| m.message_id == message_id))
+            .map(|m| LikersPage::from_likes(&m.likes, like_type, cursor, limit))
+            .transpose()
+            .map_err(ChatError::from)
+    }
+
+    async fn init_chat(
         &mut self,
-        participants_ids: HashSet<String>,
+        participants_ids: Vec<String>,
         created_by: String,
         created_at: chrono::DateTime<chrono::Utc>,
-    ) -> Result<(), String> {
-        let mut participants_ids = participants_ids.clone();
-        participants_ids.insert(created_by.clone());
-
-        if self.state.is_some() {
-            Err("Chat already exists".to_string())
-        } else if participants_ids.len() < 2 {
-            Err("Chat must have at least 2 participants".to_string())
-        } else {
-            let state = self.get_state();
-            println!(
-                "init chat - created by: {created_by}, participants: {}",
-                participants_ids.len()
-            );
-            state.created_by = created_by.clone();
-            state.participants.extend(participants_ids.clone());
-            state.created_at = created_at;
-            state.updated_at = created_at;
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
+        let result: Result<(), ChatError> = async {
+            let mut participants_ids = validate_participants(participants_ids)?;
+            participants_ids.insert(created_by.clone());
+            let max_participants = get_operations_config().await.chat_max_participants;
+
+            if self.state.is_some() {
+                Err(ChatError::AlreadyExists("Chat already exists".to_string()))
+            } else if participants_ids.len() < 2 {
+                Err(ChatError::invalid("Chat must have at least 2 participants"))
+            } else {
+                check_participants_count(participants_ids.len(), max_participants)?;
+                let state = self.get_state();
+                trace_println!(
+                    "init chat - created by: {created_by}, participants: {}",
+                    participants_ids.len()
+                );
+                state.created_by = created_by.clone();
+                state.participants.extend(participants_ids.clone());
+                state.created_at = created_at;
+                state.updated_at = created_at;
+
+                execute_add_chat(
+                    state.chat_id.clone(),
+                    created_by.clone(),
+                    created_at,
+                    participants_ids,
+                );
 
-            execute_add_chat(
-                state.chat_id.clone(),
-                created_by.clone(),
-                created_at,
-                participants_ids,
-            );
+                Ok(())
+            }
+        }
+        .await;
+        self.diagnostics.record("init_chat", &result);
+        result
+    }
 
-            Ok(())
+    async fn add_participants(
+        &mut self,
+        participants_ids: Vec<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
+        let result: Result<(), ChatError> = async {
+            let participants_ids = validate_participants(participants_ids)?;
+            let max_participants = get_operations_config().await.chat_max_participants;
+            if self.state.is_none() {
+                Err(ChatError::not_found("Chat not exists"))
+            } else {
+                self.with_state(|state| {
+                    let new_participants_ids: HashSet<String> = participants_ids
+                        .into_iter()
+                        .filter(|id| !state.participants.contains(id))
+                        .collect();
+
+                    if new_participants_ids.is_empty() {
+                        Err(ChatError::invalid("No new participants"))
+                    } else {
+                        check_participants_count(
+                            state.participants.len() + new_participants_ids.len(),
+                            max_participants,
+                        )?;
+                        trace_println!(
+                            "add participants - new participants: {}",
+                            new_participants_ids.len()
+                        );
+                        let old_participants_ids = state.participants.clone();
+
+                        state.participants.extend(new_participants_ids.clone());
+                        state.participant_names_refreshed_at = None;
+                        state.updated_at = clock::now();
+
+                        execute_add_chat(
+                            state.chat_id.clone(),
+                            state.created_by.clone(),
+                            state.updated_at,
+                            new_participants_ids,
+                        );
+
+                        execute_chat_updates(state, old_participants_ids);
+                        Ok(())
+                    }
+                })
+            }
         }
+        .await;
+        self.diagnostics.record("add_participants", &result);
+        result
     }
 
-    fn add_participants(&mut self, participants_ids: HashSet<String>) -> Result<(), String> {
-        if self.state.is_none() {
-            Err("Chat not exists".to_string())
+    fn remove_participant(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
+        let result: Result<(), ChatError> = if self.state.is_none() {
+            Err(ChatError::not_found("Chat not exists"))
         } else {
             self.with_state(|state| {
-                let new_participants_ids: HashSet<String> = participants_ids
-                    .into_iter()
-                    .filter(|id| !state.participants.contains(id))
-                    .collect();
-
-                if new_participants_ids.is_empty() {
-                    Err("No new participants".to_string())
+                if state.remove_participant(&user_id) {
+                    trace_println!("remove participant - user id: {user_id}");
+                    execute_chat_updates(state, state.participants.clone());
+                    Ok(())
                 } else {
-                    println!(
-                        "add participants - new participants: {}",
-                        new_participants_ids.len()
-                    );
-                    let old_participants_ids = state.participants.clone();
+                    Err(ChatError::not_found("Participant not found"))
+                }
+            })
+        };
+        self.diagnostics.record("remove_participant", &result);
+        result
+    }
 
-                    state.participants.extend(new_participants_ids.clone());
-                    state.updated_at = chrono::Utc::now();
+    async fn refresh_participant_names(&mut self, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        self.diagnostics.record_call("refresh_participant_names");
+        let participants = match self.state.as_ref() {
+            Some(chat) if chat.participant_names_stale(clock::now()) => {
+                chat.participants.iter().cloned().collect::<Vec<_>>()
+            }
+            _ => return,
+        };
 
-                    execute_add_chat(
-                        state.chat_id.clone(),
-                        state.created_by.clone(),
-                        state.updated_at,
-                        new_participants_ids,
-                    );
+        trace_println!(
+            "refresh participant names - participants: {}",
+            participants.len()
+        );
 
-                    execute_chat_updates(
-                        state.chat_id.clone(),
-                        old_participants_ids,
-                        state.updated_at,
+        let names = get_user_names(participants).await;
+        self.with_state(|state| state.set_participant_names(names, clock::now()));
+    }
+
+    async fn add_message(
+        &mut self,
+        user_id: String,
+        content: String,
+        kind: Option<MessageKind>,
+        correlation_id: Option<String>,
+    ) -> Result<String, ChatError> {
+        trace::init(correlation_id);
+        let result: Result<String, ChatError> = async {
+            let content = content::sanitize(&content);
+            let kind = kind.unwrap_or_default();
+            if self.state.is_none() {
+                Err(ChatError::not_found("Chat not exists"))
+            } else if self
+                .state
+                .as_ref()
+                .is_some_and(|state| !state.can_add_message(&user_id))
+            {
+                trace_println!("add message - user id: {user_id} - announcement mode");
+                Err(ChatError::Unauthorized(user_id))
+            } else {
+                kind.validate(&content).map_err(ChatError::Invalid)?;
+                check_length(ContentField::MessageContent, &content)?;
+                check_user_not_banned(&user_id).await?;
+                let content = if content.is_empty() {
+                    content
+                } else {
+                    moderate_content(content).await?
+                };
+                self.with_state(|state| {
+                    trace_println!(
+                        "add message - user id: {}, kind: {}, content: {}",
+                        user_id,
+                        kind.label(),
+                        content
                     );
-                    Ok(())
-                }
-            })
+                    if state.messages.len() >= MAX_CHAT_LENGTH {
+                        Err(ChatError::invalid("Max chat length"))
+                    } else {
+                        let id = state.add_message(user_id.clone(), content.clone(), kind);
+                        let mentioned_user_ids = state.get_mentioned_participants(&id);
+                        execute_chat_updates(state, state.participants.clone());
+                        record_interaction(
+                            &user_id,
+                            state.participants.iter().cloned().collect(),
+                            InteractionKind::Message,
+                        );
+                        for mentioned_user_id in mentioned_user_ids {
+                            notify_user(
+                                &mentioned_user_id,
+                                NotificationKind::Mention,
+                                &user_id,
+                                NotificationTarget::chat(&state.chat_id),
+                                content.clone(),
+                            );
+                        }
+                        record_user_activity(&user_id);
+                        if state.archivable_messages_count(archive_cutoff(state.updated_at))
+                            >= ARCHIVE_MIN_BATCH
+                        {
+                            ChatAgentClient::get(state.chat_id.clone())
+                                .trigger_archive_messages(trace::propagate());
+                        }
+                        Ok(id)
+                    }
+                })
+            }
         }
+        .await;
+        self.diagnostics.record("add_message", &result);
+        result
     }
 
-    fn add_message(&mut self, user_id: String, content: String) -> Result<String, String> {
-        if self.state.is_none() {
-            Err("Chat not exists".to_string())
+    fn remove_message(
+        &mut self,
+        message_id: String,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
+        let result: Result<(), ChatError> = if self.state.is_none() {
+            Err(ChatError::not_found("Chat not exists"))
         } else {
             self.with_state(|state| {
-                println!("add message - user id: {}, content: {}", user_id, content);
-                if state.messages.len() >= MAX_CHAT_LENGTH {
-                    Err("Max chat length".to_string())
+                trace_println!(
+                    "remove message - message id: {}, acting user id: {}",
+                    message_id,
+                    acting_user_id
+                );
+                if !state.messages.iter().any(|m| m.message_id == message_id) {
+                    Err(ChatError::not_found("Message not found"))
+                } else if !state.can_remove_message(&message_id, &acting_user_id) {
+                    Err(ChatError::Unauthorized(acting_user_id))
                 } else {
-                    let id = state.add_message(user_id.clone(), content);
-                    execute_chat_updates(
-                        state.chat_id.clone(),
-                        state.participants.clone(),
-                        state.updated_at,
-                    );
-                    Ok(id)
+                    state.remove_message(message_id);
+                    execute_chat_updates(state, state.participants.clone());
+                    Ok(())
                 }
             })
-        }
+        };
+        self.diagnostics.record("remove_message", &result);
+        result
     }
 
-    fn remove_message(&mut self, message_id: String) -> Result<(), String> {
-        if self.state.is_none() {
-            Err("Chat not exists".to_string())
+    fn set_announcement_mode(
+        &mut self,
+        enabled: bool,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
+        let result: Result<(), ChatError> = if self.state.is_none() {
+            Err(ChatError::not_found("Chat not exists"))
         } else {
             self.with_state(|state| {
-                println!("remove message - message id: {}", message_id);
-                if state.remove_message(message_id) {
-                    execute_chat_updates(
-                        state.chat_id.clone(),
-                        state.participants.clone(),
-                        state.updated_at,
-                    );
-                    Ok(())
+                trace_println!(
+                    "set announcement mode - enabled: {enabled}, acting user id: {acting_user_id}"
+                );
+                if !state.is_admin(&acting_user_id) {
+                    Err(ChatError::Unauthorized(acting_user_id))
                 } else {
-                    Err("Message not found".to_string())
+                    if state.announcement_mode != enabled {
+                        state.announcement_mode = enabled;
+                        state.updated_at = clock::now();
+                        execute_chat_updates(state, state.participants.clone());
+                    }
+                    Ok(())
                 }
             })
-        }
+        };
+        self.diagnostics.record("set_announcement_mode", &result);
+        result
     }
 
     fn set_message_like(
@@ -329,54 +1357,451 @@ impl ChatAgent for ChatAgentImpl {
         message_id: String,
         user_id: String,
         like_type: LikeType,
-    ) -> Result<(), String> {
-        if self.state.is_none() {
-            Err("Chat not exists".to_string())
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
+        let result: Result<(), ChatError> = if self.state.is_none() {
+            Err(ChatError::not_found("Chat not exists"))
         } else {
             self.with_state(|state| {
-                println!(
+                trace_println!(
                     "set message like - message id: {}, user id: {}, like type: {}",
-                    message_id, user_id, like_type
+                    message_id,
+                    user_id,
+                    like_type
                 );
                 if state.set_message_like(message_id, user_id, like_type) {
-                    execute_chat_updates(
-                        state.chat_id.clone(),
-                        state.participants.clone(),
-                        state.updated_at,
-                    );
+                    execute_chat_updates(state, state.participants.clone());
                     Ok(())
                 } else {
-                    Err("Message not found".to_string())
+                    Err(ChatError::not_found("Message not found"))
+                }
+            })
+        };
+        self.diagnostics.record("set_message_like", &result);
+        result
+    }
+
+    fn remove_message_like(
+        &mut self,
+        message_id: String,
+        user_id: String,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
+        let result: Result<(), ChatError> = if self.state.is_none() {
+            Err(ChatError::not_found("Chat not exists"))
+        } else if acting_user_id != user_id {
+            trace_println!(
+                "remove message like - user id: {user_id}, not authorized acting user id: {acting_user_id}"
+            );
+            Err(ChatError::Unauthorized(acting_user_id))
+        } else {
+            self.with_state(|state| {
+                trace_println!(
+                    "remove message like - chat id: {}, user id: {}",
+                    message_id,
+                    user_id
+                );
+                if state.remove_message_like(message_id, user_id) {
+                    execute_chat_updates(state, state.participants.clone());
+                    Ok(())
+                } else {
+                    Err(ChatError::not_found("Message not found"))
+                }
+            })
+        };
+        self.diagnostics.record("remove_message_like", &result);
+        result
+    }
+
+    fn get_diagnostics(&self) -> AgentDiagnostics {
+        self.diagnostics.clone()
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<Chat> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+// Archived messages of chat, ordered by creation
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ChatArchive {
+    pub chat_id: String,
+    pub messages: Vec<Message>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ChatArchive {
+    fn new(chat_id: String) -> Self {
+        let now = clock::now();
+        ChatArchive {
+            chat_id,
+            messages: vec![],
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    // Messages already in archive (repeated archival) are skipped
+    fn add_messages(&mut self, messages: Vec<Message>) {
+        let existing: HashSet<String> =
+            self.messages.iter().map(|m| m.message_id.clone()).collect();
+        self.messages.extend(
+            messages
+                .into_iter()
+                .filter(|m| !existing.contains(&m.message_id)),
+        );
+        self.messages.sort_by_key(|m| m.created_at);
+        self.updated_at = clock::now();
+    }
+}
+
+#[agent_definition]
+trait ChatArchiveAgent {
+    fn new(id: String) -> Self;
+
+    fn get_archive(&self) -> Option<ChatArchive>;
+
+    fn get_messages(&self) -> Vec<Message>;
+
+    fn add_messages(&mut self, messages: Vec<Message>, correlation_id: Option<String>) -> u32;
+}
+
+struct ChatArchiveAgentImpl {
+    _id: String,
+    state: Option<ChatArchive>,
+}
+
+#[agent_implementation]
+impl ChatArchiveAgent for ChatArchiveAgentImpl {
+    fn new(id: String) -> Self {
+        ChatArchiveAgentImpl {
+            _id: id,
+            state: None,
+        }
+    }
+
+    fn get_archive(&self) -> Option<ChatArchive> {
+        self.state.clone()
+    }
+
+    fn get_messages(&self) -> Vec<Message> {
+        self.state
+            .as_ref()
+            .map(|archive| archive.messages.clone())
+            .unwrap_or_default()
+    }
+
+    fn add_messages(&mut self, messages: Vec<Message>, correlation_id: Option<String>) -> u32 {
+        trace::init(correlation_id);
+        let archive = self.state.get_or_insert(ChatArchive::new(self._id.clone()));
+        trace_println!(
+            "add messages - chat id: {}, count: {}",
+            archive.chat_id,
+            messages.len()
+        );
+        archive.add_messages(messages);
+        archive.messages.len() as u32
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<ChatArchive> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+fn execute_chat_updates(chat: &Chat, participants_ids: HashSet<String>) {
+    ChatUpdaterAgentClient::get(chat.chat_id.clone()).trigger_chat_updated(
+        ChatUpdate::from(chat),
+        participants_ids,
+        trace::propagate(),
+    );
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ChatUpdate {
+    pub chat_id: String,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub last_message: Option<MessagePreview>,
+    pub message_count: u32,
+}
+
+impl ChatUpdate {
+    fn from(chat: &Chat) -> Self {
+        ChatUpdate {
+            chat_id: chat.chat_id.clone(),
+            updated_at: chat.updated_at,
+            last_message: chat.last_message_preview(),
+            message_count: chat.message_count(),
+        }
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct FailedChatUpdate {
+    pub user_id: String,
+    pub update: ChatUpdate,
+    pub attempts: u32,
+    pub last_error: String,
+    // none if retry attempts are exhausted, delivery can be still retried by reconciliation
+    pub next_retry_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+fn delivery_retry_delay(attempts: u32) -> chrono::Duration {
+    let exponent = attempts.saturating_sub(1).min(20);
+    let delay = DELIVERY_RETRY_BASE_DELAY_SECONDS.saturating_mul(1 << exponent);
+    chrono::Duration::seconds(delay.min(DELIVERY_RETRY_MAX_DELAY_SECONDS))
+}
+
+// Dead-letter queue of chat update deliveries to participants
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ChatUpdateDeliveries {
+    pub chat_id: String,
+    pub failed: Vec<FailedChatUpdate>,
+    pub retry_scheduled_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ChatUpdateDeliveries {
+    fn new(chat_id: String) -> Self {
+        let now = clock::now();
+        ChatUpdateDeliveries {
+            chat_id,
+            failed: vec![],
+            retry_scheduled_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn record_failure(
+        &mut self,
+        user_id: String,
+        update: ChatUpdate,
+        error: String,
+        max_attempts: u32,
+    ) {
+        let now = clock::now();
+        match self.failed.iter_mut().find(|f| f.user_id == user_id) {
+            Some(failed) => {
+                // only latest update of chat is relevant for participant
+                if update.updated_at >= failed.update.updated_at {
+                    failed.update = update;
+                }
+                failed.attempts += 1;
+                failed.last_error = error;
+                failed.next_retry_at = if failed.attempts < max_attempts {
+                    Some(now + delivery_retry_delay(failed.attempts))
+                } else {
+                    None
+                };
+                failed.updated_at = now;
+            }
+            None => {
+                self.failed.push(FailedChatUpdate {
+                    user_id,
+                    update,
+                    attempts: 1,
+                    last_error: error,
+                    next_retry_at: Some(now + delivery_retry_delay(1)),
+                    created_at: now,
+                    updated_at: now,
+                });
+            }
+        }
+        self.updated_at = now;
+    }
+
+    fn record_success(&mut self, user_id: &str, update: &ChatUpdate) {
+        let len = self.failed.len();
+        self.failed
+            .retain(|f| f.user_id != user_id || f.update.updated_at > update.updated_at);
+        if self.failed.len() != len {
+            self.updated_at = clock::now();
+        }
+    }
+
+    fn due_failures(&self, now: chrono::DateTime<chrono::Utc>) -> Vec<FailedChatUpdate> {
+        self.failed
+            .iter()
+            .filter(|f| f.next_retry_at.is_some_and(|at| at <= now))
+            .cloned()
+            .collect()
+    }
+
+    fn next_retry_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.failed.iter().filter_map(|f| f.next_retry_at).min()
+    }
+}
+
+#[agent_definition]
+trait ChatUpdaterAgent {
+    fn new(id: String) -> Self;
+
+    fn get_deliveries(&self) -> ChatUpdateDeliveries;
+
+    async fn chat_updated(
+        &mut self,
+        update: ChatUpdate,
+        participants_ids: HashSet<String>,
+        correlation_id: Option<String>,
+    );
+
+    async fn retry_failed_updates(&mut self, correlation_id: Option<String>);
+
+    async fn reconcile(&mut self, correlation_id: Option<String>) -> u32;
+}
+
+struct ChatUpdaterAgentImpl {
+    state: ChatUpdateDeliveries,
+}
+
+impl ChatUpdaterAgentImpl {
+    async fn deliver(&mut self, deliveries: Vec<(String, ChatUpdate)>) {
+        let config = get_operations_config().await;
+        for chunk in deliveries.chunks(config.delivery_chunk_size()) {
+            let tasks: Vec<_> = chunk
+                .iter()
+                .map(|(user_id, update)| async move {
+                    inject_fault(FaultPath::Fanout, user_id).await?;
+                    UserChatsAgentClient::get(user_id.clone())
+                        .chat_updated(
+                            update.chat_id.clone(),
+                            update.updated_at,
+                            update.last_message.clone(),
+                            update.message_count,
+                            trace::propagate(),
+                        )
+                        .await
+                        .map_err(|e| e.to_string())
+                })
+                .collect();
+            let responses = join_all(tasks).await;
+
+            for ((user_id, update), response) in chunk.iter().zip(responses) {
+                match response {
+                    Ok(()) => self.state.record_success(user_id, update),
+                    Err(error) => {
+                        trace_println!(
+                            "chat update delivery failed - chat id: {}, user id: {user_id}, error: {error}",
+                            update.chat_id
+                        );
+                        self.state.record_failure(
+                            user_id.clone(),
+                            update.clone(),
+                            error.to_string(),
+                            config.delivery_max_attempts,
+                        );
+                    }
                 }
-            })
+            }
         }
+        self.schedule_retry();
     }
 
-    fn remove_message_like(&mut self, message_id: String, user_id: String) -> Result<(), String> {
-        if self.state.is_none() {
-            Err("Chat not exists".to_string())
-        } else {
-            self.with_state(|state| {
-                println!(
-                    "remove message like - chat id: {}, user id: {}",
-                    message_id, user_id
+    fn schedule_retry(&mut self) {
+        if let Some(next_retry_at) = self.state.next_retry_at() {
+            if self
+                .state
+                .retry_scheduled_at
+                .is_none_or(|scheduled_at| scheduled_at > next_retry_at)
+            {
+                trace_println!(
+                    "chat update delivery - chat id: {}, retry scheduled at: {next_retry_at}",
+                    self.state.chat_id
                 );
-                if state.remove_message_like(message_id, user_id) {
-                    execute_chat_updates(
-                        state.chat_id.clone(),
-                        state.participants.clone(),
-                        state.updated_at,
+                ChatUpdaterAgentClient::get(self.state.chat_id.clone())
+                    .schedule_retry_failed_updates(
+                        trace::propagate(),
+                        to_schedule_time(next_retry_at),
                     );
-                    Ok(())
-                } else {
-                    Err("Message not found".to_string())
-                }
-            })
+                self.state.retry_scheduled_at = Some(next_retry_at);
+            }
+        }
+    }
+}
+
+#[agent_implementation]
+impl ChatUpdaterAgent for ChatUpdaterAgentImpl {
+    fn new(id: String) -> Self {
+        Self {
+            state: ChatUpdateDeliveries::new(id),
         }
     }
 
+    fn get_deliveries(&self) -> ChatUpdateDeliveries {
+        self.state.clone()
+    }
+
+    async fn chat_updated(
+        &mut self,
+        update: ChatUpdate,
+        participants_ids: HashSet<String>,
+        correlation_id: Option<String>,
+    ) {
+        trace::init(correlation_id);
+        trace_println!(
+            "chat updated - chat id: {}, participants: {}",
+            update.chat_id,
+            participants_ids.len()
+        );
+        let deliveries = participants_ids
+            .into_iter()
+            .map(|user_id| (user_id, update.clone()))
+            .collect();
+        self.deliver(deliveries).await;
+    }
+
+    async fn retry_failed_updates(&mut self, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        self.state.retry_scheduled_at = None;
+        let due = self.state.due_failures(clock::now());
+        trace_println!(
+            "retry failed updates - chat id: {}, due: {}, failed: {}",
+            self.state.chat_id,
+            due.len(),
+            self.state.failed.len()
+        );
+        let deliveries = due.into_iter().map(|f| (f.user_id, f.update)).collect();
+        self.deliver(deliveries).await;
+    }
+
+    async fn reconcile(&mut self, correlation_id: Option<String>) -> u32 {
+        trace::init(correlation_id);
+        trace_println!(
+            "reconcile - chat id: {}, failed: {}",
+            self.state.chat_id,
+            self.state.failed.len()
+        );
+        let deliveries = self
+            .state
+            .failed
+            .iter()
+            .map(|f| (f.user_id.clone(), f.update.clone()))
+            .collect();
+        self.deliver(deliveries).await;
+        self.state.failed.len() as u32
+    }
+
     async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
-        let data: Option<Chat> = crate::common::snapshot::deserialize(&bytes)?;
+        let data: ChatUpdateDeliveries = crate::common::snapshot::deserialize(&bytes)?;
         self.state = data;
         Ok(())
     }
@@ -386,16 +1811,6 @@ impl ChatAgent for ChatAgentImpl {
     }
 }
 
-fn execute_chat_updates(
-    chat_id: String,
-    participants_ids: HashSet<String>,
-    updated_at: chrono::DateTime<chrono::Utc>,
-) {
-    for p_id in participants_ids {
-        UserChatsAgentClient::get(p_id.clone()).trigger_chat_updated(chat_id.clone(), updated_at);
-    }
-}
-
 fn execute_add_chat(
     chat_id: String,
     created_by: String,
@@ -408,35 +1823,55 @@ fn execute_add_chat(
                 chat_id.clone(),
                 created_by.clone(),
                 created_at,
+                trace::propagate(),
             );
         }
     }
 }
 
-pub async fn fetch_chats_by_ids(chat_ids: &[String]) -> Vec<Chat> {
-    let mut result: Vec<Chat> = vec![];
+pub async fn fetch_chats_by_ids(chat_ids: &[String]) -> Fetched<Chat> {
+    let mut result: Fetched<Chat> = Fetched::default();
 
-    for chunk in chat_ids.chunks(10) {
+    for chunk in chat_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
         let clients = chunk
             .iter()
             .map(|chat_id| ChatAgentClient::get(chat_id.clone()))
             .collect::<Vec<_>>();
 
         let tasks: Vec<_> = clients.iter().map(|client| client.get_chat()).collect();
-        let responses = join_all(tasks).await;
+        result.extend(join_all_with_timeout(chunk, tasks).await);
+    }
+
+    result
+}
+
+pub async fn fetch_chat_views_by_ids(chat_ids: &[String], user_id: &str) -> Fetched<ChatView> {
+    let mut result: Fetched<ChatView> = Fetched::default();
 
-        let chunk_result: Vec<Chat> = responses.into_iter().flatten().collect();
+    for chunk in chat_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
+        let clients = chunk
+            .iter()
+            .map(|chat_id| ChatAgentClient::get(chat_id.clone()))
+            .collect::<Vec<_>>();
 
-        result.extend(chunk_result);
+        let tasks: Vec<_> = clients
+            .iter()
+            .map(|client| client.get_chat_view(user_id.to_string()))
+            .collect();
+        result.extend(join_all_with_timeout(chunk, tasks).await);
     }
 
     result
 }
 
-pub async fn fetch_chats_by_ids_and_query(chat_ids: &[String], query: query::Query) -> Vec<Chat> {
-    let mut result: Vec<Chat> = vec![];
+pub async fn fetch_chat_views_by_ids_and_query(
+    chat_ids: &[String],
+    user_id: &str,
+    query: query::Query,
+) -> Fetched<ChatView> {
+    let mut result: Fetched<ChatView> = Fetched::default();
 
-    for chunk in chat_ids.chunks(10) {
+    for chunk in chat_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
         let clients = chunk
             .iter()
             .map(|chat_id| ChatAgentClient::get(chat_id.clone()))
@@ -444,13 +1879,36 @@ pub async fn fetch_chats_by_ids_and_query(chat_ids: &[String], query: query::Que
 
         let tasks: Vec<_> = clients
             .iter()
-            .map(|client| client.get_chat_if_match(query.clone()))
+            .map(|client| client.get_chat_view_if_match(user_id.to_string(), query.clone()))
             .collect();
-        let responses = join_all(tasks).await;
+        result.extend(join_all_with_timeout(chunk, tasks).await);
+    }
+
+    result
+}
+
+// Summaries of chats (chat id, last read of the user) matching the query
+pub async fn fetch_chat_summaries_by_ids_and_query(
+    chats: &[(String, Option<chrono::DateTime<chrono::Utc>>)],
+    user_id: &str,
+    query: query::Query,
+) -> Fetched<ChatSummary> {
+    let mut result: Fetched<ChatSummary> = Fetched::default();
 
-        let chunk_result: Vec<Chat> = responses.into_iter().flatten().collect();
+    for chunk in chats.chunks(get_operations_config().await.fetch_chunk_size()) {
+        let clients = chunk
+            .iter()
+            .map(|(chat_id, last_read_at)| (ChatAgentClient::get(chat_id.clone()), *last_read_at))
+            .collect::<Vec<_>>();
 
-        result.extend(chunk_result);
+        let tasks: Vec<_> = clients
+            .iter()
+            .map(|(client, last_read_at)| {
+                client.get_chat_summary_if_match(user_id.to_string(), *last_read_at, query.clone())
+            })
+            .collect();
+        let chat_ids: Vec<String> = chunk.iter().map(|(chat_id, _)| chat_id.clone()).collect();
+        result.extend(join_all_with_timeout(&chat_ids, tasks).await);
     }
 
     result
@@ -459,6 +1917,7 @@ pub async fn fetch_chats_by_ids_and_query(chat_ids: &[String], query: query::Que
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::snapshot;
     use crate::common::LikeType;
 
     fn create_test_chat() -> Chat {
@@ -479,13 +1938,126 @@ mod tests {
         assert_eq!(chat.created_at, chat.updated_at);
     }
 
+    #[test]
+    fn test_chat_summary() {
+        let mut chat = create_test_chat();
+        chat.add_message("user1".to_string(), "Hello".to_string(), MessageKind::Text);
+        let read_at = clock::now();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        chat.add_message("user2".to_string(), "Hi".to_string(), MessageKind::Text);
+        chat.add_message(
+            "user1".to_string(),
+            "How are you?".to_string(),
+            MessageKind::Text,
+        );
+
+        let summary = chat.summary("user1", Some(read_at));
+        assert_eq!(summary.message_count, 3);
+        assert_eq!(summary.unread_count, 1);
+        assert_eq!(
+            summary.last_message.map(|m| m.snippet),
+            Some("How are you?".to_string())
+        );
+
+        assert_eq!(chat.summary("user2", None).unread_count, 2);
+    }
+
+    #[test]
+    fn test_last_message_preview() {
+        let mut chat = create_test_chat();
+        assert!(chat.last_message_preview().is_none());
+
+        chat.add_message("user1".to_string(), "first".to_string(), MessageKind::Text);
+        let message_id = chat.add_message(
+            "user2".to_string(),
+            "second\n  message".to_string(),
+            MessageKind::Text,
+        );
+
+        let preview = chat.last_message_preview().unwrap();
+        assert_eq!(preview.message_id, message_id);
+        assert_eq!(preview.snippet, "second message");
+        assert_eq!(preview.created_by, "user2");
+    }
+
+    #[test]
+    fn test_message_preview_truncated() {
+        let content = "a".repeat(MESSAGE_PREVIEW_LENGTH + 10);
+        let message = Message::new("user1".to_string(), content);
+
+        let preview = message.preview();
+        assert_eq!(preview.snippet.chars().count(), MESSAGE_PREVIEW_LENGTH + 1);
+        assert!(preview.snippet.ends_with('…'));
+    }
+
+    fn media(url: &str) -> MediaMetadata {
+        MediaMetadata {
+            url: url.to_string(),
+            width: 100,
+            height: 50,
+            alt_text: None,
+        }
+    }
+
+    #[test]
+    fn test_message_kind_validate() {
+        assert!(MessageKind::Text.validate("hello").is_ok());
+        assert!(MessageKind::Text.validate("").is_err());
+        assert!(MessageKind::System.validate("hello").is_err());
+
+        let sticker = MessageKind::Sticker(StickerMetadata {
+            pack_id: "pack".to_string(),
+            sticker_id: "".to_string(),
+        });
+        assert!(sticker.validate("").is_err());
+
+        assert!(MessageKind::Image(media("https://example.com/a.png"))
+            .validate("")
+            .is_ok());
+        assert!(MessageKind::Gif(media("ftp://example.com/a.gif"))
+            .validate("")
+            .is_err());
+        let mut zero = media("https://example.com/a.png");
+        zero.width = 0;
+        assert!(MessageKind::Image(zero).validate("caption").is_err());
+
+        let file = FileMetadata {
+            url: "https://example.com/doc.pdf".to_string(),
+            name: "doc.pdf".to_string(),
+            size_bytes: 1024,
+            mime_type: Some("application/pdf".to_string()),
+        };
+        assert!(MessageKind::File(file.clone()).validate("").is_ok());
+        let too_large = FileMetadata {
+            size_bytes: MESSAGE_FILE_MAX_SIZE_BYTES + 1,
+            ..file
+        };
+        assert!(MessageKind::File(too_large).validate("").is_err());
+    }
+
+    #[test]
+    fn test_message_preview_of_kind() {
+        let mut chat = create_test_chat();
+        chat.add_message(
+            "user1".to_string(),
+            "".to_string(),
+            MessageKind::Image(media("https://example.com/a.png")),
+        );
+
+        assert_eq!(chat.last_message_preview().unwrap().snippet, "[Image]");
+    }
+
     #[test]
     fn test_add_message() {
         let mut chat = create_test_chat();
         let initial_updated_at = chat.updated_at;
 
         // Add first message
-        let message_id1 = chat.add_message("user1".to_string(), "Hello world".to_string());
+        let message_id1 = chat.add_message(
+            "user1".to_string(),
+            "Hello world".to_string(),
+            MessageKind::Text,
+        );
 
         assert_eq!(chat.messages.len(), 1);
         assert_eq!(chat.messages[0].message_id, message_id1);
@@ -495,7 +2067,11 @@ mod tests {
         assert!(chat.updated_at > initial_updated_at);
 
         // Add second message
-        let message_id2 = chat.add_message("user2".to_string(), "Hi there".to_string());
+        let message_id2 = chat.add_message(
+            "user2".to_string(),
+            "Hi there".to_string(),
+            MessageKind::Text,
+        );
 
         assert_eq!(chat.messages.len(), 2);
         assert_eq!(chat.messages[1].message_id, message_id2);
@@ -504,10 +2080,227 @@ mod tests {
         assert_ne!(message_id1, message_id2);
     }
 
+    fn create_test_update(chat: &Chat) -> ChatUpdate {
+        ChatUpdate::from(chat)
+    }
+
+    #[test]
+    fn test_delivery_retry_delay() {
+        assert_eq!(delivery_retry_delay(1), chrono::Duration::seconds(5));
+        assert_eq!(delivery_retry_delay(2), chrono::Duration::seconds(10));
+        assert_eq!(delivery_retry_delay(4), chrono::Duration::seconds(40));
+        assert_eq!(
+            delivery_retry_delay(30),
+            chrono::Duration::seconds(DELIVERY_RETRY_MAX_DELAY_SECONDS)
+        );
+    }
+
+    const DELIVERY_MAX_ATTEMPTS: u32 = 10;
+
+    #[test]
+    fn test_deliveries_record_failure() {
+        let chat = create_test_chat();
+        let mut deliveries = ChatUpdateDeliveries::new(chat.chat_id.clone());
+        let update = create_test_update(&chat);
+
+        deliveries.record_failure(
+            "user2".to_string(),
+            update.clone(),
+            "error".to_string(),
+            DELIVERY_MAX_ATTEMPTS,
+        );
+        assert_eq!(deliveries.failed.len(), 1);
+        assert_eq!(deliveries.failed[0].attempts, 1);
+        assert!(deliveries.next_retry_at().is_some());
+        assert!(deliveries.due_failures(clock::now()).is_empty());
+        assert_eq!(
+            deliveries
+                .due_failures(clock::now() + chrono::Duration::minutes(1))
+                .len(),
+            1
+        );
+
+        for _ in 1..DELIVERY_MAX_ATTEMPTS {
+            deliveries.record_failure(
+                "user2".to_string(),
+                update.clone(),
+                "error".to_string(),
+                DELIVERY_MAX_ATTEMPTS,
+            );
+        }
+        assert_eq!(deliveries.failed.len(), 1);
+        assert_eq!(deliveries.failed[0].attempts, DELIVERY_MAX_ATTEMPTS);
+        assert!(deliveries.failed[0].next_retry_at.is_none());
+        assert!(deliveries.next_retry_at().is_none());
+    }
+
+    #[test]
+    fn test_deliveries_record_success() {
+        let mut chat = create_test_chat();
+        let mut deliveries = ChatUpdateDeliveries::new(chat.chat_id.clone());
+        let old_update = create_test_update(&chat);
+        chat.add_message("user1".to_string(), "Hello".to_string(), MessageKind::Text);
+        let new_update = create_test_update(&chat);
+
+        deliveries.record_failure(
+            "user2".to_string(),
+            new_update.clone(),
+            "error".to_string(),
+            DELIVERY_MAX_ATTEMPTS,
+        );
+        deliveries.record_failure(
+            "user1".to_string(),
+            new_update.clone(),
+            "error".to_string(),
+            DELIVERY_MAX_ATTEMPTS,
+        );
+
+        // delivery of older update does not resolve failed newer update
+        deliveries.record_success("user2", &old_update);
+        assert_eq!(deliveries.failed.len(), 2);
+
+        deliveries.record_success("user2", &new_update);
+        assert_eq!(deliveries.failed.len(), 1);
+        assert_eq!(deliveries.failed[0].user_id, "user1");
+    }
+
+    #[test]
+    fn test_get_messages_around() {
+        let mut chat = create_test_chat();
+        let start = clock::now();
+        for i in 0..10 {
+            chat.add_message(
+                "user1".to_string(),
+                format!("message {i}"),
+                MessageKind::Text,
+            );
+            chat.messages[i].created_at = start + chrono::Duration::minutes(i as i64);
+        }
+
+        let messages = chat.get_messages_around(start + chrono::Duration::minutes(5), 2);
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec!["message 3", "message 4", "message 5", "message 6"]
+        );
+
+        let messages = chat.get_messages_around(start - chrono::Duration::minutes(1), 3);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].content, "message 0");
+
+        let messages = chat.get_messages_around(start + chrono::Duration::hours(1), 3);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[2].content, "message 9");
+
+        assert!(chat.get_messages_around(start, 0).is_empty());
+    }
+
+    #[test]
+    fn test_remove_participant() {
+        let mut chat = create_test_chat();
+        chat.participant_names
+            .insert("user2".to_string(), "Bob".to_string());
+
+        assert!(chat.remove_participant("user2"));
+        assert!(!chat.participants.contains("user2"));
+        assert!(!chat.participant_names.contains_key("user2"));
+        assert_eq!(chat.messages.len(), 1);
+        assert_eq!(chat.messages[0].kind, MessageKind::System);
+        assert_eq!(chat.messages[0].content, "Bob left the chat");
+
+        assert!(!chat.remove_participant("user2"));
+        assert_eq!(chat.messages.len(), 1);
+    }
+
+    #[test]
+    fn test_take_archivable_messages() {
+        let mut chat = create_test_chat();
+        let now = clock::now();
+        let cutoff = archive_cutoff(now);
+        for i in 0..5 {
+            chat.add_message(
+                "user1".to_string(),
+                format!("message {i}"),
+                MessageKind::Text,
+            );
+            let created_at = cutoff - chrono::Duration::days(5 - i as i64);
+            chat.messages[i].created_at = created_at;
+            chat.messages[i].updated_at = created_at;
+        }
+        // recently liked message stops archival of newer messages
+        chat.messages[2].updated_at = now;
+
+        assert_eq!(chat.archivable_messages_count(cutoff), 2);
+        let archived = chat.take_archivable_messages(cutoff);
+        assert_eq!(archived.len(), 2);
+        assert_eq!(archived[0].content, "message 0");
+        assert_eq!(chat.messages.len(), 3);
+        assert_eq!(chat.messages[0].content, "message 2");
+        assert_eq!(chat.archived_message_count, 2);
+        assert_eq!(chat.summary("user2", Some(now)).message_count, 5);
+
+        assert!(chat.messages_around_archived(cutoff - chrono::Duration::days(10), 1));
+        assert!(!chat.messages_around_archived(now, 1));
+    }
+
+    #[test]
+    fn test_chat_archive_add_messages() {
+        let mut chat = create_test_chat();
+        chat.add_message("user1".to_string(), "a".to_string(), MessageKind::Text);
+        chat.add_message("user1".to_string(), "b".to_string(), MessageKind::Text);
+
+        let mut archive = ChatArchive::new(chat.chat_id.clone());
+        archive.add_messages(vec![chat.messages[1].clone()]);
+        archive.add_messages(chat.messages.clone());
+        assert_eq!(archive.messages.len(), 2);
+        assert_eq!(archive.messages[0].content, "a");
+    }
+
+    #[test]
+    fn test_validate_participants() {
+        let ids = validate_participants(vec!["user1".to_string(), "user2".to_string()]).unwrap();
+        assert_eq!(ids.len(), 2);
+
+        let result = validate_participants(vec![
+            "user1".to_string(),
+            " ".to_string(),
+            "user2".to_string(),
+            "user1".to_string(),
+        ]);
+        assert_eq!(
+            result,
+            Err(ChatError::InvalidParticipants(vec![
+                InvalidParticipant {
+                    index: 1,
+                    participant_id: " ".to_string(),
+                    issue: ParticipantIssue::Empty,
+                },
+                InvalidParticipant {
+                    index: 3,
+                    participant_id: "user1".to_string(),
+                    issue: ParticipantIssue::Duplicate,
+                },
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_check_participants_count() {
+        assert!(check_participants_count(3, 3).is_ok());
+        assert_eq!(
+            check_participants_count(4, 3),
+            Err(ChatError::TooManyParticipants(3))
+        );
+    }
+
     #[test]
     fn test_remove_message_success() {
         let mut chat = create_test_chat();
-        let message_id = chat.add_message("user1".to_string(), "Test message".to_string());
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "Test message".to_string(),
+            MessageKind::Text,
+        );
         let initial_updated_at = chat.updated_at;
 
         // Remove existing message
@@ -518,6 +2311,33 @@ mod tests {
         assert!(chat.updated_at > initial_updated_at);
     }
 
+    #[test]
+    fn test_can_remove_message() {
+        let mut chat = create_test_chat();
+        chat.participants.insert("user3".to_string());
+        let message_id = chat.add_message(
+            "user2".to_string(),
+            "Test message".to_string(),
+            MessageKind::Text,
+        );
+
+        assert!(chat.can_remove_message(&message_id, "user2"));
+        assert!(chat.can_remove_message(&message_id, "user1"));
+        assert!(!chat.can_remove_message(&message_id, "user3"));
+    }
+
+    #[test]
+    fn test_can_add_message_announcement_mode() {
+        let mut chat = create_test_chat();
+        assert!(chat.can_add_message("user1"));
+        assert!(chat.can_add_message("user2"));
+
+        chat.announcement_mode = true;
+        assert!(chat.can_add_message("user1"));
+        assert!(!chat.can_add_message("user2"));
+        assert!(chat.view("user2").announcement_mode);
+    }
+
     #[test]
     fn test_remove_message_not_found() {
         let mut chat = create_test_chat();
@@ -534,9 +2354,21 @@ mod tests {
     #[test]
     fn test_remove_message_from_multiple() {
         let mut chat = create_test_chat();
-        let message_id1 = chat.add_message("user1".to_string(), "Message 1".to_string());
-        let message_id2 = chat.add_message("user2".to_string(), "Message 2".to_string());
-        let message_id3 = chat.add_message("user1".to_string(), "Message 3".to_string());
+        let message_id1 = chat.add_message(
+            "user1".to_string(),
+            "Message 1".to_string(),
+            MessageKind::Text,
+        );
+        let message_id2 = chat.add_message(
+            "user2".to_string(),
+            "Message 2".to_string(),
+            MessageKind::Text,
+        );
+        let message_id3 = chat.add_message(
+            "user1".to_string(),
+            "Message 3".to_string(),
+            MessageKind::Text,
+        );
 
         assert_eq!(chat.messages.len(), 3);
 
@@ -552,7 +2384,11 @@ mod tests {
     #[test]
     fn test_set_message_like_success() {
         let mut chat = create_test_chat();
-        let message_id = chat.add_message("user1".to_string(), "Test message".to_string());
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "Test message".to_string(),
+            MessageKind::Text,
+        );
         let initial_updated_at = chat.updated_at;
 
         // Add a like
@@ -585,7 +2421,11 @@ mod tests {
     #[test]
     fn test_set_multiple_likes() {
         let mut chat = create_test_chat();
-        let message_id = chat.add_message("user1".to_string(), "Test message".to_string());
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "Test message".to_string(),
+            MessageKind::Text,
+        );
 
         // Add multiple likes from different users
         let result1 =
@@ -603,7 +2443,11 @@ mod tests {
     #[test]
     fn test_override_like() {
         let mut chat = create_test_chat();
-        let message_id = chat.add_message("user1".to_string(), "Test message".to_string());
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "Test message".to_string(),
+            MessageKind::Text,
+        );
 
         // Add initial like
         let result1 =
@@ -622,7 +2466,11 @@ mod tests {
     #[test]
     fn test_remove_message_like_success() {
         let mut chat = create_test_chat();
-        let message_id = chat.add_message("user1".to_string(), "Test message".to_string());
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "Test message".to_string(),
+            MessageKind::Text,
+        );
 
         // Add a like first
         chat.set_message_like(message_id.clone(), "user2".to_string(), LikeType::Like);
@@ -642,7 +2490,11 @@ mod tests {
     #[test]
     fn test_remove_message_like_not_found() {
         let mut chat = create_test_chat();
-        let message_id = chat.add_message("user1".to_string(), "Test message".to_string());
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "Test message".to_string(),
+            MessageKind::Text,
+        );
         let initial_updated_at = chat.updated_at;
 
         // Try to remove like from non-existent message
@@ -672,9 +2524,14 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_get_then_check)]
     fn test_like_operations_integration() {
         let mut chat = create_test_chat();
-        let message_id = chat.add_message("user1".to_string(), "Test message".to_string());
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "Test message".to_string(),
+            MessageKind::Text,
+        );
 
         // Add multiple likes
         assert!(chat.set_message_like(message_id.clone(), "user2".to_string(), LikeType::Like));
@@ -713,9 +2570,14 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn test_all_like_types() {
         let mut chat = create_test_chat();
-        let message_id = chat.add_message("user1".to_string(), "Test message".to_string());
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "Test message".to_string(),
+            MessageKind::Text,
+        );
 
         let like_types = vec![
             LikeType::Like,
@@ -742,10 +2604,111 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_message_like_counts() {
+        let mut chat = create_test_chat();
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "Test message".to_string(),
+            MessageKind::Text,
+        );
+
+        chat.set_message_like(message_id.clone(), "user2".to_string(), LikeType::Funny);
+        chat.set_message_like(message_id.clone(), "user3".to_string(), LikeType::Love);
+
+        let counts = chat.get_message_like_counts(&message_id).unwrap();
+        assert_eq!(counts.funny, 1);
+        assert_eq!(counts.love, 1);
+        assert_eq!(counts.total, 2);
+
+        assert!(chat.get_message_like_counts("unknown").is_none());
+    }
+
+    #[test]
+    fn test_mentioned_participants() {
+        let mut chat = create_test_chat();
+        chat.participants.insert("user3".to_string());
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "hi @user3 and @user2, @stranger and @user1".to_string(),
+            MessageKind::Text,
+        );
+        let message = chat.messages.last().unwrap();
+        assert_eq!(
+            message.mentions,
+            vec!["user3", "user2", "stranger", "user1"]
+        );
+        assert_eq!(
+            chat.get_mentioned_participants(&message_id),
+            vec!["user3", "user2"]
+        );
+
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "mail user2@mail.com".to_string(),
+            MessageKind::Text,
+        );
+        assert!(chat.get_mentioned_participants(&message_id).is_empty());
+    }
+
+    #[test]
+    fn test_participant_names() {
+        let mut chat = create_test_chat();
+        let now = clock::now();
+        assert!(chat.participant_names_stale(now));
+
+        chat.set_participant_names(
+            HashMap::from([
+                ("user1".to_string(), "Alice".to_string()),
+                ("user3".to_string(), "Carol".to_string()),
+            ]),
+            now,
+        );
+        assert!(!chat.participant_names_stale(now));
+        assert!(chat.participant_names_stale(
+            now + chrono::Duration::seconds(PARTICIPANT_NAMES_TTL_SECONDS + 1)
+        ));
+
+        let summary = chat.summary("user1", None);
+        assert_eq!(summary.participant_names.len(), 1);
+        assert_eq!(
+            summary.participant_names.get("user1"),
+            Some(&"Alice".to_string())
+        );
+    }
+
+    #[test]
+    fn test_chat_view() {
+        let mut chat = create_test_chat();
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "Test message".to_string(),
+            MessageKind::Text,
+        );
+        chat.set_message_like(message_id.clone(), "user2".to_string(), LikeType::Funny);
+        chat.set_message_like(message_id.clone(), "user1".to_string(), LikeType::Love);
+
+        let view = chat.view("user2");
+        assert_eq!(view.chat_id, chat.chat_id);
+        assert_eq!(view.messages.len(), 1);
+        let message = &view.messages[0];
+        assert_eq!(message.message_id, message_id);
+        assert_eq!(message.like_counts.funny, 1);
+        assert_eq!(message.like_counts.love, 1);
+        assert_eq!(message.like_counts.total, 2);
+        assert_eq!(message.own_like, Some(LikeType::Funny));
+
+        assert_eq!(chat.view("user3").messages[0].own_like, None);
+    }
+
     #[test]
     fn test_chat_matches_query_basic() {
         let mut chat = create_test_chat();
-        chat.add_message("user1".to_string(), "Hello world from Rust".to_string());
+        chat.add_message(
+            "user1".to_string(),
+            "Hello world from Rust".to_string(),
+            MessageKind::Text,
+        );
 
         let query = query::Query::new("Hello");
         assert!(chat.matches_query(&query));
@@ -757,7 +2720,11 @@ mod tests {
     #[test]
     fn test_chat_matches_query_chat_id() {
         let mut chat = create_test_chat();
-        chat.add_message("user1".to_string(), "Hello world".to_string());
+        chat.add_message(
+            "user1".to_string(),
+            "Hello world".to_string(),
+            MessageKind::Text,
+        );
 
         let query = query::Query::new("chat-id:test-chat-1");
         assert!(chat.matches_query(&query));
@@ -769,7 +2736,11 @@ mod tests {
     #[test]
     fn test_chat_matches_query_created_by() {
         let mut chat = create_test_chat();
-        chat.add_message("user1".to_string(), "Hello world".to_string());
+        chat.add_message(
+            "user1".to_string(),
+            "Hello world".to_string(),
+            MessageKind::Text,
+        );
 
         let query = query::Query::new("created-by:user1");
         assert!(chat.matches_query(&query));
@@ -781,7 +2752,11 @@ mod tests {
     #[test]
     fn test_chat_matches_query_participants() {
         let mut chat = create_test_chat();
-        chat.add_message("user1".to_string(), "Hello world".to_string());
+        chat.add_message(
+            "user1".to_string(),
+            "Hello world".to_string(),
+            MessageKind::Text,
+        );
 
         let query = query::Query::new("participants:user1");
         assert!(chat.matches_query(&query));
@@ -796,8 +2771,16 @@ mod tests {
     #[test]
     fn test_chat_matches_query_message_content() {
         let mut chat = create_test_chat();
-        chat.add_message("user1".to_string(), "Hello world from Rust".to_string());
-        chat.add_message("user2".to_string(), "Python programming".to_string());
+        chat.add_message(
+            "user1".to_string(),
+            "Hello world from Rust".to_string(),
+            MessageKind::Text,
+        );
+        chat.add_message(
+            "user2".to_string(),
+            "Python programming".to_string(),
+            MessageKind::Text,
+        );
 
         let query = query::Query::new("Rust");
         assert!(chat.matches_query(&query)); // Matches first message
@@ -812,7 +2795,11 @@ mod tests {
     #[test]
     fn test_chat_matches_query_multiple_filters() {
         let mut chat = create_test_chat();
-        chat.add_message("user1".to_string(), "Hello world".to_string());
+        chat.add_message(
+            "user1".to_string(),
+            "Hello world".to_string(),
+            MessageKind::Text,
+        );
 
         let query = query::Query::new("chat-id:test-chat-1 created-by:user1");
         assert!(chat.matches_query(&query));
@@ -824,9 +2811,48 @@ mod tests {
     #[test]
     fn test_chat_matches_query_wildcard() {
         let mut chat = create_test_chat();
-        chat.add_message("user1".to_string(), "Hello world".to_string());
+        chat.add_message(
+            "user1".to_string(),
+            "Hello world".to_string(),
+            MessageKind::Text,
+        );
 
         let query = query::Query::new("*");
         assert!(chat.matches_query(&query)); // Wildcard matches all
     }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut chat = create_test_chat();
+        let message_id = chat.add_message(
+            "user1".to_string(),
+            "Hello @user2".to_string(),
+            MessageKind::default(),
+        );
+        chat.add_message(
+            "user2".to_string(),
+            "Hi".to_string(),
+            MessageKind::default(),
+        );
+        snapshot::assert_round_trip(&Some(chat.clone()));
+
+        let mut archive = ChatArchive::new(chat.chat_id.clone());
+        archive.add_messages(
+            chat.messages
+                .iter()
+                .filter(|m| m.message_id == message_id)
+                .cloned()
+                .collect(),
+        );
+        snapshot::assert_round_trip(&Some(archive));
+
+        let mut deliveries = ChatUpdateDeliveries::new(chat.chat_id.clone());
+        deliveries.record_failure(
+            "user2".to_string(),
+            create_test_update(&chat),
+            "error".to_string(),
+            DELIVERY_MAX_ATTEMPTS,
+        );
+        snapshot::assert_round_trip(&deliveries);
+    }
 }
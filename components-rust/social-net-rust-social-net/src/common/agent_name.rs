@@ -0,0 +1,169 @@
+use crate::common::namespace::{agent_name_prefix, get_namespace};
+use golem_rust::bindings::golem::api::host::{
+    get_self_metadata, AgentAllFilter, AgentAnyFilter, AgentNameFilter, AgentPropertyFilter,
+    GetAgents, StringFilterComparator,
+};
+
+// Agent name in form: agent-type(parameter, ...), string parameters are quoted ("a\"b")
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgentName {
+    pub agent_type: String,
+    pub parameters: Vec<String>,
+}
+
+impl AgentName {
+    pub fn parse(name: &str) -> Option<AgentName> {
+        let (agent_type, rest) = name.trim().split_once('(')?;
+        let agent_type = agent_type.trim();
+        if agent_type.is_empty() {
+            return None;
+        }
+        let parameters = parse_parameters(rest.strip_suffix(')')?)?;
+
+        Some(AgentName {
+            agent_type: agent_type.to_string(),
+            parameters,
+        })
+    }
+
+    // Single parameter of agent with given type
+    pub fn parameter_of(&self, agent_type: &str) -> Option<String> {
+        match self.parameters.as_slice() {
+            [parameter] if self.agent_type == agent_type => Some(parameter.clone()),
+            _ => None,
+        }
+    }
+}
+
+// Comma separated parameters, quoted parameters are unescaped
+fn parse_parameters(value: &str) -> Option<Vec<String>> {
+    let mut result = vec![];
+    let mut chars = value.trim().chars().peekable();
+
+    while chars.peek().is_some() {
+        let mut parameter = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next()? {
+                    '\\' => parameter.push(chars.next()?),
+                    '"' => break,
+                    c => parameter.push(c),
+                }
+            }
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+        } else {
+            while let Some(c) = chars.peek().filter(|c| **c != ',') {
+                parameter.push(*c);
+                chars.next();
+            }
+            parameter = parameter.trim().to_string();
+            if parameter.is_empty() {
+                return None;
+            }
+        }
+        result.push(parameter);
+
+        match chars.next() {
+            Some(',') => {
+                while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                    chars.next();
+                }
+                chars.peek()?;
+            }
+            None => break,
+            Some(_) => return None,
+        }
+    }
+
+    Some(result)
+}
+
+// Parse string parameter of agent id in form: agent-type("parameter")
+pub fn parse_agent_id_parameter(agent_id: &str, agent_type: &str) -> Option<String> {
+    AgentName::parse(agent_id)?.parameter_of(agent_type)
+}
+
+// Ids (constructor parameter) of all existing agents of given type in this component and namespace
+pub fn get_agent_ids(agent_type: &str) -> Vec<String> {
+    let component_id = get_self_metadata().agent_id.component_id;
+    let filter = AgentAnyFilter {
+        filters: vec![AgentAllFilter {
+            filters: vec![AgentPropertyFilter::Name(AgentNameFilter {
+                comparator: StringFilterComparator::StartsWith,
+                value: agent_name_prefix(get_namespace(), agent_type),
+            })],
+        }],
+    };
+
+    let get_agents = GetAgents::new(component_id, Some(&filter), false);
+    let mut result = vec![];
+
+    while let Some(agents) = get_agents.get_next() {
+        result.extend(
+            agents
+                .into_iter()
+                .filter_map(|a| parse_agent_id_parameter(&a.agent_id.agent_id, agent_type)),
+        );
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_agent_id_parameter() {
+        assert_eq!(
+            parse_agent_id_parameter("post-agent(\"p1\")", "post-agent"),
+            Some("p1".to_string())
+        );
+        assert_eq!(
+            parse_agent_id_parameter("chat-agent(\"a\\\"b\")", "chat-agent"),
+            Some("a\"b".to_string())
+        );
+        assert_eq!(
+            parse_agent_id_parameter("user-agent(\"a(b),c\")", "user-agent"),
+            Some("a(b),c".to_string())
+        );
+        assert_eq!(
+            parse_agent_id_parameter("user-posts-agent(\"u1\")", "post-agent"),
+            None
+        );
+        assert_eq!(parse_agent_id_parameter("post-agent()", "post-agent"), None);
+    }
+
+    #[test]
+    fn test_parse_agent_name() {
+        assert_eq!(
+            AgentName::parse("user-index-agent(3)"),
+            Some(AgentName {
+                agent_type: "user-index-agent".to_string(),
+                parameters: vec!["3".to_string()],
+            })
+        );
+        assert_eq!(
+            AgentName::parse("hashtag-agent( \"rust\" , 2)"),
+            Some(AgentName {
+                agent_type: "hashtag-agent".to_string(),
+                parameters: vec!["rust".to_string(), "2".to_string()],
+            })
+        );
+        assert_eq!(
+            AgentName::parse("timelines-updater-agent()"),
+            Some(AgentName {
+                agent_type: "timelines-updater-agent".to_string(),
+                parameters: vec![],
+            })
+        );
+        assert_eq!(AgentName::parse("post-agent(\"p1)"), None);
+        assert_eq!(AgentName::parse("post-agent(\"p1\" x)"), None);
+        assert_eq!(AgentName::parse("post-agent(p1,)"), None);
+        assert_eq!(AgentName::parse("(p1)"), None);
+        assert_eq!(AgentName::parse("post-agent"), None);
+    }
+}
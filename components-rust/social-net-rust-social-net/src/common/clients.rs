@@ -0,0 +1,3 @@
+// Clients of golem agents, implement client traits of multi-agent flows (e.g. post::PostClients),
+// flows are driven with in-memory clients by test harness
+pub(crate) struct AgentClients;
@@ -0,0 +1,84 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// Source of current time of state mutations
+pub trait Clock {
+    fn now(&self) -> chrono::DateTime<chrono::Utc>;
+}
+
+#[derive(Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::Utc::now()
+    }
+}
+
+// Clock with manually set time, tests can freeze and advance time
+#[cfg(test)]
+pub struct ManualClock {
+    now: std::cell::Cell<chrono::DateTime<chrono::Utc>>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new(now: chrono::DateTime<chrono::Utc>) -> Self {
+        ManualClock {
+            now: std::cell::Cell::new(now),
+        }
+    }
+
+    pub fn advance(&self, duration: chrono::Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        self.now.get()
+    }
+}
+
+thread_local! {
+    static CLOCK: RefCell<Rc<dyn Clock>> = RefCell::new(Rc::new(SystemClock));
+}
+
+// Current time of clock of the agent (system clock, unless replaced by test)
+pub fn now() -> chrono::DateTime<chrono::Utc> {
+    CLOCK.with(|c| c.borrow().now())
+}
+
+// Replace clock of current thread (each test runs in its own thread)
+#[cfg(test)]
+pub fn set_clock(clock: Rc<dyn Clock>) {
+    CLOCK.with(|c| *c.borrow_mut() = clock);
+}
+
+// Freeze time of current thread at given time, returned clock can advance it
+#[cfg(test)]
+pub fn freeze(now: chrono::DateTime<chrono::Utc>) -> Rc<ManualClock> {
+    let clock = Rc::new(ManualClock::new(now));
+    set_clock(clock.clone());
+    clock
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frozen_clock() {
+        let start = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = freeze(start);
+        assert_eq!(now(), start);
+        assert_eq!(now(), start);
+
+        clock.advance(chrono::Duration::hours(2));
+        assert_eq!(now(), start + chrono::Duration::hours(2));
+
+        set_clock(Rc::new(SystemClock));
+        assert!(now() > start);
+    }
+}
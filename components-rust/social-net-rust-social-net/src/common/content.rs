@@ -0,0 +1,323 @@
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+// script-like blocks, removed including their content
+static DANGEROUS_BLOCK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?is)<\s*(script|style|iframe|object|embed|noscript)\b[^>]*>.*?<\s*/\s*(script|style|iframe|object|embed|noscript)\s*>",
+    )
+    .unwrap()
+});
+
+// any remaining well-formed html tag (name with optional attributes) or comment,
+// text like "a<b then c" is not a tag and is kept
+static HTML_TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?s)<!--.*?-->|<!\s*doctype\b[^<>]*>|</?[a-zA-Z][a-zA-Z0-9-]*(\s+[a-zA-Z_:][-a-zA-Z0-9_:.]*(\s*=\s*("[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*\s*/?>"#,
+    )
+    .unwrap()
+});
+
+// script uri schemes, the scheme colon is required (plain words like "javascript" are kept)
+static SCRIPT_URI_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(javascript\s*:|vbscript\s*:|data\s*:\s*text/html\s*[,;]?)").unwrap()
+});
+
+static INLINE_SPACES_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[ \t\u{a0}\u{200b}]+").unwrap());
+
+static BLANK_LINES_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n{3,}").unwrap());
+
+static MD_LINK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[([^\[\]]+)\]\((https?://[^\s()]+)\)").unwrap());
+
+static MD_BOLD_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\*\*([^*]+)\*\*|__([^_]+)__").unwrap());
+
+static MD_ITALIC_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\*([^*\s][^*]*)\*|\b_([^_\s][^_]*)_\b").unwrap());
+
+static MD_HEADING_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(#{1,6})\s+(.+)$").unwrap());
+
+// hashtag, not preceded by word character (e.g. url fragment, html entity)
+static HASHTAG_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|[^\p{L}\p{N}_&#/])#([\p{L}\p{N}_]+)").unwrap());
+
+// user mention (@user-id), not preceded by word character (e.g. email address)
+static MENTION_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|[^\p{L}\p{N}_@.])@([\p{L}\p{N}_-]+)").unwrap());
+
+// max number of mentions of content, further mentions are ignored
+const MENTIONS_MAX_COUNT: usize = 10;
+
+// max length of hashtag (without #)
+const HASHTAG_MAX_LENGTH: usize = 50;
+
+// Sanitize user provided content (post, comment, message)
+//
+// removes script-like payloads (script/style/iframe blocks, html tags, script uris)
+// and normalizes whitespace (line endings, repeated spaces, blank lines)
+pub fn sanitize(content: &str) -> String {
+    let content = DANGEROUS_BLOCK_REGEX.replace_all(content, "");
+    let content = HTML_TAG_REGEX.replace_all(&content, "");
+    let content = SCRIPT_URI_REGEX.replace_all(&content, "");
+
+    let content = content.replace("\r\n", "\n").replace('\r', "\n");
+
+    let content: String = content
+        .chars()
+        .filter(|c| *c == '\n' || *c == '\t' || !c.is_control())
+        .collect();
+
+    let content = content
+        .lines()
+        .map(|line| {
+            INLINE_SPACES_REGEX
+                .replace_all(line, " ")
+                .trim()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    BLANK_LINES_REGEX
+        .replace_all(&content, "\n\n")
+        .trim()
+        .to_string()
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+fn render_inline(text: &str) -> String {
+    // odd segments are code spans, no other formatting is applied to them
+    text.split('`')
+        .enumerate()
+        .map(|(i, segment)| {
+            let segment = escape_html(segment);
+            if i % 2 == 1 {
+                format!("<code>{segment}</code>")
+            } else {
+                let segment = MD_LINK_REGEX.replace_all(
+                    &segment,
+                    r#"<a href="$2" rel="nofollow noopener noreferrer">$1</a>"#,
+                );
+                let segment = MD_BOLD_REGEX.replace_all(&segment, "<strong>$1$2</strong>");
+                MD_ITALIC_REGEX
+                    .replace_all(&segment, "<em>$1$2</em>")
+                    .to_string()
+            }
+        })
+        .collect()
+}
+
+// Render (sanitized) markdown content to safe html
+//
+// supported: headings, unordered lists, paragraphs with line breaks, bold, italic, code spans and http(s) links,
+// all other text is html escaped
+pub fn render_markdown(content: &str) -> String {
+    let content = sanitize(content);
+
+    content
+        .split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .map(|block| {
+            let lines: Vec<&str> = block.lines().collect();
+
+            if lines.len() == 1 {
+                if let Some(caps) = MD_HEADING_REGEX.captures(lines[0]) {
+                    let level = caps[1].len();
+                    return format!("<h{level}>{}</h{level}>", render_inline(&caps[2]));
+                }
+            }
+
+            if lines
+                .iter()
+                .all(|line| line.starts_with("- ") || line.starts_with("* "))
+            {
+                let items: String = lines
+                    .iter()
+                    .map(|line| format!("<li>{}</li>", render_inline(&line[2..])))
+                    .collect();
+                format!("<ul>{items}</ul>")
+            } else {
+                let text = lines
+                    .iter()
+                    .map(|line| render_inline(line))
+                    .collect::<Vec<_>>()
+                    .join("<br/>");
+                format!("<p>{text}</p>")
+            }
+        })
+        .collect()
+}
+
+// Normalized (lowercase, without leading #) tag, none if tag is not valid
+//
+// tag has to contain at least one letter, so numbers like #1 are not tags
+pub fn normalize_hashtag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    let valid = !tag.is_empty()
+        && tag.chars().count() <= HASHTAG_MAX_LENGTH
+        && tag.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && tag.chars().any(|c| c.is_alphabetic());
+    valid.then_some(tag)
+}
+
+// Distinct normalized hashtags of content
+pub fn extract_hashtags(content: &str) -> HashSet<String> {
+    HASHTAG_REGEX
+        .captures_iter(content)
+        .filter_map(|caps| normalize_hashtag(&caps[1]))
+        .collect()
+}
+
+// Distinct user ids mentioned in content, in order of first mention
+pub fn extract_mentions(content: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = vec![];
+    for caps in MENTION_REGEX.captures_iter(content) {
+        if mentions.len() >= MENTIONS_MAX_COUNT {
+            break;
+        }
+        let mention = caps[1].to_string();
+        if !mentions.contains(&mention) {
+            mentions.push(mention);
+        }
+    }
+    mentions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_strips_script_blocks() {
+        let content = "Hello <script>alert('x')</script>world";
+        assert_eq!(sanitize(content), "Hello world");
+
+        let content = "A<STYLE type=\"text/css\">body {}</style>B";
+        assert_eq!(sanitize(content), "AB");
+    }
+
+    #[test]
+    fn test_sanitize_strips_tags_and_script_uris() {
+        let content = "<img src=x onerror=alert(1)>click <a href=\"javascript:alert(1)\">me</a>";
+        assert_eq!(sanitize(content), "click me");
+
+        let content = "go to javascript:alert(1)";
+        assert_eq!(sanitize(content), "go to alert(1)");
+    }
+
+    #[test]
+    fn test_sanitize_keeps_comparisons() {
+        assert_eq!(sanitize("1 < 2 and 3 > 2"), "1 < 2 and 3 > 2");
+        assert_eq!(
+            sanitize("if a<b then c is bigger, thanks"),
+            "if a<b then c is bigger, thanks"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_keeps_script_words() {
+        assert_eq!(
+            sanitize("I love JavaScript and vbscript tutorials"),
+            "I love JavaScript and vbscript tutorials"
+        );
+        assert_eq!(
+            sanitize("open vbscript:msgbox(1) or data:text/html,<b>x</b>"),
+            "open msgbox(1) or x"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_normalizes_whitespace() {
+        let content = "  Hello \t  world \r\n\r\n\r\n\r\nsecond   line  ";
+        assert_eq!(sanitize(content), "Hello world\n\nsecond line");
+    }
+
+    #[test]
+    fn test_sanitize_empty() {
+        assert_eq!(sanitize("   <script>x</script>  "), "");
+    }
+
+    #[test]
+    fn test_render_markdown_inline() {
+        let html = render_markdown("**bold** and *italic* and `a < b` [link](https://golem.cloud)");
+        assert_eq!(
+            html,
+            "<p><strong>bold</strong> and <em>italic</em> and <code>a &lt; b</code> <a href=\"https://golem.cloud\" rel=\"nofollow noopener noreferrer\">link</a></p>"
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_blocks() {
+        let html = render_markdown("# Title\n\n- one\n- two\n\nline 1\nline 2");
+        assert_eq!(
+            html,
+            "<h1>Title</h1><ul><li>one</li><li>two</li></ul><p>line 1<br/>line 2</p>"
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_unsafe_links() {
+        let html = render_markdown("[x](javascript:alert(1)) [y](ftp://host)");
+        assert!(!html.contains("href"));
+        assert!(!html.contains("javascript"));
+    }
+
+    #[test]
+    fn test_extract_hashtags() {
+        let tags = extract_hashtags(
+            "#Rust and #golem, #rust again\n#1 a#b https://x.com/#frag &#39; #Ünïcode_2",
+        );
+        assert_eq!(
+            tags,
+            HashSet::from([
+                "rust".to_string(),
+                "golem".to_string(),
+                "ünïcode_2".to_string()
+            ])
+        );
+        assert!(extract_hashtags("# Heading").is_empty());
+    }
+
+    #[test]
+    fn test_extract_mentions() {
+        let mentions = extract_mentions("@user1 hi, (@user-2) and @user1 again, mail a@b.com");
+        assert_eq!(mentions, vec!["user1", "user-2"]);
+        assert!(extract_mentions("@ nobody").is_empty());
+
+        let content = (0..MENTIONS_MAX_COUNT + 5)
+            .map(|i| format!("@user{i}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(extract_mentions(&content).len(), MENTIONS_MAX_COUNT);
+    }
+
+    #[test]
+    fn test_normalize_hashtag() {
+        assert_eq!(normalize_hashtag("#Rust"), Some("rust".to_string()));
+        assert_eq!(
+            normalize_hashtag("golem_cloud"),
+            Some("golem_cloud".to_string())
+        );
+        assert_eq!(normalize_hashtag("#123"), None);
+        assert_eq!(normalize_hashtag("a-b"), None);
+        assert_eq!(normalize_hashtag(""), None);
+    }
+}
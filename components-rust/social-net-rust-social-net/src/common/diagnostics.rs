@@ -0,0 +1,103 @@
+use crate::common::clock;
+use golem_rust::Schema;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+// max number of characters of stored last error message
+const LAST_ERROR_MAX_LENGTH: usize = 500;
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct MethodCounters {
+    pub method: String,
+    pub calls: u64,
+    pub errors: u64,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LastError {
+    pub method: String,
+    pub message: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Operation counters of agent write methods, kept in agent memory (not in snapshots)
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct AgentDiagnostics {
+    // counters ordered by method name
+    pub methods: Vec<MethodCounters>,
+    pub last_error: Option<LastError>,
+}
+
+impl AgentDiagnostics {
+    fn counters_mut(&mut self, method: &str) -> &mut MethodCounters {
+        let index = match self
+            .methods
+            .binary_search_by(|c| c.method.as_str().cmp(method))
+        {
+            Ok(index) => index,
+            Err(index) => {
+                self.methods.insert(
+                    index,
+                    MethodCounters {
+                        method: method.to_string(),
+                        calls: 0,
+                        errors: 0,
+                    },
+                );
+                index
+            }
+        };
+        &mut self.methods[index]
+    }
+
+    pub fn record_call(&mut self, method: &str) {
+        self.counters_mut(method).calls += 1;
+    }
+
+    pub fn record<T, E: Display>(&mut self, method: &str, result: &Result<T, E>) {
+        let counters = self.counters_mut(method);
+        counters.calls += 1;
+        if let Err(error) = result {
+            counters.errors += 1;
+            self.last_error = Some(LastError {
+                method: method.to_string(),
+                message: error
+                    .to_string()
+                    .chars()
+                    .take(LAST_ERROR_MAX_LENGTH)
+                    .collect(),
+                created_at: clock::now(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut diagnostics = AgentDiagnostics::default();
+        diagnostics.record::<(), String>("set_like", &Ok(()));
+        diagnostics.record::<(), String>("add_comment", &Err("Post not exists".to_string()));
+        diagnostics.record::<(), String>("set_like", &Ok(()));
+        diagnostics.record_call("archive_comments");
+
+        assert_eq!(
+            diagnostics
+                .methods
+                .iter()
+                .map(|c| (c.method.as_str(), c.calls, c.errors))
+                .collect::<Vec<_>>(),
+            vec![
+                ("add_comment", 1, 1),
+                ("archive_comments", 1, 0),
+                ("set_like", 2, 0)
+            ]
+        );
+        let last_error = diagnostics.last_error.unwrap();
+        assert_eq!(last_error.method, "add_comment");
+        assert_eq!(last_error.message, "Post not exists");
+    }
+}
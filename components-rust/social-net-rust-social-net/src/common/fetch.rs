@@ -0,0 +1,67 @@
+use crate::fault_injection::{inject_fault, FaultPath};
+use crate::operations::get_operations_config;
+use futures::future::join_all;
+use std::future::Future;
+use wstd::future::FutureExt;
+
+// Items fetched (hydrated) from multiple agents
+pub struct Fetched<T> {
+    pub items: Vec<T>,
+    // number of calls which timed out, their items are missing
+    pub skipped: usize,
+}
+
+impl<T> Default for Fetched<T> {
+    fn default() -> Self {
+        Fetched {
+            items: vec![],
+            skipped: 0,
+        }
+    }
+}
+
+impl<T> Fetched<T> {
+    // Some items were skipped, result is partial
+    pub fn is_degraded(&self) -> bool {
+        self.skipped > 0
+    }
+
+    pub fn extend(&mut self, other: Fetched<T>) {
+        self.items.extend(other.items);
+        self.skipped += other.skipped;
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Fetched<U> {
+        Fetched {
+            items: self.items.into_iter().map(f).collect(),
+            skipped: self.skipped,
+        }
+    }
+}
+
+// Await all agent calls (of agents with given ids), each call is limited by fetch call timeout
+// of operations config, results of slower (or failed by injected fault) calls are skipped
+pub async fn join_all_with_timeout<T, F>(agent_ids: &[String], tasks: Vec<F>) -> Fetched<T>
+where
+    F: Future<Output = Option<T>>,
+{
+    let timeout_ms = get_operations_config().await.fetch_call_timeout_ms;
+    let timeout = wstd::time::Duration::from_millis(timeout_ms);
+    let tasks = tasks.into_iter().zip(agent_ids).map(|(task, agent_id)| {
+        async move {
+            inject_fault(FaultPath::Hydration, agent_id).await.ok()?;
+            Some(task.await)
+        }
+        .timeout(timeout)
+    });
+    let responses = join_all(tasks).await;
+
+    let mut result = Fetched::default();
+    for response in responses {
+        match response {
+            Ok(Some(item)) => result.items.extend(item),
+            _ => result.skipped += 1,
+        }
+    }
+    result
+}
@@ -0,0 +1,124 @@
+use golem_rust::Schema;
+use serde::{Deserialize, Serialize};
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+const LOCATION_NAME_MAX_LENGTH: usize = 100;
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Location {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl Location {
+    // Validated location with trimmed name
+    pub fn validated(self) -> Result<Location, String> {
+        let name = self.name.trim().to_string();
+        if name.chars().count() > LOCATION_NAME_MAX_LENGTH {
+            Err(format!(
+                "Location name is too long, max length: {LOCATION_NAME_MAX_LENGTH}"
+            ))
+        } else if !valid_coordinates(self.lat, self.lon) {
+            Err("Invalid location coordinates".to_string())
+        } else {
+            Ok(Location { name, ..self })
+        }
+    }
+
+    pub fn distance_km(&self, lat: f64, lon: f64) -> f64 {
+        distance_km(self.lat, self.lon, lat, lon)
+    }
+}
+
+fn valid_coordinates(lat: f64, lon: f64) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)
+}
+
+// Great-circle (haversine) distance of two points in kilometers
+pub fn distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+// Area with center and radius, parsed from query value `lat,lon,radius-km`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Radius {
+    pub lat: f64,
+    pub lon: f64,
+    pub km: f64,
+}
+
+impl Radius {
+    pub fn parse(value: &str) -> Option<Radius> {
+        let parts: Vec<f64> = value
+            .split(',')
+            .map(|p| p.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .ok()?;
+        match parts.as_slice() {
+            [lat, lon, km] if valid_coordinates(*lat, *lon) && *km >= 0.0 => Some(Radius {
+                lat: *lat,
+                lon: *lon,
+                km: *km,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, location: &Location) -> bool {
+        location.distance_km(self.lat, self.lon) <= self.km
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(lat: f64, lon: f64) -> Location {
+        Location {
+            name: "test".to_string(),
+            lat,
+            lon,
+        }
+    }
+
+    #[test]
+    fn test_distance_km() {
+        // Berlin - Paris ~878 km
+        let distance = distance_km(52.52, 13.405, 48.8566, 2.3522);
+        assert!((distance - 878.0).abs() < 5.0, "distance: {distance}");
+        assert_eq!(distance_km(10.0, 10.0, 10.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn test_validated() {
+        let valid = Location {
+            name: " Berlin ".to_string(),
+            lat: 52.52,
+            lon: 13.405,
+        }
+        .validated()
+        .unwrap();
+        assert_eq!(valid.name, "Berlin");
+
+        assert!(location(91.0, 0.0).validated().is_err());
+        assert!(location(0.0, -181.0).validated().is_err());
+        assert!(location(f64::NAN, 0.0).validated().is_err());
+    }
+
+    #[test]
+    fn test_radius() {
+        let radius = Radius::parse("52.52, 13.405, 10").unwrap();
+        assert!(radius.contains(&location(52.5, 13.4)));
+        assert!(!radius.contains(&location(48.8566, 2.3522)));
+
+        assert!(Radius::parse("52.52,13.405").is_none());
+        assert!(Radius::parse("52.52,13.405,-1").is_none());
+        assert!(Radius::parse("berlin").is_none());
+    }
+}
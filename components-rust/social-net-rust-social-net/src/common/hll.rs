@@ -0,0 +1,154 @@
+use golem_rust::Schema;
+use serde::{Deserialize, Serialize};
+
+// number of index bits, 2^10 registers (1 KB), standard error ~3.25%
+const DEFAULT_PRECISION: u8 = 10;
+
+// HyperLogLog approximate distinct counter
+//
+// registers are hex encoded in snapshots to keep the agent state small
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct HyperLogLog {
+    pub precision: u8,
+    #[serde(with = "hex_registers")]
+    pub registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog::new()
+    }
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        HyperLogLog {
+            precision: DEFAULT_PRECISION,
+            registers: vec![0; 1 << DEFAULT_PRECISION],
+        }
+    }
+
+    fn hash(value: &str) -> u64 {
+        let digest = md5::compute(value);
+        u64::from_le_bytes([
+            digest[0], digest[1], digest[2], digest[3], digest[4], digest[5], digest[6], digest[7],
+        ])
+    }
+
+    // Add value, returns true if sketch was changed
+    pub fn add(&mut self, value: &str) -> bool {
+        let hash = Self::hash(value);
+        let p = self.precision as u32;
+        let index = (hash >> (64 - p)) as usize;
+        // guard bit limits rank to 64 - p + 1
+        let rank = ((hash << p) | (1 << (p - 1))).leading_zeros() as u8 + 1;
+
+        if self.registers[index] < rank {
+            self.registers[index] = rank;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Union with other sketch (of same precision)
+    pub fn merge(&mut self, other: &HyperLogLog) -> Result<(), String> {
+        if self.precision != other.precision {
+            Err("HyperLogLog precision mismatch".to_string())
+        } else {
+            for (register, other) in self.registers.iter_mut().zip(other.registers.iter()) {
+                if *register < *other {
+                    *register = *other;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|r| 2f64.powi(-(*r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            // small range correction (linear counting)
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+mod hex_registers {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(registers: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = registers.iter().map(|r| format!("{r:02x}")).collect();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|b| u8::from_str_radix(b, 16).ok())
+                    .ok_or_else(|| de::Error::custom("Invalid registers"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_empty() {
+        assert_eq!(HyperLogLog::new().count(), 0);
+    }
+
+    #[test]
+    fn test_count_duplicates() {
+        let mut hll = HyperLogLog::new();
+        assert!(hll.add("user-1"));
+        assert!(!hll.add("user-1"));
+        hll.add("user-2");
+        assert_eq!(hll.count(), 2);
+    }
+
+    #[test]
+    fn test_count_accuracy() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..10000 {
+            hll.add(&format!("user-{i}"));
+        }
+        let count = hll.count() as f64;
+        assert!((count - 10000.0).abs() / 10000.0 < 0.1, "count: {count}");
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut hll1 = HyperLogLog::new();
+        let mut hll2 = HyperLogLog::new();
+        for i in 0..1000 {
+            hll1.add(&format!("user-{i}"));
+            hll2.add(&format!("user-{}", i + 500));
+        }
+        hll1.merge(&hll2).unwrap();
+        let count = hll1.count() as f64;
+        assert!((count - 1500.0).abs() / 1500.0 < 0.1, "count: {count}");
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut hll = HyperLogLog::new();
+        hll.add("user-1");
+        let json = serde_json::to_string(&hll).unwrap();
+        let hll2: HyperLogLog = serde_json::from_str(&json).unwrap();
+        assert_eq!(hll, hll2);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::common::clock;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// env variable with format of generated ids: uuid-v7 (default, time ordered) or uuid-v4 (random)
+const ID_FORMAT_ENV_VAR: &str = "SOCIAL_NET_ID_FORMAT";
+
+const ID_FORMAT_UUID_V4: &str = "uuid-v4";
+
+// Generator of unique ids of posts, comments, messages and chats
+pub trait IdGenerator {
+    fn new_id(&self) -> String;
+}
+
+// Random ids
+#[derive(Default)]
+pub struct UuidV4Generator;
+
+impl IdGenerator for UuidV4Generator {
+    fn new_id(&self) -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+}
+
+// Time ordered ids (by clock time in milliseconds), ids sort by creation time
+#[derive(Default)]
+pub struct UuidV7Generator;
+
+impl IdGenerator for UuidV7Generator {
+    fn new_id(&self) -> String {
+        let now = clock::now();
+        let timestamp = uuid::Timestamp::from_unix(
+            uuid::NoContext,
+            now.timestamp().max(0) as u64,
+            now.timestamp_subsec_nanos(),
+        );
+        uuid::Uuid::new_v7(timestamp).to_string()
+    }
+}
+
+// Ids with prefix and sequence number (prefix-1, prefix-2, ...), for reproducible tests
+#[cfg(test)]
+pub struct SequentialIdGenerator {
+    prefix: String,
+    next: std::cell::Cell<u64>,
+}
+
+#[cfg(test)]
+impl SequentialIdGenerator {
+    pub fn new(prefix: &str) -> Self {
+        SequentialIdGenerator {
+            prefix: prefix.to_string(),
+            next: std::cell::Cell::new(1),
+        }
+    }
+}
+
+#[cfg(test)]
+impl IdGenerator for SequentialIdGenerator {
+    fn new_id(&self) -> String {
+        let next = self.next.get();
+        self.next.set(next + 1);
+        format!("{}-{next}", self.prefix)
+    }
+}
+
+fn default_id_generator() -> Rc<dyn IdGenerator> {
+    match std::env::var(ID_FORMAT_ENV_VAR) {
+        Ok(format) if format.trim() == ID_FORMAT_UUID_V4 => Rc::new(UuidV4Generator),
+        _ => Rc::new(UuidV7Generator),
+    }
+}
+
+thread_local! {
+    static ID_GENERATOR: RefCell<Rc<dyn IdGenerator>> = RefCell::new(default_id_generator());
+}
+
+// New unique id from id generator of the agent (by configured id format, unless replaced by test)
+pub fn new_id() -> String {
+    ID_GENERATOR.with(|g| g.borrow().new_id())
+}
+
+// Replace id generator of current thread (each test runs in its own thread)
+#[cfg(test)]
+pub fn set_id_generator(generator: Rc<dyn IdGenerator>) {
+    ID_GENERATOR.with(|g| *g.borrow_mut() = generator);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequential_ids() {
+        set_id_generator(Rc::new(SequentialIdGenerator::new("id")));
+        assert_eq!(new_id(), "id-1");
+        assert_eq!(new_id(), "id-2");
+    }
+
+    #[test]
+    fn test_uuid_v7_ids_time_ordered() {
+        let clock = clock::freeze(chrono::Utc::now());
+        let generator = UuidV7Generator;
+        let first = generator.new_id();
+        clock.advance(chrono::Duration::milliseconds(1));
+        let second = generator.new_id();
+        clock.advance(chrono::Duration::seconds(1));
+        let third = generator.new_id();
+
+        assert!(first < second && second < third);
+        assert_eq!(uuid::Uuid::parse_str(&first).unwrap().get_version_num(), 7);
+    }
+
+    #[test]
+    fn test_default_ids_time_ordered() {
+        if std::env::var(ID_FORMAT_ENV_VAR).is_ok() {
+            return;
+        }
+        let id = default_id_generator().new_id();
+        assert_eq!(uuid::Uuid::parse_str(&id).unwrap().get_version_num(), 7);
+    }
+}
@@ -0,0 +1,185 @@
+// Detection of content language, returns ISO 639-1 language code
+pub trait LanguageDetector {
+    fn detect(&self, text: &str) -> Option<String>;
+}
+
+// most common words of languages written in latin script
+const STOPWORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            "the", "and", "is", "are", "was", "of", "to", "in", "that", "it", "with", "for",
+            "this", "you", "not", "have", "be", "on", "what", "my",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ist", "nicht", "ich", "ein", "eine", "zu", "mit", "sich",
+            "auf", "für", "auch", "es", "wir", "sie", "dass", "war",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "la", "los", "las", "y", "es", "que", "de", "en", "un", "una", "por", "con",
+            "para", "pero", "muy", "está", "yo", "mi", "del",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "et", "est", "que", "de", "des", "un", "une", "pour", "pas", "dans",
+            "je", "nous", "vous", "avec", "sur", "mais", "du",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "lo", "la", "gli", "e", "è", "che", "di", "un", "una", "per", "non", "con",
+            "sono", "mi", "ma", "della", "questo", "io", "del",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "os", "as", "e", "é", "que", "de", "um", "uma", "para", "não", "com", "em", "eu",
+            "mas", "muito", "está", "do", "da", "você",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "is", "van", "niet", "ik", "dat", "met", "voor", "op",
+            "zijn", "maar", "ook", "wij", "je", "hij", "was", "naar",
+        ],
+    ),
+];
+
+// min number of stopwords needed to decide language of latin script text
+const STOPWORDS_MIN_MATCHES: usize = 2;
+
+// Heuristic detector: language by script of letters (non latin scripts),
+// by most common words for latin script, none if text is ambiguous
+#[derive(Default)]
+pub struct HeuristicLanguageDetector;
+
+impl HeuristicLanguageDetector {
+    fn script_language(c: char) -> Option<&'static str> {
+        match c {
+            '\u{0400}'..='\u{04FF}' => Some("ru"),
+            '\u{0370}'..='\u{03FF}' => Some("el"),
+            '\u{0590}'..='\u{05FF}' => Some("he"),
+            '\u{0600}'..='\u{06FF}' => Some("ar"),
+            '\u{0900}'..='\u{097F}' => Some("hi"),
+            '\u{0E00}'..='\u{0E7F}' => Some("th"),
+            '\u{3040}'..='\u{30FF}' => Some("ja"),
+            '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' => Some("ko"),
+            '\u{4E00}'..='\u{9FFF}' => Some("zh"),
+            _ => None,
+        }
+    }
+
+    fn detect_by_script(text: &str) -> Option<String> {
+        let mut counts: Vec<(&str, usize)> = vec![];
+        let mut letters = 0;
+        for c in text.chars().filter(|c| c.is_alphabetic()) {
+            letters += 1;
+            if let Some(language) = Self::script_language(c) {
+                match counts.iter_mut().find(|(l, _)| *l == language) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((language, 1)),
+                }
+            }
+        }
+        // japanese text mixes kana with han characters
+        if counts.iter().any(|(l, _)| *l == "ja") {
+            return Some("ja".to_string());
+        }
+        counts
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .filter(|(_, count)| count * 2 > letters)
+            .map(|(language, _)| language.to_string())
+    }
+
+    fn detect_by_stopwords(text: &str) -> Option<String> {
+        let text = text.to_lowercase();
+        let words: Vec<&str> = text
+            .split(|c: char| !c.is_alphabetic())
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        let mut scores: Vec<(&str, usize)> = STOPWORDS
+            .iter()
+            .map(|(language, stopwords)| {
+                let score = words.iter().filter(|w| stopwords.contains(w)).count();
+                (*language, score)
+            })
+            .collect();
+        scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+        match scores.as_slice() {
+            [(language, best), (_, second), ..]
+                if *best >= STOPWORDS_MIN_MATCHES && best > second =>
+            {
+                Some(language.to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl LanguageDetector for HeuristicLanguageDetector {
+    fn detect(&self, text: &str) -> Option<String> {
+        Self::detect_by_script(text).or_else(|| Self::detect_by_stopwords(text))
+    }
+}
+
+// Language of content detected by default detector
+pub fn detect_language(text: &str) -> Option<String> {
+    HeuristicLanguageDetector.detect(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language_latin() {
+        assert_eq!(
+            detect_language("This is the best day of my life"),
+            Some("en".to_string())
+        );
+        assert_eq!(
+            detect_language("Das ist nicht so einfach, aber wir machen es"),
+            Some("de".to_string())
+        );
+        assert_eq!(
+            detect_language("El día está muy bonito y la playa es genial"),
+            Some("es".to_string())
+        );
+        assert_eq!(
+            detect_language("Je pense que nous allons dans le parc avec les enfants"),
+            Some("fr".to_string())
+        );
+    }
+
+    #[test]
+    fn test_detect_language_script() {
+        assert_eq!(detect_language("Привет, как дела?"), Some("ru".to_string()));
+        assert_eq!(
+            detect_language("今日はいい天気です"),
+            Some("ja".to_string())
+        );
+        assert_eq!(detect_language("안녕하세요"), Some("ko".to_string()));
+        assert_eq!(detect_language("你好世界"), Some("zh".to_string()));
+    }
+
+    #[test]
+    fn test_detect_language_ambiguous() {
+        assert_eq!(detect_language("Hello"), None);
+        assert_eq!(detect_language("#golem 123"), None);
+        assert_eq!(detect_language(""), None);
+    }
+}
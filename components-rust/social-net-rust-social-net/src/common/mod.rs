@@ -1,12 +1,28 @@
+use crate::fault_injection::{inject_fault, FaultPath};
 use golem_rust::Schema;
 use md5;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 use std::thread;
 use std::time::Duration;
 use std::time::Instant;
 
+pub(crate) mod agent_name;
+pub(crate) mod clients;
+pub(crate) mod clock;
+pub(crate) mod content;
+pub(crate) mod diagnostics;
+pub(crate) mod fetch;
+pub(crate) mod geo;
+pub(crate) mod hll;
+pub(crate) mod id;
+pub(crate) mod language;
+pub(crate) mod namespace;
+pub(crate) mod schedule;
+pub(crate) mod validation;
+
 #[derive(Schema, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq)]
 pub enum UserConnectionType {
     Friend,
@@ -40,6 +56,8 @@ pub enum LikeType {
     Insightful,
     Love,
     Dislike,
+    Celebrate,
+    Funny,
 }
 
 impl LikeType {
@@ -59,10 +77,160 @@ impl Display for LikeType {
             LikeType::Insightful => write!(f, "Insightful"),
             LikeType::Love => write!(f, "Love"),
             LikeType::Dislike => write!(f, "Dislike"),
+            LikeType::Celebrate => write!(f, "Celebrate"),
+            LikeType::Funny => write!(f, "Funny"),
+        }
+    }
+}
+
+// Error of agent methods, callers can match error kind instead of message
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum SocialNetError {
+    // user, post, chat or other entity not exists
+    NotFound(String),
+    // entity (e.g. user, connection) already exists
+    AlreadyExists(String),
+    // invalid input
+    ValidationFailed(String),
+    // max count (e.g. of pending requests) reached
+    LimitExceeded(String),
+    // user is not allowed to change content of another user
+    Unauthorized(String),
+    // user is not allowed to act (e.g. banned user)
+    NotAllowed(String),
+    // content (e.g. user name) is longer than configured max length
+    ContentTooLong(validation::ContentTooLong),
+    // failure not caused by input (e.g. of serialization or invoked agent)
+    Internal(String),
+}
+
+impl SocialNetError {
+    pub fn not_found(message: &str) -> Self {
+        SocialNetError::NotFound(message.to_string())
+    }
+
+    pub fn already_exists(message: &str) -> Self {
+        SocialNetError::AlreadyExists(message.to_string())
+    }
+
+    pub fn not_allowed(message: &str) -> Self {
+        SocialNetError::NotAllowed(message.to_string())
+    }
+}
+
+impl Display for SocialNetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SocialNetError::NotFound(message)
+            | SocialNetError::AlreadyExists(message)
+            | SocialNetError::ValidationFailed(message)
+            | SocialNetError::LimitExceeded(message)
+            | SocialNetError::Unauthorized(message)
+            | SocialNetError::NotAllowed(message)
+            | SocialNetError::Internal(message) => write!(f, "{message}"),
+            SocialNetError::ContentTooLong(e) => write!(f, "{e}"),
         }
     }
 }
 
+// Likes counts by type
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct LikeCounts {
+    pub like: u32,
+    pub insightful: u32,
+    pub love: u32,
+    pub dislike: u32,
+    pub celebrate: u32,
+    pub funny: u32,
+    pub total: u32,
+}
+
+impl LikeCounts {
+    pub fn from_likes<'a>(likes: impl IntoIterator<Item = &'a LikeType>) -> Self {
+        let mut counts = LikeCounts::default();
+        for like_type in likes {
+            let count = match like_type {
+                LikeType::Like => &mut counts.like,
+                LikeType::Insightful => &mut counts.insightful,
+                LikeType::Love => &mut counts.love,
+                LikeType::Dislike => &mut counts.dislike,
+                LikeType::Celebrate => &mut counts.celebrate,
+                LikeType::Funny => &mut counts.funny,
+            };
+            *count += 1;
+            counts.total += 1;
+        }
+        counts
+    }
+}
+
+// Likes counts with reaction of given user, clients do not need all likes
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct LikeSummary {
+    pub counts: LikeCounts,
+    // reaction of the user, none if user did not react (or no user given)
+    pub user_like_type: Option<LikeType>,
+}
+
+impl LikeSummary {
+    pub fn from_likes(likes: &HashMap<String, LikeType>, user_id: Option<&str>) -> Self {
+        LikeSummary {
+            counts: LikeCounts::from_likes(likes.values()),
+            user_like_type: user_id.and_then(|id| likes.get(id)).cloned(),
+        }
+    }
+}
+
+const LIKERS_PAGE_DEFAULT_LIMIT: u32 = 50;
+const LIKERS_PAGE_MAX_LIMIT: u32 = 200;
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Liker {
+    pub user_id: String,
+    pub like_type: LikeType,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LikersPage {
+    pub likers: Vec<Liker>,
+    // cursor of next page, none if this is the last page
+    pub next_cursor: Option<String>,
+}
+
+impl LikersPage {
+    // Page of users who reacted (optionally with given type) ordered by user id,
+    // cursor is number of likers in previous pages
+    pub fn from_likes(
+        likes: &HashMap<String, LikeType>,
+        like_type: Option<LikeType>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Self, cursor::InvalidCursor> {
+        let offset = cursor::decode_or(cursor, 0)? as usize;
+        let limit = limit
+            .unwrap_or(LIKERS_PAGE_DEFAULT_LIMIT)
+            .clamp(1, LIKERS_PAGE_MAX_LIMIT) as usize;
+
+        let mut likers: Vec<Liker> = likes
+            .iter()
+            .filter(|(_, t)| like_type.as_ref().is_none_or(|like_type| *t == like_type))
+            .map(|(user_id, t)| Liker {
+                user_id: user_id.clone(),
+                like_type: t.clone(),
+            })
+            .collect();
+        likers.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+
+        let has_more = likers.len() > offset + limit;
+        let likers: Vec<Liker> = likers.into_iter().skip(offset).take(limit).collect();
+
+        Ok(LikersPage {
+            likers,
+            next_cursor: has_more.then(|| cursor::encode((offset + limit) as u64)),
+        })
+    }
+}
+
 pub(crate) mod query {
     use golem_rust::Schema;
     use std::fmt::{Display, Formatter};
@@ -83,36 +251,84 @@ pub(crate) mod query {
         query == "*" || text == query
     }
 
-    // Tokenize the query string, handling quoted strings
-    pub fn tokenize(query: &str) -> Vec<String> {
+    // Exact match by match mode of field, case insensitive for ExactIgnoreCase fields
+    pub fn exact_matches(mode: &FieldMatchMode, text: &str, query: &str) -> bool {
+        match mode {
+            FieldMatchMode::ExactIgnoreCase => {
+                query == "*" || text.to_lowercase() == query.to_lowercase()
+            }
+            _ => text_exact_matches(text, query),
+        }
+    }
+
+    pub fn opt_exact_matches(mode: &FieldMatchMode, text: Option<&str>, query: &str) -> bool {
+        query == "*" || text.is_some_and(|text| exact_matches(mode, text, query))
+    }
+
+    // Match mode of field by searchable fields (policy table of entity)
+    pub fn field_mode(fields: &[QueryField], field: &str) -> FieldMatchMode {
+        fields
+            .iter()
+            .find(|f| f.names.contains(&field))
+            .map(|f| f.mode.clone())
+            .unwrap_or(FieldMatchMode::Unknown)
+    }
+
+    // Token of query, field is set if token contains field separator (':')
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct QueryToken {
+        pub field: Option<String>,
+        pub value: String,
+    }
+
+    // Tokenize the query string, handling quoted strings and escaped characters,
+    // ':' is a field separator only if it is not quoted or escaped (\:),
+    // '"' is a quote only if it is not escaped (\")
+    pub fn tokenize(query: &str) -> Vec<QueryToken> {
         let mut tokens = Vec::new();
+        let mut field: Option<String> = None;
         let mut current = String::new();
         let mut in_quotes = false;
+        let mut chars = query.chars().peekable();
 
-        for c in query.chars() {
+        while let Some(c) = chars.next() {
             match c {
                 ' ' if !in_quotes => {
-                    if !current.is_empty() {
-                        tokens.push(current.trim().to_string());
-                        current.clear();
-                    }
+                    push_token(&mut tokens, field.take(), &current);
+                    current.clear();
                 }
                 '"' => {
                     in_quotes = !in_quotes;
                 }
+                '\\' if matches!(chars.peek(), Some(':' | '"' | '\\')) => {
+                    current.extend(chars.next());
+                }
+                ':' if !in_quotes && field.is_none() => {
+                    field = Some(current.trim().to_string());
+                    current.clear();
+                }
                 _ => {
                     current.push(c);
                 }
             }
         }
 
-        if !current.is_empty() {
-            tokens.push(current.trim().to_string());
-        }
+        push_token(&mut tokens, field, &current);
 
         tokens
     }
 
+    // blank tokens (e.g. quoted spaces) are skipped
+    fn push_token(tokens: &mut Vec<QueryToken>, field: Option<String>, value: &str) {
+        let value = value.trim();
+        if field.is_some() || !value.is_empty() {
+            tokens.push(QueryToken {
+                field,
+                value: value.to_string(),
+            });
+        }
+    }
+
     #[derive(Schema, Clone, Debug)]
     pub struct Query {
         pub terms: Vec<String>,
@@ -136,11 +352,10 @@ pub(crate) mod query {
 
             let tokens = tokenize(query);
 
-            for part in tokens {
-                if let Some((field, value)) = part.split_once(':') {
-                    field_filters.push((field.to_lowercase().to_string(), value.to_string()));
-                } else {
-                    terms.push(part.to_string());
+            for token in tokens {
+                match token.field {
+                    Some(field) => field_filters.push((field.to_lowercase(), token.value)),
+                    None => terms.push(token.value),
                 }
             }
 
@@ -149,15 +364,103 @@ pub(crate) mod query {
                 field_filters,
             }
         }
+
+        // Explain how the query is matched against fields of searched entity
+        pub fn explain(
+            &self,
+            query: &str,
+            fields: &[QueryField],
+            term_fields: &[&str],
+        ) -> QueryExplanation {
+            let field_filters: Vec<FieldFilterExplanation> = self
+                .field_filters
+                .iter()
+                .map(|(field, value)| FieldFilterExplanation {
+                    field: field.clone(),
+                    value: value.clone(),
+                    mode: field_mode(fields, field),
+                    wildcard: value == "*",
+                })
+                .collect();
+
+            let matches_nothing = field_filters
+                .iter()
+                .any(|f| f.mode == FieldMatchMode::Unknown);
+
+            QueryExplanation {
+                query: query.to_string(),
+                terms: self.terms.clone(),
+                term_fields: term_fields.iter().map(|f| f.to_string()).collect(),
+                field_filters,
+                matches_nothing,
+            }
+        }
+    }
+
+    #[derive(Schema, Clone, Debug, PartialEq)]
+    pub enum FieldMatchMode {
+        // value is equal to field
+        Exact,
+        // value is equal to field (case insensitive)
+        ExactIgnoreCase,
+        // field contains value (case insensitive)
+        Contains,
+        // location is within radius given by value (lat,lon,radius-km)
+        Radius,
+        // field is accepted, but not checked
+        Ignored,
+        // field is not supported, filter never matches
+        Unknown,
+    }
+
+    // Searchable field, with all accepted names
+    pub struct QueryField {
+        pub names: &'static [&'static str],
+        pub mode: FieldMatchMode,
+    }
+
+    #[derive(Schema, Clone, Debug)]
+    pub struct FieldFilterExplanation {
+        pub field: String,
+        pub value: String,
+        pub mode: FieldMatchMode,
+        // wildcard (*) value matches any field value
+        pub wildcard: bool,
+    }
+
+    #[derive(Schema, Clone, Debug)]
+    pub struct QueryExplanation {
+        pub query: String,
+        // any term has to be contained (case insensitive) in any of term fields
+        pub terms: Vec<String>,
+        pub term_fields: Vec<String>,
+        // all field filters have to match
+        pub field_filters: Vec<FieldFilterExplanation>,
+        // query contains unknown field filter
+        pub matches_nothing: bool,
     }
 }
 
 pub(crate) mod snapshot {
     use serde::{de, Serialize};
 
+    // pretty-printed JSON, snapshots of this version are still loaded
     pub const SERIALIZATION_VERSION_V1: u8 = 1u8;
+    // CBOR, compact and self-describing, so serde defaults of new fields keep working
+    pub const SERIALIZATION_VERSION_V2: u8 = 2u8;
 
     pub(crate) fn serialize<T>(value: &T) -> Result<Vec<u8>, String>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut result = vec![SERIALIZATION_VERSION_V2];
+        ciborium::into_writer(value, &mut result).map_err(|err| err.to_string())?;
+
+        Ok(result)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn serialize_v1<T>(value: &T) -> Result<Vec<u8>, String>
     where
         T: ?Sized + Serialize,
     {
@@ -169,21 +472,159 @@ pub(crate) mod snapshot {
         Ok(result)
     }
 
-    pub(crate) fn deserialize<'a, T>(bytes: &'a [u8]) -> Result<T, String>
+    pub(crate) fn deserialize<T>(bytes: &[u8]) -> Result<T, String>
     where
-        T: de::Deserialize<'a>,
+        T: de::DeserializeOwned,
     {
-        let (version, data) = bytes.split_at(1);
+        let (version, data) = bytes.split_first().ok_or("Empty snapshot".to_string())?;
 
-        match version[0] {
+        match *version {
             SERIALIZATION_VERSION_V1 => {
                 let value: T = serde_json::from_slice(data).map_err(|err| err.to_string())?;
 
                 Ok(value)
             }
+            SERIALIZATION_VERSION_V2 => {
+                let value: T = ciborium::from_reader(data).map_err(|err| err.to_string())?;
+
+                Ok(value)
+            }
             _ => Err("Unsupported serialization version".to_string()),
         }
     }
+
+    // JSON representation of state, arrays are sorted as hash sets are serialized in arbitrary order
+    #[cfg(test)]
+    fn to_comparable<T: Serialize>(value: &T) -> serde_json::Value {
+        fn sorted(value: serde_json::Value) -> serde_json::Value {
+            match value {
+                serde_json::Value::Array(items) => {
+                    let mut items: Vec<_> = items.into_iter().map(sorted).collect();
+                    items.sort_by_key(|item| item.to_string());
+                    serde_json::Value::Array(items)
+                }
+                serde_json::Value::Object(fields) => serde_json::Value::Object(
+                    fields.into_iter().map(|(k, v)| (k, sorted(v))).collect(),
+                ),
+                value => value,
+            }
+        }
+        sorted(serde_json::to_value(value).unwrap())
+    }
+
+    // State is the same (by JSON representation) after save and load of snapshot in both versions
+    #[cfg(test)]
+    pub(crate) fn assert_round_trip<T>(value: &T)
+    where
+        T: Serialize + de::DeserializeOwned,
+    {
+        let expected = to_comparable(value);
+
+        let bytes = serialize(value).unwrap();
+        assert_eq!(bytes[0], SERIALIZATION_VERSION_V2);
+        let loaded: T = deserialize(&bytes).unwrap();
+        assert_eq!(to_comparable(&loaded), expected);
+
+        let bytes = serialize_v1(value).unwrap();
+        assert_eq!(bytes[0], SERIALIZATION_VERSION_V1);
+        let loaded: T = deserialize(&bytes).unwrap();
+        assert_eq!(to_comparable(&loaded), expected);
+    }
+}
+
+pub(crate) mod cursor {
+    use super::SocialNetError;
+    use std::fmt::{Display, Formatter};
+
+    // Cursor is not produced by encode functions, converted to validation error of agent
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct InvalidCursor;
+
+    impl Display for InvalidCursor {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Invalid cursor")
+        }
+    }
+
+    impl From<InvalidCursor> for SocialNetError {
+        fn from(e: InvalidCursor) -> Self {
+            SocialNetError::ValidationFailed(e.to_string())
+        }
+    }
+
+    // Opaque updates cursor, encoded sequence number of the last seen update
+    pub fn encode(sequence: u64) -> String {
+        format!("{sequence:016x}")
+    }
+
+    pub fn decode(cursor: &str) -> Result<u64, InvalidCursor> {
+        u64::from_str_radix(cursor, 16).map_err(|_| InvalidCursor)
+    }
+
+    // Sequence of optional cursor, none (or empty) cursor resolves to current sequence
+    pub fn decode_or(cursor: Option<String>, current: u64) -> Result<u64, InvalidCursor> {
+        match cursor.filter(|c| !c.is_empty()) {
+            Some(cursor) => decode(&cursor),
+            None => Ok(current),
+        }
+    }
+
+    // Opaque page cursor of the last returned item, creation time (micros) and id of item,
+    // the id breaks ties of items created in the same microsecond
+    pub fn encode_key(micros: u64, id: &str) -> String {
+        format!("{}:{id}", encode(micros))
+    }
+
+    // Key of optional page cursor, cursors without id (only creation time) are accepted
+    pub fn decode_key(cursor: Option<String>) -> Result<Option<(u64, String)>, InvalidCursor> {
+        match cursor.filter(|c| !c.is_empty()) {
+            Some(cursor) => match cursor.split_once(':') {
+                Some((micros, id)) => Ok(Some((decode(micros)?, id.to_string()))),
+                None => Ok(Some((decode(&cursor)?, String::new()))),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+pub(crate) mod trace {
+    use std::cell::RefCell;
+
+    thread_local! {
+        static CORRELATION_ID: RefCell<String> = const { RefCell::new(String::new()) };
+    }
+
+    // Set correlation id of the current invocation, new id is generated if none (or empty) is provided
+    pub fn init(correlation_id: Option<String>) -> String {
+        let correlation_id = correlation_id
+            .filter(|id| !id.trim().is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+        CORRELATION_ID.with(|c| *c.borrow_mut() = correlation_id.clone());
+
+        correlation_id
+    }
+
+    pub fn correlation_id() -> String {
+        CORRELATION_ID.with(|c| c.borrow().clone())
+    }
+
+    // Correlation id to propagate to invoked agents
+    pub fn propagate() -> Option<String> {
+        Some(correlation_id()).filter(|id| !id.is_empty())
+    }
+}
+
+// println with correlation id of the current invocation
+#[macro_export]
+macro_rules! trace_println {
+    ($($arg:tt)*) => {
+        println!(
+            "[correlation id: {}] {}",
+            $crate::common::trace::correlation_id(),
+            format!($($arg)*)
+        )
+    };
 }
 
 pub async fn poll_for_updates<T, F, Fut>(
@@ -198,15 +639,43 @@ where
     F: Fn(String, chrono::DateTime<chrono::Utc>) -> Fut,
     Fut: std::future::Future<Output = Option<Vec<T>>>,
 {
-    let since = updates_since.unwrap_or(chrono::Utc::now());
-    let max_wait_time = Duration::from_millis(max_wait_time.unwrap_or(10000) as u64);
-    let iter_wait_time = Duration::from_millis(iter_wait_time.unwrap_or(1000) as u64);
+    let since = updates_since.unwrap_or_else(clock::now);
+    poll_for_updates_since(
+        user_id,
+        since,
+        iter_wait_time,
+        max_wait_time,
+        get_updates_fn,
+        log_prefix,
+    )
+    .await
+}
+
+// Poll for updates since given position (timestamp or cursor)
+pub async fn poll_for_updates_since<S, T, F, Fut>(
+    user_id: String,
+    since: S,
+    iter_wait_time: Option<u32>,
+    max_wait_time: Option<u32>,
+    get_updates_fn: F,
+    log_prefix: &str,
+) -> Option<Vec<T>>
+where
+    S: Display + Clone,
+    F: Fn(String, S) -> Fut,
+    Fut: std::future::Future<Output = Option<Vec<T>>>,
+{
+    let config = crate::operations::get_operations_config().await;
+    let max_wait_time =
+        Duration::from_millis(max_wait_time.unwrap_or(config.poll_max_wait_ms) as u64);
+    let iter_wait_time =
+        Duration::from_millis(iter_wait_time.unwrap_or(config.poll_iter_wait_ms) as u64);
     let now = Instant::now();
     let mut done = false;
     let mut result: Option<Vec<T>> = None;
 
     while !done {
-        println!(
+        crate::trace_println!(
             "{} - user id: {}, updates since: {}, elapsed time: {}ms, max wait time: {}ms",
             log_prefix,
             user_id,
@@ -215,7 +684,11 @@ where
             max_wait_time.as_millis()
         );
 
-        let res = get_updates_fn(user_id.clone(), since).await;
+        let res = match inject_fault(FaultPath::Polling, &user_id).await {
+            // failed poll iteration is repeated
+            Err(_) => Some(vec![]),
+            Ok(()) => get_updates_fn(user_id.clone(), since.clone()).await,
+        };
 
         if let Some(updates) = res {
             if !updates.is_empty() {
@@ -236,6 +709,27 @@ where
     result
 }
 
+// messages and comments not updated for this time are moved to archive agents
+const ARCHIVE_AFTER_DAYS: i64 = 90;
+
+// archival is started by writes only if at least this number of items can be archived
+pub const ARCHIVE_MIN_BATCH: usize = 50;
+
+// Messages and comments last updated before the returned time can be archived
+pub fn archive_cutoff(now: chrono::DateTime<chrono::Utc>) -> chrono::DateTime<chrono::Utc> {
+    now - chrono::Duration::days(ARCHIVE_AFTER_DAYS)
+}
+
+// Datetime of scheduled agent invocation
+pub fn to_schedule_time(
+    time: chrono::DateTime<chrono::Utc>,
+) -> golem_rust::golem_wasm::golem_rpc_0_2_x::types::Datetime {
+    golem_rust::golem_wasm::golem_rpc_0_2_x::types::Datetime {
+        seconds: time.timestamp().max(0) as u64,
+        nanoseconds: time.timestamp_subsec_nanos(),
+    }
+}
+
 pub fn get_shard_number(id: String, num_of_shards: u32) -> u32 {
     assert!(num_of_shards > 0, "Number of shards must be greater than 0");
 
@@ -317,3 +811,413 @@ mod sharding_tests {
         assert!(shard2 < num_shards);
     }
 }
+
+#[cfg(test)]
+mod like_tests {
+    use super::{LikeCounts, LikeSummary, LikeType, LikersPage};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_like_counts() {
+        let likes = HashMap::from([
+            ("user1".to_string(), LikeType::Like),
+            ("user2".to_string(), LikeType::Funny),
+            ("user3".to_string(), LikeType::Celebrate),
+            ("user4".to_string(), LikeType::Funny),
+        ]);
+
+        assert_eq!(
+            LikeCounts::from_likes(likes.values()),
+            LikeCounts {
+                like: 1,
+                celebrate: 1,
+                funny: 2,
+                total: 4,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_like_type_serialization() {
+        // previously stored types are read unchanged
+        let likes: HashMap<String, LikeType> =
+            serde_json::from_str(r#"{"user1": "Like", "user2": "Dislike"}"#).unwrap();
+        assert_eq!(likes.get("user1"), Some(&LikeType::Like));
+        assert_eq!(likes.get("user2"), Some(&LikeType::Dislike));
+
+        let json = serde_json::to_string(&LikeType::Celebrate).unwrap();
+        assert_eq!(json, r#""Celebrate""#);
+        assert_eq!(
+            serde_json::from_str::<LikeType>(&json).unwrap(),
+            LikeType::Celebrate
+        );
+    }
+
+    #[test]
+    fn test_like_summary() {
+        let likes = HashMap::from([
+            ("user1".to_string(), LikeType::Like),
+            ("user2".to_string(), LikeType::Funny),
+        ]);
+
+        let summary = LikeSummary::from_likes(&likes, Some("user2"));
+        assert_eq!(summary.counts.total, 2);
+        assert_eq!(summary.user_like_type, Some(LikeType::Funny));
+        assert_eq!(
+            LikeSummary::from_likes(&likes, Some("user3")).user_like_type,
+            None
+        );
+        assert_eq!(LikeSummary::from_likes(&likes, None).user_like_type, None);
+    }
+
+    #[test]
+    fn test_likers_page() {
+        let likes: HashMap<String, LikeType> = (0..5)
+            .map(|i| {
+                let like_type = if i % 2 == 0 {
+                    LikeType::Like
+                } else {
+                    LikeType::Love
+                };
+                (format!("user{i}"), like_type)
+            })
+            .collect();
+        let user_ids = |page: &LikersPage| -> Vec<String> {
+            page.likers.iter().map(|l| l.user_id.clone()).collect()
+        };
+
+        let page = LikersPage::from_likes(&likes, None, None, Some(2)).unwrap();
+        assert_eq!(user_ids(&page), vec!["user0", "user1"]);
+
+        let page = LikersPage::from_likes(&likes, None, page.next_cursor, Some(2)).unwrap();
+        let page = LikersPage::from_likes(&likes, None, page.next_cursor, Some(2)).unwrap();
+        assert_eq!(user_ids(&page), vec!["user4"]);
+        assert_eq!(page.next_cursor, None);
+
+        let page = LikersPage::from_likes(&likes, Some(LikeType::Love), None, None).unwrap();
+        assert_eq!(user_ids(&page), vec!["user1", "user3"]);
+
+        assert!(LikersPage::from_likes(&likes, None, Some("x".to_string()), None).is_err());
+    }
+}
+
+#[cfg(test)]
+mod snapshot_tests {
+    use super::snapshot;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct StateV1 {
+        id: String,
+        counts: HashMap<String, u32>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct StateV2 {
+        id: String,
+        counts: HashMap<String, u32>,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    fn state() -> StateV1 {
+        StateV1 {
+            id: "s1".to_string(),
+            counts: HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)]),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_versions() {
+        let bytes = snapshot::serialize(&state()).unwrap();
+        assert_eq!(bytes[0], snapshot::SERIALIZATION_VERSION_V2);
+        assert_eq!(snapshot::deserialize::<StateV1>(&bytes), Ok(state()));
+
+        // snapshots saved before V2 are loaded
+        let bytes = snapshot::serialize_v1(&state()).unwrap();
+        assert_eq!(bytes[0], snapshot::SERIALIZATION_VERSION_V1);
+        assert_eq!(snapshot::deserialize::<StateV1>(&bytes), Ok(state()));
+
+        assert!(
+            snapshot::serialize(&state()).unwrap().len()
+                < snapshot::serialize_v1(&state()).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_snapshot_new_field_default() {
+        for bytes in [
+            snapshot::serialize(&state()).unwrap(),
+            snapshot::serialize_v1(&state()).unwrap(),
+        ] {
+            let loaded: StateV2 = snapshot::deserialize(&bytes).unwrap();
+            assert_eq!(loaded.id, "s1");
+            assert_eq!(loaded.counts.len(), 2);
+            assert!(loaded.tags.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_snapshot_invalid() {
+        assert!(snapshot::deserialize::<StateV1>(&[]).is_err());
+        assert_eq!(
+            snapshot::deserialize::<StateV1>(&[9, 1, 2]),
+            Err("Unsupported serialization version".to_string())
+        );
+        assert!(
+            snapshot::deserialize::<StateV1>(&[snapshot::SERIALIZATION_VERSION_V2, 1]).is_err()
+        );
+    }
+}
+
+#[cfg(test)]
+mod cursor_tests {
+    use super::{cursor, SocialNetError};
+    use cursor::InvalidCursor;
+
+    #[test]
+    fn test_cursor_encode_decode() {
+        for sequence in [0, 1, 42, u64::MAX] {
+            assert_eq!(cursor::decode(&cursor::encode(sequence)), Ok(sequence));
+        }
+        assert!(cursor::encode(2) < cursor::encode(10));
+    }
+
+    #[test]
+    fn test_cursor_decode_invalid() {
+        assert_eq!(cursor::decode("xyz"), Err(InvalidCursor));
+        assert_eq!(
+            SocialNetError::from(InvalidCursor),
+            SocialNetError::ValidationFailed("Invalid cursor".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cursor_decode_or() {
+        assert_eq!(cursor::decode_or(None, 5), Ok(5));
+        assert_eq!(cursor::decode_or(Some("".to_string()), 5), Ok(5));
+        assert_eq!(cursor::decode_or(Some(cursor::encode(3)), 5), Ok(3));
+    }
+
+    #[test]
+    fn test_cursor_encode_decode_key() {
+        let cursor = cursor::encode_key(42, "c:1");
+        assert_eq!(
+            cursor::decode_key(Some(cursor)),
+            Ok(Some((42, "c:1".to_string())))
+        );
+        assert_eq!(
+            cursor::decode_key(Some(cursor::encode(42))),
+            Ok(Some((42, String::new())))
+        );
+        assert_eq!(cursor::decode_key(Some("".to_string())), Ok(None));
+        assert!(cursor::decode_key(Some("xyz:1".to_string())).is_err());
+    }
+}
+
+#[cfg(test)]
+mod query_tests {
+    use super::query::{
+        exact_matches, field_mode, opt_exact_matches, tokenize, FieldMatchMode, Query, QueryField,
+    };
+    use proptest::prelude::*;
+
+    const FIELDS: &[QueryField] = &[
+        QueryField {
+            names: &["user-id", "userid"],
+            mode: FieldMatchMode::Exact,
+        },
+        QueryField {
+            names: &["name"],
+            mode: FieldMatchMode::Contains,
+        },
+    ];
+
+    #[test]
+    fn test_query_explain() {
+        let query = "john \"jane doe\" Name:jo userid:*";
+        let explanation = Query::new(query).explain(query, FIELDS, &["user-id", "name"]);
+
+        assert_eq!(explanation.terms, vec!["john", "jane doe"]);
+        assert_eq!(explanation.term_fields, vec!["user-id", "name"]);
+        assert_eq!(explanation.field_filters.len(), 2);
+        assert_eq!(explanation.field_filters[0].field, "name");
+        assert_eq!(explanation.field_filters[0].mode, FieldMatchMode::Contains);
+        assert!(!explanation.field_filters[0].wildcard);
+        assert_eq!(explanation.field_filters[1].mode, FieldMatchMode::Exact);
+        assert!(explanation.field_filters[1].wildcard);
+        assert!(!explanation.matches_nothing);
+    }
+
+    #[test]
+    fn test_exact_matches_by_field_mode() {
+        let fields = &[
+            QueryField {
+                names: &["user-id"],
+                mode: FieldMatchMode::Exact,
+            },
+            QueryField {
+                names: &["email"],
+                mode: FieldMatchMode::ExactIgnoreCase,
+            },
+        ];
+        let user_id_mode = field_mode(fields, "user-id");
+        let email_mode = field_mode(fields, "email");
+
+        assert_eq!(email_mode, FieldMatchMode::ExactIgnoreCase);
+        assert_eq!(field_mode(fields, "age"), FieldMatchMode::Unknown);
+        assert!(exact_matches(&email_mode, "A@b.com", "a@B.com"));
+        assert!(!exact_matches(&user_id_mode, "User1", "user1"));
+        assert!(exact_matches(&user_id_mode, "User1", "User1"));
+        assert!(opt_exact_matches(&email_mode, None, "*"));
+        assert!(!opt_exact_matches(&email_mode, None, "a@b.com"));
+    }
+
+    #[test]
+    fn test_query_explain_unknown_field() {
+        let query = "age:42";
+        let explanation = Query::new(query).explain(query, FIELDS, &[]);
+
+        assert_eq!(explanation.field_filters[0].mode, FieldMatchMode::Unknown);
+        assert!(explanation.matches_nothing);
+    }
+
+    #[test]
+    fn test_tokenize_adversarial() {
+        assert_eq!(
+            Query::new("\"unterminated quote").terms,
+            vec!["unterminated quote"]
+        );
+        assert_eq!(Query::new("a \" \" b").terms, vec!["a", "b"]);
+        assert!(tokenize("\"\"").is_empty());
+        assert_eq!(Query::new("\\").terms, vec!["\\"]);
+
+        let query = Query::new(":: name: :x content:\"a b\"");
+        assert_eq!(
+            query.field_filters,
+            vec![
+                ("".to_string(), ":".to_string()),
+                ("name".to_string(), "".to_string()),
+                ("".to_string(), "x".to_string()),
+                ("content".to_string(), "a b".to_string()),
+            ]
+        );
+        assert!(query.terms.is_empty());
+    }
+
+    #[test]
+    fn test_query_escaping() {
+        let query = Query::new(
+            r#"content:"a:b" "12:30" 12\:45 url:"https://x.com/?q=1" say\"hi\" "c:\"d\"" a\b"#,
+        );
+        assert_eq!(
+            query.terms,
+            vec!["12:30", "12:45", "say\"hi\"", "c:\"d\"", "a\\b"]
+        );
+        assert_eq!(
+            query.field_filters,
+            vec![
+                ("content".to_string(), "a:b".to_string()),
+                ("url".to_string(), "https://x.com/?q=1".to_string()),
+            ]
+        );
+
+        let query = Query::new(r#"content:12:30 na\:me:x"#);
+        assert_eq!(
+            query.field_filters,
+            vec![
+                ("content".to_string(), "12:30".to_string()),
+                ("na:me".to_string(), "x".to_string()),
+            ]
+        );
+    }
+
+    // query string of parsed query, values are quoted and escaped
+    fn to_query_string(terms: &[String], field_filters: &[(String, String)]) -> String {
+        let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+        terms
+            .iter()
+            .map(|t| format!("\"{}\"", escape(t)))
+            .chain(
+                field_filters
+                    .iter()
+                    .map(|(f, v)| format!("{}:\"{}\"", escape(f).replace(':', "\\:"), escape(v))),
+            )
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    proptest! {
+        #[test]
+        fn prop_tokenize_no_panic(query in "\\PC*") {
+            let tokens = tokenize(&query);
+            prop_assert!(tokens
+                .iter()
+                .all(|t| (t.field.is_some() || !t.value.is_empty()) && t.value.trim() == t.value));
+        }
+
+        #[test]
+        fn prop_query_new_no_panic(query in "[a-zA-Z0-9:\"\\\\ *ěščřžÄÖÜ日本🙂-]{0,64}") {
+            let parsed = Query::new(&query);
+            prop_assert!(parsed.terms.iter().all(|t| !t.is_empty()));
+            let explanation = parsed.explain(&query, FIELDS, &["name"]);
+            prop_assert_eq!(explanation.field_filters.len(), parsed.field_filters.len());
+        }
+
+        #[test]
+        fn prop_query_round_trip(
+            terms in prop::collection::vec("[a-zA-Z0-9*:\"\\\\ěščÄ日🙂-]+( [a-zA-Z0-9*:\"\\\\ěščÄ日🙂-]+)?", 0..4),
+            field_filters in prop::collection::vec(("[a-z:-]{1,10}", "([a-zA-Z0-9*:\"\\\\ěščÄ日🙂-]+( [a-zA-Z0-9:]+)?)?"), 0..4),
+        ) {
+            let query = Query::new(&to_query_string(&terms, &field_filters));
+            prop_assert_eq!(&query.terms, &terms);
+            prop_assert_eq!(&query.field_filters, &field_filters);
+
+            let query_string = to_query_string(&query.terms, &query.field_filters);
+            let reparsed = Query::new(&query_string);
+            prop_assert_eq!(reparsed.terms, query.terms);
+            prop_assert_eq!(reparsed.field_filters, query.field_filters);
+        }
+    }
+}
+
+#[cfg(test)]
+mod error_tests {
+    use super::SocialNetError;
+
+    #[test]
+    fn test_social_net_error() {
+        let error = SocialNetError::ValidationFailed("Invalid cursor".to_string());
+        assert_eq!(error.to_string(), "Invalid cursor");
+        assert_eq!(
+            SocialNetError::not_found("User not exists").to_string(),
+            "User not exists"
+        );
+    }
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use super::trace;
+
+    #[test]
+    fn test_trace_init_with_id() {
+        let id = trace::init(Some("cid-1".to_string()));
+        assert_eq!(id, "cid-1");
+        assert_eq!(trace::correlation_id(), "cid-1");
+        assert_eq!(trace::propagate(), Some("cid-1".to_string()));
+    }
+
+    #[test]
+    fn test_trace_init_generates_id() {
+        let id = trace::init(None);
+        assert!(!id.is_empty());
+        assert_eq!(trace::correlation_id(), id);
+
+        let id2 = trace::init(Some(" ".to_string()));
+        assert_ne!(id, id2);
+        uuid::Uuid::parse_str(&id2).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+use crate::common::id;
+use std::sync::LazyLock;
+
+// env variable with namespace of agent ids, for multiple logical environments (staging, test) in one deployment
+const NAMESPACE_ENV_VAR: &str = "SOCIAL_NET_NAMESPACE";
+
+const NAMESPACE_SEPARATOR: char = ':';
+
+static NAMESPACE: LazyLock<Option<String>> = LazyLock::new(|| {
+    std::env::var(NAMESPACE_ENV_VAR)
+        .ok()
+        .map(|ns| ns.trim().to_string())
+        .filter(|ns| !ns.is_empty())
+});
+
+// Configured namespace, none if agent ids are not namespaced
+pub fn get_namespace() -> Option<&'static str> {
+    NAMESPACE.as_deref()
+}
+
+// Id with namespace prefix (namespace:id), unchanged if it has the prefix already
+pub fn namespaced_id(namespace: Option<&str>, id: String) -> String {
+    match namespace {
+        Some(ns) if !is_in_namespace(Some(ns), &id) => {
+            format!("{ns}{NAMESPACE_SEPARATOR}{id}")
+        }
+        _ => id,
+    }
+}
+
+// Id belongs to namespace, all ids belong to no (default) namespace
+pub fn is_in_namespace(namespace: Option<&str>, id: &str) -> bool {
+    match namespace {
+        Some(ns) => id
+            .strip_prefix(ns)
+            .is_some_and(|rest| rest.starts_with(NAMESPACE_SEPARATOR)),
+        None => true,
+    }
+}
+
+// Id belongs to configured namespace
+pub fn in_namespace(id: &str) -> bool {
+    is_in_namespace(get_namespace(), id)
+}
+
+// New unique id (of post, chat) in configured namespace
+pub fn new_id() -> String {
+    namespaced_id(get_namespace(), id::new_id())
+}
+
+// Prefix of agent names (agent-type("namespace:...) of agents in namespace
+pub fn agent_name_prefix(namespace: Option<&str>, agent_type: &str) -> String {
+    match namespace {
+        Some(ns) => format!("{agent_type}(\"{ns}{NAMESPACE_SEPARATOR}"),
+        None => format!("{agent_type}("),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespaced_id() {
+        assert_eq!(namespaced_id(None, "user1".to_string()), "user1");
+        assert_eq!(
+            namespaced_id(Some("staging"), "user1".to_string()),
+            "staging:user1"
+        );
+        assert_eq!(
+            namespaced_id(Some("staging"), "staging:user1".to_string()),
+            "staging:user1"
+        );
+        assert_eq!(
+            namespaced_id(Some("staging"), "test:user1".to_string()),
+            "staging:test:user1"
+        );
+    }
+
+    #[test]
+    fn test_is_in_namespace() {
+        assert!(is_in_namespace(None, "user1"));
+        assert!(is_in_namespace(None, "staging:user1"));
+        assert!(is_in_namespace(Some("staging"), "staging:user1"));
+        assert!(!is_in_namespace(Some("staging"), "user1"));
+        assert!(!is_in_namespace(Some("staging"), "staging-2:user1"));
+        assert!(!is_in_namespace(Some("test"), "staging:user1"));
+    }
+
+    #[test]
+    fn test_agent_name_prefix() {
+        assert_eq!(agent_name_prefix(None, "post-agent"), "post-agent(");
+        assert_eq!(
+            agent_name_prefix(Some("test"), "post-agent"),
+            "post-agent(\"test:"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use chrono::{Offset, TimeZone};
+use golem_rust::Schema;
+use serde::{Deserialize, Serialize};
+
+// max timezone offset from UTC
+const UTC_OFFSET_MAX_MINUTES: i32 = 14 * 60;
+
+// Local time in HH:MM format
+pub fn parse_local_time(value: &str) -> Result<chrono::NaiveTime, String> {
+    chrono::NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_| format!("Invalid time: {value}, expected HH:MM"))
+}
+
+// Local date and time in YYYY-MM-DDTHH:MM format
+pub fn parse_local_datetime(value: &str) -> Result<chrono::NaiveDateTime, String> {
+    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .map_err(|_| format!("Invalid date and time: {value}, expected YYYY-MM-DDTHH:MM"))
+}
+
+// Timezone by IANA name (e.g. Europe/Berlin)
+pub fn parse_timezone(name: &str) -> Result<chrono_tz::Tz, String> {
+    name.trim()
+        .parse::<chrono_tz::Tz>()
+        .map_err(|_| format!("Invalid timezone: {name}"))
+}
+
+// UTC time of local time in timezone, DST-safe: local time repeated when clocks go back is
+// the first occurrence, local time skipped when clocks go forward is moved forward by the gap
+pub fn local_to_utc(
+    local: chrono::NaiveDateTime,
+    timezone: &chrono_tz::Tz,
+) -> chrono::DateTime<chrono::Utc> {
+    match timezone.from_local_datetime(&local) {
+        chrono::LocalResult::Single(time) => time.with_timezone(&chrono::Utc),
+        chrono::LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&chrono::Utc),
+        chrono::LocalResult::None => {
+            // offset in effect before the gap (gaps are shorter than a day)
+            let offset = timezone
+                .offset_from_utc_datetime(&(local - chrono::Duration::days(1)))
+                .fix();
+            (local - chrono::Duration::seconds(offset.local_minus_utc() as i64)).and_utc()
+        }
+    }
+}
+
+pub fn validate_utc_offset(utc_offset_minutes: i32) -> Result<(), String> {
+    if utc_offset_minutes.abs() > UTC_OFFSET_MAX_MINUTES {
+        Err(format!("Invalid UTC offset: {utc_offset_minutes} minutes"))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn utc_offset(utc_offset_minutes: i32) -> Option<chrono::FixedOffset> {
+    chrono::FixedOffset::east_opt(utc_offset_minutes * 60)
+}
+
+// Job running every day at given local time of user
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct DailySchedule {
+    // local time in HH:MM format
+    pub time: String,
+    // timezone of user, offset from UTC in minutes
+    pub utc_offset_minutes: i32,
+    // timezone of user (IANA name), follows daylight saving time, UTC offset is ignored if set
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+impl DailySchedule {
+    pub fn validated(self) -> Result<Self, String> {
+        parse_local_time(&self.time)?;
+        validate_utc_offset(self.utc_offset_minutes)?;
+        if let Some(timezone) = &self.timezone {
+            parse_timezone(timezone)?;
+        }
+        Ok(self)
+    }
+
+    // First run strictly after given time
+    pub fn next_run_after(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Option<chrono::DateTime<chrono::Utc>> {
+        let time = parse_local_time(&self.time).ok()?;
+        if let Some(timezone) = &self.timezone {
+            let timezone = parse_timezone(timezone).ok()?;
+            let today = now.with_timezone(&timezone).date_naive();
+            let run_at = local_to_utc(today.and_time(time), &timezone);
+            return if run_at > now {
+                Some(run_at)
+            } else {
+                Some(local_to_utc(today.succ_opt()?.and_time(time), &timezone))
+            };
+        }
+        let offset = utc_offset(self.utc_offset_minutes)?;
+        let local = now.with_timezone(&offset);
+
+        let run_at = local
+            .date_naive()
+            .and_time(time)
+            .and_local_timezone(offset)
+            .single()?;
+        let run_at = if run_at > local {
+            run_at
+        } else {
+            run_at + chrono::Duration::days(1)
+        };
+        Some(run_at.with_timezone(&chrono::Utc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    fn schedule(time: &str, utc_offset_minutes: i32) -> DailySchedule {
+        DailySchedule {
+            time: time.to_string(),
+            utc_offset_minutes,
+            timezone: None,
+        }
+    }
+
+    fn local(value: &str) -> chrono::NaiveDateTime {
+        parse_local_datetime(value).unwrap()
+    }
+
+    #[test]
+    fn test_daily_schedule_validated() {
+        assert!(schedule("08:30", -300).validated().is_ok());
+        assert!(schedule("8:30pm", 0).validated().is_err());
+        assert!(schedule("08:30", 15 * 60).validated().is_err());
+
+        let mut daily = schedule("08:30", 0);
+        daily.timezone = Some("Europe/Berlin".to_string());
+        assert!(daily.clone().validated().is_ok());
+        daily.timezone = Some("Europe/Nowhere".to_string());
+        assert!(daily.validated().is_err());
+    }
+
+    #[test]
+    fn test_local_to_utc_dst() {
+        let berlin = parse_timezone("Europe/Berlin").unwrap();
+        // winter (UTC+1) and summer (UTC+2) time
+        assert_eq!(
+            local_to_utc(local("2024-01-15T09:00"), &berlin),
+            time("2024-01-15T08:00:00Z")
+        );
+        assert_eq!(
+            local_to_utc(local("2024-07-15T09:00"), &berlin),
+            time("2024-07-15T07:00:00Z")
+        );
+        // clocks go forward 02:00 -> 03:00, skipped local time is moved forward
+        assert_eq!(
+            local_to_utc(local("2024-03-31T02:30"), &berlin),
+            time("2024-03-31T01:30:00Z")
+        );
+        // clocks go back 03:00 -> 02:00, repeated local time is the first occurrence
+        assert_eq!(
+            local_to_utc(local("2024-10-27T02:30"), &berlin),
+            time("2024-10-27T00:30:00Z")
+        );
+    }
+
+    #[test]
+    fn test_daily_schedule_next_run_across_dst() {
+        let mut daily = schedule("08:00", 0);
+        daily.timezone = Some("America/New_York".to_string());
+        // last run in EST (UTC-5), next in EDT (UTC-4), clocks go forward on 2024-03-10
+        assert_eq!(
+            daily.next_run_after(time("2024-03-09T13:00:00Z")),
+            Some(time("2024-03-10T12:00:00Z"))
+        );
+        assert_eq!(
+            daily.next_run_after(time("2024-03-10T12:00:00Z")),
+            Some(time("2024-03-11T12:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_daily_schedule_next_run_after() {
+        // 08:00 in UTC-5
+        let daily = schedule("08:00", -300);
+        assert_eq!(
+            daily.next_run_after(time("2024-01-01T12:00:00Z")),
+            Some(time("2024-01-01T13:00:00Z"))
+        );
+        assert_eq!(
+            daily.next_run_after(time("2024-01-01T13:00:00Z")),
+            Some(time("2024-01-02T13:00:00Z"))
+        );
+        // local date is still previous day
+        assert_eq!(
+            daily.next_run_after(time("2024-01-02T02:00:00Z")),
+            Some(time("2024-01-02T13:00:00Z"))
+        );
+    }
+}
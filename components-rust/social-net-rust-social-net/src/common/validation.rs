@@ -0,0 +1,216 @@
+use crate::common::SocialNetError;
+use golem_rust::Schema;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::sync::LazyLock;
+
+// env variables with max lengths (in characters) of user content, defaults are used if not set or invalid
+const POST_CONTENT_MAX_LENGTH_ENV_VAR: &str = "SOCIAL_NET_POST_CONTENT_MAX_LENGTH";
+const COMMENT_CONTENT_MAX_LENGTH_ENV_VAR: &str = "SOCIAL_NET_COMMENT_CONTENT_MAX_LENGTH";
+const MESSAGE_CONTENT_MAX_LENGTH_ENV_VAR: &str = "SOCIAL_NET_MESSAGE_CONTENT_MAX_LENGTH";
+const USER_NAME_MAX_LENGTH_ENV_VAR: &str = "SOCIAL_NET_USER_NAME_MAX_LENGTH";
+const USER_BIO_MAX_LENGTH_ENV_VAR: &str = "SOCIAL_NET_USER_BIO_MAX_LENGTH";
+
+// Max lengths (in characters) of user content
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationLimits {
+    pub post_content_max_length: u32,
+    pub comment_content_max_length: u32,
+    pub message_content_max_length: u32,
+    pub user_name_max_length: u32,
+    pub user_bio_max_length: u32,
+}
+
+impl Default for ValidationLimits {
+    fn default() -> Self {
+        ValidationLimits {
+            post_content_max_length: 5000,
+            comment_content_max_length: 2000,
+            message_content_max_length: 4000,
+            user_name_max_length: 100,
+            user_bio_max_length: 300,
+        }
+    }
+}
+
+impl ValidationLimits {
+    // Limits with values of env variables (by name), defaults for missing or invalid values
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let limit = |name: &str, default: u32| {
+            var(name)
+                .and_then(|v| v.trim().parse::<u32>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default)
+        };
+        let defaults = ValidationLimits::default();
+        ValidationLimits {
+            post_content_max_length: limit(
+                POST_CONTENT_MAX_LENGTH_ENV_VAR,
+                defaults.post_content_max_length,
+            ),
+            comment_content_max_length: limit(
+                COMMENT_CONTENT_MAX_LENGTH_ENV_VAR,
+                defaults.comment_content_max_length,
+            ),
+            message_content_max_length: limit(
+                MESSAGE_CONTENT_MAX_LENGTH_ENV_VAR,
+                defaults.message_content_max_length,
+            ),
+            user_name_max_length: limit(
+                USER_NAME_MAX_LENGTH_ENV_VAR,
+                defaults.user_name_max_length,
+            ),
+            user_bio_max_length: limit(USER_BIO_MAX_LENGTH_ENV_VAR, defaults.user_bio_max_length),
+        }
+    }
+}
+
+static LIMITS: LazyLock<ValidationLimits> =
+    LazyLock::new(|| ValidationLimits::from_vars(|name| std::env::var(name).ok()));
+
+// Configured max lengths of user content
+pub fn get_limits() -> &'static ValidationLimits {
+    &LIMITS
+}
+
+// Validated field of user content
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ContentField {
+    PostContent,
+    CommentContent,
+    MessageContent,
+    UserName,
+    UserBio,
+}
+
+impl ContentField {
+    fn label(&self) -> &'static str {
+        match self {
+            ContentField::PostContent => "Post content",
+            ContentField::CommentContent => "Comment content",
+            ContentField::MessageContent => "Message content",
+            ContentField::UserName => "Name",
+            ContentField::UserBio => "Bio",
+        }
+    }
+
+    fn max_length(&self, limits: &ValidationLimits) -> u32 {
+        match self {
+            ContentField::PostContent => limits.post_content_max_length,
+            ContentField::CommentContent => limits.comment_content_max_length,
+            ContentField::MessageContent => limits.message_content_max_length,
+            ContentField::UserName => limits.user_name_max_length,
+            ContentField::UserBio => limits.user_bio_max_length,
+        }
+    }
+}
+
+// Content is longer than max length of field
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ContentTooLong {
+    pub field: ContentField,
+    pub length: u32,
+    pub max_length: u32,
+}
+
+impl Display for ContentTooLong {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is too long, max length: {}",
+            self.field.label(),
+            self.max_length
+        )
+    }
+}
+
+impl From<ContentTooLong> for SocialNetError {
+    fn from(error: ContentTooLong) -> Self {
+        SocialNetError::ContentTooLong(error)
+    }
+}
+
+// Check of content length (in characters) with given limits
+pub fn check_length_with(
+    limits: &ValidationLimits,
+    field: ContentField,
+    value: &str,
+) -> Result<(), ContentTooLong> {
+    let max_length = field.max_length(limits);
+    let length = value.chars().count();
+    if length > max_length as usize {
+        Err(ContentTooLong {
+            field,
+            length: length.min(u32::MAX as usize) as u32,
+            max_length,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+// Check of content length (in characters) with configured limits
+pub fn check_length(field: ContentField, value: &str) -> Result<(), ContentTooLong> {
+    check_length_with(get_limits(), field, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_limits_from_vars() {
+        assert_eq!(
+            ValidationLimits::from_vars(|_| None),
+            ValidationLimits::default()
+        );
+
+        let vars = HashMap::from([
+            (POST_CONTENT_MAX_LENGTH_ENV_VAR, " 100 "),
+            (COMMENT_CONTENT_MAX_LENGTH_ENV_VAR, "abc"),
+            (MESSAGE_CONTENT_MAX_LENGTH_ENV_VAR, "0"),
+            (USER_NAME_MAX_LENGTH_ENV_VAR, "-1"),
+        ]);
+        let limits = ValidationLimits::from_vars(|name| vars.get(name).map(|v| v.to_string()));
+        let defaults = ValidationLimits::default();
+        assert_eq!(limits.post_content_max_length, 100);
+        assert_eq!(
+            limits.comment_content_max_length,
+            defaults.comment_content_max_length
+        );
+        assert_eq!(
+            limits.message_content_max_length,
+            defaults.message_content_max_length
+        );
+        assert_eq!(limits.user_name_max_length, defaults.user_name_max_length);
+        assert_eq!(limits.user_bio_max_length, defaults.user_bio_max_length);
+    }
+
+    #[test]
+    fn test_check_length() {
+        let limits = ValidationLimits {
+            post_content_max_length: 5,
+            ..ValidationLimits::default()
+        };
+
+        assert!(check_length_with(&limits, ContentField::PostContent, "abcde").is_ok());
+        // length in characters, not bytes
+        assert!(check_length_with(&limits, ContentField::PostContent, "ééééé").is_ok());
+
+        let error = check_length_with(&limits, ContentField::PostContent, "abcdef").unwrap_err();
+        assert_eq!(
+            error,
+            ContentTooLong {
+                field: ContentField::PostContent,
+                length: 6,
+                max_length: 5,
+            }
+        );
+        assert_eq!(error.to_string(), "Post content is too long, max length: 5");
+        assert_eq!(
+            SocialNetError::from(error.clone()),
+            SocialNetError::ContentTooLong(error)
+        );
+    }
+}
@@ -0,0 +1,583 @@
+use crate::chat::MediaMetadata;
+use crate::common::geo::Location;
+use crate::common::schedule::{local_to_utc, parse_local_datetime, parse_timezone};
+use crate::common::{clock, content, id, to_schedule_time, trace};
+use crate::post::{PostError, POST_ATTACHMENTS_MAX_COUNT};
+use crate::trace_println;
+use crate::user::get_user_timezone;
+use crate::user_posts::UserPostsAgentClient;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum DraftStatus {
+    Open,
+    Published,
+    Discarded,
+}
+
+// Media reference of draft, media is set when upload is completed
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct DraftAttachment {
+    pub attachment_id: String,
+    pub media: Option<MediaMetadata>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub uploaded_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl DraftAttachment {
+    pub fn is_uploaded(&self) -> bool {
+        self.media.is_some()
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct DraftValidation {
+    pub valid: bool,
+    pub issues: Vec<String>,
+}
+
+// Multi-step composition of post, post is created only when draft is published
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct DraftSession {
+    // reserved id of published post
+    pub post_id: String,
+    pub user_id: String,
+    pub content: String,
+    pub location: Option<Location>,
+    pub attachments: Vec<DraftAttachment>,
+    pub status: DraftStatus,
+    // time of scheduled publishing, none if not scheduled
+    #[serde(default)]
+    pub publish_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl DraftSession {
+    fn new(post_id: String, user_id: String) -> Self {
+        let now = clock::now();
+        DraftSession {
+            post_id,
+            user_id,
+            content: "".to_string(),
+            location: None,
+            attachments: vec![],
+            status: DraftStatus::Open,
+            publish_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    // Check that draft of the user can be changed
+    fn check_open(&self, user_id: &str) -> Result<(), PostError> {
+        if self.user_id != user_id {
+            Err(PostError::not_found("Draft not found"))
+        } else {
+            match self.status {
+                DraftStatus::Open => Ok(()),
+                DraftStatus::Published => Err(PostError::invalid("Draft already published")),
+                DraftStatus::Discarded => Err(PostError::invalid("Draft discarded")),
+            }
+        }
+    }
+
+    fn set_content(
+        &mut self,
+        content: String,
+        location: Option<Location>,
+    ) -> Result<(), PostError> {
+        self.location = location
+            .map(|l| l.validated())
+            .transpose()
+            .map_err(PostError::Invalid)?;
+        self.content = content::sanitize(&content);
+        self.updated_at = clock::now();
+        Ok(())
+    }
+
+    fn add_attachment(&mut self, attachment_id: String) -> Result<(), PostError> {
+        if self.attachments.len() >= POST_ATTACHMENTS_MAX_COUNT {
+            Err(PostError::Invalid(format!(
+                "Too many attachments, max count: {POST_ATTACHMENTS_MAX_COUNT}"
+            )))
+        } else {
+            let now = clock::now();
+            self.attachments.push(DraftAttachment {
+                attachment_id,
+                media: None,
+                created_at: now,
+                uploaded_at: None,
+            });
+            self.updated_at = now;
+            Ok(())
+        }
+    }
+
+    fn complete_attachment(
+        &mut self,
+        attachment_id: &str,
+        media: MediaMetadata,
+    ) -> Result<(), PostError> {
+        media.validate("attachment").map_err(PostError::Invalid)?;
+        let attachment = self
+            .attachments
+            .iter_mut()
+            .find(|a| a.attachment_id == attachment_id)
+            .ok_or(PostError::not_found("Attachment not found"))?;
+        let now = clock::now();
+        attachment.media = Some(media);
+        attachment.uploaded_at = Some(now);
+        self.updated_at = now;
+        Ok(())
+    }
+
+    fn remove_attachment(&mut self, attachment_id: &str) -> bool {
+        let count = self.attachments.len();
+        self.attachments
+            .retain(|a| a.attachment_id != attachment_id);
+        let removed = self.attachments.len() < count;
+        if removed {
+            self.updated_at = clock::now();
+        }
+        removed
+    }
+
+    fn validate(&self) -> DraftValidation {
+        let mut issues = vec![];
+        if self.content.is_empty() {
+            issues.push("Post content is empty".to_string());
+        }
+        let pending = self.attachments.iter().filter(|a| !a.is_uploaded()).count();
+        if pending > 0 {
+            issues.push(format!("Attachments not uploaded: {pending}"));
+        }
+        DraftValidation {
+            valid: issues.is_empty(),
+            issues,
+        }
+    }
+
+    fn attachments_media(&self) -> Vec<MediaMetadata> {
+        self.attachments
+            .iter()
+            .filter_map(|a| a.media.clone())
+            .collect()
+    }
+
+    fn schedule_publish(
+        &mut self,
+        publish_at: chrono::DateTime<chrono::Utc>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(), PostError> {
+        let validation = self.validate();
+        if !validation.valid {
+            Err(PostError::Invalid(validation.issues.join(", ")))
+        } else if publish_at <= now {
+            Err(PostError::invalid("Publish time is in the past"))
+        } else {
+            self.publish_at = Some(publish_at);
+            self.updated_at = now;
+            Ok(())
+        }
+    }
+
+    fn set_status(&mut self, status: DraftStatus) {
+        self.status = status;
+        self.updated_at = clock::now();
+    }
+}
+
+#[agent_definition]
+trait DraftSessionAgent {
+    fn new(id: String) -> Self;
+
+    fn get_draft(&self) -> Option<DraftSession>;
+
+    // Start draft of user with reserved post id (called by UserPostsAgent::create_draft)
+    fn start(&mut self, user_id: String, correlation_id: Option<String>) -> Result<(), PostError>;
+
+    fn set_content(
+        &mut self,
+        user_id: String,
+        content: String,
+        location: Option<Location>,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    // Reserve media reference before upload, returns attachment id
+    fn add_attachment(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError>;
+
+    // Media of completed upload
+    fn complete_attachment(
+        &mut self,
+        user_id: String,
+        attachment_id: String,
+        media: MediaMetadata,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    fn remove_attachment(
+        &mut self,
+        user_id: String,
+        attachment_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    // Issues preventing publishing of draft, none if draft not exists
+    fn validate(&self) -> Option<DraftValidation>;
+
+    // Create post with content and uploaded attachments of valid draft, returns post id
+    async fn publish(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError>;
+
+    fn discard(&mut self, user_id: String, correlation_id: Option<String>)
+        -> Result<(), PostError>;
+
+    // Schedule publishing at local time (YYYY-MM-DDTHH:MM) in timezone (IANA name),
+    // timezone of user profile (or UTC) if not set, returns publish time in UTC
+    async fn set_publish_schedule(
+        &mut self,
+        user_id: String,
+        publish_at: String,
+        timezone: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<chrono::DateTime<chrono::Utc>, PostError>;
+
+    fn cancel_publish_schedule(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    // Publish draft at time of publish schedule
+    async fn scheduled_publish(&mut self, correlation_id: Option<String>);
+}
+
+struct DraftSessionAgentImpl {
+    _id: String,
+    state: Option<DraftSession>,
+}
+
+impl DraftSessionAgentImpl {
+    fn with_open_state<T>(
+        &mut self,
+        user_id: &str,
+        f: impl FnOnce(&mut DraftSession) -> Result<T, PostError>,
+    ) -> Result<T, PostError> {
+        match self.state.as_mut() {
+            Some(state) => {
+                state.check_open(user_id)?;
+                f(state)
+            }
+            None => Err(PostError::not_found("Draft not found")),
+        }
+    }
+
+    async fn publish_state(&mut self, user_id: String) -> Result<String, PostError> {
+        let state = self
+            .state
+            .as_mut()
+            .ok_or(PostError::not_found("Draft not found"))?;
+        if state.user_id == user_id && state.status == DraftStatus::Published {
+            return Ok(state.post_id.clone());
+        }
+        state.check_open(&user_id)?;
+        let validation = state.validate();
+        if !validation.valid {
+            return Err(PostError::Invalid(validation.issues.join(", ")));
+        }
+
+        // post is created at once with all attachments, partially composed post is never visible
+        let post_id = UserPostsAgentClient::get(user_id.clone())
+            .publish_draft(
+                state.post_id.clone(),
+                state.content.clone(),
+                state.location.clone(),
+                state.attachments_media(),
+                trace::propagate(),
+            )
+            .await?;
+        trace_println!("publish - post id: {post_id}");
+        state.set_status(DraftStatus::Published);
+        Ok(post_id)
+    }
+}
+
+#[agent_implementation]
+impl DraftSessionAgent for DraftSessionAgentImpl {
+    fn new(id: String) -> Self {
+        DraftSessionAgentImpl {
+            _id: id,
+            state: None,
+        }
+    }
+
+    fn get_draft(&self) -> Option<DraftSession> {
+        self.state.clone()
+    }
+
+    fn start(&mut self, user_id: String, correlation_id: Option<String>) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        if self.state.is_some() {
+            Err(PostError::AlreadyExists("Draft already exists".to_string()))
+        } else {
+            trace_println!("start draft - user id: {user_id}");
+            self.state = Some(DraftSession::new(self._id.clone(), user_id));
+            Ok(())
+        }
+    }
+
+    fn set_content(
+        &mut self,
+        user_id: String,
+        content: String,
+        location: Option<Location>,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        self.with_open_state(&user_id, |state| {
+            trace_println!("set content - content: {content}");
+            state.set_content(content, location)
+        })
+    }
+
+    fn add_attachment(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError> {
+        trace::init(correlation_id);
+        self.with_open_state(&user_id, |state| {
+            let attachment_id = id::new_id();
+            trace_println!("add attachment - id: {attachment_id}");
+            state.add_attachment(attachment_id.clone())?;
+            Ok(attachment_id)
+        })
+    }
+
+    fn complete_attachment(
+        &mut self,
+        user_id: String,
+        attachment_id: String,
+        media: MediaMetadata,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        self.with_open_state(&user_id, |state| {
+            trace_println!("complete attachment - id: {attachment_id}");
+            state.complete_attachment(&attachment_id, media)
+        })
+    }
+
+    fn remove_attachment(
+        &mut self,
+        user_id: String,
+        attachment_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        self.with_open_state(&user_id, |state| {
+            if state.remove_attachment(&attachment_id) {
+                trace_println!("remove attachment - id: {attachment_id}");
+                Ok(())
+            } else {
+                Err(PostError::not_found("Attachment not found"))
+            }
+        })
+    }
+
+    fn validate(&self) -> Option<DraftValidation> {
+        self.state.as_ref().map(|state| state.validate())
+    }
+
+    async fn publish(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError> {
+        trace::init(correlation_id);
+        self.publish_state(user_id).await
+    }
+
+    fn discard(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        self.with_open_state(&user_id, |state| {
+            trace_println!("discard");
+            state.set_status(DraftStatus::Discarded);
+            Ok(())
+        })
+    }
+
+    async fn set_publish_schedule(
+        &mut self,
+        user_id: String,
+        publish_at: String,
+        timezone: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<chrono::DateTime<chrono::Utc>, PostError> {
+        trace::init(correlation_id);
+        let local = parse_local_datetime(&publish_at).map_err(PostError::Invalid)?;
+        let timezone = match timezone.filter(|t| !t.trim().is_empty()) {
+            Some(timezone) => Some(timezone),
+            None => get_user_timezone(&user_id).await,
+        };
+        let timezone =
+            parse_timezone(timezone.as_deref().unwrap_or("UTC")).map_err(PostError::Invalid)?;
+        let publish_at = local_to_utc(local, &timezone);
+
+        self.with_open_state(&user_id, |state| {
+            state.schedule_publish(publish_at, clock::now())?;
+            trace_println!("set publish schedule - publish at: {publish_at}, timezone: {timezone}");
+            // runs scheduled before are not cancelled, they are ignored when they do not match
+            DraftSessionAgentClient::get(state.post_id.clone())
+                .schedule_scheduled_publish(trace::propagate(), to_schedule_time(publish_at));
+            Ok(publish_at)
+        })
+    }
+
+    fn cancel_publish_schedule(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        self.with_open_state(&user_id, |state| {
+            trace_println!("cancel publish schedule");
+            state.publish_at = None;
+            state.updated_at = clock::now();
+            Ok(())
+        })
+    }
+
+    async fn scheduled_publish(&mut self, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        let now = clock::now();
+        let due = self.state.as_ref().and_then(|state| {
+            (state.status == DraftStatus::Open && state.publish_at.is_some_and(|at| at <= now))
+                .then(|| state.user_id.clone())
+        });
+        // run is stale, if publish time was changed or cancelled after it was scheduled
+        match due {
+            Some(user_id) => {
+                if let Err(error) = self.publish_state(user_id).await {
+                    trace_println!("scheduled publish - failed: {error}");
+                }
+            }
+            None => trace_println!("scheduled publish - stale run"),
+        }
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<DraftSession> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    fn media(url: &str) -> MediaMetadata {
+        MediaMetadata {
+            url: url.to_string(),
+            width: 640,
+            height: 480,
+            alt_text: None,
+        }
+    }
+
+    #[test]
+    fn test_draft_validation() {
+        let mut draft = DraftSession::new("p1".to_string(), "u1".to_string());
+        assert_eq!(draft.validate().issues.len(), 1);
+
+        draft.set_content(" Hello ".to_string(), None).unwrap();
+        assert_eq!(draft.content, "Hello");
+        draft.add_attachment("a1".to_string()).unwrap();
+        draft.add_attachment("a2".to_string()).unwrap();
+        assert_eq!(
+            draft.validate().issues,
+            vec!["Attachments not uploaded: 2".to_string()]
+        );
+
+        draft
+            .complete_attachment("a1", media("https://example.com/a1.png"))
+            .unwrap();
+        assert!(draft.complete_attachment("a2", media("not-url")).is_err());
+        assert!(draft
+            .complete_attachment("a3", media("https://example.com/a3.png"))
+            .is_err());
+        assert!(!draft.validate().valid);
+
+        assert!(draft.remove_attachment("a2"));
+        assert!(!draft.remove_attachment("a2"));
+        assert!(draft.validate().valid);
+        assert_eq!(
+            draft.attachments_media(),
+            vec![media("https://example.com/a1.png")]
+        );
+    }
+
+    #[test]
+    fn test_draft_attachments_max_count() {
+        let mut draft = DraftSession::new("p1".to_string(), "u1".to_string());
+        for i in 0..POST_ATTACHMENTS_MAX_COUNT {
+            draft.add_attachment(format!("a{i}")).unwrap();
+        }
+        assert!(draft.add_attachment("a".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_draft_schedule_publish() {
+        let now = clock::now();
+        let mut draft = DraftSession::new("p1".to_string(), "u1".to_string());
+        let publish_at = now + chrono::Duration::hours(1);
+        assert!(draft.schedule_publish(publish_at, now).is_err());
+
+        draft.set_content("Hello".to_string(), None).unwrap();
+        assert!(draft
+            .schedule_publish(now - chrono::Duration::minutes(1), now)
+            .is_err());
+        assert!(draft.schedule_publish(publish_at, now).is_ok());
+        assert_eq!(draft.publish_at, Some(publish_at));
+    }
+
+    #[test]
+    fn test_draft_check_open() {
+        let mut draft = DraftSession::new("p1".to_string(), "u1".to_string());
+        assert!(draft.check_open("u1").is_ok());
+        assert!(draft.check_open("u2").is_err());
+
+        draft.set_status(DraftStatus::Published);
+        assert!(draft.check_open("u1").is_err());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut draft = DraftSession::new("p1".to_string(), "u1".to_string());
+        draft
+            .set_content("Draft content".to_string(), None)
+            .unwrap();
+        draft.add_attachment("a1".to_string()).unwrap();
+        snapshot::assert_round_trip(&Some(draft));
+    }
+}
@@ -0,0 +1,398 @@
+use crate::common::hll::HyperLogLog;
+use crate::common::{clock, get_shard_number, trace, SocialNetError};
+use crate::trace_println;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const VARIANTS_MAX_COUNT: usize = 10;
+
+const VARIANT_NAME_MAX_LENGTH: usize = 50;
+
+// max number of recent exposure events kept by experiment
+const EXPOSURE_EVENTS_MAX_COUNT: usize = 200;
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ExperimentVariant {
+    pub name: String,
+    // relative share of users assigned to variant
+    pub weight: u32,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ExposureEvent {
+    pub user_id: String,
+    pub variant: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct VariantExposures {
+    pub variant: String,
+    pub exposures: u64,
+    // approximate number of distinct exposed users
+    pub unique_users: u64,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ExperimentSummary {
+    pub name: String,
+    pub variants: Vec<ExperimentVariant>,
+    pub active: bool,
+    pub exposures: Vec<VariantExposures>,
+    pub recent_exposures: Vec<ExposureEvent>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct Experiment {
+    pub name: String,
+    pub variants: Vec<ExperimentVariant>,
+    pub active: bool,
+    // variant -> exposures count
+    pub exposure_counts: BTreeMap<String, u64>,
+    // variant -> distinct exposed users sketch, user ids are not stored
+    pub exposed_users: BTreeMap<String, HyperLogLog>,
+    // most recent exposure events, newest last
+    pub recent_exposures: Vec<ExposureEvent>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Experiment {
+    fn new(name: String) -> Self {
+        let now = clock::now();
+        Experiment {
+            name,
+            variants: vec![],
+            active: false,
+            exposure_counts: BTreeMap::new(),
+            exposed_users: BTreeMap::new(),
+            recent_exposures: vec![],
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn set_variants(
+        &mut self,
+        variants: Vec<ExperimentVariant>,
+        active: bool,
+    ) -> Result<(), String> {
+        validate_variants(&variants)?;
+        self.variants = variants;
+        self.active = active;
+        self.updated_at = clock::now();
+        Ok(())
+    }
+
+    // Variant of user, none if experiment is not active
+    fn get_variant(&self, user_id: &str) -> Option<&ExperimentVariant> {
+        if self.active {
+            assign_variant(&self.name, &self.variants, user_id)
+        } else {
+            None
+        }
+    }
+
+    fn record_exposure(&mut self, user_id: &str) -> Option<String> {
+        let variant = self.get_variant(user_id)?.name.clone();
+        let now = clock::now();
+
+        *self.exposure_counts.entry(variant.clone()).or_default() += 1;
+        self.exposed_users
+            .entry(variant.clone())
+            .or_default()
+            .add(user_id);
+
+        self.recent_exposures.push(ExposureEvent {
+            user_id: user_id.to_string(),
+            variant: variant.clone(),
+            created_at: now,
+        });
+        if self.recent_exposures.len() > EXPOSURE_EVENTS_MAX_COUNT {
+            let excess = self.recent_exposures.len() - EXPOSURE_EVENTS_MAX_COUNT;
+            self.recent_exposures.drain(..excess);
+        }
+
+        self.updated_at = now;
+        Some(variant)
+    }
+
+    fn get_summary(&self) -> ExperimentSummary {
+        let exposures = self
+            .variants
+            .iter()
+            .map(|v| VariantExposures {
+                variant: v.name.clone(),
+                exposures: self.exposure_counts.get(&v.name).copied().unwrap_or(0),
+                unique_users: self
+                    .exposed_users
+                    .get(&v.name)
+                    .map(|users| users.count())
+                    .unwrap_or(0),
+            })
+            .collect();
+
+        ExperimentSummary {
+            name: self.name.clone(),
+            variants: self.variants.clone(),
+            active: self.active,
+            exposures,
+            recent_exposures: self.recent_exposures.iter().rev().cloned().collect(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+fn validate_variants(variants: &[ExperimentVariant]) -> Result<(), String> {
+    if variants.len() < 2 || variants.len() > VARIANTS_MAX_COUNT {
+        return Err(format!(
+            "Experiment must have 2 - {VARIANTS_MAX_COUNT} variants"
+        ));
+    }
+    for (i, variant) in variants.iter().enumerate() {
+        if variant.name.trim().is_empty() || variant.name.len() > VARIANT_NAME_MAX_LENGTH {
+            return Err(format!(
+                "Variant name must have 1 - {VARIANT_NAME_MAX_LENGTH} characters"
+            ));
+        }
+        if variant.weight == 0 {
+            return Err(format!(
+                "Weight of variant {} must be greater than 0",
+                variant.name
+            ));
+        }
+        if variants[..i].iter().any(|v| v.name == variant.name) {
+            return Err(format!("Duplicate variant: {}", variant.name));
+        }
+    }
+    Ok(())
+}
+
+// Deterministic variant of user, by hash of experiment name and user id over variant weights
+fn assign_variant<'a>(
+    experiment: &str,
+    variants: &'a [ExperimentVariant],
+    user_id: &str,
+) -> Option<&'a ExperimentVariant> {
+    let total_weight: u32 = variants.iter().map(|v| v.weight).sum();
+    if total_weight == 0 {
+        return None;
+    }
+
+    let mut bucket = get_shard_number(format!("{experiment}:{user_id}"), total_weight);
+    variants.iter().find(|v| {
+        if bucket < v.weight {
+            true
+        } else {
+            bucket -= v.weight;
+            false
+        }
+    })
+}
+
+#[agent_definition]
+trait ExperimentAgent {
+    fn new(name: String) -> Self;
+
+    fn get_experiment(&self) -> Option<ExperimentSummary>;
+
+    // Create or update experiment variants, assignments change if variants or weights change
+    fn set_variants(
+        &mut self,
+        variants: Vec<ExperimentVariant>,
+        active: bool,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Variant assigned to user, without recording exposure
+    fn get_variant(&self, user_id: String) -> Option<String>;
+
+    // Variant assigned to user, recorded as exposure (user was shown the variant)
+    fn record_exposure(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Option<String>;
+}
+
+struct ExperimentAgentImpl {
+    _id: String,
+    state: Option<Experiment>,
+}
+
+#[agent_implementation]
+impl ExperimentAgent for ExperimentAgentImpl {
+    fn new(name: String) -> Self {
+        ExperimentAgentImpl {
+            _id: name,
+            state: None,
+        }
+    }
+
+    fn get_experiment(&self) -> Option<ExperimentSummary> {
+        self.state.as_ref().map(|state| state.get_summary())
+    }
+
+    fn set_variants(
+        &mut self,
+        variants: Vec<ExperimentVariant>,
+        active: bool,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        trace_println!("set variants - variants: {variants:?}, active: {active}");
+        let mut state = self
+            .state
+            .clone()
+            .unwrap_or_else(|| Experiment::new(self._id.clone()));
+        state
+            .set_variants(variants, active)
+            .map_err(SocialNetError::ValidationFailed)?;
+        self.state = Some(state);
+        Ok(())
+    }
+
+    fn get_variant(&self, user_id: String) -> Option<String> {
+        self.state
+            .as_ref()
+            .and_then(|state| state.get_variant(&user_id))
+            .map(|v| v.name.clone())
+    }
+
+    fn record_exposure(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Option<String> {
+        trace::init(correlation_id);
+        let variant = self
+            .state
+            .as_mut()
+            .and_then(|state| state.record_exposure(&user_id));
+        trace_println!("record exposure - user id: {user_id}, variant: {variant:?}");
+        variant
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<Experiment> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    fn variant(name: &str, weight: u32) -> ExperimentVariant {
+        ExperimentVariant {
+            name: name.to_string(),
+            weight,
+        }
+    }
+
+    #[test]
+    fn test_validate_variants() {
+        assert!(validate_variants(&[variant("control", 50), variant("ranked", 50)]).is_ok());
+        assert!(validate_variants(&[variant("control", 100)]).is_err());
+        assert!(validate_variants(&[variant("control", 50), variant("ranked", 0)]).is_err());
+        assert!(validate_variants(&[variant("control", 50), variant("control", 50)]).is_err());
+        assert!(validate_variants(&[variant("", 50), variant("ranked", 50)]).is_err());
+    }
+
+    #[test]
+    fn test_assign_variant_deterministic() {
+        let variants = vec![variant("control", 50), variant("ranked", 50)];
+        for i in 0..100 {
+            let user_id = format!("user{i}");
+            assert_eq!(
+                assign_variant("timeline-ranking", &variants, &user_id),
+                assign_variant("timeline-ranking", &variants, &user_id)
+            );
+        }
+    }
+
+    #[test]
+    fn test_assign_variant_weights() {
+        let variants = vec![variant("control", 80), variant("ranked", 20)];
+        let ranked = (0..1000)
+            .filter(|i| {
+                assign_variant("timeline-ranking", &variants, &format!("user{i}"))
+                    .unwrap()
+                    .name
+                    == "ranked"
+            })
+            .count();
+        assert!((120..280).contains(&ranked), "ranked: {ranked}");
+
+        assert!(assign_variant("timeline-ranking", &[], "user1").is_none());
+    }
+
+    #[test]
+    fn test_experiment_record_exposure() {
+        let mut experiment = Experiment::new("timeline-ranking".to_string());
+        assert_eq!(experiment.record_exposure("user1"), None);
+
+        experiment
+            .set_variants(vec![variant("control", 50), variant("ranked", 50)], true)
+            .unwrap();
+        let variant1 = experiment.record_exposure("user1").unwrap();
+        assert_eq!(experiment.record_exposure("user1").unwrap(), variant1);
+        experiment.record_exposure("user2");
+
+        let summary = experiment.get_summary();
+        assert_eq!(
+            summary.exposures.iter().map(|e| e.exposures).sum::<u64>(),
+            3
+        );
+        let exposures1 = summary
+            .exposures
+            .iter()
+            .find(|e| e.variant == variant1)
+            .unwrap();
+        assert!(exposures1.exposures >= 2);
+        assert!(exposures1.unique_users >= 1);
+        assert_eq!(summary.recent_exposures.len(), 3);
+        assert_eq!(summary.recent_exposures[0].user_id, "user2");
+
+        experiment
+            .set_variants(vec![variant("control", 50), variant("ranked", 50)], false)
+            .unwrap();
+        assert_eq!(experiment.record_exposure("user1"), None);
+    }
+
+    #[test]
+    fn test_experiment_recent_exposures_limit() {
+        let mut experiment = Experiment::new("timeline-ranking".to_string());
+        experiment
+            .set_variants(vec![variant("control", 50), variant("ranked", 50)], true)
+            .unwrap();
+        for i in 0..(EXPOSURE_EVENTS_MAX_COUNT + 10) {
+            experiment.record_exposure(&format!("user{i}"));
+        }
+        assert_eq!(experiment.recent_exposures.len(), EXPOSURE_EVENTS_MAX_COUNT);
+        assert_eq!(
+            experiment.exposure_counts.values().sum::<u64>(),
+            (EXPOSURE_EVENTS_MAX_COUNT + 10) as u64
+        );
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut experiment = Experiment::new("timeline".to_string());
+        experiment
+            .set_variants(vec![variant("control", 50), variant("ranked", 50)], true)
+            .unwrap();
+        experiment.record_exposure("u1");
+        snapshot::assert_round_trip(&Some(experiment));
+    }
+}
@@ -0,0 +1,473 @@
+use crate::common::{clock, cursor, get_shard_number, trace, SocialNetError};
+use crate::post::{fetch_posts_by_ids, Post};
+use crate::trace_println;
+use futures::future::join_all;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Number of shards for ExploreAgent
+const EXPLORE_SHARDS: u32 = 8;
+
+// posts older than window are dropped from explore
+const EXPLORE_WINDOW_HOURS: i64 = 48;
+
+// max number of (most recent) posts kept by shard, and of posts of single author in shard
+const SHARD_MAX_POSTS: usize = 500;
+const SHARD_MAX_POSTS_PER_AUTHOR: usize = 10;
+
+// default and max number of posts in feed page
+const FEED_PAGE_DEFAULT_LIMIT: u32 = 20;
+const FEED_PAGE_MAX_LIMIT: u32 = 100;
+
+// max number of posts of single author in feed page
+const FEED_PAGE_MAX_POSTS_PER_AUTHOR: usize = 2;
+
+// number of post refs read from each shard per page (multiple of page limit), for skipped authors
+const FEED_SCAN_FACTOR: usize = 3;
+
+pub fn get_explore_shard(post_id: &str) -> u32 {
+    get_shard_number(post_id.to_string(), EXPLORE_SHARDS)
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ExplorePostRef {
+    pub post_id: String,
+    pub created_by: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ExplorePostRef {
+    fn created_at_micros(&self) -> u64 {
+        self.created_at.timestamp_micros().max(0) as u64
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Default)]
+pub struct ExploreShardState {
+    // newest first
+    pub posts: Vec<ExplorePostRef>,
+}
+
+impl ExploreShardState {
+    // Add post, posts out of window are ignored, only the most recent posts of author are kept
+    fn add_post(&mut self, post: ExplorePostRef, now: chrono::DateTime<chrono::Utc>) -> bool {
+        if post.created_at < window_start(now)
+            || self.posts.iter().any(|p| p.post_id == post.post_id)
+        {
+            return false;
+        }
+        let post_id = post.post_id.clone();
+        self.posts.push(post);
+        self.posts.sort_by_key(|p| std::cmp::Reverse(p.created_at));
+
+        let mut author_counts: HashMap<String, usize> = HashMap::new();
+        self.posts.retain(|p| {
+            let count = author_counts.entry(p.created_by.clone()).or_insert(0);
+            *count += 1;
+            *count <= SHARD_MAX_POSTS_PER_AUTHOR
+        });
+        self.expire(now);
+        self.posts.iter().any(|p| p.post_id == post_id)
+    }
+
+    fn remove_post(&mut self, post_id: &str) -> bool {
+        let count = self.posts.len();
+        self.posts.retain(|p| p.post_id != post_id);
+        self.posts.len() < count
+    }
+
+    // Remove posts out of window, limit number of posts
+    fn expire(&mut self, now: chrono::DateTime<chrono::Utc>) {
+        let since = window_start(now);
+        self.posts.retain(|p| p.created_at >= since);
+        self.posts.truncate(SHARD_MAX_POSTS);
+    }
+
+    // Newest posts created before cursor (creation time in micros)
+    fn get_posts(&self, before: Option<u64>, limit: usize) -> Vec<ExplorePostRef> {
+        self.posts
+            .iter()
+            .filter(|p| before.is_none_or(|before| p.created_at_micros() < before))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+fn window_start(now: chrono::DateTime<chrono::Utc>) -> chrono::DateTime<chrono::Utc> {
+    now - chrono::Duration::hours(EXPLORE_WINDOW_HOURS)
+}
+
+fn feed_limit(limit: Option<u32>) -> usize {
+    limit
+        .unwrap_or(FEED_PAGE_DEFAULT_LIMIT)
+        .clamp(1, FEED_PAGE_MAX_LIMIT) as usize
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct ExploreRefsPage {
+    pub posts: Vec<ExplorePostRef>,
+    // cursor of next page, none if this is the last page
+    pub next_cursor: Option<String>,
+}
+
+// Page of newest posts of all shards, with at most max posts of single author (other posts of author are skipped)
+//
+// shards returned at most scan limit posts each, posts older than the oldest post of a full shard
+// may be missing in other shards, so the page ends there and next page continues from it
+fn explore_page(
+    shard_posts: Vec<Vec<ExplorePostRef>>,
+    scan_limit: usize,
+    limit: usize,
+    max_per_author: usize,
+) -> ExploreRefsPage {
+    let horizon = shard_posts
+        .iter()
+        .filter(|posts| posts.len() >= scan_limit)
+        .filter_map(|posts| posts.last().map(|p| p.created_at))
+        .max();
+
+    let mut candidates: Vec<ExplorePostRef> = shard_posts
+        .into_iter()
+        .flatten()
+        .filter(|p| horizon.is_none_or(|horizon| p.created_at >= horizon))
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.created_at
+            .cmp(&a.created_at)
+            .then_with(|| a.post_id.cmp(&b.post_id))
+    });
+
+    let mut posts = vec![];
+    let mut author_counts: HashMap<String, usize> = HashMap::new();
+    let mut last_scanned: Option<u64> = None;
+    for post in candidates.into_iter() {
+        if posts.len() >= limit {
+            break;
+        }
+        last_scanned = Some(post.created_at_micros());
+        let count = author_counts.entry(post.created_by.clone()).or_insert(0);
+        if *count < max_per_author {
+            *count += 1;
+            posts.push(post);
+        }
+    }
+
+    let next_cursor = if posts.len() >= limit || horizon.is_some() {
+        last_scanned.map(cursor::encode)
+    } else {
+        None
+    };
+
+    ExploreRefsPage { posts, next_cursor }
+}
+
+#[agent_definition]
+trait ExploreAgent {
+    fn new(shard_id: u32) -> Self;
+
+    // Recent public post, ignored if out of window or if it does not belong to shard
+    fn add_post(
+        &mut self,
+        post_id: String,
+        created_by: String,
+        created_at: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
+    );
+
+    fn remove_post(&mut self, post_id: String, correlation_id: Option<String>);
+
+    // Newest posts of shard created before cursor
+    fn get_posts(
+        &mut self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Vec<ExplorePostRef>, SocialNetError>;
+}
+
+struct ExploreAgentImpl {
+    shard_id: u32,
+    state: ExploreShardState,
+}
+
+#[agent_implementation]
+impl ExploreAgent for ExploreAgentImpl {
+    fn new(shard_id: u32) -> Self {
+        ExploreAgentImpl {
+            shard_id,
+            state: ExploreShardState::default(),
+        }
+    }
+
+    fn add_post(
+        &mut self,
+        post_id: String,
+        created_by: String,
+        created_at: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
+    ) {
+        trace::init(correlation_id);
+        if get_explore_shard(&post_id) != self.shard_id {
+            return;
+        }
+        let added = self.state.add_post(
+            ExplorePostRef {
+                post_id: post_id.clone(),
+                created_by,
+                created_at,
+            },
+            clock::now(),
+        );
+        trace_println!(
+            "add post - post id: {post_id}, shard: {}, added: {added}",
+            self.shard_id
+        );
+    }
+
+    fn remove_post(&mut self, post_id: String, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        let removed = self.state.remove_post(&post_id);
+        trace_println!(
+            "remove post - post id: {post_id}, shard: {}, removed: {removed}",
+            self.shard_id
+        );
+    }
+
+    fn get_posts(
+        &mut self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Vec<ExplorePostRef>, SocialNetError> {
+        let before = match cursor.filter(|c| !c.is_empty()) {
+            Some(c) => Some(cursor::decode(&c)?),
+            None => None,
+        };
+        self.state.expire(clock::now());
+        Ok(self
+            .state
+            .get_posts(before, limit.unwrap_or(FEED_PAGE_DEFAULT_LIMIT) as usize))
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: ExploreShardState = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+// Add new public post to explore (from write paths)
+pub fn add_explore_post(post: &Post) {
+    ExploreAgentClient::get(get_explore_shard(&post.post_id)).trigger_add_post(
+        post.post_id.clone(),
+        post.created_by.clone(),
+        post.created_at,
+        trace::propagate(),
+    );
+}
+
+// Remove deleted post from explore
+pub fn remove_explore_post(post: &Post) {
+    ExploreAgentClient::get(get_explore_shard(&post.post_id))
+        .trigger_remove_post(post.post_id.clone(), trace::propagate());
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ExploreFeed {
+    pub posts: Vec<Post>,
+    // cursor of next page, none if this is the last page
+    pub next_cursor: Option<String>,
+    // some posts were skipped, as they were not fetched in time
+    pub degraded: bool,
+}
+
+#[agent_definition(mode = "ephemeral")]
+trait ExploreViewAgent {
+    fn new() -> Self;
+
+    // Global feed of recent public posts (newest first), merged from all ExploreAgent shards,
+    // with at most few posts of single author in page, cursor is creation time of the last scanned post
+    async fn get_feed(
+        &mut self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        correlation_id: Option<String>,
+    ) -> Result<ExploreFeed, SocialNetError>;
+}
+
+struct ExploreViewAgentImpl {}
+
+#[agent_implementation]
+impl ExploreViewAgent for ExploreViewAgentImpl {
+    fn new() -> Self {
+        Self {}
+    }
+
+    async fn get_feed(
+        &mut self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        correlation_id: Option<String>,
+    ) -> Result<ExploreFeed, SocialNetError> {
+        trace::init(correlation_id);
+        let limit = feed_limit(limit);
+        let scan_limit = limit * FEED_SCAN_FACTOR;
+        trace_println!(
+            "get feed - cursor: {}, limit: {limit}",
+            cursor.clone().unwrap_or("N/A".to_string())
+        );
+
+        let shard_futures: Vec<_> = (0..EXPLORE_SHARDS)
+            .map(|shard_id| {
+                let cursor = cursor.clone();
+                async move {
+                    ExploreAgentClient::get(shard_id)
+                        .get_posts(cursor, Some(scan_limit as u32))
+                        .await
+                }
+            })
+            .collect();
+        let shard_posts = join_all(shard_futures)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, SocialNetError>>()?;
+
+        let page = explore_page(
+            shard_posts,
+            scan_limit,
+            limit,
+            FEED_PAGE_MAX_POSTS_PER_AUTHOR,
+        );
+        let post_ids: Vec<String> = page.posts.into_iter().map(|p| p.post_id).collect();
+        let posts = fetch_posts_by_ids(&post_ids).await;
+
+        Ok(ExploreFeed {
+            degraded: posts.is_degraded(),
+            posts: posts.items,
+            next_cursor: page.next_cursor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    fn time(seconds: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn post(post_id: &str, created_by: &str, seconds: i64) -> ExplorePostRef {
+        ExplorePostRef {
+            post_id: post_id.to_string(),
+            created_by: created_by.to_string(),
+            created_at: time(seconds),
+        }
+    }
+
+    fn ids(posts: &[ExplorePostRef]) -> Vec<&str> {
+        posts.iter().map(|p| p.post_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_shard_add_remove_post() {
+        let now = time(1000);
+        let mut state = ExploreShardState::default();
+        assert!(state.add_post(post("p1", "u1", 100), now));
+        assert!(state.add_post(post("p2", "u2", 300), now));
+        assert!(!state.add_post(post("p2", "u2", 300), now));
+        // out of window
+        assert!(!state.add_post(
+            post("p3", "u3", 1000 - EXPLORE_WINDOW_HOURS * 3600 - 1),
+            now
+        ));
+        assert_eq!(ids(&state.posts), vec!["p2", "p1"]);
+
+        assert!(state.remove_post("p2"));
+        assert!(!state.remove_post("p2"));
+        assert_eq!(ids(&state.posts), vec!["p1"]);
+    }
+
+    #[test]
+    fn test_shard_max_posts_per_author() {
+        let now = time(10000);
+        let mut state = ExploreShardState::default();
+        for i in 0..SHARD_MAX_POSTS_PER_AUTHOR as i64 {
+            assert!(state.add_post(post(&format!("p{i}"), "u1", i), now));
+        }
+        // only the most recent posts of author are kept
+        assert!(state.add_post(post("new", "u1", 5000), now));
+        assert!(!state.add_post(post("old", "u1", -1), now));
+        assert_eq!(state.posts.len(), SHARD_MAX_POSTS_PER_AUTHOR);
+        assert_eq!(state.posts[0].post_id, "new");
+        assert!(!state.posts.iter().any(|p| p.post_id == "p0"));
+    }
+
+    #[test]
+    fn test_shard_expire_and_get_posts() {
+        let mut state = ExploreShardState::default();
+        state.add_post(post("p1", "u1", 100), time(1000));
+        state.add_post(post("p2", "u2", 200), time(1000));
+        state.add_post(post("p3", "u3", 300), time(1000));
+
+        assert_eq!(ids(&state.get_posts(None, 2)), vec!["p3", "p2"]);
+        let before = state.posts[1].created_at_micros();
+        assert_eq!(ids(&state.get_posts(Some(before), 10)), vec!["p1"]);
+
+        state.expire(time(200 + EXPLORE_WINDOW_HOURS * 3600));
+        assert_eq!(ids(&state.posts), vec!["p3", "p2"]);
+    }
+
+    #[test]
+    fn test_explore_page_author_diversity() {
+        let shard_posts = vec![
+            vec![post("a1", "a", 10), post("a2", "a", 9), post("b1", "b", 5)],
+            vec![post("a3", "a", 8), post("c1", "c", 7), post("c2", "c", 6)],
+        ];
+        let page = explore_page(shard_posts, 10, 10, 2);
+        assert_eq!(ids(&page.posts), vec!["a1", "a2", "c1", "c2", "b1"]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_explore_page_limit() {
+        let shard_posts = vec![
+            vec![post("a1", "a", 10), post("b1", "b", 9), post("c1", "c", 8)],
+            vec![post("d1", "d", 7)],
+        ];
+        let page = explore_page(shard_posts, 10, 2, 2);
+        assert_eq!(ids(&page.posts), vec!["a1", "b1"]);
+        assert_eq!(
+            page.next_cursor,
+            Some(cursor::encode(post("b1", "b", 9).created_at_micros()))
+        );
+    }
+
+    #[test]
+    fn test_explore_page_full_shard_horizon() {
+        // first shard is full (scan limit 2), its posts older than 8 are not read yet
+        let shard_posts = vec![
+            vec![post("a1", "a", 10), post("a2", "a", 8)],
+            vec![post("b1", "b", 9), post("b2", "b", 5)],
+        ];
+        let page = explore_page(shard_posts, 2, 10, 1);
+        assert_eq!(ids(&page.posts), vec!["a1", "b1"]);
+        assert_eq!(
+            page.next_cursor,
+            Some(cursor::encode(post("a2", "a", 8).created_at_micros()))
+        );
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut state = ExploreShardState::default();
+        state.add_post(post("p1", "u1", 100), time(1000));
+        state.add_post(post("p2", "u2", 200), time(1000));
+        snapshot::assert_round_trip(&state);
+    }
+}
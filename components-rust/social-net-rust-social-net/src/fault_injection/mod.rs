@@ -0,0 +1,293 @@
+use crate::common::{trace, SocialNetError};
+use crate::trace_println;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+// how long agents use cached fault rules before reloading them
+const RULES_CACHE_TTL: Duration = Duration::from_secs(5);
+
+const RULES_MAX_COUNT: usize = 50;
+
+const ID_PATTERN_MAX_LENGTH: usize = 100;
+
+// max delay of single agent call
+const DELAY_MAX_MS: u32 = 60000;
+
+// Fault injection is available only in builds with `fault-injection` feature (off by default)
+const FAULT_INJECTION_ENABLED: bool = cfg!(feature = "fault-injection");
+
+// Retryable path of agent calls where faults are injected
+#[derive(Schema, Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum FaultPath {
+    // fan-in calls of views (posts, chats), agent id is post/chat id
+    Hydration,
+    // chat update deliveries to participants, agent id is user id
+    Fanout,
+    // long polling for updates, agent id is user id
+    Polling,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum Fault {
+    // call fails (hydration item is skipped, delivery is retried, poll iteration is repeated)
+    Fail,
+    // call is delayed by given number of milliseconds
+    Delay(u32),
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FaultRule {
+    pub path: FaultPath,
+    // agent id pattern, '*' matches any characters
+    pub id_pattern: String,
+    // percentage (0 - 100) of matching calls with fault
+    pub percentage: u8,
+    pub fault: Fault,
+}
+
+impl FaultRule {
+    fn validate(&self) -> Result<(), String> {
+        if self.id_pattern.is_empty() || self.id_pattern.len() > ID_PATTERN_MAX_LENGTH {
+            Err(format!(
+                "Id pattern must have 1 - {ID_PATTERN_MAX_LENGTH} characters"
+            ))
+        } else if self.percentage > 100 {
+            Err("Percentage must be between 0 and 100".to_string())
+        } else if matches!(self.fault, Fault::Delay(ms) if ms == 0 || ms > DELAY_MAX_MS) {
+            Err(format!("Delay must be between 1 and {DELAY_MAX_MS}ms"))
+        } else {
+            Ok(())
+        }
+    }
+
+    // roll is random number 0 - 99
+    fn applies_to(&self, path: FaultPath, agent_id: &str, roll: u8) -> bool {
+        self.path == path && roll < self.percentage && matches_pattern(&self.id_pattern, agent_id)
+    }
+}
+
+// Glob matching, '*' matches any (also empty) sequence of characters
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // pattern without '*'
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+// First rule applying to agent call
+fn find_fault(rules: &[FaultRule], path: FaultPath, agent_id: &str, roll: u8) -> Option<Fault> {
+    rules
+        .iter()
+        .find(|r| r.applies_to(path, agent_id, roll))
+        .map(|r| r.fault.clone())
+}
+
+#[agent_definition]
+trait FaultInjectionAgent {
+    fn new() -> Self;
+
+    fn get_rules(&self) -> Vec<FaultRule>;
+
+    // Replace fault rules, fails in builds without `fault-injection` feature
+    fn set_rules(
+        &mut self,
+        rules: Vec<FaultRule>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    fn clear_rules(&mut self, correlation_id: Option<String>);
+}
+
+struct FaultInjectionAgentImpl {
+    rules: Vec<FaultRule>,
+}
+
+#[agent_implementation]
+impl FaultInjectionAgent for FaultInjectionAgentImpl {
+    fn new() -> Self {
+        FaultInjectionAgentImpl { rules: vec![] }
+    }
+
+    fn get_rules(&self) -> Vec<FaultRule> {
+        self.rules.clone()
+    }
+
+    fn set_rules(
+        &mut self,
+        rules: Vec<FaultRule>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        if !FAULT_INJECTION_ENABLED {
+            return Err(SocialNetError::NotAllowed(
+                "Fault injection is not enabled (fault-injection feature)".to_string(),
+            ));
+        }
+        if rules.len() > RULES_MAX_COUNT {
+            return Err(SocialNetError::LimitExceeded(format!(
+                "Max {RULES_MAX_COUNT} fault rules allowed"
+            )));
+        }
+        for rule in rules.iter() {
+            rule.validate().map_err(SocialNetError::ValidationFailed)?;
+        }
+        trace_println!("set fault rules: {rules:?}");
+        self.rules = rules;
+        Ok(())
+    }
+
+    fn clear_rules(&mut self, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        trace_println!("clear fault rules");
+        self.rules.clear();
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Vec<FaultRule> = crate::common::snapshot::deserialize(&bytes)?;
+        self.rules = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.rules)
+    }
+}
+
+thread_local! {
+    static RULES_CACHE: RefCell<Option<(Vec<FaultRule>, Instant)>> = const { RefCell::new(None) };
+}
+
+async fn get_cached_rules() -> Vec<FaultRule> {
+    let cached = RULES_CACHE.with(|c| {
+        c.borrow()
+            .as_ref()
+            .filter(|(_, loaded_at)| loaded_at.elapsed() < RULES_CACHE_TTL)
+            .map(|(rules, _)| rules.clone())
+    });
+
+    match cached {
+        Some(rules) => rules,
+        None => {
+            let rules = FaultInjectionAgentClient::get().get_rules().await;
+            RULES_CACHE.with(|c| *c.borrow_mut() = Some((rules.clone(), Instant::now())));
+            rules
+        }
+    }
+}
+
+// Apply fault of first matching rule to agent call: delay it, or return error if call should fail,
+// no-op (without loading of rules) in builds without `fault-injection` feature
+pub async fn inject_fault(path: FaultPath, agent_id: &str) -> Result<(), String> {
+    if !FAULT_INJECTION_ENABLED {
+        return Ok(());
+    }
+    let rules = get_cached_rules().await;
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let roll = (uuid::Uuid::new_v4().as_u128() % 100) as u8;
+
+    match find_fault(&rules, path, agent_id, roll) {
+        Some(Fault::Fail) => {
+            trace_println!("injected fault - path: {path:?}, agent id: {agent_id} - fail");
+            Err(format!("Injected fault - agent id: {agent_id}"))
+        }
+        Some(Fault::Delay(ms)) => {
+            trace_println!("injected fault - path: {path:?}, agent id: {agent_id} - delay: {ms}ms");
+            wstd::task::sleep(wstd::time::Duration::from_millis(ms as u64)).await;
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    fn rule(path: FaultPath, id_pattern: &str, percentage: u8, fault: Fault) -> FaultRule {
+        FaultRule {
+            path,
+            id_pattern: id_pattern.to_string(),
+            percentage,
+            fault,
+        }
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("*", "post1"));
+        assert!(matches_pattern("post1", "post1"));
+        assert!(!matches_pattern("post1", "post12"));
+        assert!(matches_pattern("post*", "post12"));
+        assert!(matches_pattern("*12", "post12"));
+        assert!(matches_pattern("p*t*2", "post12"));
+        assert!(!matches_pattern("p*x*2", "post12"));
+        assert!(!matches_pattern("post*12", "post1"));
+    }
+
+    #[test]
+    fn test_find_fault() {
+        let rules = vec![
+            rule(FaultPath::Hydration, "post1", 100, Fault::Fail),
+            rule(FaultPath::Hydration, "post*", 50, Fault::Delay(1000)),
+            rule(FaultPath::Fanout, "*", 100, Fault::Fail),
+        ];
+
+        assert_eq!(
+            find_fault(&rules, FaultPath::Hydration, "post1", 99),
+            Some(Fault::Fail)
+        );
+        assert_eq!(
+            find_fault(&rules, FaultPath::Hydration, "post2", 49),
+            Some(Fault::Delay(1000))
+        );
+        assert_eq!(find_fault(&rules, FaultPath::Hydration, "post2", 50), None);
+        assert_eq!(find_fault(&rules, FaultPath::Polling, "user1", 0), None);
+        assert_eq!(
+            find_fault(&rules, FaultPath::Fanout, "user1", 99),
+            Some(Fault::Fail)
+        );
+    }
+
+    #[test]
+    fn test_rule_validate() {
+        assert!(rule(FaultPath::Polling, "*", 100, Fault::Delay(500))
+            .validate()
+            .is_ok());
+        assert!(rule(FaultPath::Polling, "", 100, Fault::Fail)
+            .validate()
+            .is_err());
+        assert!(rule(FaultPath::Polling, "*", 101, Fault::Fail)
+            .validate()
+            .is_err());
+        assert!(rule(FaultPath::Polling, "*", 100, Fault::Delay(0))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let rules = vec![
+            rule(FaultPath::Fanout, "u*", 50, Fault::Fail),
+            rule(FaultPath::Hydration, "*", 10, Fault::Delay(100)),
+        ];
+        snapshot::assert_round_trip(&rules);
+    }
+}
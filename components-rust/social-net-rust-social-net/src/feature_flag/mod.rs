@@ -0,0 +1,231 @@
+use crate::common::{clock, get_shard_number, trace, SocialNetError};
+use crate::trace_println;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+// how long agents use cached feature flags before reloading them
+const FLAGS_CACHE_TTL: Duration = Duration::from_secs(30);
+
+const FLAG_NAME_MAX_LENGTH: usize = 50;
+
+// Notifications of post comments and reactions
+pub const FEATURE_NOTIFICATIONS: &str = "notifications";
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FeatureFlag {
+    pub name: String,
+    pub enabled: bool,
+    // percentage (0 - 100) of users for which enabled flag is on, users are bucketed by hash of user id
+    pub rollout_percentage: u8,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl FeatureFlag {
+    pub fn is_enabled_for(&self, user_id: &str) -> bool {
+        self.enabled && user_bucket(&self.name, user_id) < self.rollout_percentage as u32
+    }
+}
+
+// Stable bucket (0 - 99) of user for flag, each flag buckets users independently
+fn user_bucket(flag: &str, user_id: &str) -> u32 {
+    get_shard_number(format!("{flag}:{user_id}"), 100)
+}
+
+fn validate_flag_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > FLAG_NAME_MAX_LENGTH {
+        Err(format!(
+            "Flag name must have 1 - {FLAG_NAME_MAX_LENGTH} characters"
+        ))
+    } else if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        Err("Flag name can contain only lowercase letters, digits and '-'".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+#[agent_definition]
+trait FeatureFlagAgent {
+    fn new() -> Self;
+
+    fn get_flags(&self) -> Vec<FeatureFlag>;
+
+    fn get_flag(&self, name: String) -> Option<FeatureFlag>;
+
+    // Flag state for user, none if flag is not defined
+    fn is_enabled(&self, name: String, user_id: String) -> Option<bool>;
+
+    fn set_flag(
+        &mut self,
+        name: String,
+        enabled: bool,
+        rollout_percentage: u8,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    fn remove_flag(&mut self, name: String, correlation_id: Option<String>) -> bool;
+}
+
+struct FeatureFlagAgentImpl {
+    flags: BTreeMap<String, FeatureFlag>,
+}
+
+#[agent_implementation]
+impl FeatureFlagAgent for FeatureFlagAgentImpl {
+    fn new() -> Self {
+        FeatureFlagAgentImpl {
+            flags: BTreeMap::new(),
+        }
+    }
+
+    fn get_flags(&self) -> Vec<FeatureFlag> {
+        self.flags.values().cloned().collect()
+    }
+
+    fn get_flag(&self, name: String) -> Option<FeatureFlag> {
+        self.flags.get(&name).cloned()
+    }
+
+    fn is_enabled(&self, name: String, user_id: String) -> Option<bool> {
+        self.flags.get(&name).map(|f| f.is_enabled_for(&user_id))
+    }
+
+    fn set_flag(
+        &mut self,
+        name: String,
+        enabled: bool,
+        rollout_percentage: u8,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        validate_flag_name(&name).map_err(SocialNetError::ValidationFailed)?;
+        if rollout_percentage > 100 {
+            return Err(SocialNetError::ValidationFailed(
+                "Rollout percentage must be between 0 and 100".to_string(),
+            ));
+        }
+        trace_println!(
+            "set flag - name: {name}, enabled: {enabled}, rollout percentage: {rollout_percentage}"
+        );
+        self.flags.insert(
+            name.clone(),
+            FeatureFlag {
+                name,
+                enabled,
+                rollout_percentage,
+                updated_at: clock::now(),
+            },
+        );
+        Ok(())
+    }
+
+    fn remove_flag(&mut self, name: String, correlation_id: Option<String>) -> bool {
+        trace::init(correlation_id);
+        trace_println!("remove flag - name: {name}");
+        self.flags.remove(&name).is_some()
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: BTreeMap<String, FeatureFlag> = crate::common::snapshot::deserialize(&bytes)?;
+        self.flags = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.flags)
+    }
+}
+
+thread_local! {
+    static FLAGS_CACHE: RefCell<Option<(Vec<FeatureFlag>, Instant)>> = const { RefCell::new(None) };
+}
+
+async fn get_cached_flags() -> Vec<FeatureFlag> {
+    let cached = FLAGS_CACHE.with(|c| {
+        c.borrow()
+            .as_ref()
+            .filter(|(_, loaded_at)| loaded_at.elapsed() < FLAGS_CACHE_TTL)
+            .map(|(flags, _)| flags.clone())
+    });
+
+    match cached {
+        Some(flags) => flags,
+        None => {
+            let flags = FeatureFlagAgentClient::get().get_flags().await;
+            FLAGS_CACHE.with(|c| *c.borrow_mut() = Some((flags.clone(), Instant::now())));
+            flags
+        }
+    }
+}
+
+// Feature is enabled for user, default is used if flag is not defined
+pub async fn is_feature_enabled(flag: &str, user_id: &str, default: bool) -> bool {
+    get_cached_flags()
+        .await
+        .iter()
+        .find(|f| f.name == flag)
+        .map(|f| f.is_enabled_for(user_id))
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    fn flag(enabled: bool, rollout_percentage: u8) -> FeatureFlag {
+        FeatureFlag {
+            name: "ranked-timeline".to_string(),
+            enabled,
+            rollout_percentage,
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_flag_is_enabled_for() {
+        assert!(flag(true, 100).is_enabled_for("user1"));
+        assert!(!flag(true, 0).is_enabled_for("user1"));
+        assert!(!flag(false, 100).is_enabled_for("user1"));
+    }
+
+    #[test]
+    fn test_flag_rollout_percentage() {
+        let flag = flag(true, 30);
+        let enabled = (0..1000)
+            .filter(|i| flag.is_enabled_for(&format!("user{i}")))
+            .count();
+        assert!((200..400).contains(&enabled), "enabled: {enabled}");
+
+        // rollout is stable for user
+        assert_eq!(flag.is_enabled_for("user1"), flag.is_enabled_for("user1"));
+    }
+
+    #[test]
+    fn test_user_bucket() {
+        assert!(user_bucket("flag", "user1") < 100);
+        assert_eq!(user_bucket("flag", "user1"), user_bucket("flag", "user1"));
+    }
+
+    #[test]
+    fn test_validate_flag_name() {
+        assert!(validate_flag_name("ranked-timeline").is_ok());
+        assert!(validate_flag_name("").is_err());
+        assert!(validate_flag_name("Ranked Timeline").is_err());
+        assert!(validate_flag_name(&"a".repeat(51)).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let flags = BTreeMap::from([
+            ("ranked-timeline".to_string(), flag(true, 50)),
+            ("explore".to_string(), flag(false, 0)),
+        ]);
+        snapshot::assert_round_trip(&flags);
+    }
+}
@@ -0,0 +1,979 @@
+// Test harness of multi-agent flows, agent states are kept in memory and flows of agent
+// implementations are run with in-memory clients, calls between agents (triggered by agent
+// clients in golem) are queued and delivered to agent states after the calling flow
+use crate::chat::MediaMetadata;
+use crate::common::clock::{self, ManualClock};
+use crate::common::fetch::Fetched;
+use crate::common::geo::Location;
+use crate::common::query::Query;
+use crate::common::UserConnectionType;
+use crate::moderation::{ContentRejected, ModerationConfig, Severity};
+use crate::notification::{NotificationKind, NotificationTarget};
+use crate::post::{self, Post, PostClients, PostError, PostSummary, PostUpdate};
+use crate::rate_limit::{RateLimitAction, RateLimited};
+use crate::user::{InteractionKind, OnboardingStep, User, UserBanned, UserStatus};
+use crate::user_posts::{self, UserPosts, UserPostsClients};
+use crate::user_timeline::{
+    self, TimelinePreferences, TimelineSort, UserTimeline, UserTimelineViewClients,
+};
+use futures::executor::block_on;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+
+pub(crate) struct Harness {
+    // frozen time of agents, advanced by harness operations
+    clock: Rc<ManualClock>,
+    moderation: ModerationConfig,
+    users: HashMap<String, User>,
+    user_posts: HashMap<String, UserPosts>,
+    posts: HashMap<String, Post>,
+    timelines: HashMap<String, UserTimeline>,
+    // triggered calls not yet delivered
+    calls: VecDeque<Call>,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Harness {
+            clock: clock::freeze(chrono::Utc::now()),
+            moderation: ModerationConfig::new(),
+            users: HashMap::new(),
+            user_posts: HashMap::new(),
+            posts: HashMap::new(),
+            timelines: HashMap::new(),
+            calls: VecDeque::new(),
+        }
+    }
+}
+
+// Triggered call of agent
+enum Call {
+    InitPost {
+        post_id: String,
+        user_id: String,
+        content: String,
+        location: Option<Location>,
+        attachments: Vec<MediaMetadata>,
+    },
+    InitRepost {
+        post_id: String,
+        user_id: String,
+        reposted_from: String,
+        content: String,
+    },
+    DeletePost {
+        post_id: String,
+        acting_user_id: String,
+    },
+    AddRepost(String),
+    RemoveRepost(String),
+    SetAuthorName {
+        post_id: String,
+        name: Option<String>,
+    },
+    // updates are processed immediately by timelines updater
+    PostUpdated {
+        user_id: String,
+        update: PostUpdate,
+    },
+    PostDeleted {
+        user_id: String,
+        post_id: String,
+    },
+    TimelinePostsUpdated {
+        user_id: String,
+        posts: Vec<user_timeline::PostRef>,
+    },
+    TimelinePostsRemoved {
+        user_id: String,
+        post_ids: Vec<String>,
+    },
+    UserPostsRemoved {
+        user_id: String,
+        post_ids: HashSet<String>,
+    },
+}
+
+impl Harness {
+    pub fn create_user(&mut self, user_id: &str, name: &str) {
+        let mut user = User::new(user_id.to_string());
+        user.set_name(Some(name.to_string()));
+        self.users.insert(user_id.to_string(), user);
+    }
+
+    // Connection of user with other user, other user gets opposite connection (as UserAgent::connect_user)
+    pub fn connect_users(
+        &mut self,
+        user_id: &str,
+        other_user_id: &str,
+        connection_type: UserConnectionType,
+    ) {
+        let opposite_connection_type = connection_type.get_opposite();
+        self.user_mut(user_id)
+            .connect_user(other_user_id.to_string(), connection_type);
+        self.user_mut(other_user_id)
+            .connect_user(user_id.to_string(), opposite_connection_type);
+    }
+
+    pub fn set_user_status(&mut self, user_id: &str, status: UserStatus) {
+        self.user_mut(user_id).set_status(status);
+    }
+
+    // Interaction of user with other user (UserAgent::record_interactions from post and chat agents)
+    pub fn record_interaction(
+        &mut self,
+        user_id: &str,
+        other_user_id: &str,
+        kind: InteractionKind,
+    ) {
+        self.user_mut(user_id)
+            .record_interactions(&[other_user_id.to_string()], &kind);
+    }
+
+    // Words of moderation config (ModerationAgent::add_words) for all languages
+    pub fn add_moderation_words(&mut self, severity: Severity, words: &[&str]) {
+        self.moderation
+            .add_words("", severity, words.iter().map(|w| w.to_string()).collect());
+    }
+
+    pub fn set_timeline_preferences(&mut self, user_id: &str, preferences: TimelinePreferences) {
+        self.timeline_mut(user_id).preferences = preferences.validated().unwrap();
+    }
+
+    // Mute user in timeline (UserTimelineAgent::mute_user)
+    pub fn mute_user(&mut self, user_id: &str, muted_user_id: &str) {
+        self.timeline_mut(user_id)
+            .preferences
+            .mute_user(muted_user_id.to_string())
+            .unwrap();
+    }
+
+    pub fn unmute_user(&mut self, user_id: &str, muted_user_id: &str) {
+        self.timeline_mut(user_id)
+            .preferences
+            .unmute_user(muted_user_id);
+    }
+
+    // Mute keyword in timeline (UserTimelineAgent::mute_keyword)
+    pub fn mute_keyword(&mut self, user_id: &str, keyword: &str) {
+        self.timeline_mut(user_id)
+            .preferences
+            .mute_keyword(keyword.to_string())
+            .unwrap();
+    }
+
+    pub fn create_post(&mut self, post_id: &str, user_id: &str, content: &str) {
+        self.try_create_post(post_id, user_id, content)
+            .expect("post not created");
+    }
+
+    // Create post with given id (UserPostsAgent::create_post) and deliver calls of the flow
+    pub fn try_create_post(
+        &mut self,
+        post_id: &str,
+        user_id: &str,
+        content: &str,
+    ) -> Result<String, PostError> {
+        // posts get distinct creation times, timeline order is deterministic
+        self.clock.advance(chrono::Duration::seconds(1));
+        let result = self.agent_flow(
+            |h| &mut h.user_posts,
+            user_id,
+            |clients, state| {
+                block_on(user_posts::add_post(
+                    clients,
+                    state,
+                    user_id,
+                    post_id.to_string(),
+                    content.to_string(),
+                    None,
+                    vec![],
+                ))
+            },
+        );
+        self.deliver_calls();
+        result
+    }
+
+    // Create repost with given id (UserPostsAgent::create_repost) and deliver calls of the flow
+    pub fn create_repost(&mut self, post_id: &str, user_id: &str, reposted_from: &str) {
+        self.clock.advance(chrono::Duration::seconds(1));
+        self.agent_flow(
+            |h| &mut h.user_posts,
+            user_id,
+            |clients, state| {
+                block_on(user_posts::create_repost(
+                    clients,
+                    state,
+                    user_id,
+                    post_id.to_string(),
+                    reposted_from.to_string(),
+                    None,
+                ))
+            },
+        )
+        .expect("repost not created");
+        self.deliver_calls();
+    }
+
+    // Delete post by the author (UserPostsAgent::delete_post) and deliver calls of the flow
+    pub fn delete_post(&mut self, post_id: &str) {
+        let user_id = self.posts[post_id].created_by.clone();
+        self.agent_flow(
+            |h| &mut h.user_posts,
+            &user_id,
+            |clients, state| user_posts::delete_post(clients, state, post_id.to_string()),
+        )
+        .expect("post not deleted");
+        self.deliver_calls();
+    }
+
+    // Post ids of timeline view (UserTimelineViewAgent::get_posts_view without query)
+    pub fn timeline_view(&self, user_id: &str) -> Vec<String> {
+        let clients = HarnessClients::new(self);
+        block_on(user_timeline::get_posts_view(&clients, user_id, ""))
+            .map(|view| view.posts.into_iter().map(|p| p.post_id).collect())
+            .unwrap_or_default()
+    }
+
+    // Run flow with in-memory clients, calls triggered by the flow are queued
+    fn flow<T>(&mut self, flow: impl FnOnce(&HarnessClients) -> T) -> T {
+        let clients = HarnessClients::new(self);
+        let result = flow(&clients);
+        let calls = clients.calls.into_inner();
+        self.calls.extend(calls);
+        result
+    }
+
+    // Run flow of agent with given id, agent state is taken out of harness while flow runs
+    fn agent_flow<S, T>(
+        &mut self,
+        states: fn(&mut Harness) -> &mut HashMap<String, S>,
+        id: &str,
+        flow: impl FnOnce(&HarnessClients, &mut Option<S>) -> T,
+    ) -> T {
+        let mut state = states(self).remove(id);
+        let result = self.flow(|clients| flow(clients, &mut state));
+        if let Some(state) = state {
+            states(self).insert(id.to_string(), state);
+        }
+        result
+    }
+
+    // Deliver queued calls in order, including calls triggered by delivered calls
+    fn deliver_calls(&mut self) {
+        while let Some(call) = self.calls.pop_front() {
+            self.deliver(call);
+        }
+    }
+
+    // Errors of triggered calls are not returned to the caller (as in golem)
+    fn deliver(&mut self, call: Call) {
+        match call {
+            Call::InitPost {
+                post_id,
+                user_id,
+                content,
+                location,
+                attachments,
+            } => {
+                let _ = self.agent_flow(
+                    |h| &mut h.posts,
+                    &post_id,
+                    |clients, state| {
+                        block_on(post::init_post(
+                            clients,
+                            state,
+                            &post_id,
+                            user_id,
+                            content,
+                            location,
+                            attachments,
+                        ))
+                    },
+                );
+            }
+            Call::InitRepost {
+                post_id,
+                user_id,
+                reposted_from,
+                content,
+            } => {
+                let _ = self.agent_flow(
+                    |h| &mut h.posts,
+                    &post_id,
+                    |clients, state| {
+                        block_on(post::init_repost(
+                            clients,
+                            state,
+                            &post_id,
+                            user_id,
+                            reposted_from,
+                            content,
+                        ))
+                    },
+                );
+            }
+            Call::DeletePost {
+                post_id,
+                acting_user_id,
+            } => {
+                let _ = self.agent_flow(
+                    |h| &mut h.posts,
+                    &post_id,
+                    |clients, state| post::delete_post(clients, state, acting_user_id),
+                );
+            }
+            Call::AddRepost(post_id) => {
+                self.agent_flow(
+                    |h| &mut h.posts,
+                    &post_id,
+                    |_, state| post::add_repost(state),
+                );
+            }
+            Call::RemoveRepost(post_id) => {
+                self.agent_flow(
+                    |h| &mut h.posts,
+                    &post_id,
+                    |_, state| post::remove_repost(state),
+                );
+            }
+            Call::SetAuthorName { post_id, name } => {
+                self.agent_flow(
+                    |h| &mut h.posts,
+                    &post_id,
+                    |_, state| post::set_author_name(state, name),
+                );
+            }
+            Call::PostUpdated { user_id, update } => {
+                self.flow(|clients| {
+                    block_on(post::execute_posts_updates(clients, &user_id, &[update]))
+                });
+            }
+            Call::PostDeleted { user_id, post_id } => {
+                self.flow(|clients| {
+                    block_on(post::execute_post_deleted(clients, &user_id, &post_id))
+                });
+            }
+            Call::TimelinePostsUpdated { user_id, posts } => {
+                self.agent_flow(
+                    |h| &mut h.timelines,
+                    &user_id,
+                    |_, state| user_timeline::posts_updated(state, &user_id, posts),
+                );
+            }
+            Call::TimelinePostsRemoved { user_id, post_ids } => {
+                self.agent_flow(
+                    |h| &mut h.timelines,
+                    &user_id,
+                    |_, state| user_timeline::posts_removed(state, post_ids),
+                );
+            }
+            Call::UserPostsRemoved { user_id, post_ids } => {
+                self.agent_flow(
+                    |h| &mut h.user_posts,
+                    &user_id,
+                    |_, state| user_posts::remove_posts(state, &post_ids),
+                );
+            }
+        }
+    }
+
+    fn user_mut(&mut self, user_id: &str) -> &mut User {
+        self.users.get_mut(user_id).expect("user not created")
+    }
+
+    fn timeline_mut(&mut self, user_id: &str) -> &mut UserTimeline {
+        self.timelines
+            .entry(user_id.to_string())
+            .or_insert_with(|| UserTimeline::new(user_id.to_string()))
+    }
+}
+
+// In-memory clients of flows, agent states are read from harness and triggered calls are queued,
+// hashtags, explore, notifications, archive, rate limits and analytics are not part of harness
+struct HarnessClients<'a> {
+    harness: &'a Harness,
+    calls: RefCell<Vec<Call>>,
+}
+
+impl<'a> HarnessClients<'a> {
+    fn new(harness: &'a Harness) -> Self {
+        HarnessClients {
+            harness,
+            calls: RefCell::new(vec![]),
+        }
+    }
+
+    fn call(&self, call: Call) {
+        self.calls.borrow_mut().push(call);
+    }
+}
+
+impl PostClients for HarnessClients<'_> {
+    async fn user_exists(&self, user_id: &str) -> bool {
+        self.harness.users.contains_key(user_id)
+    }
+
+    async fn is_user_shadowbanned(&self, user_id: &str) -> bool {
+        self.harness
+            .users
+            .get(user_id)
+            .is_some_and(|u| u.is_shadowbanned())
+    }
+
+    async fn moderate_content(&self, content: String) -> Result<String, ContentRejected> {
+        self.harness.moderation.moderate(content)
+    }
+
+    async fn get_user(&self, user_id: &str) -> Option<User> {
+        self.harness.users.get(user_id).cloned()
+    }
+
+    fn index_post_hashtags(&self, _post: &Post) {}
+
+    fn unindex_post_hashtags(&self, _post: &Post) {}
+
+    fn add_explore_post(&self, _post: &Post) {}
+
+    fn remove_explore_post(&self, _post: &Post) {}
+
+    fn notify_user(
+        &self,
+        _user_id: &str,
+        _kind: NotificationKind,
+        _created_by: &str,
+        _target: NotificationTarget,
+        _content: String,
+    ) {
+    }
+
+    fn delete_archive(&self, _post_id: &str) {}
+
+    fn add_repost(&self, post_id: &str) {
+        self.call(Call::AddRepost(post_id.to_string()));
+    }
+
+    fn remove_repost(&self, post_id: &str) {
+        self.call(Call::RemoveRepost(post_id.to_string()));
+    }
+
+    fn set_author_name(&self, post_id: &str, name: Option<String>) {
+        self.call(Call::SetAuthorName {
+            post_id: post_id.to_string(),
+            name,
+        });
+    }
+
+    fn post_updated(&self, user_id: &str, update: PostUpdate, _process_immediately: bool) {
+        self.call(Call::PostUpdated {
+            user_id: user_id.to_string(),
+            update,
+        });
+    }
+
+    fn post_deleted(&self, user_id: &str, post_id: &str) {
+        self.call(Call::PostDeleted {
+            user_id: user_id.to_string(),
+            post_id: post_id.to_string(),
+        });
+    }
+
+    fn timeline_posts_updated(&self, user_id: &str, posts: Vec<user_timeline::PostRef>) {
+        self.call(Call::TimelinePostsUpdated {
+            user_id: user_id.to_string(),
+            posts,
+        });
+    }
+
+    fn timeline_posts_removed(&self, user_id: &str, post_ids: Vec<String>) {
+        self.call(Call::TimelinePostsRemoved {
+            user_id: user_id.to_string(),
+            post_ids,
+        });
+    }
+
+    fn user_posts_removed(&self, user_id: &str, post_ids: HashSet<String>) {
+        self.call(Call::UserPostsRemoved {
+            user_id: user_id.to_string(),
+            post_ids,
+        });
+    }
+}
+
+impl UserPostsClients for HarnessClients<'_> {
+    async fn check_user_not_banned(&self, user_id: &str) -> Result<(), UserBanned> {
+        if self
+            .harness
+            .users
+            .get(user_id)
+            .is_some_and(|u| u.is_banned())
+        {
+            Err(UserBanned(user_id.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn check_rate_limit(
+        &self,
+        _user_id: &str,
+        _action: RateLimitAction,
+    ) -> Result<(), RateLimited> {
+        Ok(())
+    }
+
+    async fn moderate_content(&self, content: String) -> Result<String, ContentRejected> {
+        self.harness.moderation.moderate(content)
+    }
+
+    async fn get_post(&self, post_id: &str) -> Option<Post> {
+        self.harness
+            .posts
+            .get(post_id)
+            .filter(|p| p.deleted_at.is_none())
+            .cloned()
+    }
+
+    async fn can_like(&self, user_id: &str, other_user_id: &str) -> bool {
+        self.harness
+            .users
+            .get(user_id)
+            .is_some_and(|u| u.can_like(other_user_id))
+    }
+
+    fn init_post(
+        &self,
+        post_id: &str,
+        user_id: &str,
+        content: String,
+        location: Option<Location>,
+        attachments: Vec<MediaMetadata>,
+    ) {
+        self.call(Call::InitPost {
+            post_id: post_id.to_string(),
+            user_id: user_id.to_string(),
+            content,
+            location,
+            attachments,
+        });
+    }
+
+    fn init_repost(&self, post_id: &str, user_id: &str, reposted_from: String, content: String) {
+        self.call(Call::InitRepost {
+            post_id: post_id.to_string(),
+            user_id: user_id.to_string(),
+            reposted_from,
+            content,
+        });
+    }
+
+    fn delete_post(&self, post_id: &str, acting_user_id: &str) {
+        self.call(Call::DeletePost {
+            post_id: post_id.to_string(),
+            acting_user_id: acting_user_id.to_string(),
+        });
+    }
+
+    fn record_onboarding_step(&self, _user_id: &str, _step: OnboardingStep) {}
+
+    fn record_user_activity(&self, _user_id: &str) {}
+}
+
+impl UserTimelineViewClients for HarnessClients<'_> {
+    async fn get_timeline(&self, user_id: &str) -> Option<UserTimeline> {
+        self.harness.timelines.get(user_id).cloned()
+    }
+
+    async fn get_connection_scores(&self, user_id: &str) -> HashMap<String, u32> {
+        self.harness
+            .users
+            .get(user_id)
+            .map(|u| u.connection_scores())
+            .unwrap_or_default()
+    }
+
+    async fn fetch_post_summaries(
+        &self,
+        post_ids: &[String],
+        user_id: &str,
+        query: Query,
+    ) -> Fetched<PostSummary> {
+        Fetched {
+            items: post_ids
+                .iter()
+                .filter_map(|id| self.harness.posts.get(id))
+                .filter_map(|p| p.summary_if_match(Some(user_id), &query))
+                .collect(),
+            skipped: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{cursor, snapshot};
+    use crate::user_chats::{ChatRef, UserChats};
+    use crate::user_posts::{PostRef, PostsOrder, UserPosts};
+    use crate::user_timeline::get_export_page_refs;
+
+    fn harness() -> Harness {
+        let mut harness = Harness::default();
+        harness.create_user("alice", "Alice");
+        harness.create_user("bob", "Bob");
+        harness.create_user("carol", "Carol");
+        harness
+    }
+
+    #[test]
+    fn test_post_fanout_to_connections() {
+        let mut harness = harness();
+        harness.connect_users("alice", "bob", UserConnectionType::Friend);
+        // carol follows alice
+        harness.connect_users("carol", "alice", UserConnectionType::Following);
+
+        harness.create_post("p1", "alice", "Hello from Alice");
+        harness.create_post("p2", "carol", "Hello from Carol");
+
+        assert_eq!(harness.timeline_view("alice"), vec!["p1"]);
+        assert_eq!(harness.timeline_view("bob"), vec!["p1"]);
+        assert_eq!(harness.timeline_view("carol"), vec!["p2", "p1"]);
+    }
+
+    #[test]
+    fn test_post_fanout_of_shadowbanned_user() {
+        let mut harness = harness();
+        harness.connect_users("alice", "bob", UserConnectionType::Friend);
+        harness.set_user_status("alice", UserStatus::Shadowbanned);
+
+        harness.create_post("p1", "alice", "Hello from Alice");
+
+        assert_eq!(harness.timeline_view("alice"), vec!["p1"]);
+        assert!(harness.timeline_view("bob").is_empty());
+    }
+
+    #[test]
+    fn test_timeline_muted_keywords() {
+        let mut harness = harness();
+        harness.connect_users("alice", "bob", UserConnectionType::Friend);
+        harness.set_timeline_preferences(
+            "alice",
+            TimelinePreferences {
+                muted_keywords: vec!["Spoiler".to_string()],
+                ..TimelinePreferences::default()
+            },
+        );
+
+        harness.create_post("p1", "bob", "Big spoiler ahead");
+        harness.create_post("p2", "bob", "Nothing to see");
+        harness.create_post("p3", "alice", "My own spoiler");
+
+        assert_eq!(harness.timeline_view("alice"), vec!["p3", "p2"]);
+        assert_eq!(harness.timeline_view("bob"), vec!["p3", "p2", "p1"]);
+    }
+
+    #[test]
+    fn test_timeline_muted_users() {
+        let mut harness = harness();
+        harness.connect_users("alice", "bob", UserConnectionType::Friend);
+        harness.connect_users("alice", "carol", UserConnectionType::Friend);
+        harness.create_post("p1", "bob", "Hello from Bob");
+        harness.create_post("p2", "carol", "Hello from Carol");
+
+        harness.mute_user("alice", "bob");
+        // new posts of muted user are not added, older are hidden by view
+        harness.create_post("p3", "bob", "Bob again");
+        assert_eq!(harness.timeline_view("alice"), vec!["p2"]);
+
+        harness.unmute_user("alice", "bob");
+        assert_eq!(harness.timeline_view("alice"), vec!["p2", "p1"]);
+        assert!(harness
+            .timelines
+            .get("alice")
+            .unwrap()
+            .posts
+            .iter()
+            .all(|p| p.post_id != "p3"));
+    }
+
+    #[test]
+    fn test_timeline_mute_keyword() {
+        let mut harness = harness();
+        harness.connect_users("alice", "bob", UserConnectionType::Friend);
+        harness.mute_keyword("alice", " Spoiler ");
+        harness.mute_keyword("alice", "spoiler");
+        assert_eq!(
+            harness
+                .timelines
+                .get("alice")
+                .unwrap()
+                .preferences
+                .muted_keywords,
+            vec!["spoiler"]
+        );
+
+        harness.create_post("p1", "bob", "Spoiler: it was the butler");
+        harness.create_post("p2", "bob", "Nothing to see");
+        assert_eq!(harness.timeline_view("alice"), vec!["p2"]);
+    }
+
+    #[test]
+    fn test_timeline_connection_boost() {
+        let mut harness = harness();
+        harness.connect_users("bob", "alice", UserConnectionType::Friend);
+        harness.connect_users("bob", "carol", UserConnectionType::Friend);
+        harness.create_post("p1", "alice", "Hello from Alice");
+        harness.create_post("p2", "carol", "Hello from Carol");
+        harness.record_interaction("bob", "alice", InteractionKind::Comment);
+
+        let preferences = TimelinePreferences {
+            sort: TimelineSort::MostEngaged,
+            ..TimelinePreferences::default()
+        };
+        harness.set_timeline_preferences("bob", preferences.clone());
+        assert_eq!(harness.timeline_view("bob"), vec!["p2", "p1"]);
+
+        harness.set_timeline_preferences(
+            "bob",
+            TimelinePreferences {
+                connection_boost: true,
+                ..preferences
+            },
+        );
+        assert_eq!(harness.timeline_view("bob"), vec!["p1", "p2"]);
+    }
+
+    #[test]
+    fn test_post_deleted_evicted_from_timelines() {
+        let mut harness = harness();
+        harness.connect_users("alice", "bob", UserConnectionType::Friend);
+        harness.create_post("p1", "alice", "Hello from Alice");
+        harness.create_post("p2", "alice", "Hello again");
+
+        harness.delete_post("p1");
+
+        assert_eq!(harness.timeline_view("alice"), vec!["p2"]);
+        assert_eq!(harness.timeline_view("bob"), vec!["p2"]);
+        assert!(harness
+            .timelines
+            .values()
+            .all(|t| t.posts.iter().all(|p| p.post_id != "p1")));
+    }
+
+    #[test]
+    fn test_post_deleted_reported_by_timeline_updates() {
+        let mut harness = harness();
+        harness.connect_users("alice", "bob", UserConnectionType::Friend);
+        harness.create_post("p1", "alice", "Hello from Alice");
+        let sequence = harness.timelines["bob"].sequence;
+        let post_ref = harness.timelines["bob"].posts[0].clone();
+
+        harness.delete_post("p1");
+
+        let updates = harness.timelines["bob"].get_updates_since_sequence(sequence);
+        assert!(updates.posts.is_empty());
+        assert_eq!(updates.removed_post_ids, vec!["p1"]);
+        assert_eq!(updates.cursor, cursor::encode(sequence + 1));
+
+        // removal is reported once, re-added post is not reported as removed
+        let updates = harness.timelines["bob"].get_updates_since_sequence(sequence + 1);
+        assert!(updates.removed_post_ids.is_empty());
+        let timeline = harness.timelines.get_mut("bob").unwrap();
+        timeline.add_or_update_posts(vec![post_ref]);
+        assert!(timeline.removed_posts.is_empty());
+    }
+
+    #[test]
+    fn test_post_validated_and_moderated() {
+        let mut harness = harness();
+        harness.connect_users("alice", "bob", UserConnectionType::Friend);
+        harness.add_moderation_words(Severity::Low, &["darn"]);
+        harness.add_moderation_words(Severity::High, &["scam"]);
+
+        assert!(matches!(
+            harness.try_create_post("p1", "alice", "  "),
+            Err(PostError::Invalid(_))
+        ));
+        assert!(matches!(
+            harness.try_create_post("p2", "alice", "Great scam offer"),
+            Err(PostError::Invalid(_))
+        ));
+        harness.create_post("p3", "alice", "Darn it");
+
+        assert_eq!(harness.timeline_view("bob"), vec!["p3"]);
+        assert_eq!(harness.posts["p3"].content, "**** it");
+        assert_eq!(harness.user_posts["alice"].posts.len(), 1);
+    }
+
+    #[test]
+    fn test_post_of_banned_user_rejected() {
+        let mut harness = harness();
+        harness.connect_users("alice", "bob", UserConnectionType::Friend);
+        harness.set_user_status("alice", UserStatus::Banned);
+
+        assert!(matches!(
+            harness.try_create_post("p1", "alice", "Hello from Alice"),
+            Err(PostError::NotAllowed(_))
+        ));
+        assert!(harness.posts.is_empty());
+        assert!(harness.timeline_view("bob").is_empty());
+    }
+
+    #[test]
+    fn test_delete_post_not_found() {
+        let mut harness = harness();
+        harness.create_post("p1", "alice", "Hello from Alice");
+        harness.delete_post("p1");
+
+        let result = harness.agent_flow(
+            |h| &mut h.user_posts,
+            "alice",
+            |clients, state| user_posts::delete_post(clients, state, "p1".to_string()),
+        );
+        assert!(matches!(result, Err(PostError::NotFound(_))));
+        assert!(harness.posts["p1"].deleted_at.is_some());
+        assert!(harness.user_posts["alice"].posts.is_empty());
+    }
+
+    #[test]
+    fn test_post_refs_author_name() {
+        let mut harness = harness();
+        harness.connect_users("alice", "bob", UserConnectionType::Friend);
+
+        harness.create_post("p1", "alice", "Hello from Alice");
+
+        // author name is cached on post by timelines updater
+        assert_eq!(
+            harness.posts["p1"].created_by_name,
+            Some("Alice".to_string())
+        );
+        let timeline = harness.timelines.get("bob").unwrap();
+        assert_eq!(timeline.posts[0].created_by_name, Some("Alice".to_string()));
+        assert_eq!(
+            timeline.posts[0].created_by_connection_type,
+            Some(UserConnectionType::Friend)
+        );
+    }
+
+    #[test]
+    fn test_repost_fanout_to_connections() {
+        let mut harness = harness();
+        // carol follows bob, bob does not follow alice
+        harness.connect_users("carol", "bob", UserConnectionType::Following);
+
+        harness.create_post("p1", "alice", "Hello from Alice");
+        harness.create_repost("r1", "bob", "p1");
+
+        assert_eq!(harness.timeline_view("bob"), vec!["r1"]);
+        assert_eq!(harness.timeline_view("carol"), vec!["r1"]);
+        assert_eq!(harness.posts["r1"].reposted_from, Some("p1".to_string()));
+        assert_eq!(harness.posts["p1"].repost_count, 1);
+
+        harness.delete_post("r1");
+
+        assert!(harness.timeline_view("carol").is_empty());
+        assert_eq!(harness.posts["p1"].repost_count, 0);
+    }
+
+    #[test]
+    fn test_timeline_export_page_same_created_at() {
+        let mut harness = harness();
+        for post_id in ["p2", "p1", "p3"] {
+            harness.create_post(post_id, "alice", "Hello from Alice");
+        }
+        let now = chrono::Utc::now();
+        let mut posts = harness.timelines["alice"].posts.clone();
+        for post in posts.iter_mut() {
+            post.created_at = now;
+        }
+
+        let mut ids = vec![];
+        let mut after = None;
+        loop {
+            let (page, next) = get_export_page_refs(posts.clone(), after, 1);
+            ids.extend(page.into_iter().map(|p| p.post_id));
+            match next {
+                Some(next) => after = cursor::decode_key(Some(next)).unwrap(),
+                None => break,
+            }
+        }
+        assert_eq!(ids, vec!["p1", "p2", "p3"]);
+    }
+
+    #[test]
+    fn test_user_posts_page_same_created_at() {
+        let now = chrono::Utc::now();
+        let user_posts = UserPosts {
+            user_id: "alice".to_string(),
+            posts: ["p2", "p1", "p3"]
+                .iter()
+                .map(|id| PostRef {
+                    post_id: id.to_string(),
+                    created_at: now,
+                })
+                .collect(),
+            created_at: now,
+            updated_at: now,
+        };
+
+        for (order, expected) in [
+            (PostsOrder::OldestFirst, vec!["p1", "p2", "p3"]),
+            (PostsOrder::NewestFirst, vec!["p3", "p2", "p1"]),
+        ] {
+            let mut ids = vec![];
+            let mut cursor = None;
+            loop {
+                let page = user_posts.get_page(cursor, Some(1), order.clone()).unwrap();
+                ids.extend(page.posts.into_iter().map(|p| p.post_id));
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+            assert_eq!(ids, expected);
+        }
+    }
+
+    // states of user posts, timeline and chats agents, other states are tested in their modules
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut harness = harness();
+        harness.connect_users("alice", "bob", UserConnectionType::Friend);
+        harness.create_post("p1", "alice", "Hello from Alice");
+
+        snapshot::assert_round_trip(&harness.timelines.get("bob").cloned());
+
+        let now = chrono::Utc::now();
+        let user_posts = UserPosts {
+            user_id: "alice".to_string(),
+            posts: vec![PostRef {
+                post_id: "p1".to_string(),
+                created_at: now,
+            }],
+            created_at: now,
+            updated_at: now,
+        };
+        snapshot::assert_round_trip(&Some(user_posts));
+
+        let user_chats = UserChats {
+            user_id: "alice".to_string(),
+            chats: vec![ChatRef {
+                chat_id: "c1".to_string(),
+                created_by: "alice".to_string(),
+                created_at: now,
+                updated_at: now,
+                sequence: 1,
+                last_message: None,
+                message_count: 0,
+                pinned: true,
+                last_read_at: Some(now),
+            }],
+            sequence: 1,
+            created_at: now,
+            updated_at: now,
+        };
+        snapshot::assert_round_trip(&Some(user_chats));
+    }
+}
@@ -0,0 +1,435 @@
+use crate::common::{clock, content, cursor, trace, SocialNetError};
+use crate::post::{fetch_posts_by_ids, Post};
+use crate::trace_println;
+use crate::trending::record_hashtags_usage;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+
+// max number of (most recent) posts kept in hashtag index
+const HASHTAG_MAX_POSTS: usize = 1000;
+
+// default and max number of posts in feed page
+const FEED_PAGE_DEFAULT_LIMIT: u32 = 20;
+const FEED_PAGE_MAX_LIMIT: u32 = 100;
+
+// number of most recent posts ranked by engagement
+const FEED_ENGAGEMENT_WINDOW: usize = 200;
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct HashtagPostRef {
+    pub post_id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl HashtagPostRef {
+    fn created_at_micros(&self) -> u64 {
+        self.created_at.timestamp_micros().max(0) as u64
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct HashtagPostRefsPage {
+    pub tag: String,
+    pub posts: Vec<HashtagPostRef>,
+    // cursor of next page, none if this is the last page
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct Hashtag {
+    pub tag: String,
+    // newest first
+    pub posts: Vec<HashtagPostRef>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Hashtag {
+    fn new(tag: String) -> Self {
+        let now = clock::now();
+        Hashtag {
+            tag,
+            posts: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn add_post(&mut self, post_id: String, created_at: chrono::DateTime<chrono::Utc>) -> bool {
+        if self.posts.iter().any(|p| p.post_id == post_id) {
+            false
+        } else {
+            self.posts.push(HashtagPostRef {
+                post_id,
+                created_at,
+            });
+            self.posts.sort_by_key(|p| std::cmp::Reverse(p.created_at));
+            self.posts.truncate(HASHTAG_MAX_POSTS);
+            self.updated_at = clock::now();
+            true
+        }
+    }
+
+    fn remove_post(&mut self, post_id: &str) -> bool {
+        let count = self.posts.len();
+        self.posts.retain(|p| p.post_id != post_id);
+        let removed = self.posts.len() < count;
+        if removed {
+            self.updated_at = clock::now();
+        }
+        removed
+    }
+
+    // Page of post refs (newest first), cursor is creation time (micros) of the last post of previous page
+    fn get_page(
+        &self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<HashtagPostRefsPage, cursor::InvalidCursor> {
+        let before = match cursor.filter(|c| !c.is_empty()) {
+            Some(c) => Some(cursor::decode(&c)?),
+            None => None,
+        };
+        let limit = limit
+            .unwrap_or(FEED_PAGE_DEFAULT_LIMIT)
+            .clamp(1, FEED_PAGE_MAX_LIMIT) as usize;
+
+        let mut posts: Vec<HashtagPostRef> = self
+            .posts
+            .iter()
+            .filter(|p| before.is_none_or(|before| p.created_at_micros() < before))
+            .take(limit + 1)
+            .cloned()
+            .collect();
+
+        let has_more = posts.len() > limit;
+        posts.truncate(limit);
+        let next_cursor = if has_more {
+            posts.last().map(|p| cursor::encode(p.created_at_micros()))
+        } else {
+            None
+        };
+
+        Ok(HashtagPostRefsPage {
+            tag: self.tag.clone(),
+            posts,
+            next_cursor,
+        })
+    }
+}
+
+#[agent_definition]
+trait HashtagAgent {
+    fn new(tag: String) -> Self;
+
+    fn get_hashtag(&self) -> Option<Hashtag>;
+
+    fn add_post(
+        &mut self,
+        post_id: String,
+        created_at: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
+    );
+
+    fn remove_post(&mut self, post_id: String, correlation_id: Option<String>);
+
+    fn list_posts(
+        &self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<HashtagPostRefsPage>, SocialNetError>;
+}
+
+struct HashtagAgentImpl {
+    _id: String,
+    state: Option<Hashtag>,
+}
+
+impl HashtagAgentImpl {
+    fn get_state(&mut self) -> &mut Hashtag {
+        self.state.get_or_insert(Hashtag::new(self._id.clone()))
+    }
+
+    fn with_state<T>(&mut self, f: impl FnOnce(&mut Hashtag) -> T) -> T {
+        f(self.get_state())
+    }
+}
+
+#[agent_implementation]
+impl HashtagAgent for HashtagAgentImpl {
+    fn new(tag: String) -> Self {
+        HashtagAgentImpl {
+            _id: tag,
+            state: None,
+        }
+    }
+
+    fn get_hashtag(&self) -> Option<Hashtag> {
+        self.state.clone()
+    }
+
+    fn add_post(
+        &mut self,
+        post_id: String,
+        created_at: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
+    ) {
+        trace::init(correlation_id);
+        self.with_state(|state| {
+            trace_println!("add post - tag: {}, post id: {post_id}", state.tag);
+            state.add_post(post_id, created_at);
+        });
+    }
+
+    fn remove_post(&mut self, post_id: String, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        if let Some(state) = self.state.as_mut() {
+            trace_println!("remove post - tag: {}, post id: {post_id}", state.tag);
+            state.remove_post(&post_id);
+        }
+    }
+
+    fn list_posts(
+        &self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<HashtagPostRefsPage>, SocialNetError> {
+        match &self.state {
+            Some(state) => Ok(Some(state.get_page(cursor, limit)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<Hashtag> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+// Index post under hashtags of its content, usage of hashtags is counted by trending agents
+pub fn index_post_hashtags(post: &Post) {
+    let tags = content::extract_hashtags(&post.content);
+    record_hashtags_usage(&tags, post.created_at);
+    for tag in tags {
+        HashtagAgentClient::get(tag).trigger_add_post(
+            post.post_id.clone(),
+            post.created_at,
+            trace::propagate(),
+        );
+    }
+}
+
+// Remove post from index of hashtags of its content
+pub fn unindex_post_hashtags(post: &Post) {
+    for tag in content::extract_hashtags(&post.content) {
+        HashtagAgentClient::get(tag).trigger_remove_post(post.post_id.clone(), trace::propagate());
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum HashtagFeedOrder {
+    // reverse-chronological
+    Newest,
+    // by likes and comments, of the most recent posts
+    Engagement,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct HashtagFeed {
+    pub tag: String,
+    pub posts: Vec<Post>,
+    // cursor of next page, none if this is the last page
+    pub next_cursor: Option<String>,
+    // some posts were skipped, as they were not fetched in time
+    pub degraded: bool,
+}
+
+// Engagement score of post, positive likes and comments
+fn engagement(post: &Post) -> usize {
+    post.likes.values().filter(|l| l.is_positive()).count() + post.comments.len()
+}
+
+// Posts ordered by engagement (newest first on tie)
+fn sort_by_engagement(posts: &mut [Post]) {
+    posts.sort_by_key(|p| std::cmp::Reverse((engagement(p), p.created_at)));
+}
+
+#[agent_definition(mode = "ephemeral")]
+trait HashtagFeedAgent {
+    fn new() -> Self;
+
+    // Feed of posts with tag, none if tag is not used
+    //
+    // newest feed cursor is creation time of the last post, engagement feed cursor is offset in ranked posts
+    async fn get_feed(
+        &mut self,
+        tag: String,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        order: Option<HashtagFeedOrder>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<HashtagFeed>, SocialNetError>;
+}
+
+struct HashtagFeedAgentImpl {}
+
+impl HashtagFeedAgentImpl {
+    async fn get_newest_feed(
+        tag: String,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<HashtagFeed>, SocialNetError> {
+        let page = HashtagAgentClient::get(tag.clone())
+            .list_posts(cursor, limit)
+            .await?;
+
+        match page {
+            Some(page) => {
+                let post_ids: Vec<String> = page.posts.into_iter().map(|p| p.post_id).collect();
+                let posts = fetch_posts_by_ids(&post_ids).await;
+
+                Ok(Some(HashtagFeed {
+                    tag,
+                    degraded: posts.is_degraded(),
+                    posts: posts.items,
+                    next_cursor: page.next_cursor,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_engagement_feed(
+        tag: String,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<HashtagFeed>, SocialNetError> {
+        let offset = cursor::decode_or(cursor, 0)? as usize;
+        let limit = limit
+            .unwrap_or(FEED_PAGE_DEFAULT_LIMIT)
+            .clamp(1, FEED_PAGE_MAX_LIMIT) as usize;
+
+        let hashtag = HashtagAgentClient::get(tag.clone()).get_hashtag().await;
+
+        match hashtag {
+            Some(hashtag) => {
+                let post_ids: Vec<String> = hashtag
+                    .posts
+                    .into_iter()
+                    .take(FEED_ENGAGEMENT_WINDOW)
+                    .map(|p| p.post_id)
+                    .collect();
+                let fetched = fetch_posts_by_ids(&post_ids).await;
+                let degraded = fetched.is_degraded();
+                let mut posts = fetched.items;
+                sort_by_engagement(&mut posts);
+
+                let next_cursor = if posts.len() > offset + limit {
+                    Some(cursor::encode((offset + limit) as u64))
+                } else {
+                    None
+                };
+                let posts = posts.into_iter().skip(offset).take(limit).collect();
+
+                Ok(Some(HashtagFeed {
+                    tag,
+                    posts,
+                    next_cursor,
+                    degraded,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[agent_implementation]
+impl HashtagFeedAgent for HashtagFeedAgentImpl {
+    fn new() -> Self {
+        Self {}
+    }
+
+    async fn get_feed(
+        &mut self,
+        tag: String,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        order: Option<HashtagFeedOrder>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<HashtagFeed>, SocialNetError> {
+        trace::init(correlation_id);
+        let tag = content::normalize_hashtag(&tag).ok_or(SocialNetError::ValidationFailed(
+            format!("Invalid tag: {tag}"),
+        ))?;
+        let order = order.unwrap_or(HashtagFeedOrder::Newest);
+
+        trace_println!("get feed - tag: {tag}, order: {order:?}");
+
+        match order {
+            HashtagFeedOrder::Newest => Self::get_newest_feed(tag, cursor, limit).await,
+            HashtagFeedOrder::Engagement => Self::get_engagement_feed(tag, cursor, limit).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    fn time(seconds: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn test_hashtag_add_remove_post() {
+        let mut hashtag = Hashtag::new("rust".to_string());
+        assert!(hashtag.add_post("p1".to_string(), time(100)));
+        assert!(hashtag.add_post("p2".to_string(), time(300)));
+        assert!(hashtag.add_post("p3".to_string(), time(200)));
+        assert!(!hashtag.add_post("p1".to_string(), time(100)));
+
+        let ids: Vec<&str> = hashtag.posts.iter().map(|p| p.post_id.as_str()).collect();
+        assert_eq!(ids, vec!["p2", "p3", "p1"]);
+
+        assert!(hashtag.remove_post("p3"));
+        assert!(!hashtag.remove_post("p3"));
+        assert_eq!(hashtag.posts.len(), 2);
+    }
+
+    #[test]
+    fn test_hashtag_get_page() {
+        let mut hashtag = Hashtag::new("rust".to_string());
+        for i in 1..=5 {
+            hashtag.add_post(format!("p{i}"), time(i * 100));
+        }
+
+        let page = hashtag.get_page(None, Some(2)).unwrap();
+        let ids: Vec<&str> = page.posts.iter().map(|p| p.post_id.as_str()).collect();
+        assert_eq!(ids, vec!["p5", "p4"]);
+
+        let page = hashtag.get_page(page.next_cursor, Some(2)).unwrap();
+        let ids: Vec<&str> = page.posts.iter().map(|p| p.post_id.as_str()).collect();
+        assert_eq!(ids, vec!["p3", "p2"]);
+
+        let page = hashtag.get_page(page.next_cursor, Some(2)).unwrap();
+        let ids: Vec<&str> = page.posts.iter().map(|p| p.post_id.as_str()).collect();
+        assert_eq!(ids, vec!["p1"]);
+        assert!(page.next_cursor.is_none());
+
+        assert!(hashtag.get_page(Some("xyz".to_string()), None).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut hashtag = Hashtag::new("rust".to_string());
+        hashtag.add_post("p1".to_string(), time(100));
+        hashtag.add_post("p2".to_string(), time(200));
+        snapshot::assert_round_trip(&Some(hashtag));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::common::{clock, trace, SocialNetError};
+use crate::trace_println;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+
+const INVITE_CODE_LENGTH: usize = 10;
+
+// characters of invite codes, without similar looking characters (0/O, 1/I/L)
+const INVITE_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+
+// New random invite code
+pub fn new_invite_code() -> String {
+    uuid::Uuid::new_v4()
+        .as_bytes()
+        .iter()
+        .take(INVITE_CODE_LENGTH)
+        .map(|b| INVITE_CODE_ALPHABET[*b as usize % INVITE_CODE_ALPHABET.len()] as char)
+        .collect()
+}
+
+// Normalized (trimmed, uppercase) invite code
+pub fn normalize_invite_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+// Single-use invite code issued by user, redeemed by new user on signup
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Invite {
+    pub code: String,
+    // inviting user
+    pub created_by: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub redeemed_by: Option<String>,
+    pub redeemed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Invite {
+    fn new(code: String, created_by: String) -> Self {
+        Invite {
+            code,
+            created_by,
+            created_at: clock::now(),
+            redeemed_by: None,
+            redeemed_at: None,
+        }
+    }
+
+    pub fn is_redeemed(&self) -> bool {
+        self.redeemed_by.is_some()
+    }
+
+    fn redeem(&mut self, user_id: String) -> Result<(), SocialNetError> {
+        if self.is_redeemed() {
+            Err(SocialNetError::already_exists(
+                "Invite code already redeemed",
+            ))
+        } else if self.created_by == user_id {
+            Err(SocialNetError::not_allowed(
+                "Invite code can not be redeemed by inviting user",
+            ))
+        } else {
+            self.redeemed_by = Some(user_id);
+            self.redeemed_at = Some(clock::now());
+            Ok(())
+        }
+    }
+}
+
+#[agent_definition]
+trait InviteAgent {
+    fn new(code: String) -> Self;
+
+    fn get_invite(&self) -> Option<Invite>;
+
+    // Issue invite code of user, fails if code already issued
+    fn init_invite(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Redeem invite code by new user, returns inviting user id
+    fn redeem(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<String, SocialNetError>;
+}
+
+struct InviteAgentImpl {
+    _id: String,
+    state: Option<Invite>,
+}
+
+#[agent_implementation]
+impl InviteAgent for InviteAgentImpl {
+    fn new(code: String) -> Self {
+        InviteAgentImpl {
+            _id: code,
+            state: None,
+        }
+    }
+
+    fn get_invite(&self) -> Option<Invite> {
+        self.state.clone()
+    }
+
+    fn init_invite(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        if self.state.is_some() {
+            Err(SocialNetError::already_exists("Invite code already exists"))
+        } else {
+            trace_println!("init invite - user id: {user_id}");
+            self.state = Some(Invite::new(self._id.clone(), user_id));
+            Ok(())
+        }
+    }
+
+    fn redeem(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<String, SocialNetError> {
+        trace::init(correlation_id);
+        let invite = self
+            .state
+            .as_mut()
+            .ok_or(SocialNetError::not_found("Invite code not found"))?;
+        invite.redeem(user_id.clone())?;
+        trace_println!(
+            "redeem - user id: {user_id}, invited by: {}",
+            invite.created_by
+        );
+        Ok(invite.created_by.clone())
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<Invite> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    #[test]
+    fn test_new_invite_code() {
+        let code = new_invite_code();
+        assert_eq!(code.len(), INVITE_CODE_LENGTH);
+        assert!(code.bytes().all(|c| INVITE_CODE_ALPHABET.contains(&c)));
+        assert_ne!(code, new_invite_code());
+        assert_eq!(normalize_invite_code(&code.to_lowercase()), code);
+    }
+
+    #[test]
+    fn test_redeem_invite_single_use() {
+        let mut invite = Invite::new("CODE".to_string(), "u1".to_string());
+
+        assert!(invite.redeem("u1".to_string()).is_err());
+        assert!(invite.redeem("u2".to_string()).is_ok());
+        assert!(invite.is_redeemed());
+        assert_eq!(invite.redeemed_by, Some("u2".to_string()));
+        assert!(invite.redeem("u3".to_string()).is_err());
+        assert_eq!(invite.redeemed_by, Some("u2".to_string()));
+    }
+
+    #[test]
+    fn test_redeem_invite_errors() {
+        let mut invite = Invite::new("CODE".to_string(), "u1".to_string());
+
+        assert!(matches!(
+            invite.redeem("u1".to_string()),
+            Err(SocialNetError::NotAllowed(_))
+        ));
+        invite.redeem("u2".to_string()).unwrap();
+        assert!(matches!(
+            invite.redeem("u3".to_string()),
+            Err(SocialNetError::AlreadyExists(_))
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut invite = Invite::new(new_invite_code(), "u1".to_string());
+        invite.redeem("u2".to_string()).unwrap();
+        snapshot::assert_round_trip(&Some(invite));
+    }
+}
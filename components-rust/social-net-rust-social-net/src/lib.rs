@@ -1,7 +1,26 @@
+pub mod abuse;
+pub mod analytics;
 pub mod chat;
 pub mod common;
+pub mod draft;
+pub mod experiment;
+pub mod explore;
+pub mod fault_injection;
+pub mod feature_flag;
+#[cfg(test)]
+mod harness;
+pub mod hashtag;
+pub mod invite;
+pub mod moderation;
+pub mod notification;
+pub mod operations;
 pub mod post;
+pub mod rate_limit;
+pub mod reconciler;
+pub mod trending;
 pub mod user;
 pub mod user_chats;
+pub mod user_export;
+pub mod user_handle;
 pub mod user_posts;
 pub mod user_timeline;
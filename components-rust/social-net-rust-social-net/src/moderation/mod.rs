@@ -0,0 +1,836 @@
+use crate::common::{clock, trace, SocialNetError};
+use crate::trace_println;
+use crate::user::{UserAgentClient, UserStatus};
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+// language of word lists applied regardless of content language
+pub const ALL_LANGUAGES: &str = "*";
+
+// how long content paths use cached moderation config before reloading it
+const CONFIG_CACHE_TTL: Duration = Duration::from_secs(30);
+
+// max number of closed (dismissed or resolved) reports kept in moderation queue
+const CLOSED_REPORTS_MAX_COUNT: usize = 1000;
+
+static WORD_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\w+").unwrap());
+
+// Content contains word with reject action
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContentRejected;
+
+impl Display for ContentRejected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Content rejected by moderation")
+    }
+}
+
+impl From<ContentRejected> for SocialNetError {
+    fn from(e: ContentRejected) -> Self {
+        SocialNetError::ValidationFailed(e.to_string())
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq)]
+pub enum ModerationAction {
+    Mask,
+    Reject,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ModerationConfig {
+    // language -> word -> severity
+    pub word_lists: BTreeMap<String, BTreeMap<String, Severity>>,
+    pub severity_actions: HashMap<Severity, ModerationAction>,
+    pub version: u64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ModerationConfig {
+    pub(crate) fn new() -> Self {
+        let now = clock::now();
+        ModerationConfig {
+            word_lists: BTreeMap::new(),
+            severity_actions: HashMap::from([
+                (Severity::Low, ModerationAction::Mask),
+                (Severity::Medium, ModerationAction::Mask),
+                (Severity::High, ModerationAction::Reject),
+            ]),
+            version: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn normalize_word(word: &str) -> Option<String> {
+        let word = word.trim().to_lowercase();
+        if word.is_empty() || word.chars().any(|c| c.is_whitespace()) {
+            None
+        } else {
+            Some(word)
+        }
+    }
+
+    fn normalize_language(language: &str) -> String {
+        let language = language.trim().to_lowercase();
+        if language.is_empty() {
+            ALL_LANGUAGES.to_string()
+        } else {
+            language
+        }
+    }
+
+    fn touch(&mut self) {
+        self.version += 1;
+        self.updated_at = clock::now();
+    }
+
+    pub(crate) fn add_words(
+        &mut self,
+        language: &str,
+        severity: Severity,
+        words: Vec<String>,
+    ) -> u32 {
+        let list = self
+            .word_lists
+            .entry(Self::normalize_language(language))
+            .or_default();
+
+        let mut added = 0;
+        for word in words.iter().filter_map(|w| Self::normalize_word(w)) {
+            if list.insert(word, severity.clone()).as_ref() != Some(&severity) {
+                added += 1;
+            }
+        }
+
+        if added > 0 {
+            self.touch();
+        }
+        added
+    }
+
+    fn remove_words(&mut self, language: &str, words: Vec<String>) -> u32 {
+        let language = Self::normalize_language(language);
+        let mut removed = 0;
+
+        if let Some(list) = self.word_lists.get_mut(&language) {
+            for word in words.iter().filter_map(|w| Self::normalize_word(w)) {
+                if list.remove(&word).is_some() {
+                    removed += 1;
+                }
+            }
+            if list.is_empty() {
+                self.word_lists.remove(&language);
+            }
+        }
+
+        if removed > 0 {
+            self.touch();
+        }
+        removed
+    }
+
+    fn import_words(
+        &mut self,
+        language: &str,
+        severity: Severity,
+        words: Vec<String>,
+        replace: bool,
+    ) -> u32 {
+        if replace {
+            self.word_lists.remove(&Self::normalize_language(language));
+        }
+        let imported = self.add_words(language, severity, words);
+        if replace && imported == 0 {
+            self.touch();
+        }
+        imported
+    }
+
+    fn set_severity_action(&mut self, severity: Severity, action: ModerationAction) -> bool {
+        if self.severity_actions.get(&severity) == Some(&action) {
+            false
+        } else {
+            self.severity_actions.insert(severity, action);
+            self.touch();
+            true
+        }
+    }
+
+    fn get_action(&self, severity: &Severity) -> ModerationAction {
+        self.severity_actions
+            .get(severity)
+            .cloned()
+            .unwrap_or(ModerationAction::Mask)
+    }
+
+    fn find_severity(&self, word: &str, language: Option<&str>) -> Option<Severity> {
+        let word = word.to_lowercase();
+        self.word_lists
+            .iter()
+            .filter(|(lang, _)| match language {
+                Some(language) => {
+                    lang.as_str() == ALL_LANGUAGES
+                        || lang.as_str() == Self::normalize_language(language)
+                }
+                None => true,
+            })
+            .filter_map(|(_, list)| list.get(&word).cloned())
+            .max()
+    }
+
+    // Apply word lists to content
+    //
+    // words with reject action fail the whole content, words with mask action are replaced by '*',
+    // without language all lists are applied
+    // Moderated content of any language, content is kept if there are no word lists
+    pub(crate) fn moderate(&self, content: String) -> Result<String, ContentRejected> {
+        if self.word_lists.is_empty() {
+            Ok(content)
+        } else {
+            self.apply(&content, None)
+        }
+    }
+
+    pub fn apply(&self, content: &str, language: Option<&str>) -> Result<String, ContentRejected> {
+        let mut result = String::with_capacity(content.len());
+        let mut last = 0;
+
+        for m in WORD_REGEX.find_iter(content) {
+            if let Some(severity) = self.find_severity(m.as_str(), language) {
+                match self.get_action(&severity) {
+                    ModerationAction::Reject => {
+                        return Err(ContentRejected);
+                    }
+                    ModerationAction::Mask => {
+                        result.push_str(&content[last..m.start()]);
+                        result.push_str(&"*".repeat(m.as_str().chars().count()));
+                        last = m.end();
+                    }
+                }
+            }
+        }
+        result.push_str(&content[last..]);
+
+        Ok(result)
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub enum ReportStatus {
+    Open,
+    Dismissed,
+    Resolved,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct UserReport {
+    pub report_id: String,
+    pub reporter_id: String,
+    pub target_id: String,
+    pub reason: String,
+    pub status: ReportStatus,
+    // user status set by moderation action which resolved the report
+    pub resolution: Option<UserStatus>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl UserReport {
+    fn new(reporter_id: String, target_id: String, reason: String) -> Self {
+        let now = clock::now();
+        UserReport {
+            report_id: uuid::Uuid::new_v4().to_string(),
+            reporter_id,
+            target_id,
+            reason,
+            status: ReportStatus::Open,
+            resolution: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn close(&mut self, status: ReportStatus, resolution: Option<UserStatus>) {
+        self.status = status;
+        self.resolution = resolution;
+        self.updated_at = clock::now();
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct Moderation {
+    pub config: ModerationConfig,
+    pub reports: Vec<UserReport>,
+}
+
+impl Moderation {
+    fn new() -> Self {
+        Moderation {
+            config: ModerationConfig::new(),
+            reports: Vec::new(),
+        }
+    }
+
+    fn report_user(
+        &mut self,
+        reporter_id: String,
+        target_id: String,
+        reason: String,
+    ) -> Result<String, SocialNetError> {
+        let reason = reason.trim().to_string();
+        if reporter_id == target_id {
+            Err(SocialNetError::ValidationFailed(
+                "User can not report himself".to_string(),
+            ))
+        } else if reason.is_empty() {
+            Err(SocialNetError::ValidationFailed(
+                "Report reason is empty".to_string(),
+            ))
+        } else if self.reports.iter().any(|r| {
+            r.status == ReportStatus::Open
+                && r.reporter_id == reporter_id
+                && r.target_id == target_id
+        }) {
+            Err(SocialNetError::already_exists("User already reported"))
+        } else {
+            let report = UserReport::new(reporter_id, target_id, reason);
+            let report_id = report.report_id.clone();
+            self.reports.push(report);
+            Ok(report_id)
+        }
+    }
+
+    fn get_reports(&self, status: Option<ReportStatus>) -> Vec<UserReport> {
+        self.reports
+            .iter()
+            .filter(|r| status.as_ref().is_none_or(|s| r.status == *s))
+            .cloned()
+            .collect()
+    }
+
+    fn dismiss_report(&mut self, report_id: String) -> Result<(), SocialNetError> {
+        match self
+            .reports
+            .iter_mut()
+            .find(|r| r.report_id == report_id && r.status == ReportStatus::Open)
+        {
+            Some(report) => {
+                report.close(ReportStatus::Dismissed, None);
+                self.truncate_closed_reports();
+                Ok(())
+            }
+            None => Err(SocialNetError::not_found("Open report not found")),
+        }
+    }
+
+    // Resolve all open reports of user with moderation action
+    fn resolve_reports(&mut self, target_id: &str, status: UserStatus) -> u32 {
+        let mut resolved = 0;
+        for report in self
+            .reports
+            .iter_mut()
+            .filter(|r| r.target_id == target_id && r.status == ReportStatus::Open)
+        {
+            report.close(ReportStatus::Resolved, Some(status.clone()));
+            resolved += 1;
+        }
+        self.truncate_closed_reports();
+        resolved
+    }
+
+    fn truncate_closed_reports(&mut self) {
+        let closed_count = self
+            .reports
+            .iter()
+            .filter(|r| r.status != ReportStatus::Open)
+            .count();
+
+        if closed_count > CLOSED_REPORTS_MAX_COUNT {
+            // reports are ordered by creation, oldest closed reports are removed first
+            let mut to_remove = closed_count - CLOSED_REPORTS_MAX_COUNT;
+            self.reports.retain(|r| {
+                if to_remove > 0 && r.status != ReportStatus::Open {
+                    to_remove -= 1;
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+    }
+}
+
+#[agent_definition]
+trait ModerationAgent {
+    fn new() -> Self;
+
+    fn get_config(&self) -> ModerationConfig;
+
+    fn get_reports(&self, status: Option<ReportStatus>) -> Vec<UserReport>;
+
+    fn report_user(
+        &mut self,
+        reporter_id: String,
+        target_id: String,
+        reason: String,
+        correlation_id: Option<String>,
+    ) -> Result<String, SocialNetError>;
+
+    fn dismiss_report(
+        &mut self,
+        report_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    async fn ban_user(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    async fn shadowban_user(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    async fn unban_user(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    fn add_words(
+        &mut self,
+        language: String,
+        severity: Severity,
+        words: Vec<String>,
+        correlation_id: Option<String>,
+    ) -> u32;
+
+    fn remove_words(
+        &mut self,
+        language: String,
+        words: Vec<String>,
+        correlation_id: Option<String>,
+    ) -> u32;
+
+    fn import_words(
+        &mut self,
+        language: String,
+        severity: Severity,
+        words: Vec<String>,
+        replace: bool,
+        correlation_id: Option<String>,
+    ) -> u32;
+
+    fn set_severity_action(
+        &mut self,
+        severity: Severity,
+        action: ModerationAction,
+        correlation_id: Option<String>,
+    ) -> bool;
+}
+
+struct ModerationAgentImpl {
+    state: Moderation,
+}
+
+impl ModerationAgentImpl {
+    async fn set_user_status(
+        &mut self,
+        user_id: String,
+        status: UserStatus,
+    ) -> Result<(), SocialNetError> {
+        trace_println!("set user status - user id: {user_id}, status: {status}");
+
+        UserAgentClient::get(user_id.clone())
+            .set_status(status.clone(), trace::propagate())
+            .await?;
+
+        if status != UserStatus::Active {
+            let resolved = self.state.resolve_reports(&user_id, status);
+            trace_println!("set user status - user id: {user_id}, resolved reports: {resolved}");
+        }
+        Ok(())
+    }
+}
+
+#[agent_implementation]
+impl ModerationAgent for ModerationAgentImpl {
+    fn new() -> Self {
+        ModerationAgentImpl {
+            state: Moderation::new(),
+        }
+    }
+
+    fn get_config(&self) -> ModerationConfig {
+        self.state.config.clone()
+    }
+
+    fn get_reports(&self, status: Option<ReportStatus>) -> Vec<UserReport> {
+        self.state.get_reports(status)
+    }
+
+    fn report_user(
+        &mut self,
+        reporter_id: String,
+        target_id: String,
+        reason: String,
+        correlation_id: Option<String>,
+    ) -> Result<String, SocialNetError> {
+        trace::init(correlation_id);
+        trace_println!("report user - reporter id: {reporter_id}, target id: {target_id}");
+        self.state.report_user(reporter_id, target_id, reason)
+    }
+
+    fn dismiss_report(
+        &mut self,
+        report_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        trace_println!("dismiss report - report id: {report_id}");
+        self.state.dismiss_report(report_id)
+    }
+
+    async fn ban_user(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        self.set_user_status(user_id, UserStatus::Banned).await
+    }
+
+    async fn shadowban_user(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        self.set_user_status(user_id, UserStatus::Shadowbanned)
+            .await
+    }
+
+    async fn unban_user(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        self.set_user_status(user_id, UserStatus::Active).await
+    }
+
+    fn add_words(
+        &mut self,
+        language: String,
+        severity: Severity,
+        words: Vec<String>,
+        correlation_id: Option<String>,
+    ) -> u32 {
+        trace::init(correlation_id);
+        trace_println!(
+            "add words - language: {language}, severity: {severity:?}, count: {}",
+            words.len()
+        );
+        self.state.config.add_words(&language, severity, words)
+    }
+
+    fn remove_words(
+        &mut self,
+        language: String,
+        words: Vec<String>,
+        correlation_id: Option<String>,
+    ) -> u32 {
+        trace::init(correlation_id);
+        trace_println!(
+            "remove words - language: {language}, count: {}",
+            words.len()
+        );
+        self.state.config.remove_words(&language, words)
+    }
+
+    fn import_words(
+        &mut self,
+        language: String,
+        severity: Severity,
+        words: Vec<String>,
+        replace: bool,
+        correlation_id: Option<String>,
+    ) -> u32 {
+        trace::init(correlation_id);
+        trace_println!(
+            "import words - language: {language}, severity: {severity:?}, count: {}, replace: {replace}",
+            words.len()
+        );
+        self.state
+            .config
+            .import_words(&language, severity, words, replace)
+    }
+
+    fn set_severity_action(
+        &mut self,
+        severity: Severity,
+        action: ModerationAction,
+        correlation_id: Option<String>,
+    ) -> bool {
+        trace::init(correlation_id);
+        trace_println!("set severity action - severity: {severity:?}, action: {action:?}");
+        self.state.config.set_severity_action(severity, action)
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Moderation = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+thread_local! {
+    static CONFIG_CACHE: RefCell<Option<(ModerationConfig, Instant)>> = const { RefCell::new(None) };
+}
+
+async fn get_cached_config() -> ModerationConfig {
+    let cached = CONFIG_CACHE.with(|c| {
+        c.borrow()
+            .as_ref()
+            .filter(|(_, loaded_at)| loaded_at.elapsed() < CONFIG_CACHE_TTL)
+            .map(|(config, _)| config.clone())
+    });
+
+    match cached {
+        Some(config) => config,
+        None => {
+            let config = ModerationAgentClient::get().get_config().await;
+            trace_println!("moderation config loaded - version: {}", config.version);
+            CONFIG_CACHE.with(|c| *c.borrow_mut() = Some((config.clone(), Instant::now())));
+            config
+        }
+    }
+}
+
+// Moderate (sanitized) content of post, comment or message with current word lists
+pub async fn moderate_content(content: String) -> Result<String, ContentRejected> {
+    get_cached_config().await.moderate(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_report_user() {
+        let mut moderation = Moderation::new();
+
+        let report_id = moderation
+            .report_user(
+                "user1".to_string(),
+                "user2".to_string(),
+                " spam ".to_string(),
+            )
+            .unwrap();
+        assert_eq!(moderation.reports.len(), 1);
+        assert_eq!(moderation.reports[0].report_id, report_id);
+        assert_eq!(moderation.reports[0].reason, "spam");
+        assert_eq!(moderation.reports[0].status, ReportStatus::Open);
+
+        let result =
+            moderation.report_user("user1".to_string(), "user2".to_string(), "spam".to_string());
+        assert_eq!(
+            result,
+            Err(SocialNetError::already_exists("User already reported"))
+        );
+
+        let result =
+            moderation.report_user("user1".to_string(), "user1".to_string(), "spam".to_string());
+        assert!(result.is_err());
+
+        let result =
+            moderation.report_user("user3".to_string(), "user2".to_string(), " ".to_string());
+        assert_eq!(
+            result,
+            Err(SocialNetError::ValidationFailed(
+                "Report reason is empty".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_dismiss_report() {
+        let mut moderation = Moderation::new();
+        let report_id = moderation
+            .report_user("user1".to_string(), "user2".to_string(), "spam".to_string())
+            .unwrap();
+
+        assert!(moderation.dismiss_report(report_id.clone()).is_ok());
+        assert!(moderation.dismiss_report(report_id).is_err());
+        assert_eq!(moderation.get_reports(Some(ReportStatus::Open)).len(), 0);
+        assert_eq!(
+            moderation.get_reports(Some(ReportStatus::Dismissed)).len(),
+            1
+        );
+
+        // user can be reported again after dismissal
+        assert!(moderation
+            .report_user("user1".to_string(), "user2".to_string(), "spam".to_string())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_resolve_reports() {
+        let mut moderation = Moderation::new();
+        moderation
+            .report_user("user1".to_string(), "user2".to_string(), "spam".to_string())
+            .unwrap();
+        moderation
+            .report_user(
+                "user3".to_string(),
+                "user2".to_string(),
+                "abuse".to_string(),
+            )
+            .unwrap();
+        moderation
+            .report_user("user1".to_string(), "user3".to_string(), "spam".to_string())
+            .unwrap();
+
+        assert_eq!(moderation.resolve_reports("user2", UserStatus::Banned), 2);
+        let resolved = moderation.get_reports(Some(ReportStatus::Resolved));
+        assert_eq!(resolved.len(), 2);
+        assert!(resolved
+            .iter()
+            .all(|r| r.resolution == Some(UserStatus::Banned)));
+        assert_eq!(moderation.get_reports(None).len(), 3);
+    }
+
+    #[test]
+    fn test_truncate_closed_reports() {
+        let mut moderation = Moderation::new();
+        for i in 0..CLOSED_REPORTS_MAX_COUNT + 5 {
+            moderation
+                .report_user(format!("user{i}"), "target".to_string(), "spam".to_string())
+                .unwrap();
+        }
+        moderation
+            .report_user("user1".to_string(), "other".to_string(), "spam".to_string())
+            .unwrap();
+
+        moderation.resolve_reports("target", UserStatus::Banned);
+        assert_eq!(moderation.reports.len(), CLOSED_REPORTS_MAX_COUNT + 1);
+        assert_eq!(moderation.get_reports(Some(ReportStatus::Open)).len(), 1);
+        assert_eq!(moderation.reports[0].reporter_id, "user5");
+    }
+
+    #[test]
+    fn test_add_words() {
+        let mut config = ModerationConfig::new();
+        let added = config.add_words(
+            "EN",
+            Severity::Low,
+            words(&["Darn", "heck", " ", "two words"]),
+        );
+        assert_eq!(added, 2);
+        assert_eq!(config.version, 1);
+        assert!(config.word_lists["en"].contains_key("darn"));
+
+        let added = config.add_words("en", Severity::Low, words(&["darn"]));
+        assert_eq!(added, 0);
+        assert_eq!(config.version, 1);
+    }
+
+    #[test]
+    fn test_remove_words() {
+        let mut config = ModerationConfig::new();
+        config.add_words("en", Severity::Low, words(&["darn", "heck"]));
+
+        assert_eq!(config.remove_words("en", words(&["darn", "other"])), 1);
+        assert_eq!(config.remove_words("de", words(&["heck"])), 0);
+        assert_eq!(config.remove_words("en", words(&["heck"])), 1);
+        assert!(!config.word_lists.contains_key("en"));
+    }
+
+    #[test]
+    fn test_import_words_replace() {
+        let mut config = ModerationConfig::new();
+        config.add_words("en", Severity::Low, words(&["darn"]));
+
+        let imported = config.import_words("en", Severity::High, words(&["heck"]), true);
+        assert_eq!(imported, 1);
+        assert!(!config.word_lists["en"].contains_key("darn"));
+        assert_eq!(config.word_lists["en"]["heck"], Severity::High);
+    }
+
+    #[test]
+    fn test_apply_mask() {
+        let mut config = ModerationConfig::new();
+        config.add_words("en", Severity::Low, words(&["darn"]));
+
+        let result = config.apply("Oh DARN it, darning", None).unwrap();
+        assert_eq!(result, "Oh **** it, darning");
+    }
+
+    #[test]
+    fn test_apply_reject() {
+        let mut config = ModerationConfig::new();
+        config.add_words("en", Severity::High, words(&["darn"]));
+
+        assert!(config.apply("oh darn", None).is_err());
+
+        config.set_severity_action(Severity::High, ModerationAction::Mask);
+        assert_eq!(config.apply("oh darn", None).unwrap(), "oh ****");
+    }
+
+    #[test]
+    fn test_apply_language_lists() {
+        let mut config = ModerationConfig::new();
+        config.add_words("de", Severity::Low, words(&["mist"]));
+        config.add_words(ALL_LANGUAGES, Severity::Low, words(&["darn"]));
+
+        assert_eq!(config.apply("mist darn", Some("en")).unwrap(), "mist ****");
+        assert_eq!(config.apply("mist darn", Some("de")).unwrap(), "**** ****");
+        assert_eq!(config.apply("mist darn", None).unwrap(), "**** ****");
+    }
+
+    #[test]
+    fn test_apply_highest_severity_wins() {
+        let mut config = ModerationConfig::new();
+        config.add_words("en", Severity::Low, words(&["darn"]));
+        config.add_words("de", Severity::High, words(&["darn"]));
+
+        assert!(config.apply("darn", None).is_err());
+        assert!(config.apply("darn", Some("en")).is_ok());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut moderation = Moderation::new();
+        moderation
+            .report_user("user1".to_string(), "user2".to_string(), "spam".to_string())
+            .unwrap();
+        snapshot::assert_round_trip(&moderation);
+    }
+}
@@ -0,0 +1,957 @@
+use crate::common::schedule::{parse_local_time, utc_offset, validate_utc_offset, DailySchedule};
+use crate::common::{clock, id, to_schedule_time, trace, SocialNetError};
+use crate::feature_flag::{is_feature_enabled, FEATURE_NOTIFICATIONS};
+use crate::trace_println;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+
+// max number of delivered notifications kept for user
+const NOTIFICATIONS_MAX_COUNT: usize = 200;
+
+// reactions on the same post (comment) within window are aggregated into one notification
+const REACTIONS_AGGREGATION_WINDOW_MINUTES: i64 = 60;
+
+const NOTIFICATIONS_PAGE_DEFAULT_LIMIT: u32 = 20;
+const NOTIFICATIONS_PAGE_MAX_LIMIT: u32 = 100;
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum NotificationKind {
+    Comment,
+    Like,
+    // other user connected as friend, sent or accepted friend request, or started following
+    Connection,
+    // user mentioned in chat message
+    Mention,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub notification_id: String,
+    pub kind: NotificationKind,
+    // user who caused the notification (last one of aggregated notifications)
+    pub created_by: String,
+    // set for notifications of posts and comments
+    pub post_id: Option<String>,
+    // set for notifications of comment reactions
+    #[serde(default)]
+    pub comment_id: Option<String>,
+    // set for notifications of chat mentions
+    #[serde(default)]
+    pub chat_id: Option<String>,
+    pub content: String,
+    // number of aggregated actions (reactions)
+    #[serde(default = "default_count")]
+    pub count: u32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    // none while notification is held (quiet hours)
+    pub delivered_at: Option<chrono::DateTime<chrono::Utc>>,
+    // none while notification is unread
+    #[serde(default)]
+    pub read_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Post, comment or chat the notification is about, nothing for connections
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct NotificationTarget {
+    pub post_id: Option<String>,
+    pub comment_id: Option<String>,
+    pub chat_id: Option<String>,
+}
+
+impl NotificationTarget {
+    pub fn post(post_id: &str) -> Self {
+        NotificationTarget {
+            post_id: Some(post_id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn comment(post_id: &str, comment_id: &str) -> Self {
+        NotificationTarget {
+            post_id: Some(post_id.to_string()),
+            comment_id: Some(comment_id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn chat(chat_id: &str) -> Self {
+        NotificationTarget {
+            chat_id: Some(chat_id.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+// Delivered notifications, newest first
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct NotificationsPage {
+    pub notifications: Vec<Notification>,
+    pub unread_count: u32,
+    // cursor of next (older) page, none if this is the last page
+    pub next_cursor: Option<String>,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+// Aggregate reaction notification into the latest notification of reactions on the same target
+// created since given time, returns false if there is no such notification
+fn aggregate_reaction(
+    notifications: &mut [Notification],
+    notification: &Notification,
+    since: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    if notification.kind != NotificationKind::Like {
+        return false;
+    }
+    let existing = notifications.iter_mut().rev().find(|n| {
+        n.kind == NotificationKind::Like
+            && n.post_id == notification.post_id
+            && n.comment_id == notification.comment_id
+            && n.created_at > since
+    });
+    match existing {
+        Some(existing) => {
+            existing.count += notification.count;
+            existing.created_by = notification.created_by.clone();
+            existing.content = format!("{} new reactions", existing.count);
+            // new reactions make read notification unread again
+            existing.read_at = None;
+            true
+        }
+        None => false,
+    }
+}
+
+// Daily window (in local time of user) in which notifications are held, may wrap midnight
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct QuietHours {
+    // local time in HH:MM format
+    pub start: String,
+    pub end: String,
+    // timezone of user, offset from UTC in minutes
+    pub utc_offset_minutes: i32,
+}
+
+impl QuietHours {
+    pub fn validated(self) -> Result<Self, String> {
+        let start = parse_local_time(&self.start)?;
+        let end = parse_local_time(&self.end)?;
+        validate_utc_offset(self.utc_offset_minutes)?;
+        if start == end {
+            Err("Quiet hours start and end must differ".to_string())
+        } else {
+            Ok(self)
+        }
+    }
+
+    // End of quiet window containing given time, none if time is outside of window
+    pub fn window_end(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Option<chrono::DateTime<chrono::Utc>> {
+        let start = parse_local_time(&self.start).ok()?;
+        let end = parse_local_time(&self.end).ok()?;
+        let offset = utc_offset(self.utc_offset_minutes)?;
+        let local = now.with_timezone(&offset);
+        let time = local.time();
+
+        let end_date = if start < end {
+            (start <= time && time < end).then_some(local.date_naive())
+        } else if time >= start {
+            local.date_naive().succ_opt()
+        } else {
+            (time < end).then_some(local.date_naive())
+        }?;
+
+        end_date
+            .and_time(end)
+            .and_local_timezone(offset)
+            .single()
+            .map(|end| end.with_timezone(&chrono::Utc))
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct NotificationPreferences {
+    pub quiet_hours: Option<QuietHours>,
+    // daily time of notification digest, none if digest is created only on demand
+    #[serde(default)]
+    pub digest_schedule: Option<DailySchedule>,
+}
+
+impl NotificationPreferences {
+    fn validated(self) -> Result<Self, String> {
+        Ok(NotificationPreferences {
+            quiet_hours: self.quiet_hours.map(|q| q.validated()).transpose()?,
+            digest_schedule: self.digest_schedule.map(|d| d.validated()).transpose()?,
+        })
+    }
+}
+
+// Summary of notifications delivered since previous digest
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct NotificationDigest {
+    // none for first digest of user
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    pub comment_count: u32,
+    pub like_count: u32,
+    #[serde(default)]
+    pub connection_count: u32,
+    #[serde(default)]
+    pub mention_count: u32,
+    // distinct users who caused the notifications
+    pub created_by: Vec<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct Notifications {
+    pub user_id: String,
+    pub preferences: NotificationPreferences,
+    // delivered notifications, oldest first
+    pub delivered: Vec<Notification>,
+    // notifications held during quiet hours, delivered as batch when quiet hours end
+    pub held: Vec<Notification>,
+    pub flush_scheduled_at: Option<chrono::DateTime<chrono::Utc>>,
+    // last created digest
+    #[serde(default)]
+    pub digest: Option<NotificationDigest>,
+    #[serde(default)]
+    pub digest_scheduled_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Notifications {
+    fn new(user_id: String) -> Self {
+        let now = clock::now();
+        Notifications {
+            user_id,
+            preferences: NotificationPreferences::default(),
+            delivered: vec![],
+            held: vec![],
+            flush_scheduled_at: None,
+            digest: None,
+            digest_scheduled_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    // End of current quiet window, none if notifications can be delivered
+    fn quiet_until(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.preferences
+            .quiet_hours
+            .as_ref()
+            .and_then(|q| q.window_end(now))
+    }
+
+    // Deliver or hold notification, returns end of quiet window if notification was held
+    fn add(
+        &mut self,
+        mut notification: Notification,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.updated_at = now;
+        let aggregate_since = now - chrono::Duration::minutes(REACTIONS_AGGREGATION_WINDOW_MINUTES);
+        match self.quiet_until(now) {
+            Some(quiet_until) => {
+                if !aggregate_reaction(&mut self.held, &notification, aggregate_since) {
+                    self.held.push(notification);
+                }
+                Some(quiet_until)
+            }
+            None => {
+                // reactions delivered before last digest are not aggregated (digest counted them already)
+                let digest_at = self.digest.as_ref().map(|d| d.created_at);
+                let aggregate_since =
+                    digest_at.map_or(aggregate_since, |at| at.max(aggregate_since));
+                if !aggregate_reaction(&mut self.delivered, &notification, aggregate_since) {
+                    notification.delivered_at = Some(now);
+                    self.push_delivered(vec![notification]);
+                }
+                None
+            }
+        }
+    }
+
+    // Deliver all held notifications as one batch, returns number of delivered notifications
+    fn flush(&mut self, now: chrono::DateTime<chrono::Utc>) -> usize {
+        let held: Vec<Notification> = self
+            .held
+            .drain(..)
+            .map(|mut n| {
+                n.delivered_at = Some(now);
+                n
+            })
+            .collect();
+        let count = held.len();
+        if count > 0 {
+            self.push_delivered(held);
+            self.updated_at = now;
+        }
+        count
+    }
+
+    fn create_digest(&mut self, now: chrono::DateTime<chrono::Utc>) -> NotificationDigest {
+        let since = self.digest.as_ref().map(|d| d.created_at);
+        let mut digest = NotificationDigest {
+            since,
+            comment_count: 0,
+            like_count: 0,
+            connection_count: 0,
+            mention_count: 0,
+            created_by: vec![],
+            created_at: now,
+        };
+        for notification in self.delivered.iter().filter(|n| {
+            n.delivered_at
+                .is_some_and(|at| since.is_none_or(|since| at > since))
+        }) {
+            match notification.kind {
+                NotificationKind::Comment => digest.comment_count += 1,
+                NotificationKind::Like => digest.like_count += notification.count,
+                NotificationKind::Connection => digest.connection_count += 1,
+                NotificationKind::Mention => digest.mention_count += 1,
+            }
+            if !digest.created_by.contains(&notification.created_by) {
+                digest.created_by.push(notification.created_by.clone());
+            }
+        }
+        self.digest = Some(digest.clone());
+        self.updated_at = now;
+        digest
+    }
+
+    fn unread_count(&self) -> u32 {
+        self.delivered
+            .iter()
+            .filter(|n| n.read_at.is_none())
+            .count() as u32
+    }
+
+    // Mark delivered notifications as read (all if no ids given), returns number of newly read notifications
+    fn mark_read(
+        &mut self,
+        notification_ids: &[String],
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> u32 {
+        let mut count = 0;
+        for notification in self.delivered.iter_mut().filter(|n| {
+            n.read_at.is_none()
+                && (notification_ids.is_empty() || notification_ids.contains(&n.notification_id))
+        }) {
+            notification.read_at = Some(now);
+            count += 1;
+        }
+        if count > 0 {
+            self.updated_at = now;
+        }
+        count
+    }
+
+    // Delivered notifications (delivered after since, if given), newest first,
+    // cursor is id of last notification of previous page
+    fn get_page(
+        &self,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> NotificationsPage {
+        let limit = limit
+            .unwrap_or(NOTIFICATIONS_PAGE_DEFAULT_LIMIT)
+            .clamp(1, NOTIFICATIONS_PAGE_MAX_LIMIT) as usize;
+        // older notifications than cursor, nothing if cursor notification was already dropped
+        let end = match cursor.filter(|c| !c.is_empty()) {
+            Some(cursor) => self
+                .delivered
+                .iter()
+                .position(|n| n.notification_id == cursor)
+                .unwrap_or(0),
+            None => self.delivered.len(),
+        };
+
+        let mut notifications: Vec<Notification> = self.delivered[..end]
+            .iter()
+            .rev()
+            .filter(|n| since.is_none_or(|since| n.delivered_at.is_some_and(|at| at > since)))
+            .take(limit + 1)
+            .cloned()
+            .collect();
+        let has_more = notifications.len() > limit;
+        notifications.truncate(limit);
+        let next_cursor = notifications
+            .last()
+            .filter(|_| has_more)
+            .map(|n| n.notification_id.clone());
+
+        NotificationsPage {
+            notifications,
+            unread_count: self.unread_count(),
+            next_cursor,
+        }
+    }
+
+    fn push_delivered(&mut self, notifications: Vec<Notification>) {
+        self.delivered.extend(notifications);
+        if self.delivered.len() > NOTIFICATIONS_MAX_COUNT {
+            let overflow = self.delivered.len() - NOTIFICATIONS_MAX_COUNT;
+            self.delivered.drain(..overflow);
+        }
+    }
+}
+
+#[agent_definition]
+trait NotificationAgent {
+    fn new(id: String) -> Self;
+
+    fn get_notifications(&self) -> Option<Notifications>;
+
+    // Page of delivered notifications (delivered after since, if given), newest first
+    fn get_notifications_page(
+        &self,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> NotificationsPage;
+
+    fn get_unread_count(&self) -> u32;
+
+    // Mark notifications as read (all if no ids given), returns number of newly read notifications
+    fn mark_read(&mut self, notification_ids: Vec<String>, correlation_id: Option<String>) -> u32;
+
+    fn get_preferences(&self) -> NotificationPreferences;
+
+    fn set_preferences(
+        &mut self,
+        preferences: NotificationPreferences,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Add notification, dropped if notifications feature is disabled for user
+    // reactions on the same post (comment) are aggregated
+    async fn notify(
+        &mut self,
+        kind: NotificationKind,
+        created_by: String,
+        target: NotificationTarget,
+        content: String,
+        correlation_id: Option<String>,
+    );
+
+    // Deliver notifications held during quiet hours (scheduled at the end of quiet hours)
+    fn flush_held(&mut self, correlation_id: Option<String>);
+
+    fn create_digest(&mut self, correlation_id: Option<String>) -> NotificationDigest;
+
+    // Create digest at time of digest schedule and schedule next one
+    fn scheduled_digest(&mut self, correlation_id: Option<String>);
+}
+
+struct NotificationAgentImpl {
+    _id: String,
+    state: Option<Notifications>,
+}
+
+impl NotificationAgentImpl {
+    fn get_state(&mut self) -> &mut Notifications {
+        self.state
+            .get_or_insert(Notifications::new(self._id.clone()))
+    }
+
+    fn schedule_flush(&mut self, flush_at: chrono::DateTime<chrono::Utc>) {
+        let state = self.get_state();
+        if state
+            .flush_scheduled_at
+            .is_none_or(|scheduled_at| scheduled_at > flush_at)
+        {
+            trace_println!(
+                "notifications - user id: {}, flush scheduled at: {flush_at}",
+                state.user_id
+            );
+            NotificationAgentClient::get(state.user_id.clone())
+                .schedule_flush_held(trace::propagate(), to_schedule_time(flush_at));
+            state.flush_scheduled_at = Some(flush_at);
+        }
+    }
+
+    // Schedule next digest run, if digest schedule is set
+    fn schedule_digest(&mut self) {
+        let now = clock::now();
+        let state = self.get_state();
+        let next_run_at = state
+            .preferences
+            .digest_schedule
+            .as_ref()
+            .and_then(|d| d.next_run_after(now));
+        // runs scheduled before are not cancelled, they are ignored when they do not match
+        if next_run_at != state.digest_scheduled_at {
+            if let Some(next_run_at) = next_run_at {
+                trace_println!(
+                    "notifications - user id: {}, digest scheduled at: {next_run_at}",
+                    state.user_id
+                );
+                NotificationAgentClient::get(state.user_id.clone())
+                    .schedule_scheduled_digest(trace::propagate(), to_schedule_time(next_run_at));
+            }
+            state.digest_scheduled_at = next_run_at;
+        }
+    }
+
+    // Flush held notifications if quiet hours are over, otherwise schedule flush
+    fn flush_or_schedule(&mut self) {
+        let now = clock::now();
+        let state = self.get_state();
+        if state.held.is_empty() {
+            return;
+        }
+        match state.quiet_until(now) {
+            Some(quiet_until) => self.schedule_flush(quiet_until),
+            None => {
+                let state = self.get_state();
+                let count = state.flush(now);
+                trace_println!(
+                    "notifications - user id: {}, flushed: {count}",
+                    state.user_id
+                );
+            }
+        }
+    }
+}
+
+#[agent_implementation]
+impl NotificationAgent for NotificationAgentImpl {
+    fn new(id: String) -> Self {
+        NotificationAgentImpl {
+            _id: id,
+            state: None,
+        }
+    }
+
+    fn get_notifications(&self) -> Option<Notifications> {
+        self.state.clone()
+    }
+
+    fn get_notifications_page(
+        &self,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> NotificationsPage {
+        match self.state.as_ref() {
+            Some(state) => state.get_page(since, cursor, limit),
+            None => NotificationsPage {
+                notifications: vec![],
+                unread_count: 0,
+                next_cursor: None,
+            },
+        }
+    }
+
+    fn get_unread_count(&self) -> u32 {
+        self.state.as_ref().map_or(0, |s| s.unread_count())
+    }
+
+    fn mark_read(&mut self, notification_ids: Vec<String>, correlation_id: Option<String>) -> u32 {
+        trace::init(correlation_id);
+        let state = self.get_state();
+        let count = state.mark_read(&notification_ids, clock::now());
+        trace_println!(
+            "mark read - user id: {}, ids: {:?}, read: {count}",
+            state.user_id,
+            notification_ids
+        );
+        count
+    }
+
+    fn get_preferences(&self) -> NotificationPreferences {
+        self.state
+            .as_ref()
+            .map(|s| s.preferences.clone())
+            .unwrap_or_default()
+    }
+
+    fn set_preferences(
+        &mut self,
+        preferences: NotificationPreferences,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let preferences = preferences
+            .validated()
+            .map_err(SocialNetError::ValidationFailed)?;
+        trace_println!(
+            "set preferences - quiet hours: {:?}, digest schedule: {:?}",
+            preferences.quiet_hours,
+            preferences.digest_schedule
+        );
+        let state = self.get_state();
+        state.preferences = preferences;
+        state.updated_at = clock::now();
+        self.flush_or_schedule();
+        self.schedule_digest();
+        Ok(())
+    }
+
+    async fn notify(
+        &mut self,
+        kind: NotificationKind,
+        created_by: String,
+        target: NotificationTarget,
+        content: String,
+        correlation_id: Option<String>,
+    ) {
+        trace::init(correlation_id);
+        if !is_feature_enabled(FEATURE_NOTIFICATIONS, &self._id, true).await {
+            trace_println!("notify - user id: {}, notifications disabled", self._id);
+            return;
+        }
+        let now = clock::now();
+        let notification = Notification {
+            notification_id: id::new_id(),
+            kind,
+            created_by,
+            post_id: target.post_id,
+            comment_id: target.comment_id,
+            chat_id: target.chat_id,
+            content,
+            count: 1,
+            created_at: now,
+            delivered_at: None,
+            read_at: None,
+        };
+        trace_println!(
+            "notify - kind: {:?}, created by: {}, post id: {:?}, comment id: {:?}, chat id: {:?}",
+            notification.kind,
+            notification.created_by,
+            notification.post_id,
+            notification.comment_id,
+            notification.chat_id
+        );
+        if let Some(quiet_until) = self.get_state().add(notification, now) {
+            self.schedule_flush(quiet_until);
+        }
+    }
+
+    fn flush_held(&mut self, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        self.get_state().flush_scheduled_at = None;
+        self.flush_or_schedule();
+    }
+
+    fn create_digest(&mut self, correlation_id: Option<String>) -> NotificationDigest {
+        trace::init(correlation_id);
+        let state = self.get_state();
+        trace_println!("create digest - user id: {}", state.user_id);
+        state.create_digest(clock::now())
+    }
+
+    fn scheduled_digest(&mut self, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        let now = clock::now();
+        let state = self.get_state();
+        // run is stale, if digest schedule was changed or removed after it was scheduled
+        if state.digest_scheduled_at.is_some_and(|at| at <= now) {
+            let digest = state.create_digest(now);
+            trace_println!(
+                "scheduled digest - user id: {}, comments: {}, likes: {}",
+                state.user_id,
+                digest.comment_count,
+                digest.like_count
+            );
+            self.schedule_digest();
+        } else {
+            trace_println!("scheduled digest - user id: {} - stale run", state.user_id);
+        }
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<Notifications> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+// Notify user about action of other user (from write paths)
+pub fn notify_user(
+    user_id: &str,
+    kind: NotificationKind,
+    created_by: &str,
+    target: NotificationTarget,
+    content: String,
+) {
+    if user_id != created_by {
+        NotificationAgentClient::get(user_id.to_string()).trigger_notify(
+            kind,
+            created_by.to_string(),
+            target,
+            content,
+            trace::propagate(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    fn time(value: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    fn quiet_hours(start: &str, end: &str, utc_offset_minutes: i32) -> QuietHours {
+        QuietHours {
+            start: start.to_string(),
+            end: end.to_string(),
+            utc_offset_minutes,
+        }
+    }
+
+    fn notification(id: &str) -> Notification {
+        Notification {
+            notification_id: id.to_string(),
+            kind: NotificationKind::Comment,
+            created_by: "user2".to_string(),
+            post_id: Some("post1".to_string()),
+            comment_id: None,
+            chat_id: None,
+            content: "hello".to_string(),
+            count: 1,
+            created_at: chrono::Utc::now(),
+            delivered_at: None,
+            read_at: None,
+        }
+    }
+
+    #[test]
+    fn test_quiet_hours_validated() {
+        assert!(quiet_hours("22:00", "07:00", 60).validated().is_ok());
+        assert!(quiet_hours("22:00", "22:00", 0).validated().is_err());
+        assert!(quiet_hours("25:00", "07:00", 0).validated().is_err());
+        assert!(quiet_hours("22", "07:00", 0).validated().is_err());
+        assert!(quiet_hours("22:00", "07:00", 15 * 60).validated().is_err());
+    }
+
+    #[test]
+    fn test_quiet_hours_window_end() {
+        let quiet = quiet_hours("09:00", "17:00", 0);
+        assert_eq!(
+            quiet.window_end(time("2024-01-01T10:00:00Z")),
+            Some(time("2024-01-01T17:00:00Z"))
+        );
+        assert_eq!(quiet.window_end(time("2024-01-01T17:00:00Z")), None);
+        assert_eq!(quiet.window_end(time("2024-01-01T08:59:00Z")), None);
+    }
+
+    #[test]
+    fn test_quiet_hours_window_end_over_midnight() {
+        // 22:00 - 07:00 in UTC+2
+        let quiet = quiet_hours("22:00", "07:00", 120);
+        assert_eq!(
+            quiet.window_end(time("2024-01-01T21:00:00Z")),
+            Some(time("2024-01-02T05:00:00Z"))
+        );
+        assert_eq!(
+            quiet.window_end(time("2024-01-02T03:00:00Z")),
+            Some(time("2024-01-02T05:00:00Z"))
+        );
+        assert_eq!(quiet.window_end(time("2024-01-01T19:59:00Z")), None);
+        assert_eq!(quiet.window_end(time("2024-01-02T05:00:00Z")), None);
+    }
+
+    #[test]
+    fn test_notifications_held_and_flushed() {
+        let mut notifications = Notifications::new("user1".to_string());
+        notifications.preferences.quiet_hours = Some(quiet_hours("22:00", "07:00", 0));
+
+        let held_until = notifications.add(notification("n1"), time("2024-01-01T23:00:00Z"));
+        assert_eq!(held_until, Some(time("2024-01-02T07:00:00Z")));
+        notifications.add(notification("n2"), time("2024-01-02T01:00:00Z"));
+        assert_eq!(notifications.held.len(), 2);
+        assert!(notifications.delivered.is_empty());
+
+        let flushed_at = time("2024-01-02T07:00:00Z");
+        assert_eq!(notifications.flush(flushed_at), 2);
+        assert!(notifications.held.is_empty());
+        assert_eq!(notifications.delivered.len(), 2);
+        assert!(notifications
+            .delivered
+            .iter()
+            .all(|n| n.delivered_at == Some(flushed_at)));
+
+        let held_until = notifications.add(notification("n3"), time("2024-01-02T12:00:00Z"));
+        assert_eq!(held_until, None);
+        assert_eq!(notifications.delivered.len(), 3);
+        assert_eq!(notifications.flush(time("2024-01-02T13:00:00Z")), 0);
+    }
+
+    #[test]
+    fn test_notifications_digest() {
+        let mut notifications = Notifications::new("user1".to_string());
+        notifications.add(notification("n1"), time("2024-01-01T10:00:00Z"));
+        let mut like = notification("n2");
+        like.kind = NotificationKind::Like;
+        like.created_by = "user3".to_string();
+        notifications.add(like, time("2024-01-01T11:00:00Z"));
+        notifications.add(notification("n3"), time("2024-01-01T12:00:00Z"));
+
+        let digest = notifications.create_digest(time("2024-01-01T12:00:00Z"));
+        assert_eq!(digest.since, None);
+        assert_eq!(digest.comment_count, 2);
+        assert_eq!(digest.like_count, 1);
+        assert_eq!(digest.created_by, vec!["user2", "user3"]);
+
+        notifications.add(notification("n4"), time("2024-01-02T09:00:00Z"));
+        let digest = notifications.create_digest(time("2024-01-02T12:00:00Z"));
+        assert_eq!(digest.since, Some(time("2024-01-01T12:00:00Z")));
+        assert_eq!(digest.comment_count, 1);
+        assert_eq!(digest.like_count, 0);
+    }
+
+    fn like(id: &str, created_by: &str, comment_id: Option<&str>, at: &str) -> Notification {
+        Notification {
+            kind: NotificationKind::Like,
+            created_by: created_by.to_string(),
+            comment_id: comment_id.map(|id| id.to_string()),
+            content: "like".to_string(),
+            created_at: time(at),
+            ..notification(id)
+        }
+    }
+
+    #[test]
+    fn test_notifications_reactions_aggregated() {
+        let mut notifications = Notifications::new("user1".to_string());
+        let at = "2024-01-01T10:00:00Z";
+        notifications.add(like("n1", "user2", None, at), time(at));
+        let at = "2024-01-01T10:10:00Z";
+        notifications.add(like("n2", "user3", None, at), time(at));
+        let at = "2024-01-01T10:20:00Z";
+        notifications.add(like("n3", "user4", None, at), time(at));
+        // other target
+        let at = "2024-01-01T10:30:00Z";
+        notifications.add(like("n4", "user2", Some("comment1"), at), time(at));
+
+        assert_eq!(notifications.delivered.len(), 2);
+        let aggregated = &notifications.delivered[0];
+        assert_eq!(aggregated.notification_id, "n1");
+        assert_eq!(aggregated.count, 3);
+        assert_eq!(aggregated.content, "3 new reactions");
+        assert_eq!(aggregated.created_by, "user4");
+        assert_eq!(notifications.delivered[1].count, 1);
+
+        // out of aggregation window
+        let at = "2024-01-01T11:30:00Z";
+        notifications.add(like("n5", "user5", None, at), time(at));
+        assert_eq!(notifications.delivered.len(), 3);
+
+        let digest = notifications.create_digest(time("2024-01-01T12:00:00Z"));
+        assert_eq!(digest.like_count, 5);
+
+        // reactions counted by digest are not aggregated
+        let at = "2024-01-01T12:10:00Z";
+        notifications.add(like("n6", "user6", None, at), time(at));
+        assert_eq!(notifications.delivered.len(), 4);
+    }
+
+    #[test]
+    fn test_notifications_reactions_aggregated_when_held() {
+        let mut notifications = Notifications::new("user1".to_string());
+        notifications.preferences.quiet_hours = Some(quiet_hours("22:00", "07:00", 0));
+
+        let at = "2024-01-01T23:00:00Z";
+        notifications.add(like("n1", "user2", None, at), time(at));
+        let at = "2024-01-01T23:30:00Z";
+        notifications.add(like("n2", "user3", None, at), time(at));
+        assert_eq!(notifications.held.len(), 1);
+        assert_eq!(notifications.held[0].count, 2);
+        assert!(notifications.delivered.is_empty());
+    }
+
+    #[test]
+    fn test_notifications_mark_read() {
+        let mut notifications = Notifications::new("user1".to_string());
+        let at = "2024-01-01T10:00:00Z";
+        notifications.add(like("n1", "user2", None, at), time(at));
+        notifications.add(notification("n2"), time(at));
+        notifications.add(notification("n3"), time(at));
+        assert_eq!(notifications.unread_count(), 3);
+
+        let read_at = time("2024-01-01T10:05:00Z");
+        assert_eq!(notifications.mark_read(&["n1".to_string()], read_at), 1);
+        assert_eq!(notifications.mark_read(&["n1".to_string()], read_at), 0);
+        assert_eq!(notifications.unread_count(), 2);
+
+        // new reaction aggregated into read notification
+        let at = "2024-01-01T10:10:00Z";
+        notifications.add(like("n4", "user3", None, at), time(at));
+        assert_eq!(notifications.unread_count(), 3);
+
+        assert_eq!(notifications.mark_read(&[], read_at), 3);
+        assert_eq!(notifications.unread_count(), 0);
+    }
+
+    #[test]
+    fn test_notifications_page() {
+        let mut notifications = Notifications::new("user1".to_string());
+        for i in 0..5 {
+            notifications.add(
+                notification(&format!("n{i}")),
+                time("2024-01-01T10:00:00Z") + chrono::Duration::minutes(i),
+            );
+        }
+        let ids = |page: &NotificationsPage| -> Vec<String> {
+            page.notifications
+                .iter()
+                .map(|n| n.notification_id.clone())
+                .collect()
+        };
+
+        let page = notifications.get_page(None, None, Some(2));
+        assert_eq!(ids(&page), vec!["n4", "n3"]);
+        assert_eq!(page.unread_count, 5);
+        let page = notifications.get_page(None, page.next_cursor, Some(2));
+        assert_eq!(ids(&page), vec!["n2", "n1"]);
+        let page = notifications.get_page(None, page.next_cursor, Some(2));
+        assert_eq!(ids(&page), vec!["n0"]);
+        assert_eq!(page.next_cursor, None);
+
+        let page = notifications.get_page(Some(time("2024-01-01T10:02:00Z")), None, None);
+        assert_eq!(ids(&page), vec!["n4", "n3"]);
+
+        // cursor notification dropped
+        let page = notifications.get_page(None, Some("unknown".to_string()), None);
+        assert!(page.notifications.is_empty());
+    }
+
+    #[test]
+    fn test_notifications_max_count() {
+        let mut notifications = Notifications::new("user1".to_string());
+        let now = chrono::Utc::now();
+        for i in 0..NOTIFICATIONS_MAX_COUNT + 5 {
+            notifications.add(notification(&format!("n{i}")), now);
+        }
+        assert_eq!(notifications.delivered.len(), NOTIFICATIONS_MAX_COUNT);
+        assert_eq!(notifications.delivered[0].notification_id, "n5");
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut notifications = Notifications::new("user1".to_string());
+        notifications.add(notification("n1"), chrono::Utc::now());
+        notifications.add(notification("n2"), chrono::Utc::now());
+        snapshot::assert_round_trip(&Some(notifications));
+    }
+}
@@ -0,0 +1,272 @@
+use crate::common::{trace, SocialNetError};
+use crate::trace_println;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+// how long agents use cached operations config before reloading it
+const CONFIG_CACHE_TTL: Duration = Duration::from_secs(30);
+
+// Operational parameters, tunable at runtime without redeploy
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct OperationsConfig {
+    // number of parallel agent calls in fan-in hydration (posts, chats) and fan-out scans
+    pub fetch_chunk_size: u32,
+    // number of parallel user agent calls (user profiles)
+    pub user_fetch_chunk_size: u32,
+    // max time of single agent call in fan-in hydration
+    pub fetch_call_timeout_ms: u64,
+    // number of parallel chat update deliveries
+    pub delivery_chunk_size: u32,
+    // failed chat update deliveries are retried up to max attempts
+    pub delivery_max_attempts: u32,
+    // default wait times of long polling for updates
+    pub poll_iter_wait_ms: u32,
+    pub poll_max_wait_ms: u32,
+    // max number of participants of chat (including creator)
+    #[serde(default = "default_chat_max_participants")]
+    pub chat_max_participants: u32,
+    // fan-in / fan-out operations taking longer are logged as slow
+    #[serde(default = "default_slow_operation_threshold_ms")]
+    pub slow_operation_threshold_ms: u64,
+}
+
+fn default_chat_max_participants() -> u32 {
+    100
+}
+
+fn default_slow_operation_threshold_ms() -> u64 {
+    1000
+}
+
+impl Default for OperationsConfig {
+    fn default() -> Self {
+        OperationsConfig {
+            fetch_chunk_size: 10,
+            user_fetch_chunk_size: 20,
+            fetch_call_timeout_ms: 5000,
+            delivery_chunk_size: 10,
+            delivery_max_attempts: 10,
+            poll_iter_wait_ms: 1000,
+            poll_max_wait_ms: 10000,
+            chat_max_participants: default_chat_max_participants(),
+            slow_operation_threshold_ms: default_slow_operation_threshold_ms(),
+        }
+    }
+}
+
+impl OperationsConfig {
+    fn validated(self) -> Result<Self, String> {
+        if self.fetch_chunk_size == 0
+            || self.user_fetch_chunk_size == 0
+            || self.delivery_chunk_size == 0
+        {
+            Err("Chunk sizes must be greater than 0".to_string())
+        } else if self.fetch_call_timeout_ms == 0 {
+            Err("Fetch call timeout must be greater than 0".to_string())
+        } else if self.delivery_max_attempts == 0 {
+            Err("Delivery max attempts must be greater than 0".to_string())
+        } else if self.poll_iter_wait_ms == 0 || self.poll_iter_wait_ms > self.poll_max_wait_ms {
+            Err("Poll iteration wait time must be between 1 and max wait time".to_string())
+        } else if self.chat_max_participants < 2 {
+            Err("Chat max participants must be at least 2".to_string())
+        } else if self.slow_operation_threshold_ms == 0 {
+            Err("Slow operation threshold must be greater than 0".to_string())
+        } else {
+            Ok(self)
+        }
+    }
+
+    pub fn fetch_chunk_size(&self) -> usize {
+        self.fetch_chunk_size.max(1) as usize
+    }
+
+    pub fn user_fetch_chunk_size(&self) -> usize {
+        self.user_fetch_chunk_size.max(1) as usize
+    }
+
+    pub fn delivery_chunk_size(&self) -> usize {
+        self.delivery_chunk_size.max(1) as usize
+    }
+
+    pub fn is_slow_operation(&self, elapsed: Duration) -> bool {
+        elapsed >= Duration::from_millis(self.slow_operation_threshold_ms)
+    }
+}
+
+#[agent_definition]
+trait OperationsConfigAgent {
+    fn new() -> Self;
+
+    fn get_config(&self) -> OperationsConfig;
+
+    fn set_config(
+        &mut self,
+        config: OperationsConfig,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Restore default operations config
+    fn reset_config(&mut self, correlation_id: Option<String>);
+}
+
+struct OperationsConfigAgentImpl {
+    state: OperationsConfig,
+}
+
+#[agent_implementation]
+impl OperationsConfigAgent for OperationsConfigAgentImpl {
+    fn new() -> Self {
+        OperationsConfigAgentImpl {
+            state: OperationsConfig::default(),
+        }
+    }
+
+    fn get_config(&self) -> OperationsConfig {
+        self.state.clone()
+    }
+
+    fn set_config(
+        &mut self,
+        config: OperationsConfig,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let config = config
+            .validated()
+            .map_err(SocialNetError::ValidationFailed)?;
+        trace_println!("set config: {config:?}");
+        self.state = config;
+        Ok(())
+    }
+
+    fn reset_config(&mut self, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        trace_println!("reset config");
+        self.state = OperationsConfig::default();
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: OperationsConfig = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+thread_local! {
+    static CONFIG_CACHE: RefCell<Option<(OperationsConfig, Instant)>> = const { RefCell::new(None) };
+}
+
+// Operations config, cached by calling agent for CONFIG_CACHE_TTL
+pub async fn get_operations_config() -> OperationsConfig {
+    let cached = CONFIG_CACHE.with(|c| {
+        c.borrow()
+            .as_ref()
+            .filter(|(_, loaded_at)| loaded_at.elapsed() < CONFIG_CACHE_TTL)
+            .map(|(config, _)| config.clone())
+    });
+
+    match cached {
+        Some(config) => config,
+        None => {
+            let config = OperationsConfigAgentClient::get().get_config().await;
+            CONFIG_CACHE.with(|c| *c.borrow_mut() = Some((config.clone(), Instant::now())));
+            config
+        }
+    }
+}
+
+// Timing of fan-in / fan-out operation, operation exceeding slow operation threshold is logged
+// with number of involved downstream agents (to find hot users, posts)
+pub struct SlowOperationTimer {
+    operation: &'static str,
+    subject: String,
+    started_at: Instant,
+}
+
+impl SlowOperationTimer {
+    pub fn start(operation: &'static str, subject: impl Into<String>) -> Self {
+        SlowOperationTimer {
+            operation,
+            subject: subject.into(),
+            started_at: Instant::now(),
+        }
+    }
+
+    pub async fn finish(self, downstream_agents: usize) {
+        let elapsed = self.started_at.elapsed();
+        if get_operations_config().await.is_slow_operation(elapsed) {
+            trace_println!(
+                "slow operation - {} - id: {}, duration: {}ms, downstream agents: {downstream_agents}",
+                self.operation,
+                self.subject,
+                elapsed.as_millis()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    #[test]
+    fn test_config_validated() {
+        assert!(OperationsConfig::default().validated().is_ok());
+
+        let config = OperationsConfig {
+            fetch_chunk_size: 0,
+            ..OperationsConfig::default()
+        };
+        assert!(config.validated().is_err());
+
+        let config = OperationsConfig {
+            delivery_max_attempts: 0,
+            ..OperationsConfig::default()
+        };
+        assert!(config.validated().is_err());
+
+        let config = OperationsConfig {
+            poll_iter_wait_ms: 20000,
+            ..OperationsConfig::default()
+        };
+        assert!(config.validated().is_err());
+
+        let config = OperationsConfig {
+            chat_max_participants: 1,
+            ..OperationsConfig::default()
+        };
+        assert!(config.validated().is_err());
+
+        let config = OperationsConfig {
+            slow_operation_threshold_ms: 0,
+            ..OperationsConfig::default()
+        };
+        assert!(config.validated().is_err());
+    }
+
+    #[test]
+    fn test_is_slow_operation() {
+        let config = OperationsConfig {
+            slow_operation_threshold_ms: 500,
+            ..OperationsConfig::default()
+        };
+        assert!(!config.is_slow_operation(Duration::from_millis(499)));
+        assert!(config.is_slow_operation(Duration::from_millis(500)));
+        assert!(config.is_slow_operation(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        snapshot::assert_round_trip(&OperationsConfig {
+            chat_max_participants: 50,
+            ..OperationsConfig::default()
+        });
+    }
+}
@@ -1,89 +1,551 @@
-use crate::common::{query, LikeType, UserConnectionType};
-use crate::user::UserAgentClient;
+use crate::abuse::{record_abuse_event, AbuseEventKind};
+use crate::analytics::record_user_activity;
+use crate::chat::MediaMetadata;
+use crate::common::clients::AgentClients;
+use crate::common::clock;
+use crate::common::cursor::InvalidCursor;
+use crate::common::diagnostics::AgentDiagnostics;
+use crate::common::fetch::{join_all_with_timeout, Fetched};
+use crate::common::geo::{Location, Radius};
+use crate::common::id;
+use crate::common::language::detect_language;
+use crate::common::validation::{check_length, ContentField, ContentTooLong};
+use crate::common::{
+    archive_cutoff, content, cursor, query, trace, LikeCounts, LikeSummary, LikeType, LikersPage,
+    SocialNetError, UserConnectionType, ARCHIVE_MIN_BATCH,
+};
+use crate::explore::{add_explore_post, remove_explore_post};
+use crate::hashtag::{index_post_hashtags, unindex_post_hashtags};
+use crate::moderation::{moderate_content, ContentRejected};
+use crate::notification::{notify_user, NotificationKind, NotificationTarget};
+use crate::operations::{get_operations_config, SlowOperationTimer};
+use crate::rate_limit::{check_rate_limit, RateLimitAction, RateLimited};
+use crate::trace_println;
+use crate::user::{
+    check_user_not_banned, is_user_shadowbanned, record_interaction, user_exists, InteractionKind,
+    User, UserAgentClient, UserBanned,
+};
+use crate::user_posts::UserPostsAgentClient;
 use crate::user_timeline::{PostRef, UserTimelineAgentClient};
 use futures::future::join_all;
 use golem_rust::{agent_definition, agent_implementation, Schema};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 
 // max number of comments
 const COMMENTS_MAX_COUNT: usize = 2000;
 
+// content of soft deleted comment
+const DELETED_COMMENT_CONTENT: &str = "[deleted]";
+
+// default and max number of comments in comments page
+const COMMENTS_PAGE_DEFAULT_LIMIT: u32 = 50;
+const COMMENTS_PAGE_MAX_LIMIT: u32 = 200;
+
+// max number of media attachments of post
+pub(crate) const POST_ATTACHMENTS_MAX_COUNT: usize = 10;
+
+// max number of characters of post content carried by timeline updates
+const CONTENT_SNIPPET_MAX_LENGTH: usize = 280;
+
+// searchable post fields, see Post::matches_query
+pub(crate) const POST_QUERY_FIELDS: &[query::QueryField] = &[
+    query::QueryField {
+        names: &["post-id", "postid"],
+        mode: query::FieldMatchMode::Exact,
+    },
+    query::QueryField {
+        names: &["content"],
+        mode: query::FieldMatchMode::Contains,
+    },
+    query::QueryField {
+        names: &["created-by", "createdby"],
+        mode: query::FieldMatchMode::Exact,
+    },
+    query::QueryField {
+        names: &["location"],
+        mode: query::FieldMatchMode::Contains,
+    },
+    query::QueryField {
+        names: &["near"],
+        mode: query::FieldMatchMode::Radius,
+    },
+    query::QueryField {
+        names: &["language", "lang"],
+        mode: query::FieldMatchMode::ExactIgnoreCase,
+    },
+    query::QueryField {
+        names: &["comment-author", "commentauthor"],
+        mode: query::FieldMatchMode::Exact,
+    },
+];
+
+pub(crate) const POST_QUERY_TERM_FIELDS: &[&str] = &["post-id", "content", "created-by"];
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum PostError {
+    // user with given id not exists
+    UnknownUser(String),
+    // post, comment or draft not exists (or is deleted)
+    NotFound(String),
+    // post or draft with given id already exists
+    AlreadyExists(String),
+    // user is not allowed to interact with post by privacy settings of post author, or is banned
+    NotAllowed(String),
+    // user is not allowed to remove content created by another user
+    Unauthorized(String),
+    Invalid(String),
+    // user exceeded rate limit of action
+    RateLimited(RateLimited),
+    // content is longer than configured max length
+    ContentTooLong(ContentTooLong),
+}
+
+impl Display for PostError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostError::UnknownUser(user_id) => write!(f, "Unknown user: {user_id}"),
+            PostError::NotAllowed(user_id) => write!(f, "User not allowed: {user_id}"),
+            PostError::Unauthorized(user_id) => write!(f, "User not authorized: {user_id}"),
+            PostError::NotFound(message)
+            | PostError::AlreadyExists(message)
+            | PostError::Invalid(message) => write!(f, "{message}"),
+            PostError::RateLimited(e) => write!(f, "{e}"),
+            PostError::ContentTooLong(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl PostError {
+    pub fn not_found(message: &str) -> Self {
+        PostError::NotFound(message.to_string())
+    }
+
+    pub fn invalid(message: &str) -> Self {
+        PostError::Invalid(message.to_string())
+    }
+}
+
+impl From<RateLimited> for PostError {
+    fn from(e: RateLimited) -> Self {
+        PostError::RateLimited(e)
+    }
+}
+
+impl From<ContentTooLong> for PostError {
+    fn from(e: ContentTooLong) -> Self {
+        PostError::ContentTooLong(e)
+    }
+}
+
+impl From<UserBanned> for PostError {
+    fn from(e: UserBanned) -> Self {
+        PostError::NotAllowed(e.0)
+    }
+}
+
+impl From<InvalidCursor> for PostError {
+    fn from(e: InvalidCursor) -> Self {
+        PostError::Invalid(e.to_string())
+    }
+}
+
+impl From<ContentRejected> for PostError {
+    fn from(e: ContentRejected) -> Self {
+        PostError::Invalid(e.to_string())
+    }
+}
+
+// Post errors of view agents, kinds are kept
+impl From<PostError> for SocialNetError {
+    fn from(e: PostError) -> Self {
+        match e {
+            PostError::UnknownUser(_) => SocialNetError::NotFound(e.to_string()),
+            PostError::NotFound(message) => SocialNetError::NotFound(message),
+            PostError::AlreadyExists(message) => SocialNetError::AlreadyExists(message),
+            PostError::NotAllowed(_) => SocialNetError::NotAllowed(e.to_string()),
+            PostError::Unauthorized(_) => SocialNetError::Unauthorized(e.to_string()),
+            PostError::Invalid(message) => SocialNetError::ValidationFailed(message),
+            PostError::RateLimited(_) => SocialNetError::LimitExceeded(e.to_string()),
+            PostError::ContentTooLong(e) => SocialNetError::ContentTooLong(e),
+        }
+    }
+}
+
+// Action on content of user (e.g. removing reaction) is allowed only for the user
+fn check_acting_user(acting_user_id: &str, user_id: &str) -> Result<(), PostError> {
+    if acting_user_id == user_id {
+        Ok(())
+    } else {
+        trace_println!("not authorized - user id: {user_id}, acting user id: {acting_user_id}");
+        Err(PostError::Unauthorized(acting_user_id.to_string()))
+    }
+}
+
+async fn check_user_exists(clients: &impl PostClients, user_id: &str) -> Result<(), PostError> {
+    if clients.user_exists(user_id).await {
+        Ok(())
+    } else {
+        trace_println!("unknown user - user id: {user_id}");
+        Err(PostError::UnknownUser(user_id.to_string()))
+    }
+}
+
+pub(crate) fn validate_attachments(attachments: &[MediaMetadata]) -> Result<(), PostError> {
+    if attachments.len() > POST_ATTACHMENTS_MAX_COUNT {
+        Err(PostError::Invalid(format!(
+            "Too many attachments, max count: {POST_ATTACHMENTS_MAX_COUNT}"
+        )))
+    } else {
+        attachments
+            .iter()
+            .try_for_each(|a| a.validate("attachment"))
+            .map_err(PostError::Invalid)
+    }
+}
+
+// Mentioned users to notify, without author and unknown users
+async fn get_mentioned_users(
+    clients: &impl PostClients,
+    mentions: Vec<String>,
+    user_id: &str,
+) -> Vec<String> {
+    let mentions: Vec<String> = mentions.into_iter().filter(|id| id != user_id).collect();
+    let exists = join_all(mentions.iter().map(|id| clients.user_exists(id))).await;
+    mentions
+        .into_iter()
+        .zip(exists)
+        .filter_map(|(id, exists)| exists.then_some(id))
+        .collect()
+}
+
 #[derive(Schema, Clone, Serialize, Deserialize)]
 pub struct Comment {
     pub comment_id: String,
     pub parent_comment_id: Option<String>,
     pub content: String,
+    // markdown rendered content, set only by view agents
+    #[serde(default)]
+    pub content_html: Option<String>,
     pub likes: HashMap<String, LikeType>,
     pub created_by: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    // soft deleted comment, content is replaced with placeholder and replies are kept
+    #[serde(default)]
+    pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+    // content was edited by the author (updated at is the time of the last edit)
+    #[serde(default)]
+    pub edited: bool,
+    // user ids mentioned (@user-id) in content
+    #[serde(default)]
+    pub mentions: Vec<String>,
 }
 
 impl Comment {
     fn new(user_id: String, content: String, parent_comment_id: Option<String>) -> Self {
-        let now = chrono::Utc::now();
-        let comment_id = uuid::Uuid::new_v4().to_string();
+        let now = clock::now();
+        let comment_id = id::new_id();
         Comment {
             comment_id,
             parent_comment_id,
+            mentions: content::extract_mentions(&content),
             content,
+            content_html: None,
             likes: HashMap::new(),
             created_by: user_id,
             created_at: now,
             updated_at: now,
+            deleted_at: None,
+            edited: false,
         }
     }
+
+    fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    fn created_at_micros(&self) -> u64 {
+        self.created_at.timestamp_micros().max(0) as u64
+    }
+
+    // key of comments page cursor
+    fn cursor_key(&self) -> (u64, String) {
+        (self.created_at_micros(), self.comment_id.clone())
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub enum CommentSort {
+    #[default]
+    OldestFirst,
+    NewestFirst,
+    // most likes first, then oldest first
+    MostLiked,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct CommentsPage {
+    pub post_id: String,
+    pub highlighted_comment_id: Option<String>,
+    pub comments: Vec<Comment>,
+    // cursor of next page, none if this is the last page
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Schema, Clone, Serialize, Deserialize)]
 pub struct Post {
     pub post_id: String,
     pub content: String,
+    // markdown rendered content, set only by view agents
+    #[serde(default)]
+    pub content_html: Option<String>,
     pub created_by: String,
+    // display name of author, cached best effort (refreshed by timelines fan-out), may be stale
+    #[serde(default)]
+    pub created_by_name: Option<String>,
     pub likes: HashMap<String, LikeType>,
     pub comments: HashMap<String, Comment>,
+    // number of comments moved to post archive agent (whole comment threads)
+    #[serde(default)]
+    pub archived_comment_count: u32,
+    // creation time of the newest archived comment, none if not known (posts archived before it was kept)
+    #[serde(default)]
+    pub archived_comment_newest_at: Option<chrono::DateTime<chrono::Utc>>,
+    // comment highlighted by post author (e.g. best answer), returned first in comments page
+    #[serde(default)]
+    pub highlighted_comment_id: Option<String>,
+    // denormalized counts of likes and comments, kept in sync by mutations
+    #[serde(default)]
+    pub like_count: u32,
+    #[serde(default)]
+    pub comment_count: u32,
+    // id of original post, if post is repost (set at creation)
+    #[serde(default)]
+    pub reposted_from: Option<String>,
+    // number of reposts of post
+    #[serde(default)]
+    pub repost_count: u32,
+    // set at creation
+    #[serde(default)]
+    pub location: Option<Location>,
+    // language code (ISO 639-1) detected at creation, none if not detected
+    #[serde(default)]
+    pub language: Option<String>,
+    // user ids mentioned (@user-id) in content
+    #[serde(default)]
+    pub mentions: Vec<String>,
+    // media attached at creation (uploaded before the post is published)
+    #[serde(default)]
+    pub attachments: Vec<MediaMetadata>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    // tombstone, content of deleted post is removed
+    #[serde(default)]
+    pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Post without comments and likes maps, for list rendering
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct PostSummary {
+    pub post_id: String,
+    pub content: String,
+    // markdown rendered content, set only by view agents
+    pub content_html: Option<String>,
+    pub created_by: String,
+    // cached display name of author, may be stale or missing
+    pub created_by_name: Option<String>,
+    pub like_count: u32,
+    pub comment_count: u32,
+    // reaction of the requesting user, if any
+    pub own_like: Option<LikeType>,
+    pub location: Option<Location>,
+    pub language: Option<String>,
+    pub attachments: Vec<MediaMetadata>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PostSummary {
+    pub fn with_content_html(mut self) -> Self {
+        self.content_html = Some(content::render_markdown(&self.content));
+        self
+    }
 }
 
 impl Post {
-    fn new(post_id: String) -> Self {
-        let now = chrono::Utc::now();
+    pub(crate) fn new(post_id: String) -> Self {
+        let now = clock::now();
         Post {
             post_id,
             content: "".to_string(),
+            content_html: None,
             comments: HashMap::new(),
+            archived_comment_count: 0,
+            archived_comment_newest_at: None,
+            highlighted_comment_id: None,
             created_by: "".to_string(),
+            created_by_name: None,
             likes: HashMap::new(),
+            like_count: 0,
+            comment_count: 0,
+            reposted_from: None,
+            repost_count: 0,
+            location: None,
+            language: None,
+            mentions: vec![],
+            attachments: vec![],
             created_at: now,
             updated_at: now,
+            deleted_at: None,
         }
     }
 
+    pub(crate) fn init(&mut self, user_id: String, content: String, location: Option<Location>) {
+        let now = clock::now();
+        self.created_by = user_id;
+        self.language = detect_language(&content);
+        self.mentions = content::extract_mentions(&content);
+        self.content = content;
+        self.location = location;
+        self.created_at = now;
+        self.updated_at = now;
+    }
+
+    pub(crate) fn init_repost(&mut self, user_id: String, reposted_from: String, content: String) {
+        self.init(user_id, content, None);
+        self.reposted_from = Some(reposted_from);
+    }
+
+    pub(crate) fn add_repost(&mut self) {
+        self.repost_count += 1;
+        self.updated_at = clock::now();
+    }
+
+    pub(crate) fn remove_repost(&mut self) {
+        self.repost_count = self.repost_count.saturating_sub(1);
+        self.updated_at = clock::now();
+    }
+
+    pub(crate) fn delete(&mut self) -> bool {
+        if self.deleted_at.is_some() {
+            false
+        } else {
+            let now = clock::now();
+            self.content = "".to_string();
+            self.likes.clear();
+            self.comments.clear();
+            self.archived_comment_count = 0;
+            self.archived_comment_newest_at = None;
+            self.highlighted_comment_id = None;
+            self.location = None;
+            self.language = None;
+            self.mentions.clear();
+            self.attachments.clear();
+            self.update_counts();
+            self.deleted_at = Some(now);
+            self.updated_at = now;
+            true
+        }
+    }
+
+    fn update_counts(&mut self) {
+        self.like_count = self.likes.len() as u32;
+        self.comment_count = self.comments.len() as u32 + self.archived_comment_count;
+    }
+
+    // Ids of comments in threads (top-level comment with all replies) not updated since cutoff
+    fn archivable_comment_ids(&self, cutoff: chrono::DateTime<chrono::Utc>) -> HashSet<String> {
+        let root_id = |comment: &Comment| {
+            let mut current = comment;
+            // depth is limited by number of comments (guard against cycles)
+            for _ in 0..self.comments.len() {
+                match current
+                    .parent_comment_id
+                    .as_ref()
+                    .and_then(|parent_id| self.comments.get(parent_id))
+                {
+                    Some(parent) => current = parent,
+                    None => break,
+                }
+            }
+            current.comment_id.clone()
+        };
+
+        let mut threads: HashMap<String, Vec<&Comment>> = HashMap::new();
+        for comment in self.comments.values() {
+            threads.entry(root_id(comment)).or_default().push(comment);
+        }
+
+        // thread of highlighted comment stays in post
+        let highlighted_root_id = self
+            .highlighted_comment_id
+            .as_ref()
+            .and_then(|id| self.comments.get(id))
+            .map(root_id);
+        if let Some(root_id) = highlighted_root_id {
+            threads.remove(&root_id);
+        }
+
+        threads
+            .into_values()
+            .filter(|thread| thread.iter().all(|c| c.updated_at < cutoff))
+            .flatten()
+            .map(|c| c.comment_id.clone())
+            .collect()
+    }
+
+    fn take_archivable_comments(
+        &mut self,
+        cutoff: chrono::DateTime<chrono::Utc>,
+    ) -> HashMap<String, Comment> {
+        let archived: HashMap<String, Comment> = self
+            .archivable_comment_ids(cutoff)
+            .into_iter()
+            .filter_map(|id| self.comments.remove_entry(&id))
+            .collect();
+        let newest_at = archived.values().map(|c| c.created_at).max();
+        if self.archived_comment_count == 0 || self.archived_comment_newest_at.is_some() {
+            self.archived_comment_newest_at = self.archived_comment_newest_at.max(newest_at);
+        }
+        self.archived_comment_count += archived.len() as u32;
+        self.update_counts();
+        archived
+    }
+
+    // Set like of user, returns true if user did not like post before
     fn set_like(&mut self, user_id: String, like_type: LikeType) -> bool {
         let res = self.likes.insert(user_id, like_type);
-        self.updated_at = chrono::Utc::now();
-        res.is_some()
+        self.update_counts();
+        self.updated_at = clock::now();
+        res.is_none()
     }
 
     fn remove_like(&mut self, user_id: String) -> bool {
         let res = self.likes.remove(&user_id);
         if res.is_some() {
-            self.updated_at = chrono::Utc::now();
+            self.update_counts();
+            self.updated_at = clock::now();
         }
         res.is_some()
     }
 
+    // Add comment, parent comment may be archived (then it is given by caller)
     fn add_comment(
         &mut self,
         user_id: String,
         content: String,
         parent_comment_id: Option<String>,
-    ) -> Result<String, String> {
-        match parent_comment_id {
-            Some(parent_id) if !self.comments.contains_key(&parent_id) => {
-                Err("Parent comment not found".to_string())
+        archived_parent: Option<&Comment>,
+    ) -> Result<String, PostError> {
+        match parent_comment_id
+            .as_ref()
+            .map(|id| self.comments.get(id).or(archived_parent))
+        {
+            Some(None) => Err(PostError::not_found("Parent comment not found")),
+            Some(Some(parent)) if parent.is_deleted() => {
+                Err(PostError::not_found("Parent comment deleted"))
             }
             _ => {
                 let comment = Comment::new(user_id.clone(), content, parent_comment_id);
@@ -91,16 +553,203 @@ impl Post {
 
                 self.comments.insert(comment_id.clone(), comment);
 
-                self.updated_at = chrono::Utc::now();
+                self.update_counts();
+                self.updated_at = clock::now();
 
                 Ok(comment_id)
             }
         }
     }
 
-    fn remove_comment(&mut self, comment_id: String) -> Result<(), String> {
+    fn set_highlight(&mut self, comment_id: Option<String>) -> Result<(), PostError> {
+        if let Some(comment_id) = &comment_id {
+            match self.comments.get(comment_id) {
+                None => return Err(PostError::not_found("Comment not found")),
+                Some(comment) if comment.is_deleted() => {
+                    return Err(PostError::not_found("Comment deleted"))
+                }
+                _ => (),
+            }
+        }
+        if self.highlighted_comment_id != comment_id {
+            self.highlighted_comment_id = comment_id;
+            self.updated_at = clock::now();
+        }
+        Ok(())
+    }
+
+    // Comments page (computed from kept comments) may contain archived comments, so it has to be
+    // computed from all comments: archived comments are ranked with kept ones (most liked), or
+    // their creation times are within the page range (by time orders)
+    fn comments_page_archived(
+        &self,
+        cursor: Option<String>,
+        page: &CommentsPage,
+        sort: &CommentSort,
+    ) -> bool {
+        if self.archived_comment_count == 0 {
+            return false;
+        }
+        let Some(newest_at) = self.archived_comment_newest_at else {
+            return true;
+        };
+        let newest_at = newest_at.timestamp_micros().max(0) as u64;
+        match sort {
+            CommentSort::MostLiked => true,
+            CommentSort::OldestFirst => cursor::decode_key(cursor)
+                .ok()
+                .flatten()
+                .is_none_or(|(after, _)| after <= newest_at),
+            CommentSort::NewestFirst => {
+                page.next_cursor.is_none()
+                    || page
+                        .comments
+                        .last()
+                        .is_none_or(|last| last.created_at_micros() <= newest_at)
+            }
+        }
+    }
+
+    // Comments in given order, highlighted comment first on first page,
+    // cursor is creation time and id of last comment (by time orders) or number of returned comments (most liked)
+    fn get_comments_page(
+        &self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        sort: CommentSort,
+    ) -> Result<CommentsPage, InvalidCursor> {
+        let after = cursor::decode_key(cursor)?;
+        let limit = limit
+            .unwrap_or(COMMENTS_PAGE_DEFAULT_LIMIT)
+            .clamp(1, COMMENTS_PAGE_MAX_LIMIT) as usize;
+
+        let highlighted = self
+            .highlighted_comment_id
+            .as_ref()
+            .and_then(|id| self.comments.get(id));
+
+        let mut comments: Vec<&Comment> = self
+            .comments
+            .values()
+            .filter(|c| Some(&c.comment_id) != self.highlighted_comment_id.as_ref())
+            .filter(|c| match (&sort, &after) {
+                (CommentSort::OldestFirst, Some(after)) => &c.cursor_key() > after,
+                (CommentSort::NewestFirst, Some(after)) => &c.cursor_key() < after,
+                _ => true,
+            })
+            .collect();
+        match sort {
+            CommentSort::OldestFirst => comments.sort_by_key(|c| c.cursor_key()),
+            CommentSort::NewestFirst => comments.sort_by_key(|c| std::cmp::Reverse(c.cursor_key())),
+            CommentSort::MostLiked => {
+                comments.sort_by_key(|c| {
+                    (
+                        std::cmp::Reverse(c.likes.len()),
+                        c.created_at,
+                        c.comment_id.clone(),
+                    )
+                });
+                let offset = after.as_ref().map_or(0, |(offset, _)| *offset) as usize;
+                comments.drain(..offset.min(comments.len()));
+            }
+        }
+
+        let has_more = comments.len() > limit;
+        comments.truncate(limit);
+        let next_cursor = match comments.last() {
+            Some(_) if sort == CommentSort::MostLiked && has_more => {
+                let offset = after.as_ref().map_or(0, |(offset, _)| *offset);
+                Some(cursor::encode(offset + limit as u64))
+            }
+            Some(last) if has_more => Some(cursor::encode_key(
+                last.created_at_micros(),
+                &last.comment_id,
+            )),
+            _ => None,
+        };
+
+        let comments = match highlighted {
+            Some(highlighted) if after.is_none() => std::iter::once(highlighted)
+                .chain(comments)
+                .cloned()
+                .collect(),
+            _ => comments.into_iter().cloned().collect(),
+        };
+
+        Ok(CommentsPage {
+            post_id: self.post_id.clone(),
+            highlighted_comment_id: self.highlighted_comment_id.clone(),
+            comments,
+            next_cursor,
+        })
+    }
+
+    // Replace content of comment, allowed only for the comment author
+    fn edit_comment(
+        &mut self,
+        comment_id: &str,
+        user_id: &str,
+        content: String,
+    ) -> Result<(), PostError> {
+        match self.comments.get_mut(comment_id) {
+            None => Err(PostError::not_found("Comment not found")),
+            Some(comment) if comment.is_deleted() => Err(PostError::not_found("Comment deleted")),
+            Some(comment) if comment.created_by != user_id => {
+                Err(PostError::Unauthorized(user_id.to_string()))
+            }
+            Some(comment) if comment.content == content => Ok(()),
+            Some(comment) => {
+                let now = clock::now();
+                comment.mentions = content::extract_mentions(&content);
+                comment.content = content;
+                comment.edited = true;
+                comment.updated_at = now;
+                self.updated_at = now;
+                Ok(())
+            }
+        }
+    }
+
+    // Comment can be removed by its author or the post author
+    fn can_remove_comment(&self, comment_id: &str, user_id: &str) -> bool {
+        self.created_by == user_id
+            || self
+                .comments
+                .get(comment_id)
+                .is_some_and(|c| c.created_by == user_id)
+    }
+
+    // Replace comment content with placeholder and keep its replies,
+    // comment without replies is removed
+    fn soft_remove_comment(&mut self, comment_id: String) -> Result<(), PostError> {
+        let has_replies = self
+            .comments
+            .values()
+            .any(|c| c.parent_comment_id.as_ref() == Some(&comment_id));
+
+        match self.comments.get_mut(&comment_id) {
+            None => Err(PostError::not_found("Comment not found")),
+            Some(comment) if comment.is_deleted() => Ok(()),
+            Some(_) if !has_replies => self.remove_comment(comment_id),
+            Some(comment) => {
+                let now = clock::now();
+                comment.content = DELETED_COMMENT_CONTENT.to_string();
+                comment.likes.clear();
+                comment.mentions.clear();
+                comment.deleted_at = Some(now);
+                comment.updated_at = now;
+                if self.highlighted_comment_id.as_ref() == Some(&comment_id) {
+                    self.highlighted_comment_id = None;
+                }
+                self.updated_at = now;
+                Ok(())
+            }
+        }
+    }
+
+    fn remove_comment(&mut self, comment_id: String) -> Result<(), PostError> {
         if !self.comments.contains_key(&comment_id) {
-            Err("Comment not found".to_string())
+            Err(PostError::not_found("Comment not found"))
         } else {
             fn collect_comments_to_remove(
                 comments: &HashMap<String, Comment>,
@@ -131,49 +780,134 @@ impl Post {
             for remove_id in to_remove {
                 self.comments.remove(&remove_id);
             }
+            if self
+                .highlighted_comment_id
+                .as_ref()
+                .is_some_and(|id| !self.comments.contains_key(id))
+            {
+                self.highlighted_comment_id = None;
+            }
 
-            self.updated_at = chrono::Utc::now();
+            self.update_counts();
+            self.updated_at = clock::now();
 
             Ok(())
         }
     }
 
+    // Set like of user, returns true if user did not like comment before
     fn set_comment_like(
         &mut self,
         comment_id: String,
         user_id: String,
         like_type: LikeType,
-    ) -> Result<(), String> {
+    ) -> Result<bool, PostError> {
         match self.comments.get_mut(&comment_id) {
+            Some(comment) if comment.is_deleted() => Err(PostError::not_found("Comment deleted")),
             Some(comment) => {
-                comment.likes.insert(user_id, like_type);
-                comment.updated_at = chrono::Utc::now();
-                Ok(())
+                let res = comment.likes.insert(user_id, like_type);
+                comment.updated_at = clock::now();
+                Ok(res.is_none())
             }
-            None => Err("Comment not found".to_string()),
+            None => Err(PostError::not_found("Comment not found")),
         }
     }
 
-    fn remove_comment_like(&mut self, comment_id: String, user_id: String) -> Result<(), String> {
+    fn remove_comment_like(
+        &mut self,
+        comment_id: String,
+        user_id: String,
+    ) -> Result<(), PostError> {
         match self.comments.get_mut(&comment_id) {
             Some(comment) => {
                 let removed = comment.likes.remove(&user_id).is_some();
                 if removed {
-                    comment.updated_at = chrono::Utc::now();
+                    comment.updated_at = clock::now();
                 }
                 Ok(())
             }
-            None => Err("Comment not found".to_string()),
+            None => Err(PostError::not_found("Comment not found")),
+        }
+    }
+
+    fn get_like_counts(&self) -> LikeCounts {
+        LikeCounts::from_likes(self.likes.values())
+    }
+
+    pub fn summary(&self, user_id: Option<&str>) -> PostSummary {
+        PostSummary {
+            post_id: self.post_id.clone(),
+            content: self.content.clone(),
+            content_html: self.content_html.clone(),
+            created_by: self.created_by.clone(),
+            created_by_name: self.created_by_name.clone(),
+            like_count: self.like_count,
+            comment_count: self.comment_count,
+            own_like: user_id.and_then(|user_id| self.likes.get(user_id).cloned()),
+            location: self.location.clone(),
+            language: self.language.clone(),
+            attachments: self.attachments.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    // Summary of active post matching the query
+    pub(crate) fn summary_if_match(
+        &self,
+        user_id: Option<&str>,
+        query: &query::Query,
+    ) -> Option<PostSummary> {
+        (self.deleted_at.is_none() && self.matches_query(query)).then(|| self.summary(user_id))
+    }
+
+    fn get_comment_like_counts(&self, comment_id: &str) -> Option<LikeCounts> {
+        self.comments
+            .get(comment_id)
+            .map(|comment| LikeCounts::from_likes(comment.likes.values()))
+    }
+
+    fn get_comment_like_summary(
+        &self,
+        comment_id: &str,
+        user_id: Option<&str>,
+    ) -> Option<LikeSummary> {
+        self.comments
+            .get(comment_id)
+            .map(|comment| LikeSummary::from_likes(&comment.likes, user_id))
+    }
+
+    // Post with markdown rendered content of post and comments
+    pub fn with_content_html(mut self) -> Self {
+        self.content_html = Some(content::render_markdown(&self.content));
+        for comment in self.comments.values_mut() {
+            comment.content_html = Some(content::render_markdown(&comment.content));
         }
+        self
     }
 
     pub fn matches_query(&self, query: &query::Query) -> bool {
         // Check field filters first
         for (field, value) in query.field_filters.iter() {
+            let mode = query::field_mode(POST_QUERY_FIELDS, field);
             let matches = match field.as_str() {
-                "post-id" | "postid" => query::text_exact_matches(&self.post_id, value),
+                "post-id" | "postid" => query::exact_matches(&mode, &self.post_id, value),
                 "content" => query::text_matches(&self.content, value),
-                "created-by" | "createdby" => query::text_exact_matches(&self.created_by, value),
+                "created-by" | "createdby" => query::exact_matches(&mode, &self.created_by, value),
+                "location" => {
+                    query::opt_text_matches(self.location.as_ref().map(|l| l.name.clone()), value)
+                }
+                "near" => Radius::parse(value).is_some_and(|radius| {
+                    self.location.as_ref().is_some_and(|l| radius.contains(l))
+                }),
+                "language" | "lang" => {
+                    query::opt_exact_matches(&mode, self.language.as_deref(), value)
+                }
+                // comments kept by post (not archived), without soft deleted
+                "comment-author" | "commentauthor" => self
+                    .comments
+                    .values()
+                    .any(|c| !c.is_deleted() && query::exact_matches(&mode, &c.created_by, value)),
                 _ => false, // Unknown field
             };
             if !matches {
@@ -199,34 +933,178 @@ trait PostAgent {
 
     fn get_post_if_match(&self, query: query::Query) -> Option<Post>;
 
-    async fn init_post(&mut self, user_id: String, content: String) -> Result<(), String>;
+    // Post without comments, with the reaction of given user
+    fn get_post_summary(&self, user_id: Option<String>) -> Option<PostSummary>;
 
-    fn add_comment(
+    fn get_post_summary_if_match(
+        &self,
+        user_id: Option<String>,
+        query: query::Query,
+    ) -> Option<PostSummary>;
+
+    // Post with archived comments
+    async fn get_post_with_archive(&self) -> Option<Post>;
+
+    // Move comment threads not updated since archive threshold to post archive agent
+    async fn archive_comments(&mut self, correlation_id: Option<String>) -> u32;
+
+    // Post likes counts by type
+    fn get_like_counts(&self) -> Option<LikeCounts>;
+
+    // Comment likes counts by type
+    fn get_comment_like_counts(&self, comment_id: String) -> Option<LikeCounts>;
+
+    // Post likes counts by type, with reaction of given user
+    fn get_like_summary(&self, user_id: Option<String>) -> Option<LikeSummary>;
+
+    // Comment likes counts by type, with reaction of given user
+    fn get_comment_like_summary(
+        &self,
+        comment_id: String,
+        user_id: Option<String>,
+    ) -> Option<LikeSummary>;
+
+    // Page of users who liked post (optionally with given type), ordered by user id
+    fn get_likers(
+        &self,
+        like_type: Option<LikeType>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<LikersPage>, PostError>;
+
+    // Page of users who liked comment (optionally with given type), ordered by user id
+    fn get_comment_likers(
+        &self,
+        comment_id: String,
+        like_type: Option<LikeType>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<LikersPage>, PostError>;
+
+    // Page of comments (oldest first by default), highlighted comment first on first page,
+    // archived comments are included
+    async fn get_comments_page(
+        &self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        sort: Option<CommentSort>,
+    ) -> Result<Option<CommentsPage>, PostError>;
+
+    async fn init_post(
+        &mut self,
+        user_id: String,
+        content: String,
+        location: Option<Location>,
+        attachments: Vec<MediaMetadata>,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    async fn add_comment(
         &mut self,
         user_id: String,
         content: String,
         parent_comment_id: Option<String>,
-    ) -> Result<String, String>;
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError>;
 
-    fn remove_comment(&mut self, comment_id: String) -> Result<(), String>;
+    // Edit comment content, allowed only for comment author
+    async fn edit_comment(
+        &mut self,
+        comment_id: String,
+        user_id: String,
+        content: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
 
-    fn set_like(&mut self, user_id: String, like_type: LikeType) -> Result<(), String>;
+    // Remove comment, allowed for comment author and post author
+    // soft: content replaced with placeholder and replies kept, hard (moderation): comment removed with replies
+    fn remove_comment(
+        &mut self,
+        comment_id: String,
+        acting_user_id: String,
+        soft: bool,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
 
-    fn remove_like(&mut self, user_id: String) -> Result<(), String>;
+    async fn set_like(
+        &mut self,
+        user_id: String,
+        like_type: LikeType,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
 
-    fn set_comment_like(
+    // Remove like of user, allowed only for the user (acting user)
+    fn remove_like(
+        &mut self,
+        user_id: String,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    async fn set_comment_like(
         &mut self,
         comment_id: String,
         user_id: String,
         like_type: LikeType,
-    ) -> Result<(), String>;
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    // Remove comment like of user, allowed only for the user (acting user)
+    fn remove_comment_like(
+        &mut self,
+        comment_id: String,
+        user_id: String,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    // Highlight comment (e.g. best answer), allowed for post author
+    fn mark_highlight(
+        &mut self,
+        comment_id: String,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    fn clear_highlight(
+        &mut self,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    // Tombstone post, deleted post is not returned anymore and is removed from user posts and timelines
+    // Delete post, only by its author
+    fn delete_post(
+        &mut self,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    // Refresh cached display name of author
+    fn set_author_name(&mut self, name: Option<String>, correlation_id: Option<String>);
+
+    // Init post as repost of original post, with optional comment (content) of reposting user
+    async fn init_repost(
+        &mut self,
+        user_id: String,
+        reposted_from: String,
+        content: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    // Repost of post was created or deleted, updates repost count
+    fn add_repost(&mut self, correlation_id: Option<String>);
 
-    fn remove_comment_like(&mut self, comment_id: String, user_id: String) -> Result<(), String>;
+    fn remove_repost(&mut self, correlation_id: Option<String>);
+
+    // Operation counters of write methods, for admins
+    fn get_diagnostics(&self) -> AgentDiagnostics;
 }
 
 struct PostAgentImpl {
     _id: String,
     state: Option<Post>,
+    diagnostics: AgentDiagnostics,
 }
 
 impl PostAgentImpl {
@@ -237,6 +1115,18 @@ impl PostAgentImpl {
     fn with_state<T>(&mut self, f: impl FnOnce(&mut Post) -> T) -> T {
         f(self.get_state())
     }
+
+    // Post if created and not deleted
+    fn active_post(&self) -> Option<&Post> {
+        self.state.as_ref().filter(|post| post.deleted_at.is_none())
+    }
+
+    // Post exists and is not deleted
+    fn is_active(&self) -> bool {
+        self.state
+            .as_ref()
+            .is_some_and(|post| post.deleted_at.is_none())
+    }
 }
 
 #[agent_implementation]
@@ -245,139 +1135,750 @@ impl PostAgent for PostAgentImpl {
         PostAgentImpl {
             _id: id,
             state: None,
+            diagnostics: AgentDiagnostics::default(),
         }
     }
 
     fn get_post(&self) -> Option<Post> {
-        self.state.clone()
+        self.active_post().cloned()
     }
 
     fn get_post_if_match(&self, query: query::Query) -> Option<Post> {
-        self.state.clone().filter(|post| post.matches_query(&query))
+        self.state
+            .clone()
+            .filter(|post| post.deleted_at.is_none() && post.matches_query(&query))
     }
 
-    async fn init_post(&mut self, user_id: String, content: String) -> Result<(), String> {
-        if self.state.is_some() {
-            Err("Post already exists".to_string())
-        } else {
-            let state = self.get_state();
-            println!("init post - user id: {user_id}, content: {content}");
-            let now = chrono::Utc::now();
-            state.created_by = user_id.clone();
-            state.content = content;
-            state.created_at = now;
-            state.updated_at = now;
+    async fn get_post_with_archive(&self) -> Option<Post> {
+        let mut post = self.get_post()?;
+        if post.archived_comment_count > 0 {
+            let archived = PostArchiveAgentClient::get(self._id.clone())
+                .get_comments()
+                .await;
+            post.comments.extend(archived);
+        }
+        Some(post)
+    }
 
-            TimelinesUpdaterAgentClient::get(user_id.clone())
-                .trigger_post_updated(PostUpdate::from(state), true);
+    async fn archive_comments(&mut self, correlation_id: Option<String>) -> u32 {
+        trace::init(correlation_id);
+        self.diagnostics.record_call("archive_comments");
+        if !self.is_active() {
+            return 0;
+        }
+        let cutoff = archive_cutoff(clock::now());
+        let state = self.get_state();
+        let comments: HashMap<String, Comment> = state
+            .archivable_comment_ids(cutoff)
+            .into_iter()
+            .filter_map(|id| state.comments.get(&id).map(|c| (id, c.clone())))
+            .collect();
+        if comments.is_empty() {
+            return 0;
+        }
 
-            Ok(())
+        trace_println!("archive comments - count: {}", comments.len());
+
+        // comments are removed from post only after they are stored by archive
+        PostArchiveAgentClient::get(self._id.clone())
+            .add_comments(comments, trace::propagate())
+            .await;
+        self.with_state(|state| state.take_archivable_comments(cutoff).len() as u32)
+    }
+
+    fn get_post_summary(&self, user_id: Option<String>) -> Option<PostSummary> {
+        self.get_post().map(|post| post.summary(user_id.as_deref()))
+    }
+
+    fn get_post_summary_if_match(
+        &self,
+        user_id: Option<String>,
+        query: query::Query,
+    ) -> Option<PostSummary> {
+        self.state
+            .as_ref()
+            .and_then(|post| post.summary_if_match(user_id.as_deref(), &query))
+    }
+
+    fn get_like_counts(&self) -> Option<LikeCounts> {
+        self.get_post().map(|post| post.get_like_counts())
+    }
+
+    fn get_comment_like_counts(&self, comment_id: String) -> Option<LikeCounts> {
+        self.get_post()
+            .and_then(|post| post.get_comment_like_counts(&comment_id))
+    }
+
+    fn get_like_summary(&self, user_id: Option<String>) -> Option<LikeSummary> {
+        self.active_post()
+            .map(|post| LikeSummary::from_likes(&post.likes, user_id.as_deref()))
+    }
+
+    fn get_comment_like_summary(
+        &self,
+        comment_id: String,
+        user_id: Option<String>,
+    ) -> Option<LikeSummary> {
+        self.active_post()
+            .and_then(|post| post.get_comment_like_summary(&comment_id, user_id.as_deref()))
+    }
+
+    fn get_likers(
+        &self,
+        like_type: Option<LikeType>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<LikersPage>, PostError> {
+        self.active_post()
+            .map(|post| LikersPage::from_likes(&post.likes, like_type, cursor, limit))
+            .transpose()
+            .map_err(PostError::from)
+    }
+
+    fn get_comment_likers(
+        &self,
+        comment_id: String,
+        like_type: Option<LikeType>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<LikersPage>, PostError> {
+        self.active_post()
+            .and_then(|post| post.comments.get(&comment_id))
+            .map(|comment| LikersPage::from_likes(&comment.likes, like_type, cursor, limit))
+            .transpose()
+            .map_err(PostError::from)
+    }
+
+    async fn get_comments_page(
+        &self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        sort: Option<CommentSort>,
+    ) -> Result<Option<CommentsPage>, PostError> {
+        let Some(post) = self.active_post() else {
+            return Ok(None);
+        };
+        let sort = sort.unwrap_or_default();
+        let page = post.get_comments_page(cursor.clone(), limit, sort.clone())?;
+        if !post.comments_page_archived(cursor.clone(), &page, &sort) {
+            return Ok(Some(page));
         }
+        let mut post = post.clone();
+        let archived = PostArchiveAgentClient::get(self._id.clone())
+            .get_comments()
+            .await;
+        post.comments.extend(archived);
+        Ok(Some(post.get_comments_page(cursor, limit, sort)?))
     }
 
-    fn add_comment(
+    async fn init_post(
+        &mut self,
+        user_id: String,
+        content: String,
+        location: Option<Location>,
+        attachments: Vec<MediaMetadata>,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        let result = init_post(
+            &AgentClients,
+            &mut self.state,
+            &self._id,
+            user_id,
+            content,
+            location,
+            attachments,
+        )
+        .await;
+        self.diagnostics.record("init_post", &result);
+        result
+    }
+
+    async fn add_comment(
         &mut self,
         user_id: String,
         content: String,
         parent_comment_id: Option<String>,
-    ) -> Result<String, String> {
-        if self.state.is_none() {
-            Err("Post not exists".to_string())
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError> {
+        trace::init(correlation_id);
+        let result: Result<String, PostError> = async {
+            let content = content::sanitize(&content);
+            if !self.is_active() {
+                Err(PostError::not_found("Post not exists"))
+            } else if content.is_empty() {
+                Err(PostError::invalid("Comment content is empty"))
+            } else {
+                check_length(ContentField::CommentContent, &content)?;
+                check_user_exists(&AgentClients, &user_id).await?;
+                check_user_not_banned(&user_id).await?;
+                let author_id = self.get_state().created_by.clone();
+                if user_id != author_id
+                    && !UserAgentClient::get(author_id)
+                        .can_comment(user_id.clone())
+                        .await
+                {
+                    trace_println!("add comment - user id: {user_id} - not allowed");
+                    return Err(PostError::NotAllowed(user_id));
+                }
+                check_rate_limit(&user_id, RateLimitAction::Comment).await?;
+                let content = moderate_content(content).await?;
+                let mentioned_user_ids = get_mentioned_users(
+                    &AgentClients,
+                    content::extract_mentions(&content),
+                    &user_id,
+                )
+                .await;
+                // reply to comment of archived thread
+                let archived_parent = match &parent_comment_id {
+                    Some(parent_id)
+                        if self.get_state().archived_comment_count > 0
+                            && !self.get_state().comments.contains_key(parent_id) =>
+                    {
+                        PostArchiveAgentClient::get(self._id.clone())
+                            .get_comment(parent_id.clone())
+                            .await
+                    }
+                    _ => None,
+                };
+                self.with_state(|state| {
+                    trace_println!(
+                        "add comment - user id: {}, content: {}, parent id: {}",
+                        user_id,
+                        content,
+                        parent_comment_id.clone().unwrap_or("N/A".to_string())
+                    );
+                    // max count of hot and archived comments
+                    if state.comment_count as usize >= COMMENTS_MAX_COUNT {
+                        Err(PostError::invalid("Max comment length"))
+                    } else {
+                        let comment_id = state.add_comment(
+                            user_id.clone(),
+                            content.clone(),
+                            parent_comment_id,
+                            archived_parent.as_ref(),
+                        )?;
+                        record_user_activity(&user_id);
+                        record_interaction(
+                            &user_id,
+                            vec![state.created_by.clone()],
+                            InteractionKind::Comment,
+                        );
+                        record_abuse_event(&user_id, AbuseEventKind::Comment, Some(&content));
+                        if state
+                            .archivable_comment_ids(archive_cutoff(state.updated_at))
+                            .len()
+                            >= ARCHIVE_MIN_BATCH
+                        {
+                            PostAgentClient::get(state.post_id.clone())
+                                .trigger_archive_comments(trace::propagate());
+                        }
+                        for mentioned_user_id in mentioned_user_ids {
+                            notify_user(
+                                &mentioned_user_id,
+                                NotificationKind::Mention,
+                                &user_id,
+                                NotificationTarget::comment(&state.post_id, &comment_id),
+                                content.clone(),
+                            );
+                        }
+                        notify_user(
+                            &state.created_by,
+                            NotificationKind::Comment,
+                            &user_id,
+                            NotificationTarget::post(&state.post_id),
+                            content,
+                        );
+                        TimelinesUpdaterAgentClient::get(user_id.clone()).trigger_post_updated(
+                            PostUpdate::from(state),
+                            false,
+                            trace::propagate(),
+                        );
+                        Ok(comment_id)
+                    }
+                })
+            }
+        }
+        .await;
+        self.diagnostics.record("add_comment", &result);
+        result
+    }
+
+    async fn edit_comment(
+        &mut self,
+        comment_id: String,
+        user_id: String,
+        content: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        let result: Result<(), PostError> = async {
+            let content = content::sanitize(&content);
+            if !self.is_active() {
+                Err(PostError::not_found("Post not exists"))
+            } else if content.is_empty() {
+                Err(PostError::invalid("Comment content is empty"))
+            } else {
+                check_length(ContentField::CommentContent, &content)?;
+                check_user_not_banned(&user_id).await?;
+                let content = moderate_content(content).await?;
+                let mentioned_user_ids = get_mentioned_users(
+                    &AgentClients,
+                    content::extract_mentions(&content),
+                    &user_id,
+                )
+                .await;
+                self.with_state(|state| {
+                    trace_println!(
+                        "edit comment - comment id: {comment_id}, user id: {user_id}, content: {content}"
+                    );
+                    let previous_mentions = state
+                        .comments
+                        .get(&comment_id)
+                        .map(|c| c.mentions.clone())
+                        .unwrap_or_default();
+                    state.edit_comment(&comment_id, &user_id, content.clone())?;
+                    // only users newly mentioned by edit are notified
+                    for mentioned_user_id in mentioned_user_ids
+                        .into_iter()
+                        .filter(|id| !previous_mentions.contains(id))
+                    {
+                        notify_user(
+                            &mentioned_user_id,
+                            NotificationKind::Mention,
+                            &user_id,
+                            NotificationTarget::comment(&state.post_id, &comment_id),
+                            content.clone(),
+                        );
+                    }
+                    TimelinesUpdaterAgentClient::get(state.created_by.clone())
+                        .trigger_post_updated(PostUpdate::from(state), false, trace::propagate());
+                    Ok(())
+                })
+            }
+        }
+        .await;
+        self.diagnostics.record("edit_comment", &result);
+        result
+    }
+
+    fn remove_comment(
+        &mut self,
+        comment_id: String,
+        acting_user_id: String,
+        soft: bool,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        let result: Result<(), PostError> =
+            if !self.is_active() {
+                Err(PostError::not_found("Post not exists"))
+            } else {
+                self.with_state(|state| {
+                    trace_println!(
+                        "remove comment - comment id: {}, acting user id: {}, soft: {}",
+                        comment_id,
+                        acting_user_id,
+                        soft
+                    );
+                    if state.comments.contains_key(&comment_id)
+                        && !state.can_remove_comment(&comment_id, &acting_user_id)
+                    {
+                        return Err(PostError::Unauthorized(acting_user_id));
+                    }
+                    if soft {
+                        state.soft_remove_comment(comment_id)?;
+                    } else {
+                        state.remove_comment(comment_id)?;
+                    }
+                    TimelinesUpdaterAgentClient::get(state.created_by.clone())
+                        .trigger_post_updated(PostUpdate::from(state), false, trace::propagate());
+                    Ok(())
+                })
+            };
+        self.diagnostics.record("remove_comment", &result);
+        result
+    }
+
+    async fn set_like(
+        &mut self,
+        user_id: String,
+        like_type: LikeType,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        let result: Result<(), PostError> = async {
+            if !self.is_active() {
+                Err(PostError::not_found("Post not exists"))
+            } else {
+                check_user_exists(&AgentClients, &user_id).await?;
+                check_user_not_banned(&user_id).await?;
+                let author_id = self.get_state().created_by.clone();
+                if user_id != author_id
+                    && !UserAgentClient::get(author_id)
+                        .can_like(user_id.clone())
+                        .await
+                {
+                    trace_println!("set like - user id: {user_id} - not allowed");
+                    return Err(PostError::NotAllowed(user_id));
+                }
+                self.with_state(|state| {
+                    trace_println!("set like - user id: {}, like type: {}", user_id, like_type);
+                    record_user_activity(&user_id);
+                    // changed like type is not a new interaction
+                    if state.set_like(user_id.clone(), like_type.clone()) {
+                        record_interaction(
+                            &user_id,
+                            vec![state.created_by.clone()],
+                            InteractionKind::Like,
+                        );
+                        record_abuse_event(&user_id, AbuseEventKind::Like, None);
+                        notify_user(
+                            &state.created_by,
+                            NotificationKind::Like,
+                            &user_id,
+                            NotificationTarget::post(&state.post_id),
+                            like_type.to_string(),
+                        );
+                    }
+                    TimelinesUpdaterAgentClient::get(state.created_by.clone())
+                        .trigger_post_updated(PostUpdate::from(state), false, trace::propagate());
+                    Ok(())
+                })
+            }
+        }
+        .await;
+        self.diagnostics.record("set_like", &result);
+        result
+    }
+
+    fn remove_like(
+        &mut self,
+        user_id: String,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        let result: Result<(), PostError> = if !self.is_active() {
+            Err(PostError::not_found("Post not exists"))
         } else {
+            check_acting_user(&acting_user_id, &user_id)?;
             self.with_state(|state| {
-                println!(
-                    "add comment - user id: {}, content: {}, parent id: {}",
-                    user_id,
-                    content,
-                    parent_comment_id.clone().unwrap_or("N/A".to_string())
-                );
-                if state.comments.len() >= COMMENTS_MAX_COUNT {
-                    Err("Max comment length".to_string())
-                } else {
-                    let comment_id =
-                        state.add_comment(user_id.clone(), content, parent_comment_id)?;
-                    TimelinesUpdaterAgentClient::get(user_id.clone())
-                        .trigger_post_updated(PostUpdate::from(state), false);
-                    Ok(comment_id)
+                trace_println!("remove like - user id: {}", user_id);
+                if state.remove_like(user_id) {
+                    TimelinesUpdaterAgentClient::get(state.created_by.clone())
+                        .trigger_post_updated(PostUpdate::from(state), false, trace::propagate());
                 }
+                Ok(())
             })
+        };
+        self.diagnostics.record("remove_like", &result);
+        result
+    }
+
+    async fn set_comment_like(
+        &mut self,
+        comment_id: String,
+        user_id: String,
+        like_type: LikeType,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        let result: Result<(), PostError> = async {
+            if !self.is_active() {
+                Err(PostError::not_found("Post not exists"))
+            } else {
+                check_user_exists(&AgentClients, &user_id).await?;
+                check_user_not_banned(&user_id).await?;
+                // privacy settings of comment author, as for likes of post author
+                let author_id = self
+                    .get_state()
+                    .comments
+                    .get(&comment_id)
+                    .map(|c| c.created_by.clone());
+                if let Some(author_id) = author_id.filter(|a| *a != user_id) {
+                    if !UserAgentClient::get(author_id)
+                        .can_like(user_id.clone())
+                        .await
+                    {
+                        trace_println!("set comment like - user id: {user_id} - not allowed");
+                        return Err(PostError::NotAllowed(user_id));
+                    }
+                }
+                self.with_state(|state| {
+                    trace_println!(
+                        "set comment like - comment id: {}, user id: {}, like type: {}",
+                        comment_id,
+                        user_id,
+                        like_type
+                    );
+
+                    let new_like = state.set_comment_like(
+                        comment_id.clone(),
+                        user_id.clone(),
+                        like_type.clone(),
+                    )?;
+                    if !new_like {
+                        return Ok(());
+                    }
+                    record_abuse_event(&user_id, AbuseEventKind::Like, None);
+                    if let Some(comment) = state.comments.get(&comment_id) {
+                        record_interaction(
+                            &user_id,
+                            vec![comment.created_by.clone()],
+                            InteractionKind::Like,
+                        );
+                        notify_user(
+                            &comment.created_by,
+                            NotificationKind::Like,
+                            &user_id,
+                            NotificationTarget::comment(&state.post_id, &comment_id),
+                            like_type.to_string(),
+                        );
+                    }
+                    Ok(())
+                })
+            }
+        }
+        .await;
+        self.diagnostics.record("set_comment_like", &result);
+        result
+    }
+
+    fn remove_comment_like(
+        &mut self,
+        comment_id: String,
+        user_id: String,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        let result: Result<(), PostError> = if !self.is_active() {
+            Err(PostError::not_found("Post not exists"))
+        } else {
+            check_acting_user(&acting_user_id, &user_id)?;
+            self.with_state(|state| {
+                trace_println!(
+                    "remove comment like - comment id: {}, user id: {}",
+                    comment_id,
+                    user_id
+                );
+                state.remove_comment_like(comment_id, user_id)
+            })
+        };
+        self.diagnostics.record("remove_comment_like", &result);
+        result
+    }
+
+    fn mark_highlight(
+        &mut self,
+        comment_id: String,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        let result: Result<(), PostError> = if !self.is_active() {
+            Err(PostError::not_found("Post not exists"))
+        } else {
+            self.with_state(|state| {
+                trace_println!(
+                    "mark highlight - comment id: {}, acting user id: {}",
+                    comment_id,
+                    acting_user_id
+                );
+                check_acting_user(&acting_user_id, &state.created_by)?;
+                state.set_highlight(Some(comment_id))
+            })
+        };
+        self.diagnostics.record("mark_highlight", &result);
+        result
+    }
+
+    fn clear_highlight(
+        &mut self,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        let result: Result<(), PostError> = if !self.is_active() {
+            Err(PostError::not_found("Post not exists"))
+        } else {
+            self.with_state(|state| {
+                trace_println!("clear highlight - acting user id: {}", acting_user_id);
+                check_acting_user(&acting_user_id, &state.created_by)?;
+                state.set_highlight(None)
+            })
+        };
+        self.diagnostics.record("clear_highlight", &result);
+        result
+    }
+
+    fn delete_post(
+        &mut self,
+        acting_user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        let result = delete_post(&AgentClients, &mut self.state, acting_user_id);
+        self.diagnostics.record("delete_post", &result);
+        result
+    }
+
+    fn set_author_name(&mut self, name: Option<String>, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        self.diagnostics.record_call("set_author_name");
+        set_author_name(&mut self.state, name);
+    }
+
+    async fn init_repost(
+        &mut self,
+        user_id: String,
+        reposted_from: String,
+        content: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        let result = init_repost(
+            &AgentClients,
+            &mut self.state,
+            &self._id,
+            user_id,
+            reposted_from,
+            content,
+        )
+        .await;
+        self.diagnostics.record("init_repost", &result);
+        result
+    }
+
+    fn add_repost(&mut self, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        self.diagnostics.record_call("add_repost");
+        add_repost(&mut self.state);
+    }
+
+    fn remove_repost(&mut self, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        self.diagnostics.record_call("remove_repost");
+        remove_repost(&mut self.state);
+    }
+
+    fn get_diagnostics(&self) -> AgentDiagnostics {
+        self.diagnostics.clone()
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let mut data: Option<Post> = crate::common::snapshot::deserialize(&bytes)?;
+        // counts are not stored in older snapshots
+        if let Some(post) = data.as_mut() {
+            post.update_counts();
+        }
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+// Archived comments of post
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct PostArchive {
+    pub post_id: String,
+    pub comments: HashMap<String, Comment>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PostArchive {
+    fn new(post_id: String) -> Self {
+        let now = clock::now();
+        PostArchive {
+            post_id,
+            comments: HashMap::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+#[agent_definition]
+trait PostArchiveAgent {
+    fn new(id: String) -> Self;
+
+    fn get_archive(&self) -> Option<PostArchive>;
+
+    fn get_comments(&self) -> HashMap<String, Comment>;
+
+    fn get_comment(&self, comment_id: String) -> Option<Comment>;
+
+    fn add_comments(
+        &mut self,
+        comments: HashMap<String, Comment>,
+        correlation_id: Option<String>,
+    ) -> u32;
+
+    // Remove archived comments of deleted post
+    fn delete_archive(&mut self, correlation_id: Option<String>);
+}
+
+struct PostArchiveAgentImpl {
+    _id: String,
+    state: Option<PostArchive>,
+}
+
+#[agent_implementation]
+impl PostArchiveAgent for PostArchiveAgentImpl {
+    fn new(id: String) -> Self {
+        PostArchiveAgentImpl {
+            _id: id,
+            state: None,
         }
     }
 
-    fn remove_comment(&mut self, comment_id: String) -> Result<(), String> {
-        if self.state.is_none() {
-            Err("Post not exists".to_string())
-        } else {
-            self.with_state(|state| {
-                println!("remove comment - comment id: {}", comment_id);
-                state.remove_comment(comment_id)?;
-                TimelinesUpdaterAgentClient::get(state.created_by.clone())
-                    .trigger_post_updated(PostUpdate::from(state), false);
-                Ok(())
-            })
-        }
+    fn get_archive(&self) -> Option<PostArchive> {
+        self.state.clone()
     }
 
-    fn set_like(&mut self, user_id: String, like_type: LikeType) -> Result<(), String> {
-        if self.state.is_none() {
-            Err("Post not exists".to_string())
-        } else {
-            self.with_state(|state| {
-                println!("set like - user id: {}, like type: {}", user_id, like_type);
-                state.set_like(user_id, like_type);
-                Ok(())
-            })
-        }
+    fn get_comments(&self) -> HashMap<String, Comment> {
+        self.state
+            .as_ref()
+            .map(|archive| archive.comments.clone())
+            .unwrap_or_default()
     }
 
-    fn remove_like(&mut self, user_id: String) -> Result<(), String> {
-        if self.state.is_none() {
-            Err("Post not exists".to_string())
-        } else {
-            self.with_state(|state| {
-                println!("remove like - user id: {}", user_id);
-                state.remove_like(user_id);
-                Ok(())
-            })
-        }
+    fn get_comment(&self, comment_id: String) -> Option<Comment> {
+        self.state
+            .as_ref()
+            .and_then(|archive| archive.comments.get(&comment_id).cloned())
     }
 
-    fn set_comment_like(
+    fn add_comments(
         &mut self,
-        comment_id: String,
-        user_id: String,
-        like_type: LikeType,
-    ) -> Result<(), String> {
-        if self.state.is_none() {
-            Err("Post not exists".to_string())
-        } else {
-            self.with_state(|state| {
-                println!(
-                    "set comment like - comment id: {}, user id: {}, like type: {}",
-                    comment_id, user_id, like_type
-                );
-
-                state.set_comment_like(comment_id, user_id, like_type)
-            })
-        }
+        comments: HashMap<String, Comment>,
+        correlation_id: Option<String>,
+    ) -> u32 {
+        trace::init(correlation_id);
+        let archive = self.state.get_or_insert(PostArchive::new(self._id.clone()));
+        trace_println!(
+            "add comments - post id: {}, count: {}",
+            archive.post_id,
+            comments.len()
+        );
+        archive.comments.extend(comments);
+        archive.updated_at = clock::now();
+        archive.comments.len() as u32
     }
 
-    fn remove_comment_like(&mut self, comment_id: String, user_id: String) -> Result<(), String> {
-        if self.state.is_none() {
-            Err("Post not exists".to_string())
-        } else {
-            self.with_state(|state| {
-                println!(
-                    "remove comment like - comment id: {}, user id: {}",
-                    comment_id, user_id
-                );
-                state.remove_comment_like(comment_id, user_id)
-            })
-        }
+    fn delete_archive(&mut self, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        trace_println!("delete archive - post id: {}", self._id);
+        self.state = None;
     }
 
     async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
-        let data: Option<Post> = crate::common::snapshot::deserialize(&bytes)?;
+        let data: Option<PostArchive> = crate::common::snapshot::deserialize(&bytes)?;
         self.state = data;
         Ok(())
     }
@@ -390,14 +1891,32 @@ impl PostAgent for PostAgentImpl {
 #[derive(Schema, Clone, Serialize, Deserialize)]
 pub struct PostUpdate {
     pub post_id: String,
+    // beginning of post content, for muted keywords pre-filtering in timelines
+    #[serde(default)]
+    pub content_snippet: String,
+    // cached display name of author on post
+    #[serde(default)]
+    pub created_by_name: Option<String>,
+    #[serde(default)]
+    pub like_count: u32,
+    #[serde(default)]
+    pub comment_count: u32,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl PostUpdate {
-    fn from(value: &Post) -> Self {
+    pub(crate) fn from(value: &Post) -> Self {
         PostUpdate {
             post_id: value.post_id.clone(),
+            content_snippet: value
+                .content
+                .chars()
+                .take(CONTENT_SNIPPET_MAX_LENGTH)
+                .collect(),
+            created_by_name: value.created_by_name.clone(),
+            like_count: value.like_count,
+            comment_count: value.comment_count,
             created_at: value.created_at,
             updated_at: value.updated_at,
         }
@@ -414,7 +1933,7 @@ pub struct PostUpdates {
 
 impl PostUpdates {
     fn new(user_id: String) -> Self {
-        let now = chrono::Utc::now();
+        let now = clock::now();
         Self {
             user_id,
             updates: vec![],
@@ -430,9 +1949,17 @@ trait TimelinesUpdaterAgent {
 
     fn get_updates(&self) -> PostUpdates;
 
-    async fn post_updated(&mut self, update: PostUpdate, process_immediately: bool);
+    async fn post_updated(
+        &mut self,
+        update: PostUpdate,
+        process_immediately: bool,
+        correlation_id: Option<String>,
+    );
+
+    async fn process_posts_updates(&mut self, correlation_id: Option<String>);
 
-    async fn process_posts_updates(&mut self);
+    // Evict deleted post from user posts of the author and timelines of the author and connected users
+    async fn post_deleted(&mut self, post_id: String, correlation_id: Option<String>);
 }
 
 struct TimelinesUpdaterAgentImpl {
@@ -441,16 +1968,22 @@ struct TimelinesUpdaterAgentImpl {
 impl TimelinesUpdaterAgentImpl {
     async fn execute_posts_updates(&mut self) {
         if !self.state.updates.is_empty() {
-            execute_posts_updates(self.state.user_id.clone(), self.state.updates.clone()).await;
+            let user_id = self.state.user_id.clone();
+            let timer = SlowOperationTimer::start("posts updates", user_id.clone());
+            if let Some(downstream_agents) =
+                execute_posts_updates(&AgentClients, &user_id, &self.state.updates).await
+            {
+                timer.finish(downstream_agents).await;
+            }
             self.state.updates.clear();
-            self.state.updated_at = chrono::Utc::now();
+            self.state.updated_at = clock::now();
         }
     }
 
     fn add_update(&mut self, update: PostUpdate) {
         self.state.updates.retain(|x| x.post_id != update.post_id);
         self.state.updates.push(update);
-        self.state.updated_at = chrono::Utc::now();
+        self.state.updated_at = clock::now();
     }
 }
 
@@ -466,8 +1999,14 @@ impl TimelinesUpdaterAgent for TimelinesUpdaterAgentImpl {
         self.state.clone()
     }
 
-    async fn post_updated(&mut self, update: PostUpdate, process_immediately: bool) {
-        println!(
+    async fn post_updated(
+        &mut self,
+        update: PostUpdate,
+        process_immediately: bool,
+        correlation_id: Option<String>,
+    ) {
+        trace::init(correlation_id);
+        trace_println!(
             "post updates - user id: {}, post id: {}",
             self.state.user_id.clone(),
             update.post_id.clone()
@@ -475,7 +2014,7 @@ impl TimelinesUpdaterAgent for TimelinesUpdaterAgentImpl {
         self.add_update(update);
 
         if process_immediately {
-            println!(
+            trace_println!(
                 "post updates - user id: {}, updates: {} - processing ...",
                 self.state.user_id.clone(),
                 self.state.updates.len()
@@ -484,8 +2023,9 @@ impl TimelinesUpdaterAgent for TimelinesUpdaterAgentImpl {
         }
     }
 
-    async fn process_posts_updates(&mut self) {
-        println!(
+    async fn process_posts_updates(&mut self, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        trace_println!(
             "posts updates - user id: {}, updates: {} - processing ...",
             self.state.user_id.clone(),
             self.state.updates.len()
@@ -493,6 +2033,17 @@ impl TimelinesUpdaterAgent for TimelinesUpdaterAgentImpl {
         self.execute_posts_updates().await;
     }
 
+    async fn post_deleted(&mut self, post_id: String, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        trace_println!(
+            "post deleted - user id: {}, post id: {}",
+            self.state.user_id.clone(),
+            post_id.clone()
+        );
+        self.state.updates.retain(|x| x.post_id != post_id);
+        execute_post_deleted(&AgentClients, &self.state.user_id, &post_id).await;
+    }
+
     async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
         let data: PostUpdates = crate::common::snapshot::deserialize(&bytes)?;
         self.state = data;
@@ -504,103 +2055,449 @@ impl TimelinesUpdaterAgent for TimelinesUpdaterAgentImpl {
     }
 }
 
-async fn execute_posts_updates(user_id: String, updates: Vec<PostUpdate>) -> bool {
-    let user = UserAgentClient::get(user_id.clone()).get_user().await;
-
-    if let Some(user) = user {
-        let mut notify_user_ids: HashMap<String, UserConnectionType> = HashMap::new();
-
-        for (connected_user_id, connection) in user.connected_users {
-            if connection
-                .connection_types
-                .contains(&UserConnectionType::Friend)
-            {
-                notify_user_ids.insert(connected_user_id, UserConnectionType::Friend);
-            } else if connection
-                .connection_types
-                .contains(&UserConnectionType::Follower)
-            {
-                notify_user_ids.insert(connected_user_id, UserConnectionType::Follower);
-            }
+// Connected users notified about posts updates of user (friends and followers)
+fn get_notify_user_ids(user: &User) -> HashMap<String, UserConnectionType> {
+    let mut notify_user_ids: HashMap<String, UserConnectionType> = HashMap::new();
+
+    // updates of (shadow)banned user stay visible to the user only
+    if user.is_banned() || user.is_shadowbanned() {
+        return notify_user_ids;
+    }
+
+    for (connected_user_id, connection) in user.connected_users.iter() {
+        if connection
+            .connection_types
+            .contains(&UserConnectionType::Friend)
+        {
+            notify_user_ids.insert(connected_user_id.clone(), UserConnectionType::Friend);
+        } else if connection
+            .connection_types
+            .contains(&UserConnectionType::Follower)
+        {
+            notify_user_ids.insert(connected_user_id.clone(), UserConnectionType::Follower);
         }
-
-        println!(
-            "posts updates - user id: {user_id} - updates: {}, notify users: {}",
-            updates.len(),
-            notify_user_ids.len()
-        );
-        execute_posts_update(user_id.clone(), updates, notify_user_ids.clone());
-
-        true
-    } else {
-        println!("posts updates - user id: {user_id} - not found");
-        false
     }
+    notify_user_ids
 }
 
-fn execute_posts_update(
-    user_id: String,
-    updates: Vec<PostUpdate>,
+// Post refs of posts updates for timelines of the author and notified users
+fn get_timelines_posts_updates(
+    user_id: &str,
+    user_name: Option<String>,
+    updates: &[PostUpdate],
     notify_user_ids: HashMap<String, UserConnectionType>,
-) {
-    let user_updates = updates
-        .clone()
-        .into_iter()
-        .map(|update| {
-            PostRef::new(
-                update.post_id.clone(),
-                user_id.clone(),
-                update.created_at,
-                None,
-                update.updated_at,
-            )
-        })
-        .collect();
-
-    UserTimelineAgentClient::get(user_id.clone()).trigger_posts_updated(user_updates);
-
-    for (connected_user_id, connection_type) in notify_user_ids {
-        let user_updates = updates
-            .clone()
-            .into_iter()
+) -> Vec<(String, Vec<PostRef>)> {
+    let post_refs = |connection_type: Option<UserConnectionType>| {
+        updates
+            .iter()
             .map(|update| {
                 PostRef::new(
                     update.post_id.clone(),
-                    user_id.clone(),
+                    user_id.to_string(),
                     update.created_at,
-                    Some(connection_type.clone()),
+                    connection_type.clone(),
                     update.updated_at,
                 )
+                .with_counts(update.like_count, update.comment_count)
+                .with_content_snippet(update.content_snippet.clone())
+                .with_created_by_name(user_name.clone())
             })
-            .collect();
-        UserTimelineAgentClient::get(connected_user_id).trigger_posts_updated(user_updates);
+            .collect::<Vec<_>>()
+    };
+
+    let mut result = vec![(user_id.to_string(), post_refs(None))];
+    for (connected_user_id, connection_type) in notify_user_ids {
+        result.push((connected_user_id, post_refs(Some(connection_type))));
+    }
+    result
+}
+
+// Users with timelines where deleted post of user may be, the author and connected users
+fn get_post_deleted_notify_user_ids(user_id: &str, user: Option<&User>) -> HashSet<String> {
+    // post may be in timelines of all (also former) connections, eviction of missing post is no-op
+    let mut notify_user_ids: HashSet<String> = user
+        .map(|user| user.connected_users.keys().cloned().collect())
+        .unwrap_or_default();
+    notify_user_ids.insert(user_id.to_string());
+    notify_user_ids
+}
+
+// Calls of post flows to other agents
+pub(crate) trait PostClients {
+    async fn user_exists(&self, user_id: &str) -> bool;
+
+    async fn is_user_shadowbanned(&self, user_id: &str) -> bool;
+
+    async fn moderate_content(&self, content: String) -> Result<String, ContentRejected>;
+
+    async fn get_user(&self, user_id: &str) -> Option<User>;
+
+    fn index_post_hashtags(&self, post: &Post);
+
+    fn unindex_post_hashtags(&self, post: &Post);
+
+    fn add_explore_post(&self, post: &Post);
+
+    fn remove_explore_post(&self, post: &Post);
+
+    fn notify_user(
+        &self,
+        user_id: &str,
+        kind: NotificationKind,
+        created_by: &str,
+        target: NotificationTarget,
+        content: String,
+    );
+
+    fn delete_archive(&self, post_id: &str);
+
+    fn add_repost(&self, post_id: &str);
+
+    fn remove_repost(&self, post_id: &str);
+
+    fn set_author_name(&self, post_id: &str, name: Option<String>);
+
+    fn post_updated(&self, user_id: &str, update: PostUpdate, process_immediately: bool);
+
+    fn post_deleted(&self, user_id: &str, post_id: &str);
+
+    fn timeline_posts_updated(&self, user_id: &str, posts: Vec<PostRef>);
+
+    fn timeline_posts_removed(&self, user_id: &str, post_ids: Vec<String>);
+
+    fn user_posts_removed(&self, user_id: &str, post_ids: HashSet<String>);
+}
+
+impl PostClients for AgentClients {
+    async fn user_exists(&self, user_id: &str) -> bool {
+        user_exists(user_id).await
+    }
+
+    async fn is_user_shadowbanned(&self, user_id: &str) -> bool {
+        is_user_shadowbanned(user_id).await
+    }
+
+    async fn moderate_content(&self, content: String) -> Result<String, ContentRejected> {
+        moderate_content(content).await
+    }
+
+    async fn get_user(&self, user_id: &str) -> Option<User> {
+        UserAgentClient::get(user_id.to_string()).get_user().await
+    }
+
+    fn index_post_hashtags(&self, post: &Post) {
+        index_post_hashtags(post);
+    }
+
+    fn unindex_post_hashtags(&self, post: &Post) {
+        unindex_post_hashtags(post);
+    }
+
+    fn add_explore_post(&self, post: &Post) {
+        add_explore_post(post);
+    }
+
+    fn remove_explore_post(&self, post: &Post) {
+        remove_explore_post(post);
+    }
+
+    fn notify_user(
+        &self,
+        user_id: &str,
+        kind: NotificationKind,
+        created_by: &str,
+        target: NotificationTarget,
+        content: String,
+    ) {
+        notify_user(user_id, kind, created_by, target, content);
+    }
+
+    fn delete_archive(&self, post_id: &str) {
+        PostArchiveAgentClient::get(post_id.to_string()).trigger_delete_archive(trace::propagate());
+    }
+
+    fn add_repost(&self, post_id: &str) {
+        PostAgentClient::get(post_id.to_string()).trigger_add_repost(trace::propagate());
+    }
+
+    fn remove_repost(&self, post_id: &str) {
+        PostAgentClient::get(post_id.to_string()).trigger_remove_repost(trace::propagate());
+    }
+
+    fn set_author_name(&self, post_id: &str, name: Option<String>) {
+        PostAgentClient::get(post_id.to_string()).trigger_set_author_name(name, trace::propagate());
+    }
+
+    fn post_updated(&self, user_id: &str, update: PostUpdate, process_immediately: bool) {
+        TimelinesUpdaterAgentClient::get(user_id.to_string()).trigger_post_updated(
+            update,
+            process_immediately,
+            trace::propagate(),
+        );
+    }
+
+    fn post_deleted(&self, user_id: &str, post_id: &str) {
+        TimelinesUpdaterAgentClient::get(user_id.to_string())
+            .trigger_post_deleted(post_id.to_string(), trace::propagate());
+    }
+
+    fn timeline_posts_updated(&self, user_id: &str, posts: Vec<PostRef>) {
+        UserTimelineAgentClient::get(user_id.to_string())
+            .trigger_posts_updated(posts, trace::propagate());
+    }
+
+    fn timeline_posts_removed(&self, user_id: &str, post_ids: Vec<String>) {
+        UserTimelineAgentClient::get(user_id.to_string())
+            .trigger_posts_removed(post_ids, trace::propagate());
+    }
+
+    fn user_posts_removed(&self, user_id: &str, post_ids: HashSet<String>) {
+        UserPostsAgentClient::get(user_id.to_string())
+            .trigger_remove_posts(post_ids, trace::propagate());
+    }
+}
+
+// Init post of user (PostAgent::init_post), post update is processed immediately
+pub(crate) async fn init_post(
+    clients: &impl PostClients,
+    state: &mut Option<Post>,
+    post_id: &str,
+    user_id: String,
+    content: String,
+    location: Option<Location>,
+    attachments: Vec<MediaMetadata>,
+) -> Result<(), PostError> {
+    let content = content::sanitize(&content);
+    if state.is_some() {
+        return Err(PostError::AlreadyExists("Post already exists".to_string()));
+    } else if content.is_empty() {
+        return Err(PostError::invalid("Post content is empty"));
+    }
+    check_length(ContentField::PostContent, &content)?;
+    let location = location
+        .map(|l| l.validated())
+        .transpose()
+        .map_err(PostError::Invalid)?;
+    validate_attachments(&attachments)?;
+    check_user_exists(clients, &user_id).await?;
+    let content = clients.moderate_content(content).await?;
+    let shadowbanned = clients.is_user_shadowbanned(&user_id).await;
+    let mentioned_user_ids = if shadowbanned {
+        vec![]
+    } else {
+        get_mentioned_users(clients, content::extract_mentions(&content), &user_id).await
+    };
+    let state = state.get_or_insert(Post::new(post_id.to_string()));
+    trace_println!("init post - user id: {user_id}, content: {content}");
+    state.init(user_id.clone(), content, location);
+    state.attachments = attachments;
+
+    // posts of shadowbanned user are not distributed to hashtag feeds and explore
+    if !shadowbanned {
+        clients.index_post_hashtags(state);
+        clients.add_explore_post(state);
+    }
+    for mentioned_user_id in mentioned_user_ids {
+        clients.notify_user(
+            &mentioned_user_id,
+            NotificationKind::Mention,
+            &user_id,
+            NotificationTarget::post(&state.post_id),
+            state.content.clone(),
+        );
+    }
+
+    clients.post_updated(&user_id, PostUpdate::from(state), true);
+
+    Ok(())
+}
+
+// Init repost of user (PostAgent::init_repost), repost count of the original post is updated
+pub(crate) async fn init_repost(
+    clients: &impl PostClients,
+    state: &mut Option<Post>,
+    post_id: &str,
+    user_id: String,
+    reposted_from: String,
+    content: String,
+) -> Result<(), PostError> {
+    let content = content::sanitize(&content);
+    if state.is_some() {
+        return Err(PostError::AlreadyExists("Post already exists".to_string()));
+    }
+    check_length(ContentField::PostContent, &content)?;
+    check_user_exists(clients, &user_id).await?;
+    let content = if content.is_empty() {
+        content
+    } else {
+        clients.moderate_content(content).await?
+    };
+    let shadowbanned = clients.is_user_shadowbanned(&user_id).await;
+    let state = state.get_or_insert(Post::new(post_id.to_string()));
+    trace_println!("init repost - user id: {user_id}, reposted from: {reposted_from}");
+    state.init_repost(user_id.clone(), reposted_from.clone(), content);
+
+    if !shadowbanned {
+        clients.index_post_hashtags(state);
+    }
+    clients.add_repost(&reposted_from);
+
+    // reposts are delivered to timelines as regular posts
+    clients.post_updated(&user_id, PostUpdate::from(state), true);
+
+    Ok(())
+}
+
+// Delete post by the author (PostAgent::delete_post)
+pub(crate) fn delete_post(
+    clients: &impl PostClients,
+    state: &mut Option<Post>,
+    acting_user_id: String,
+) -> Result<(), PostError> {
+    let Some(state) = state.as_mut() else {
+        return Err(PostError::not_found("Post not exists"));
+    };
+    trace_println!(
+        "delete post - user id: {}, acting user id: {acting_user_id}",
+        state.created_by
+    );
+    if state.created_by != acting_user_id {
+        return Err(PostError::Unauthorized(acting_user_id));
+    }
+    clients.unindex_post_hashtags(state);
+    clients.remove_explore_post(state);
+    if state.archived_comment_count > 0 {
+        clients.delete_archive(&state.post_id);
+    }
+    // deleted post is evicted from user posts and timelines
+    if state.delete() {
+        if let Some(reposted_from) = &state.reposted_from {
+            clients.remove_repost(reposted_from);
+        }
+        clients.post_deleted(&state.created_by, &state.post_id);
+    }
+    Ok(())
+}
+
+pub(crate) fn set_author_name(state: &mut Option<Post>, name: Option<String>) {
+    if let Some(state) = state.as_mut() {
+        if state.deleted_at.is_none() && state.created_by_name != name {
+            trace_println!("set author name - user id: {}", state.created_by);
+            state.created_by_name = name;
+        }
+    }
+}
+
+pub(crate) fn add_repost(state: &mut Option<Post>) {
+    if let Some(state) = state.as_mut().filter(|p| p.deleted_at.is_none()) {
+        trace_println!("add repost - count: {}", state.repost_count + 1);
+        state.add_repost();
     }
 }
 
-pub async fn fetch_posts_by_ids(post_ids: &[String]) -> Vec<Post> {
-    let mut result: Vec<Post> = vec![];
+pub(crate) fn remove_repost(state: &mut Option<Post>) {
+    if let Some(state) = state.as_mut().filter(|p| p.deleted_at.is_none()) {
+        trace_println!("remove repost - count: {}", state.repost_count);
+        state.remove_repost();
+    }
+}
+
+// Distribute posts updates of user to timelines (TimelinesUpdaterAgent),
+// returns number of downstream agents, none if user not exists
+pub(crate) async fn execute_posts_updates(
+    clients: &impl PostClients,
+    user_id: &str,
+    updates: &[PostUpdate],
+) -> Option<usize> {
+    let Some(user) = clients.get_user(user_id).await else {
+        trace_println!("posts updates - user id: {user_id} - not found");
+        return None;
+    };
+    let notify_user_ids = get_notify_user_ids(&user);
+
+    trace_println!(
+        "posts updates - user id: {user_id} - updates: {}, notify users: {}",
+        updates.len(),
+        notify_user_ids.len()
+    );
+
+    // author name cached on posts is refreshed if changed
+    for update in updates.iter().filter(|u| u.created_by_name != user.name) {
+        clients.set_author_name(&update.post_id, user.name.clone());
+    }
+
+    // user, own timeline and timelines of connected users
+    let downstream_agents = notify_user_ids.len() + 2;
+    for (timeline_user_id, posts) in
+        get_timelines_posts_updates(user_id, user.name, updates, notify_user_ids)
+    {
+        clients.timeline_posts_updated(&timeline_user_id, posts);
+    }
+    Some(downstream_agents)
+}
+
+// Evict deleted post from user posts and timelines (TimelinesUpdaterAgent)
+pub(crate) async fn execute_post_deleted(clients: &impl PostClients, user_id: &str, post_id: &str) {
+    let user = clients.get_user(user_id).await;
+    let notify_user_ids = get_post_deleted_notify_user_ids(user_id, user.as_ref());
+
+    clients.user_posts_removed(user_id, HashSet::from([post_id.to_string()]));
+
+    trace_println!(
+        "post deleted - user id: {user_id}, post id: {post_id}, notify users: {}",
+        notify_user_ids.len()
+    );
+
+    for notify_user_id in notify_user_ids {
+        clients.timeline_posts_removed(&notify_user_id, vec![post_id.to_string()]);
+    }
+}
 
-    for chunk in post_ids.chunks(10) {
+pub async fn fetch_posts_by_ids(post_ids: &[String]) -> Fetched<Post> {
+    let mut result: Fetched<Post> = Fetched::default();
+
+    for chunk in post_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
         let clients = chunk
             .iter()
             .map(|post_id| PostAgentClient::get(post_id.clone()))
             .collect::<Vec<_>>();
 
         let tasks: Vec<_> = clients.iter().map(|client| client.get_post()).collect();
-        let responses = join_all(tasks).await;
+        result.extend(join_all_with_timeout(chunk, tasks).await);
+    }
+
+    result
+}
+
+pub async fn fetch_posts_by_ids_and_query(
+    post_ids: &[String],
+    query: query::Query,
+) -> Fetched<Post> {
+    let mut result: Fetched<Post> = Fetched::default();
 
-        let chunk_result: Vec<Post> = responses.into_iter().flatten().collect();
+    for chunk in post_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
+        let clients = chunk
+            .iter()
+            .map(|post_id| PostAgentClient::get(post_id.clone()))
+            .collect::<Vec<_>>();
 
-        result.extend(chunk_result);
+        let tasks: Vec<_> = clients
+            .iter()
+            .map(|client| client.get_post_if_match(query.clone()))
+            .collect();
+        result.extend(join_all_with_timeout(chunk, tasks).await);
     }
 
     result
 }
 
-pub async fn fetch_posts_by_ids_and_query(post_ids: &[String], query: query::Query) -> Vec<Post> {
-    let mut result: Vec<Post> = vec![];
+pub async fn fetch_post_summaries_by_ids(
+    post_ids: &[String],
+    user_id: Option<String>,
+) -> Fetched<PostSummary> {
+    let mut result: Fetched<PostSummary> = Fetched::default();
 
-    for chunk in post_ids.chunks(10) {
+    for chunk in post_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
         let clients = chunk
             .iter()
             .map(|post_id| PostAgentClient::get(post_id.clone()))
@@ -608,13 +2505,32 @@ pub async fn fetch_posts_by_ids_and_query(post_ids: &[String], query: query::Que
 
         let tasks: Vec<_> = clients
             .iter()
-            .map(|client| client.get_post_if_match(query.clone()))
+            .map(|client| client.get_post_summary(user_id.clone()))
             .collect();
-        let responses = join_all(tasks).await;
+        result.extend(join_all_with_timeout(chunk, tasks).await);
+    }
+
+    result
+}
+
+pub async fn fetch_post_summaries_by_ids_and_query(
+    post_ids: &[String],
+    user_id: Option<String>,
+    query: query::Query,
+) -> Fetched<PostSummary> {
+    let mut result: Fetched<PostSummary> = Fetched::default();
 
-        let chunk_result: Vec<Post> = responses.into_iter().flatten().collect();
+    for chunk in post_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
+        let clients = chunk
+            .iter()
+            .map(|post_id| PostAgentClient::get(post_id.clone()))
+            .collect::<Vec<_>>();
 
-        result.extend(chunk_result);
+        let tasks: Vec<_> = clients
+            .iter()
+            .map(|client| client.get_post_summary_if_match(user_id.clone(), query.clone()))
+            .collect();
+        result.extend(join_all_with_timeout(chunk, tasks).await);
     }
 
     result
@@ -623,8 +2539,83 @@ pub async fn fetch_posts_by_ids_and_query(post_ids: &[String], query: query::Que
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::snapshot;
     use crate::common::LikeType;
 
+    #[test]
+    fn test_delete_post() {
+        let mut post = create_test_post();
+        post.set_like("user-2".to_string(), LikeType::Like);
+        post.add_comment("user-2".to_string(), "comment".to_string(), None, None)
+            .unwrap();
+
+        assert!(post.delete());
+        assert!(post.deleted_at.is_some());
+        assert!(post.content.is_empty());
+        assert!(post.likes.is_empty());
+        assert!(post.comments.is_empty());
+
+        assert!(!post.delete());
+    }
+
+    #[test]
+    fn test_repost() {
+        let mut repost = Post::new("repost-1".to_string());
+        repost.init_repost(
+            "user-2".to_string(),
+            "test-post-1".to_string(),
+            "".to_string(),
+        );
+        assert_eq!(repost.reposted_from, Some("test-post-1".to_string()));
+        assert_eq!(repost.created_by, "user-2");
+
+        // reference to original post is kept on delete
+        assert!(repost.delete());
+        assert_eq!(repost.reposted_from, Some("test-post-1".to_string()));
+
+        let mut post = create_test_post();
+        post.add_repost();
+        post.add_repost();
+        assert_eq!(post.repost_count, 2);
+        post.remove_repost();
+        post.remove_repost();
+        post.remove_repost();
+        assert_eq!(post.repost_count, 0);
+    }
+
+    #[test]
+    fn test_post_error_not_allowed() {
+        let error = PostError::NotAllowed("user-1".to_string());
+        assert_eq!(error.to_string(), "User not allowed: user-1");
+    }
+
+    #[test]
+    fn test_post_error() {
+        let error = PostError::not_found("Post not exists");
+        assert_eq!(error, PostError::NotFound("Post not exists".to_string()));
+        assert_eq!(error.to_string(), "Post not exists");
+
+        let error = PostError::UnknownUser("user-1".to_string());
+        assert_eq!(error.to_string(), "Unknown user: user-1");
+
+        let error: SocialNetError = PostError::UnknownUser("user-1".to_string()).into();
+        assert_eq!(
+            error,
+            SocialNetError::NotFound("Unknown user: user-1".to_string())
+        );
+
+        let error: PostError = RateLimited {
+            action: RateLimitAction::Comment,
+            limit: 100,
+            retry_after_seconds: 60,
+        }
+        .into();
+        assert_eq!(
+            error.to_string(),
+            "Rate limit of comment actions exceeded (max: 100 per hour), retry after 60 seconds"
+        );
+    }
+
     fn create_test_post() -> Post {
         let mut post = Post::new("test-post-1".to_string());
         post.created_by = "user1".to_string();
@@ -650,7 +2641,7 @@ mod tests {
 
         let result = post.set_like("user2".to_string(), LikeType::Like);
 
-        assert!(!result); // First time like, returns false (no previous like)
+        assert!(result); // First time like, returns true (no previous like)
         assert_eq!(post.likes.len(), 1);
         assert_eq!(post.likes.get("user2"), Some(&LikeType::Like));
         assert!(post.updated_at > initial_updated_at);
@@ -667,7 +2658,7 @@ mod tests {
         // Override with different like type
         let result = post.set_like("user2".to_string(), LikeType::Love);
 
-        assert!(result); // Override, returns true (previous like existed)
+        assert!(!result); // Override, returns false (previous like existed)
         assert_eq!(post.likes.len(), 1);
         assert_eq!(post.likes.get("user2"), Some(&LikeType::Love));
         assert!(post.updated_at > initial_updated_at);
@@ -692,16 +2683,81 @@ mod tests {
     }
 
     #[test]
-    fn test_remove_like_not_found() {
+    fn test_remove_like_not_found() {
+        let mut post = create_test_post();
+        let initial_updated_at = post.updated_at;
+
+        // Try to remove non-existent like
+        let result = post.remove_like("user2".to_string());
+
+        assert!(!result);
+        assert_eq!(post.likes.len(), 0);
+        assert_eq!(post.updated_at, initial_updated_at);
+    }
+
+    #[test]
+    fn test_counts_maintained() {
+        let mut post = create_test_post();
+
+        post.set_like("user2".to_string(), LikeType::Like);
+        post.set_like("user3".to_string(), LikeType::Love);
+        post.set_like("user2".to_string(), LikeType::Funny);
+        let parent_id = post
+            .add_comment("user2".to_string(), "Parent".to_string(), None, None)
+            .unwrap();
+        post.add_comment(
+            "user3".to_string(),
+            "Child".to_string(),
+            Some(parent_id.clone()),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(post.like_count, 2);
+        assert_eq!(post.comment_count, 2);
+
+        post.remove_like("user3".to_string());
+        post.remove_comment(parent_id).unwrap();
+
+        assert_eq!(post.like_count, 1);
+        assert_eq!(post.comment_count, 0);
+
+        post.delete();
+        assert_eq!(post.like_count, 0);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut post = create_test_post();
+        post.set_like("user2".to_string(), LikeType::Love);
+        post.add_comment("user3".to_string(), "Comment".to_string(), None, None)
+            .unwrap();
+
+        let summary = post.summary(Some("user2"));
+        assert_eq!(summary.post_id, post.post_id);
+        assert_eq!(summary.content, post.content);
+        assert_eq!(summary.like_count, 1);
+        assert_eq!(summary.comment_count, 1);
+        assert_eq!(summary.own_like, Some(LikeType::Love));
+
+        assert_eq!(post.summary(Some("user3")).own_like, None);
+        assert_eq!(post.summary(None).own_like, None);
+    }
+
+    #[test]
+    fn test_author_name_in_summary_and_update() {
         let mut post = create_test_post();
-        let initial_updated_at = post.updated_at;
-
-        // Try to remove non-existent like
-        let result = post.remove_like("user2".to_string());
+        assert_eq!(post.summary(None).created_by_name, None);
 
-        assert!(!result);
-        assert_eq!(post.likes.len(), 0);
-        assert_eq!(post.updated_at, initial_updated_at);
+        post.created_by_name = Some("User One".to_string());
+        assert_eq!(
+            post.summary(None).created_by_name,
+            Some("User One".to_string())
+        );
+        assert_eq!(
+            PostUpdate::from(&post).created_by_name,
+            Some("User One".to_string())
+        );
     }
 
     #[test]
@@ -710,7 +2766,7 @@ mod tests {
         let initial_updated_at = post.updated_at;
 
         // Add root comment
-        let result = post.add_comment("user2".to_string(), "Great post!".to_string(), None);
+        let result = post.add_comment("user2".to_string(), "Great post!".to_string(), None, None);
 
         assert!(result.is_ok());
         let comment_id = result.unwrap();
@@ -730,7 +2786,12 @@ mod tests {
 
         // Add parent comment first
         let parent_id = post
-            .add_comment("user2".to_string(), "Parent comment".to_string(), None)
+            .add_comment(
+                "user2".to_string(),
+                "Parent comment".to_string(),
+                None,
+                None,
+            )
             .unwrap();
 
         // Add child comment
@@ -738,6 +2799,7 @@ mod tests {
             "user3".to_string(),
             "Child comment".to_string(),
             Some(parent_id.clone()),
+            None,
         );
 
         assert!(result.is_ok());
@@ -749,6 +2811,124 @@ mod tests {
         assert_eq!(child_comment.parent_comment_id, Some(parent_id));
     }
 
+    #[test]
+    fn test_take_archivable_comments() {
+        let mut post = create_test_post();
+        let now = clock::now();
+        let cutoff = archive_cutoff(now);
+
+        let old_id = post
+            .add_comment("user2".to_string(), "Old thread".to_string(), None, None)
+            .unwrap();
+        let old_reply_id = post
+            .add_comment(
+                "user3".to_string(),
+                "Old reply".to_string(),
+                Some(old_id.clone()),
+                None,
+            )
+            .unwrap();
+        let active_id = post
+            .add_comment("user2".to_string(), "Active thread".to_string(), None, None)
+            .unwrap();
+        let active_reply_id = post
+            .add_comment(
+                "user3".to_string(),
+                "Old reply".to_string(),
+                Some(active_id.clone()),
+                None,
+            )
+            .unwrap();
+        for id in [&old_id, &old_reply_id, &active_id] {
+            post.comments.get_mut(id).unwrap().updated_at = cutoff - chrono::Duration::days(1);
+        }
+        // recent reply keeps whole thread in post
+        post.comments.get_mut(&active_reply_id).unwrap().updated_at = now;
+
+        assert_eq!(
+            post.archivable_comment_ids(cutoff),
+            HashSet::from([old_id.clone(), old_reply_id.clone()])
+        );
+
+        let archived = post.take_archivable_comments(cutoff);
+        assert_eq!(archived.len(), 2);
+        assert!(archived.contains_key(&old_reply_id));
+        assert_eq!(post.comments.len(), 2);
+        assert_eq!(post.archived_comment_count, 2);
+        assert_eq!(post.comment_count, 4);
+
+        assert!(post.delete());
+        assert_eq!(post.comment_count, 0);
+    }
+
+    #[test]
+    fn test_comments_page_with_archived_comments() {
+        let mut post = create_test_post();
+        let now = clock::now();
+        let cutoff = archive_cutoff(now);
+
+        let mut comment_ids = vec![];
+        for i in 0..4 {
+            let comment_id = post
+                .add_comment("user2".to_string(), format!("Comment {i}"), None, None)
+                .unwrap();
+            let comment = post.comments.get_mut(&comment_id).unwrap();
+            comment.created_at = cutoff - chrono::Duration::days(10 - i);
+            comment.updated_at = if i < 2 { comment.created_at } else { now };
+            comment_ids.push(comment_id);
+        }
+        let archived = post.take_archivable_comments(cutoff);
+        assert_eq!(archived.len(), 2);
+        assert_eq!(
+            post.archived_comment_newest_at,
+            Some(archived[&comment_ids[1]].created_at)
+        );
+
+        let page_archived = |cursor: Option<String>, sort: CommentSort| {
+            let page = post
+                .get_comments_page(cursor.clone(), Some(1), sort.clone())
+                .unwrap();
+            (post.comments_page_archived(cursor, &page, &sort), page)
+        };
+
+        // newest kept comments are paged without archive, until kept comments are exhausted
+        let (is_archived, page) = page_archived(None, CommentSort::NewestFirst);
+        assert!(!is_archived);
+        let (is_archived, _) = page_archived(page.next_cursor, CommentSort::NewestFirst);
+        assert!(is_archived);
+        assert!(page_archived(None, CommentSort::OldestFirst).0);
+        assert!(page_archived(None, CommentSort::MostLiked).0);
+
+        // page computed from all comments
+        let mut all = post.clone();
+        all.comments.extend(archived.clone());
+        let page = all
+            .get_comments_page(None, Some(10), CommentSort::OldestFirst)
+            .unwrap();
+        let ids: Vec<String> = page.comments.into_iter().map(|c| c.comment_id).collect();
+        assert_eq!(ids, comment_ids);
+
+        // reply to archived comment
+        assert_eq!(
+            post.add_comment(
+                "user3".to_string(),
+                "Reply".to_string(),
+                Some(comment_ids[0].clone()),
+                None
+            ),
+            Err(PostError::not_found("Parent comment not found"))
+        );
+        assert!(post
+            .add_comment(
+                "user3".to_string(),
+                "Reply".to_string(),
+                Some(comment_ids[0].clone()),
+                archived.get(&comment_ids[0])
+            )
+            .is_ok());
+        assert_eq!(post.comment_count, 5);
+    }
+
     #[test]
     fn test_add_comment_parent_not_found() {
         let mut post = create_test_post();
@@ -758,10 +2938,14 @@ mod tests {
             "user2".to_string(),
             "Orphan comment".to_string(),
             Some("non-existent".to_string()),
+            None,
         );
 
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Parent comment not found");
+        assert_eq!(
+            result.unwrap_err(),
+            PostError::not_found("Parent comment not found")
+        );
         assert_eq!(post.comments.len(), 0);
     }
 
@@ -771,7 +2955,7 @@ mod tests {
 
         // Add a comment first
         let comment_id = post
-            .add_comment("user2".to_string(), "Test comment".to_string(), None)
+            .add_comment("user2".to_string(), "Test comment".to_string(), None, None)
             .unwrap();
         assert_eq!(post.comments.len(), 1);
 
@@ -785,6 +2969,344 @@ mod tests {
         assert!(post.updated_at > initial_updated_at);
     }
 
+    #[test]
+    fn test_check_acting_user() {
+        assert!(check_acting_user("user1", "user1").is_ok());
+        assert_eq!(
+            check_acting_user("user2", "user1"),
+            Err(PostError::Unauthorized("user2".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_set_highlight() {
+        let mut post = create_test_post();
+        let comment_id = post
+            .add_comment("user2".to_string(), "Answer".to_string(), None, None)
+            .unwrap();
+
+        assert!(post
+            .set_highlight(Some("non-existent".to_string()))
+            .is_err());
+        assert!(post.set_highlight(Some(comment_id.clone())).is_ok());
+        assert_eq!(post.highlighted_comment_id, Some(comment_id.clone()));
+        assert!(post.set_highlight(None).is_ok());
+        assert_eq!(post.highlighted_comment_id, None);
+
+        // highlight is cleared, when comment is removed
+        post.set_highlight(Some(comment_id.clone())).unwrap();
+        post.remove_comment(comment_id).unwrap();
+        assert_eq!(post.highlighted_comment_id, None);
+    }
+
+    #[test]
+    fn test_get_comments_page_highlight_first() {
+        let mut post = create_test_post();
+        let mut comment_ids = vec![];
+        for i in 0..5 {
+            let comment_id = post
+                .add_comment("user2".to_string(), format!("Comment {i}"), None, None)
+                .unwrap();
+            post.comments.get_mut(&comment_id).unwrap().created_at =
+                clock::now() + chrono::Duration::seconds(i);
+            comment_ids.push(comment_id);
+        }
+        post.set_highlight(Some(comment_ids[3].clone())).unwrap();
+
+        let page = post
+            .get_comments_page(None, Some(2), CommentSort::OldestFirst)
+            .unwrap();
+        assert_eq!(page.highlighted_comment_id, Some(comment_ids[3].clone()));
+        let ids: Vec<String> = page.comments.iter().map(|c| c.comment_id.clone()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                comment_ids[3].clone(),
+                comment_ids[0].clone(),
+                comment_ids[1].clone()
+            ]
+        );
+
+        let page = post
+            .get_comments_page(page.next_cursor, Some(2), CommentSort::OldestFirst)
+            .unwrap();
+        let ids: Vec<String> = page.comments.iter().map(|c| c.comment_id.clone()).collect();
+        assert_eq!(ids, vec![comment_ids[2].clone(), comment_ids[4].clone()]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_get_comments_page_sort() {
+        let mut post = create_test_post();
+        let mut comment_ids = vec![];
+        for i in 0..5 {
+            let comment_id = post
+                .add_comment("user2".to_string(), format!("Comment {i}"), None, None)
+                .unwrap();
+            post.comments.get_mut(&comment_id).unwrap().created_at =
+                clock::now() + chrono::Duration::seconds(i);
+            comment_ids.push(comment_id);
+        }
+        for user_id in ["user3", "user4"] {
+            post.set_comment_like(comment_ids[2].clone(), user_id.to_string(), LikeType::Like)
+                .unwrap();
+        }
+        post.set_comment_like(comment_ids[4].clone(), "user3".to_string(), LikeType::Like)
+            .unwrap();
+
+        let page_ids = |sort: CommentSort| {
+            let mut ids: Vec<String> = vec![];
+            let mut cursor = None;
+            loop {
+                let page = post
+                    .get_comments_page(cursor, Some(2), sort.clone())
+                    .unwrap();
+                ids.extend(page.comments.into_iter().map(|c| c.comment_id));
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => return ids,
+                }
+            }
+        };
+
+        let ids = |indexes: &[usize]| -> Vec<String> {
+            indexes.iter().map(|i| comment_ids[*i].clone()).collect()
+        };
+        assert_eq!(page_ids(CommentSort::NewestFirst), ids(&[4, 3, 2, 1, 0]));
+        assert_eq!(page_ids(CommentSort::MostLiked), ids(&[2, 4, 0, 1, 3]));
+        assert_eq!(page_ids(CommentSort::OldestFirst), ids(&[0, 1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn test_get_comments_page_same_created_at() {
+        let mut post = create_test_post();
+        let created_at = clock::now();
+        let mut comment_ids = vec![];
+        for i in 0..2 {
+            let comment_id = post
+                .add_comment("user2".to_string(), format!("Comment {i}"), None, None)
+                .unwrap();
+            post.comments.get_mut(&comment_id).unwrap().created_at = created_at;
+            comment_ids.push(comment_id);
+        }
+        comment_ids.sort();
+
+        for (sort, expected) in [
+            (CommentSort::OldestFirst, comment_ids.clone()),
+            (
+                CommentSort::NewestFirst,
+                comment_ids.iter().rev().cloned().collect(),
+            ),
+        ] {
+            let page = post.get_comments_page(None, Some(1), sort.clone()).unwrap();
+            let next_page = post
+                .get_comments_page(page.next_cursor, Some(1), sort)
+                .unwrap();
+            let ids: Vec<String> = page
+                .comments
+                .into_iter()
+                .chain(next_page.comments)
+                .map(|c| c.comment_id)
+                .collect();
+            assert_eq!(ids, expected);
+            assert_eq!(next_page.next_cursor, None);
+        }
+    }
+
+    #[test]
+    fn test_archivable_comment_ids_keep_highlighted_thread() {
+        let mut post = create_test_post();
+        let comment_id = post
+            .add_comment("user2".to_string(), "Answer".to_string(), None, None)
+            .unwrap();
+        let cutoff = clock::now() + chrono::Duration::seconds(1);
+        assert_eq!(post.archivable_comment_ids(cutoff).len(), 1);
+
+        post.set_highlight(Some(comment_id)).unwrap();
+        assert!(post.archivable_comment_ids(cutoff).is_empty());
+    }
+
+    #[test]
+    fn test_soft_remove_comment() {
+        let mut post = create_test_post();
+        let parent_id = post
+            .add_comment(
+                "user2".to_string(),
+                "Parent comment".to_string(),
+                None,
+                None,
+            )
+            .unwrap();
+        let child_id = post
+            .add_comment(
+                "user3".to_string(),
+                "Child comment".to_string(),
+                Some(parent_id.clone()),
+                None,
+            )
+            .unwrap();
+        post.set_comment_like(parent_id.clone(), "user3".to_string(), LikeType::Like)
+            .unwrap();
+
+        // comment with replies is replaced with placeholder
+        assert!(post.soft_remove_comment(parent_id.clone()).is_ok());
+        assert_eq!(post.comments.len(), 2);
+        let parent = post.comments.get(&parent_id).unwrap();
+        assert_eq!(parent.content, DELETED_COMMENT_CONTENT);
+        assert!(parent.likes.is_empty());
+        assert!(parent.deleted_at.is_some());
+        assert!(post.comments.contains_key(&child_id));
+
+        // deleted comment can not be replied or liked
+        assert_eq!(
+            post.add_comment(
+                "user3".to_string(),
+                "Reply".to_string(),
+                Some(parent_id.clone()),
+                None
+            ),
+            Err(PostError::not_found("Parent comment deleted"))
+        );
+        assert!(post
+            .set_comment_like(parent_id.clone(), "user3".to_string(), LikeType::Like)
+            .is_err());
+
+        // repeated soft delete is no-op
+        assert!(post.soft_remove_comment(parent_id.clone()).is_ok());
+
+        // comment without replies is removed
+        assert!(post.soft_remove_comment(child_id.clone()).is_ok());
+        assert!(!post.comments.contains_key(&child_id));
+        assert_eq!(post.comments.len(), 1);
+
+        // hard delete removes placeholder
+        assert!(post.remove_comment(parent_id).is_ok());
+        assert!(post.comments.is_empty());
+
+        assert!(post
+            .soft_remove_comment("non-existent".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn test_edit_comment() {
+        let mut post = create_test_post();
+        let comment_id = post
+            .add_comment("user2".to_string(), "Original".to_string(), None, None)
+            .unwrap();
+        let created_at = post.comments.get(&comment_id).unwrap().created_at;
+
+        assert_eq!(
+            post.edit_comment(&comment_id, "user3", "Hijacked".to_string()),
+            Err(PostError::Unauthorized("user3".to_string()))
+        );
+        // post author can remove, but not edit comments of other users
+        assert_eq!(
+            post.edit_comment(&comment_id, &post.created_by.clone(), "Edited".to_string()),
+            Err(PostError::Unauthorized(post.created_by.clone()))
+        );
+        assert!(!post.comments.get(&comment_id).unwrap().edited);
+
+        assert!(post
+            .edit_comment(&comment_id, "user2", "Edited".to_string())
+            .is_ok());
+        let comment = post.comments.get(&comment_id).unwrap();
+        assert_eq!(comment.content, "Edited");
+        assert!(comment.edited);
+        assert!(comment.updated_at >= created_at);
+        assert_eq!(post.updated_at, comment.updated_at);
+
+        assert_eq!(
+            post.edit_comment("missing", "user2", "Edited".to_string()),
+            Err(PostError::not_found("Comment not found"))
+        );
+        post.soft_remove_comment(comment_id.clone()).unwrap();
+        assert!(post
+            .edit_comment(&comment_id, "user2", "Edited again".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn test_mentions() {
+        let mut post = Post::new("post1".to_string());
+        post.init(
+            "user1".to_string(),
+            "Hello @user2 and @user3".to_string(),
+            None,
+        );
+        assert_eq!(post.mentions, vec!["user2", "user3"]);
+
+        let comment_id = post
+            .add_comment("user2".to_string(), "Thanks @user1".to_string(), None, None)
+            .unwrap();
+        assert_eq!(post.comments[&comment_id].mentions, vec!["user1"]);
+        post.edit_comment(&comment_id, "user2", "Thanks @user4".to_string())
+            .unwrap();
+        assert_eq!(post.comments[&comment_id].mentions, vec!["user4"]);
+
+        post.delete();
+        assert!(post.mentions.is_empty());
+    }
+
+    #[test]
+    fn test_edit_comment_updated_at() {
+        let clock = clock::freeze(chrono::Utc::now());
+        let mut post = create_test_post();
+        let comment_id = post
+            .add_comment("user2".to_string(), "Original".to_string(), None, None)
+            .unwrap();
+        let created_at = post.comments.get(&comment_id).unwrap().created_at;
+
+        clock.advance(chrono::Duration::minutes(5));
+        post.edit_comment(&comment_id, "user2", "Edited".to_string())
+            .unwrap();
+
+        let comment = post.comments.get(&comment_id).unwrap();
+        assert_eq!(comment.created_at, created_at);
+        assert_eq!(
+            comment.updated_at,
+            created_at + chrono::Duration::minutes(5)
+        );
+        assert_eq!(post.updated_at, comment.updated_at);
+    }
+
+    #[test]
+    fn test_comment_ids_from_id_generator() {
+        id::set_id_generator(std::rc::Rc::new(id::SequentialIdGenerator::new("comment")));
+        let mut post = create_test_post();
+        let first = post
+            .add_comment("user2".to_string(), "First".to_string(), None, None)
+            .unwrap();
+        let second = post
+            .add_comment(
+                "user2".to_string(),
+                "Second".to_string(),
+                Some(first.clone()),
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(first, "comment-1");
+        assert_eq!(second, "comment-2");
+        assert_eq!(
+            post.comments.get(&second).unwrap().parent_comment_id,
+            Some("comment-1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_can_remove_comment() {
+        let mut post = create_test_post();
+        let comment_id = post
+            .add_comment("user2".to_string(), "Test comment".to_string(), None, None)
+            .unwrap();
+
+        assert!(post.can_remove_comment(&comment_id, "user2"));
+        assert!(post.can_remove_comment(&comment_id, "user1"));
+        assert!(!post.can_remove_comment(&comment_id, "user3"));
+        assert!(!post.can_remove_comment("non-existent", "user3"));
+    }
+
     #[test]
     fn test_remove_comment_not_found() {
         let mut post = create_test_post();
@@ -794,7 +3316,10 @@ mod tests {
         let result = post.remove_comment("non-existent".to_string());
 
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Comment not found");
+        assert_eq!(
+            result.unwrap_err(),
+            PostError::not_found("Comment not found")
+        );
         assert_eq!(post.comments.len(), 0);
         assert_eq!(post.updated_at, initial_updated_at);
     }
@@ -805,7 +3330,12 @@ mod tests {
 
         // Add parent comment
         let parent_id = post
-            .add_comment("user2".to_string(), "Parent comment".to_string(), None)
+            .add_comment(
+                "user2".to_string(),
+                "Parent comment".to_string(),
+                None,
+                None,
+            )
             .unwrap();
 
         // Add child comment
@@ -814,6 +3344,7 @@ mod tests {
                 "user3".to_string(),
                 "Child comment".to_string(),
                 Some(parent_id.clone()),
+                None,
             )
             .unwrap();
 
@@ -823,6 +3354,7 @@ mod tests {
                 "user4".to_string(),
                 "Grandchild comment".to_string(),
                 Some(child_id.clone()),
+                None,
             )
             .unwrap();
 
@@ -846,7 +3378,12 @@ mod tests {
 
         // Add parent comment
         let parent_id = post
-            .add_comment("user2".to_string(), "Parent comment".to_string(), None)
+            .add_comment(
+                "user2".to_string(),
+                "Parent comment".to_string(),
+                None,
+                None,
+            )
             .unwrap();
 
         // Add child comment
@@ -855,6 +3392,7 @@ mod tests {
                 "user3".to_string(),
                 "Child comment".to_string(),
                 Some(parent_id.clone()),
+                None,
             )
             .unwrap();
 
@@ -875,14 +3413,14 @@ mod tests {
     fn test_set_comment_like_success() {
         let mut post = create_test_post();
         let comment_id = post
-            .add_comment("user2".to_string(), "Test comment".to_string(), None)
+            .add_comment("user2".to_string(), "Test comment".to_string(), None, None)
             .unwrap();
         let initial_updated_at = post.comments.get(&comment_id).unwrap().updated_at;
 
         // Add a like to comment
         let result = post.set_comment_like(comment_id.clone(), "user3".to_string(), LikeType::Like);
 
-        assert!(result.is_ok());
+        assert_eq!(result, Ok(true));
         let comment = post.comments.get(&comment_id).unwrap();
         assert_eq!(comment.likes.len(), 1);
         assert_eq!(comment.likes.get("user3"), Some(&LikeType::Like));
@@ -901,14 +3439,17 @@ mod tests {
         );
 
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Comment not found");
+        assert_eq!(
+            result.unwrap_err(),
+            PostError::not_found("Comment not found")
+        );
     }
 
     #[test]
     fn test_remove_comment_like_success() {
         let mut post = create_test_post();
         let comment_id = post
-            .add_comment("user2".to_string(), "Test comment".to_string(), None)
+            .add_comment("user2".to_string(), "Test comment".to_string(), None, None)
             .unwrap();
 
         // Add a like first
@@ -932,7 +3473,7 @@ mod tests {
     fn test_remove_comment_like_not_found() {
         let mut post = create_test_post();
         let comment_id = post
-            .add_comment("user2".to_string(), "Test comment".to_string(), None)
+            .add_comment("user2".to_string(), "Test comment".to_string(), None, None)
             .unwrap();
         let initial_updated_at = post.comments.get(&comment_id).unwrap().updated_at;
 
@@ -943,7 +3484,10 @@ mod tests {
         let result2 = post.remove_comment_like(comment_id.clone(), "user3".to_string());
 
         assert!(result1.is_err());
-        assert_eq!(result1.unwrap_err(), "Comment not found");
+        assert_eq!(
+            result1.unwrap_err(),
+            PostError::not_found("Comment not found")
+        );
 
         assert!(result2.is_ok()); // Function succeeds even if like didn't exist
         let comment = post.comments.get(&comment_id).unwrap();
@@ -983,13 +3527,14 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_get_then_check)]
     fn test_post_like_operations_integration() {
         let mut post = create_test_post();
 
         // Add multiple likes
-        assert!(!post.set_like("user2".to_string(), LikeType::Like));
-        assert!(!post.set_like("user3".to_string(), LikeType::Love));
-        assert!(!post.set_like("user4".to_string(), LikeType::Insightful));
+        assert!(post.set_like("user2".to_string(), LikeType::Like));
+        assert!(post.set_like("user3".to_string(), LikeType::Love));
+        assert!(post.set_like("user4".to_string(), LikeType::Insightful));
 
         assert_eq!(post.likes.len(), 3);
 
@@ -1002,7 +3547,7 @@ mod tests {
         assert!(post.likes.get("user3").is_none());
 
         // Override remaining like
-        assert!(post.set_like("user2".to_string(), LikeType::Dislike));
+        assert!(!post.set_like("user2".to_string(), LikeType::Dislike));
 
         assert_eq!(post.likes.len(), 2);
         assert_eq!(post.likes.get("user2"), Some(&LikeType::Dislike));
@@ -1010,10 +3555,11 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_get_then_check)]
     fn test_comment_like_operations_integration() {
         let mut post = create_test_post();
         let comment_id = post
-            .add_comment("user2".to_string(), "Test comment".to_string(), None)
+            .add_comment("user2".to_string(), "Test comment".to_string(), None, None)
             .unwrap();
 
         // Add multiple likes to comment
@@ -1046,9 +3592,10 @@ mod tests {
         assert!(comment.likes.get("user4").is_none());
 
         // Override remaining like
-        assert!(post
-            .set_comment_like(comment_id.clone(), "user3".to_string(), LikeType::Dislike)
-            .is_ok());
+        assert_eq!(
+            post.set_comment_like(comment_id.clone(), "user3".to_string(), LikeType::Dislike),
+            Ok(false)
+        );
 
         let comment = post.comments.get(&comment_id).unwrap();
         assert_eq!(comment.likes.len(), 2);
@@ -1057,6 +3604,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn test_all_post_like_types() {
         let mut post = create_test_post();
 
@@ -1069,7 +3617,7 @@ mod tests {
 
         for (i, like_type) in like_types.iter().enumerate() {
             let user_id = format!("user{}", i + 2);
-            assert!(!post.set_like(user_id, like_type.clone()));
+            assert!(post.set_like(user_id, like_type.clone()));
         }
 
         assert_eq!(post.likes.len(), 4);
@@ -1080,10 +3628,11 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn test_all_comment_like_types() {
         let mut post = create_test_post();
         let comment_id = post
-            .add_comment("user2".to_string(), "Test comment".to_string(), None)
+            .add_comment("user2".to_string(), "Test comment".to_string(), None, None)
             .unwrap();
 
         let like_types = vec![
@@ -1108,6 +3657,31 @@ mod tests {
         assert_eq!(comment.likes.get("user6"), Some(&LikeType::Dislike));
     }
 
+    #[test]
+    fn test_like_counts() {
+        let mut post = create_test_post();
+        let comment_id = post
+            .add_comment("user2".to_string(), "Test comment".to_string(), None, None)
+            .unwrap();
+
+        post.set_like("user2".to_string(), LikeType::Celebrate);
+        post.set_like("user3".to_string(), LikeType::Funny);
+        post.set_like("user4".to_string(), LikeType::Celebrate);
+        post.set_comment_like(comment_id.clone(), "user3".to_string(), LikeType::Funny)
+            .unwrap();
+
+        let counts = post.get_like_counts();
+        assert_eq!(counts.celebrate, 2);
+        assert_eq!(counts.funny, 1);
+        assert_eq!(counts.total, 3);
+
+        let counts = post.get_comment_like_counts(&comment_id).unwrap();
+        assert_eq!(counts.funny, 1);
+        assert_eq!(counts.total, 1);
+
+        assert!(post.get_comment_like_counts("unknown").is_none());
+    }
+
     #[test]
     fn test_complex_comment_hierarchy() {
         let mut post = create_test_post();
@@ -1119,13 +3693,14 @@ mod tests {
         // └── comment3
 
         let comment1 = post
-            .add_comment("user2".to_string(), "Comment 1".to_string(), None)
+            .add_comment("user2".to_string(), "Comment 1".to_string(), None, None)
             .unwrap();
         let comment2 = post
             .add_comment(
                 "user3".to_string(),
                 "Comment 2".to_string(),
                 Some(comment1.clone()),
+                None,
             )
             .unwrap();
         let comment3 = post
@@ -1133,6 +3708,7 @@ mod tests {
                 "user4".to_string(),
                 "Comment 3".to_string(),
                 Some(comment1.clone()),
+                None,
             )
             .unwrap();
         let comment4 = post
@@ -1140,6 +3716,7 @@ mod tests {
                 "user5".to_string(),
                 "Comment 4".to_string(),
                 Some(comment2.clone()),
+                None,
             )
             .unwrap();
 
@@ -1166,6 +3743,8 @@ mod tests {
         let update = PostUpdate::from(&post);
 
         assert_eq!(update.post_id, post.post_id);
+        assert_eq!(update.like_count, post.like_count);
+        assert_eq!(update.comment_count, post.comment_count);
         assert_eq!(update.created_at, post.created_at);
         assert_eq!(update.updated_at, post.updated_at);
     }
@@ -1218,6 +3797,39 @@ mod tests {
         assert!(!post.matches_query(&query));
     }
 
+    #[test]
+    fn test_post_matches_query_comment_author() {
+        let mut post = Post::new("post1".to_string());
+        post.created_by = "user1".to_string();
+        assert!(!post.matches_query(&query::Query::new("comment-author:user2")));
+
+        let comment_id = post
+            .add_comment("user2".to_string(), "Nice".to_string(), None, None)
+            .unwrap();
+        post.add_comment("user3".to_string(), "Agreed".to_string(), None, None)
+            .unwrap();
+        assert!(post.matches_query(&query::Query::new("comment-author:user2")));
+        assert!(post.matches_query(&query::Query::new("commentauthor:user3")));
+        assert!(!post.matches_query(&query::Query::new("comment-author:user1")));
+
+        post.comments.get_mut(&comment_id).unwrap().deleted_at = Some(chrono::Utc::now());
+        assert!(!post.matches_query(&query::Query::new("comment-author:user2")));
+    }
+
+    #[test]
+    fn test_post_matches_query_language() {
+        let mut post = Post::new("post1".to_string());
+        post.content = "Hello world".to_string();
+
+        let query = query::Query::new("language:en");
+        assert!(!post.matches_query(&query));
+
+        post.language = Some("en".to_string());
+        assert!(post.matches_query(&query));
+        assert!(post.matches_query(&query::Query::new("lang:EN")));
+        assert!(!post.matches_query(&query::Query::new("language:de")));
+    }
+
     #[test]
     fn test_post_matches_query_multiple_filters() {
         let mut post = Post::new("post1".to_string());
@@ -1259,4 +3871,53 @@ mod tests {
         let query = query::Query::new("*");
         assert!(post.matches_query(&query)); // Wildcard matches all
     }
+
+    #[test]
+    fn test_post_matches_query_location() {
+        let mut post = Post::new("post1".to_string());
+        post.content = "Hello world".to_string();
+        post.created_by = "user1".to_string();
+
+        let query = query::Query::new("near:52.52,13.405,10");
+        assert!(!post.matches_query(&query)); // No location
+
+        post.location = Some(Location {
+            name: "Berlin Mitte".to_string(),
+            lat: 52.52,
+            lon: 13.405,
+        });
+
+        assert!(post.matches_query(&query::Query::new("location:berlin")));
+        assert!(!post.matches_query(&query::Query::new("location:paris")));
+        assert!(post.matches_query(&query::Query::new("near:52.5,13.4,10")));
+        assert!(!post.matches_query(&query::Query::new("near:48.8566,2.3522,100")));
+        assert!(!post.matches_query(&query::Query::new("near:berlin")));
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut post = create_test_post();
+        post.set_like("user-2".to_string(), LikeType::Like);
+        let comment_id = post
+            .add_comment("user-2".to_string(), "comment".to_string(), None, None)
+            .unwrap();
+        post.add_comment(
+            "user-1".to_string(),
+            "reply".to_string(),
+            Some(comment_id.clone()),
+            None,
+        )
+        .unwrap();
+        snapshot::assert_round_trip(&Some(post.clone()));
+
+        let mut archive = PostArchive::new(post.post_id.clone());
+        archive
+            .comments
+            .insert(comment_id.clone(), post.comments[&comment_id].clone());
+        snapshot::assert_round_trip(&Some(archive));
+
+        let mut updates = PostUpdates::new("user1".to_string());
+        updates.updates.push(PostUpdate::from(&post));
+        snapshot::assert_round_trip(&updates);
+    }
 }
@@ -0,0 +1,331 @@
+use crate::common::{clock, trace};
+use crate::trace_println;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+// window of rate limits
+const RATE_LIMIT_WINDOW_SECONDS: i64 = 3600;
+
+// default max number of actions of user in window
+const DEFAULT_MAX_POSTS_PER_HOUR: u32 = 20;
+const DEFAULT_MAX_COMMENTS_PER_HOUR: u32 = 100;
+
+// how long rate limiters use cached limits before reloading them
+const LIMITS_CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq)]
+pub enum RateLimitAction {
+    Post,
+    Comment,
+}
+
+impl Display for RateLimitAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateLimitAction::Post => write!(f, "post"),
+            RateLimitAction::Comment => write!(f, "comment"),
+        }
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RateLimits {
+    pub max_posts_per_hour: u32,
+    pub max_comments_per_hour: u32,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            max_posts_per_hour: DEFAULT_MAX_POSTS_PER_HOUR,
+            max_comments_per_hour: DEFAULT_MAX_COMMENTS_PER_HOUR,
+        }
+    }
+}
+
+impl RateLimits {
+    fn get_limit(&self, action: &RateLimitAction) -> u32 {
+        match action {
+            RateLimitAction::Post => self.max_posts_per_hour,
+            RateLimitAction::Comment => self.max_comments_per_hour,
+        }
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RateLimited {
+    pub action: RateLimitAction,
+    pub limit: u32,
+    // time until the action is allowed again
+    pub retry_after_seconds: u64,
+}
+
+impl Display for RateLimited {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Rate limit of {} actions exceeded (max: {} per hour), retry after {} seconds",
+            self.action, self.limit, self.retry_after_seconds
+        )
+    }
+}
+
+// Times of recent actions of user, in sliding window
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct RateLimiter {
+    pub user_id: String,
+    pub actions: HashMap<RateLimitAction, Vec<chrono::DateTime<chrono::Utc>>>,
+}
+
+impl RateLimiter {
+    fn new(user_id: String) -> Self {
+        RateLimiter {
+            user_id,
+            actions: HashMap::new(),
+        }
+    }
+
+    // Record action if limit in window is not reached
+    fn try_acquire(
+        &mut self,
+        action: RateLimitAction,
+        limit: u32,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(), RateLimited> {
+        let window_start = now - chrono::Duration::seconds(RATE_LIMIT_WINDOW_SECONDS);
+        let times = self.actions.entry(action.clone()).or_default();
+        times.retain(|t| *t > window_start);
+
+        if times.len() >= limit as usize {
+            // oldest action in window, allowed again when it leaves the window
+            let retry_after_seconds = times
+                .iter()
+                .min()
+                .map(|oldest| (*oldest - window_start).num_seconds().max(1) as u64)
+                .unwrap_or(RATE_LIMIT_WINDOW_SECONDS as u64);
+            Err(RateLimited {
+                action,
+                limit,
+                retry_after_seconds,
+            })
+        } else {
+            times.push(now);
+            Ok(())
+        }
+    }
+}
+
+#[agent_definition]
+trait RateLimitConfigAgent {
+    fn new() -> Self;
+
+    fn get_limits(&self) -> RateLimits;
+
+    fn set_limits(&mut self, limits: RateLimits, correlation_id: Option<String>);
+}
+
+struct RateLimitConfigAgentImpl {
+    state: RateLimits,
+}
+
+#[agent_implementation]
+impl RateLimitConfigAgent for RateLimitConfigAgentImpl {
+    fn new() -> Self {
+        RateLimitConfigAgentImpl {
+            state: RateLimits::default(),
+        }
+    }
+
+    fn get_limits(&self) -> RateLimits {
+        self.state.clone()
+    }
+
+    fn set_limits(&mut self, limits: RateLimits, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        trace_println!(
+            "set limits - max posts per hour: {}, max comments per hour: {}",
+            limits.max_posts_per_hour,
+            limits.max_comments_per_hour
+        );
+        self.state = limits;
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: RateLimits = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+thread_local! {
+    static LIMITS_CACHE: RefCell<Option<(RateLimits, Instant)>> = const { RefCell::new(None) };
+}
+
+async fn get_cached_limits() -> RateLimits {
+    let cached = LIMITS_CACHE.with(|c| {
+        c.borrow()
+            .as_ref()
+            .filter(|(_, loaded_at)| loaded_at.elapsed() < LIMITS_CACHE_TTL)
+            .map(|(limits, _)| limits.clone())
+    });
+
+    match cached {
+        Some(limits) => limits,
+        None => {
+            let limits = RateLimitConfigAgentClient::get().get_limits().await;
+            LIMITS_CACHE.with(|c| *c.borrow_mut() = Some((limits.clone(), Instant::now())));
+            limits
+        }
+    }
+}
+
+#[agent_definition]
+trait RateLimiterAgent {
+    fn new(id: String) -> Self;
+
+    fn get_rate_limiter(&self) -> Option<RateLimiter>;
+
+    // Record action of user, error if limit of action is reached
+    async fn acquire(
+        &mut self,
+        action: RateLimitAction,
+        correlation_id: Option<String>,
+    ) -> Result<(), RateLimited>;
+}
+
+struct RateLimiterAgentImpl {
+    _id: String,
+    state: Option<RateLimiter>,
+}
+
+impl RateLimiterAgentImpl {
+    fn get_state(&mut self) -> &mut RateLimiter {
+        self.state.get_or_insert(RateLimiter::new(self._id.clone()))
+    }
+}
+
+#[agent_implementation]
+impl RateLimiterAgent for RateLimiterAgentImpl {
+    fn new(id: String) -> Self {
+        RateLimiterAgentImpl {
+            _id: id,
+            state: None,
+        }
+    }
+
+    fn get_rate_limiter(&self) -> Option<RateLimiter> {
+        self.state.clone()
+    }
+
+    async fn acquire(
+        &mut self,
+        action: RateLimitAction,
+        correlation_id: Option<String>,
+    ) -> Result<(), RateLimited> {
+        trace::init(correlation_id);
+        let limit = get_cached_limits().await.get_limit(&action);
+        let result = self
+            .get_state()
+            .try_acquire(action.clone(), limit, clock::now());
+        if let Err(e) = &result {
+            trace_println!("acquire - action: {action}, {e}");
+        }
+        result
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<RateLimiter> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+// Check and record action of user against rate limits
+pub async fn check_rate_limit(user_id: &str, action: RateLimitAction) -> Result<(), RateLimited> {
+    RateLimiterAgentClient::get(user_id.to_string())
+        .acquire(action, trace::propagate())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    #[test]
+    fn test_try_acquire() {
+        let mut limiter = RateLimiter::new("user1".to_string());
+        let now = chrono::Utc::now();
+
+        assert!(limiter.try_acquire(RateLimitAction::Post, 2, now).is_ok());
+        assert!(limiter
+            .try_acquire(
+                RateLimitAction::Post,
+                2,
+                now + chrono::Duration::minutes(10)
+            )
+            .is_ok());
+
+        let err = limiter
+            .try_acquire(
+                RateLimitAction::Post,
+                2,
+                now + chrono::Duration::minutes(20),
+            )
+            .unwrap_err();
+        assert_eq!(err.action, RateLimitAction::Post);
+        assert_eq!(err.limit, 2);
+        assert_eq!(err.retry_after_seconds, 40 * 60);
+
+        // other actions have own limits
+        assert!(limiter
+            .try_acquire(
+                RateLimitAction::Comment,
+                2,
+                now + chrono::Duration::minutes(20)
+            )
+            .is_ok());
+
+        // the oldest action left the window
+        assert!(limiter
+            .try_acquire(
+                RateLimitAction::Post,
+                2,
+                now + chrono::Duration::minutes(61)
+            )
+            .is_ok());
+        assert_eq!(limiter.actions[&RateLimitAction::Post].len(), 2);
+    }
+
+    #[test]
+    fn test_try_acquire_zero_limit() {
+        let mut limiter = RateLimiter::new("user1".to_string());
+        let err = limiter
+            .try_acquire(RateLimitAction::Comment, 0, chrono::Utc::now())
+            .unwrap_err();
+        assert_eq!(err.retry_after_seconds, RATE_LIMIT_WINDOW_SECONDS as u64);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        snapshot::assert_round_trip(&RateLimits::default());
+
+        let mut limiter = RateLimiter::new("user1".to_string());
+        limiter
+            .try_acquire(RateLimitAction::Post, 2, chrono::Utc::now())
+            .unwrap();
+        snapshot::assert_round_trip(&Some(limiter));
+    }
+}
@@ -0,0 +1,455 @@
+use crate::chat::fetch_chats_by_ids;
+use crate::common::agent_name::get_agent_ids;
+use crate::common::{clock, trace};
+use crate::operations::get_operations_config;
+use crate::post::fetch_posts_by_ids;
+use crate::trace_println;
+use crate::user_chats::UserChatsAgentClient;
+use crate::user_posts::UserPostsAgentClient;
+use futures::future::join_all;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// refs younger than this are not audited, as source agents are initialized asynchronously
+const RECONCILE_GRACE_PERIOD_SECONDS: i64 = 300;
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    pub user_id: String,
+    // user posts refs without existing post
+    pub missing_posts: Vec<String>,
+    // user chats refs without existing chat
+    pub missing_chats: Vec<String>,
+    // missing refs were removed
+    pub repaired: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ReconciliationReport {
+    pub fn has_discrepancies(&self) -> bool {
+        !self.missing_posts.is_empty() || !self.missing_chats.is_empty()
+    }
+}
+
+// Ids of refs (id, created at) created before threshold, which do not exist
+fn find_missing(
+    refs: Vec<(String, chrono::DateTime<chrono::Utc>)>,
+    existing_ids: &HashSet<String>,
+    threshold: chrono::DateTime<chrono::Utc>,
+) -> Vec<String> {
+    refs.into_iter()
+        .filter(|(id, created_at)| *created_at < threshold && !existing_ids.contains(id))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+async fn find_missing_posts(
+    user_id: &str,
+    threshold: chrono::DateTime<chrono::Utc>,
+) -> Vec<String> {
+    match UserPostsAgentClient::get(user_id.to_string())
+        .get_posts()
+        .await
+    {
+        Some(user_posts) => {
+            let refs: Vec<(String, chrono::DateTime<chrono::Utc>)> = user_posts
+                .posts
+                .into_iter()
+                .filter(|p| p.created_at < threshold)
+                .map(|p| (p.post_id, p.created_at))
+                .collect();
+            let post_ids: Vec<String> = refs.iter().map(|(id, _)| id.clone()).collect();
+            let posts = fetch_posts_by_ids(&post_ids).await;
+            if posts.is_degraded() {
+                // timed out posts may exist, missing posts are not reported
+                trace_println!("find missing posts - user id: {user_id}, fetch degraded");
+                return vec![];
+            }
+            let existing_ids: HashSet<String> =
+                posts.items.into_iter().map(|p| p.post_id).collect();
+            find_missing(refs, &existing_ids, threshold)
+        }
+        None => vec![],
+    }
+}
+
+async fn find_missing_chats(
+    user_id: &str,
+    threshold: chrono::DateTime<chrono::Utc>,
+) -> Vec<String> {
+    match UserChatsAgentClient::get(user_id.to_string())
+        .get_chats()
+        .await
+    {
+        Some(user_chats) => {
+            let refs: Vec<(String, chrono::DateTime<chrono::Utc>)> = user_chats
+                .chats
+                .into_iter()
+                .filter(|c| c.created_at < threshold)
+                .map(|c| (c.chat_id, c.created_at))
+                .collect();
+            let chat_ids: Vec<String> = refs.iter().map(|(id, _)| id.clone()).collect();
+            let chats = fetch_chats_by_ids(&chat_ids).await;
+            if chats.is_degraded() {
+                // timed out chats may exist, missing chats are not reported
+                trace_println!("find missing chats - user id: {user_id}, fetch degraded");
+                return vec![];
+            }
+            let existing_ids: HashSet<String> =
+                chats.items.into_iter().map(|c| c.chat_id).collect();
+            find_missing(refs, &existing_ids, threshold)
+        }
+        None => vec![],
+    }
+}
+
+#[agent_definition(mode = "ephemeral")]
+trait ReconcilerAgent {
+    fn new() -> Self;
+
+    // Audit user refs (posts, chats) against source agents, missing refs are removed if repair is set
+    async fn reconcile_user(
+        &mut self,
+        user_id: String,
+        repair: bool,
+        correlation_id: Option<String>,
+    ) -> ReconciliationReport;
+}
+
+struct ReconcilerAgentImpl {}
+
+#[agent_implementation]
+impl ReconcilerAgent for ReconcilerAgentImpl {
+    fn new() -> Self {
+        Self {}
+    }
+
+    async fn reconcile_user(
+        &mut self,
+        user_id: String,
+        repair: bool,
+        correlation_id: Option<String>,
+    ) -> ReconciliationReport {
+        trace::init(correlation_id);
+        let now = clock::now();
+        let threshold = now - chrono::Duration::seconds(RECONCILE_GRACE_PERIOD_SECONDS);
+
+        let missing_posts = find_missing_posts(&user_id, threshold).await;
+        let missing_chats = find_missing_chats(&user_id, threshold).await;
+
+        trace_println!(
+            "reconcile user - user id: {user_id}, missing posts: {}, missing chats: {}",
+            missing_posts.len(),
+            missing_chats.len()
+        );
+
+        let mut report = ReconciliationReport {
+            user_id: user_id.clone(),
+            missing_posts,
+            missing_chats,
+            repaired: false,
+            created_at: now,
+        };
+
+        if repair && report.has_discrepancies() {
+            if !report.missing_posts.is_empty() {
+                let removed = UserPostsAgentClient::get(user_id.clone())
+                    .remove_posts(
+                        report.missing_posts.iter().cloned().collect(),
+                        trace::propagate(),
+                    )
+                    .await;
+                trace_println!("reconcile user - user id: {user_id}, removed posts: {removed}");
+            }
+            if !report.missing_chats.is_empty() {
+                let removed = UserChatsAgentClient::get(user_id.clone())
+                    .remove_chats(
+                        report.missing_chats.iter().cloned().collect(),
+                        trace::propagate(),
+                    )
+                    .await;
+                trace_println!("reconcile user - user id: {user_id}, removed chats: {removed}");
+            }
+            report.repaired = true;
+        }
+
+        report
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct OrphanedPost {
+    pub post_id: String,
+    // none if post agent was not initialized
+    pub created_by: Option<String>,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct OrphanedChat {
+    pub chat_id: String,
+    // none if chat agent was not initialized
+    pub created_by: Option<String>,
+    pub participants: Vec<String>,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct OrphanScan {
+    // post agents not referenced by any user posts agent
+    pub orphaned_posts: Vec<OrphanedPost>,
+    // chat agents not referenced by any user chats agent
+    pub orphaned_chats: Vec<OrphanedChat>,
+    pub scanned_posts: u32,
+    pub scanned_chats: u32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+async fn get_referenced_post_ids() -> HashSet<String> {
+    let user_ids = get_agent_ids("user-posts-agent");
+    let mut result = HashSet::new();
+
+    for chunk in user_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
+        let clients = chunk
+            .iter()
+            .map(|user_id| UserPostsAgentClient::get(user_id.clone()))
+            .collect::<Vec<_>>();
+
+        let tasks: Vec<_> = clients.iter().map(|client| client.get_posts()).collect();
+        let responses = join_all(tasks).await;
+
+        result.extend(
+            responses
+                .into_iter()
+                .flatten()
+                .flat_map(|user_posts| user_posts.posts.into_iter().map(|p| p.post_id)),
+        );
+    }
+
+    result
+}
+
+async fn get_referenced_chat_ids() -> HashSet<String> {
+    let user_ids = get_agent_ids("user-chats-agent");
+    let mut result = HashSet::new();
+
+    for chunk in user_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
+        let clients = chunk
+            .iter()
+            .map(|user_id| UserChatsAgentClient::get(user_id.clone()))
+            .collect::<Vec<_>>();
+
+        let tasks: Vec<_> = clients.iter().map(|client| client.get_chats()).collect();
+        let responses = join_all(tasks).await;
+
+        result.extend(
+            responses
+                .into_iter()
+                .flatten()
+                .flat_map(|user_chats| user_chats.chats.into_iter().map(|c| c.chat_id)),
+        );
+    }
+
+    result
+}
+
+fn find_unreferenced(ids: Vec<String>, referenced_ids: &HashSet<String>) -> Vec<String> {
+    ids.into_iter()
+        .filter(|id| !referenced_ids.contains(id))
+        .collect()
+}
+
+async fn find_orphaned_posts(post_ids: Vec<String>) -> Vec<OrphanedPost> {
+    let referenced_ids = get_referenced_post_ids().await;
+    let orphaned_ids = find_unreferenced(post_ids, &referenced_ids);
+
+    let posts = fetch_posts_by_ids(&orphaned_ids).await.items;
+
+    orphaned_ids
+        .into_iter()
+        .map(
+            |post_id| match posts.iter().find(|p| p.post_id == post_id) {
+                Some(post) => OrphanedPost {
+                    post_id,
+                    created_by: Some(post.created_by.clone()),
+                    created_at: Some(post.created_at),
+                },
+                None => OrphanedPost {
+                    post_id,
+                    created_by: None,
+                    created_at: None,
+                },
+            },
+        )
+        .collect()
+}
+
+async fn find_orphaned_chats(chat_ids: Vec<String>) -> Vec<OrphanedChat> {
+    let referenced_ids = get_referenced_chat_ids().await;
+    let orphaned_ids = find_unreferenced(chat_ids, &referenced_ids);
+
+    let chats = fetch_chats_by_ids(&orphaned_ids).await.items;
+
+    orphaned_ids
+        .into_iter()
+        .map(
+            |chat_id| match chats.iter().find(|c| c.chat_id == chat_id) {
+                Some(chat) => OrphanedChat {
+                    chat_id,
+                    created_by: Some(chat.created_by.clone()),
+                    participants: chat.participants.iter().cloned().collect(),
+                    created_at: Some(chat.created_at),
+                },
+                None => OrphanedChat {
+                    chat_id,
+                    created_by: None,
+                    participants: vec![],
+                    created_at: None,
+                },
+            },
+        )
+        .collect()
+}
+
+#[agent_definition]
+trait OrphanScanAgent {
+    fn new() -> Self;
+
+    fn get_last_scan(&self) -> Option<OrphanScan>;
+
+    // Find post and chat agents which are not referenced by any user posts and user chats agent
+    async fn scan(&mut self, correlation_id: Option<String>) -> OrphanScan;
+}
+
+struct OrphanScanAgentImpl {
+    state: Option<OrphanScan>,
+}
+
+#[agent_implementation]
+impl OrphanScanAgent for OrphanScanAgentImpl {
+    fn new() -> Self {
+        OrphanScanAgentImpl { state: None }
+    }
+
+    fn get_last_scan(&self) -> Option<OrphanScan> {
+        self.state.clone()
+    }
+
+    async fn scan(&mut self, correlation_id: Option<String>) -> OrphanScan {
+        trace::init(correlation_id);
+        let post_ids = get_agent_ids("post-agent");
+        let chat_ids = get_agent_ids("chat-agent");
+
+        trace_println!(
+            "scan - post agents: {}, chat agents: {}",
+            post_ids.len(),
+            chat_ids.len()
+        );
+
+        let scanned_posts = post_ids.len() as u32;
+        let scanned_chats = chat_ids.len() as u32;
+
+        let orphaned_posts = find_orphaned_posts(post_ids).await;
+        let orphaned_chats = find_orphaned_chats(chat_ids).await;
+
+        trace_println!(
+            "scan - orphaned posts: {}, orphaned chats: {}",
+            orphaned_posts.len(),
+            orphaned_chats.len()
+        );
+
+        let scan = OrphanScan {
+            orphaned_posts,
+            orphaned_chats,
+            scanned_posts,
+            scanned_chats,
+            created_at: clock::now(),
+        };
+
+        self.state = Some(scan.clone());
+        scan
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<OrphanScan> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    #[test]
+    fn test_find_unreferenced() {
+        let referenced_ids = HashSet::from(["p1".to_string(), "p3".to_string()]);
+        let ids = vec!["p1".to_string(), "p2".to_string(), "p3".to_string()];
+        assert_eq!(
+            find_unreferenced(ids, &referenced_ids),
+            vec!["p2".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_find_missing() {
+        let now = chrono::Utc::now();
+        let old = now - chrono::Duration::seconds(RECONCILE_GRACE_PERIOD_SECONDS + 10);
+        let threshold = now - chrono::Duration::seconds(RECONCILE_GRACE_PERIOD_SECONDS);
+
+        let refs = vec![
+            ("p1".to_string(), old),
+            ("p2".to_string(), old),
+            // too recent, source agent may not be initialized yet
+            ("p3".to_string(), now),
+        ];
+        let existing_ids = HashSet::from(["p1".to_string()]);
+
+        assert_eq!(
+            find_missing(refs, &existing_ids, threshold),
+            vec!["p2".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_report_has_discrepancies() {
+        let mut report = ReconciliationReport {
+            user_id: "u1".to_string(),
+            missing_posts: vec![],
+            missing_chats: vec![],
+            repaired: false,
+            created_at: chrono::Utc::now(),
+        };
+        assert!(!report.has_discrepancies());
+
+        report.missing_chats.push("c1".to_string());
+        assert!(report.has_discrepancies());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let scan = OrphanScan {
+            orphaned_posts: vec![OrphanedPost {
+                post_id: "p1".to_string(),
+                created_by: Some("u1".to_string()),
+                created_at: Some(chrono::Utc::now()),
+            }],
+            orphaned_chats: vec![OrphanedChat {
+                chat_id: "c1".to_string(),
+                created_by: None,
+                participants: vec![],
+                created_at: None,
+            }],
+            scanned_posts: 2,
+            scanned_chats: 1,
+            created_at: chrono::Utc::now(),
+        };
+        snapshot::assert_round_trip(&Some(scan));
+    }
+}
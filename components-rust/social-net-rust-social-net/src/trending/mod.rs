@@ -0,0 +1,420 @@
+use crate::common::{clock, get_shard_number, trace};
+use crate::trace_println;
+use futures::future::join_all;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Number of shards for TrendingAgent
+const TRENDING_SHARDS: u32 = 8;
+
+// usage of tag is counted in buckets of given length, buckets older than day window are expired
+const BUCKET_MINUTES: i64 = 5;
+const HOUR_WINDOW_MINUTES: i64 = 60;
+const DAY_WINDOW_MINUTES: i64 = 24 * 60;
+
+// max number of tags kept by shard, least recently used tags are dropped
+const SHARD_MAX_TAGS: usize = 10000;
+
+const TRENDING_DEFAULT_LIMIT: u32 = 10;
+const TRENDING_MAX_LIMIT: u32 = 100;
+
+pub fn get_trending_shard(tag: &str) -> u32 {
+    get_shard_number(tag.to_string(), TRENDING_SHARDS)
+}
+
+#[derive(Schema, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub enum TrendingWindow {
+    #[default]
+    LastHour,
+    LastDay,
+}
+
+impl TrendingWindow {
+    fn start(&self, now: chrono::DateTime<chrono::Utc>) -> chrono::DateTime<chrono::Utc> {
+        let minutes = match self {
+            TrendingWindow::LastHour => HOUR_WINDOW_MINUTES,
+            TrendingWindow::LastDay => DAY_WINDOW_MINUTES,
+        };
+        now - chrono::Duration::minutes(minutes)
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct UsageBucket {
+    pub start: chrono::DateTime<chrono::Utc>,
+    pub count: u32,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TrendingHashtag {
+    pub tag: String,
+    // usages in last hour and last day
+    pub hour_count: u32,
+    pub day_count: u32,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct TagUsage {
+    pub tag: String,
+    // oldest first
+    pub buckets: Vec<UsageBucket>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TagUsage {
+    fn new(tag: String, now: chrono::DateTime<chrono::Utc>) -> Self {
+        TagUsage {
+            tag,
+            buckets: vec![],
+            updated_at: now,
+        }
+    }
+
+    fn record(&mut self, used_at: chrono::DateTime<chrono::Utc>) {
+        let start = bucket_start(used_at);
+        match self.buckets.iter_mut().find(|b| b.start == start) {
+            Some(bucket) => bucket.count += 1,
+            None => {
+                self.buckets.push(UsageBucket { start, count: 1 });
+                self.buckets.sort_by_key(|b| b.start);
+            }
+        }
+    }
+
+    fn count_since(&self, since: chrono::DateTime<chrono::Utc>) -> u32 {
+        self.buckets
+            .iter()
+            .filter(|b| b.start >= bucket_start(since))
+            .map(|b| b.count)
+            .sum()
+    }
+
+    fn expire(&mut self, since: chrono::DateTime<chrono::Utc>) {
+        let since = bucket_start(since);
+        self.buckets.retain(|b| b.start >= since);
+    }
+
+    fn trending(&self, now: chrono::DateTime<chrono::Utc>) -> TrendingHashtag {
+        TrendingHashtag {
+            tag: self.tag.clone(),
+            hour_count: self.count_since(TrendingWindow::LastHour.start(now)),
+            day_count: self.count_since(TrendingWindow::LastDay.start(now)),
+        }
+    }
+}
+
+// Start of bucket containing given time
+fn bucket_start(time: chrono::DateTime<chrono::Utc>) -> chrono::DateTime<chrono::Utc> {
+    let bucket_seconds = BUCKET_MINUTES * 60;
+    let seconds = time.timestamp().div_euclid(bucket_seconds) * bucket_seconds;
+    chrono::DateTime::from_timestamp(seconds, 0).unwrap_or(time)
+}
+
+// Most used tags in window (by usage count, then by tag), tags without usage are skipped
+fn top_trending(
+    mut hashtags: Vec<TrendingHashtag>,
+    window: TrendingWindow,
+    limit: usize,
+) -> Vec<TrendingHashtag> {
+    let count = |h: &TrendingHashtag| match window {
+        TrendingWindow::LastHour => h.hour_count,
+        TrendingWindow::LastDay => h.day_count,
+    };
+    hashtags.retain(|h| count(h) > 0);
+    hashtags.sort_by(|a, b| count(b).cmp(&count(a)).then_with(|| a.tag.cmp(&b.tag)));
+    hashtags.truncate(limit);
+    hashtags
+}
+
+fn trending_limit(limit: Option<u32>) -> usize {
+    limit
+        .unwrap_or(TRENDING_DEFAULT_LIMIT)
+        .clamp(1, TRENDING_MAX_LIMIT) as usize
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Default)]
+pub struct TrendingShardState {
+    pub tags: HashMap<String, TagUsage>,
+}
+
+impl TrendingShardState {
+    // Record usage of tag, usages older than day window are ignored
+    fn record_usage(
+        &mut self,
+        tag: String,
+        used_at: chrono::DateTime<chrono::Utc>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> bool {
+        if used_at < TrendingWindow::LastDay.start(now) {
+            return false;
+        }
+        let usage = self
+            .tags
+            .entry(tag.clone())
+            .or_insert_with(|| TagUsage::new(tag, now));
+        usage.record(used_at.min(now));
+        usage.updated_at = now;
+        self.expire(now);
+        true
+    }
+
+    // Remove buckets out of day window and tags without usage, limit number of tags
+    fn expire(&mut self, now: chrono::DateTime<chrono::Utc>) {
+        let since = TrendingWindow::LastDay.start(now);
+        for usage in self.tags.values_mut() {
+            usage.expire(since);
+        }
+        self.tags.retain(|_, usage| !usage.buckets.is_empty());
+
+        if self.tags.len() > SHARD_MAX_TAGS {
+            let mut tags: Vec<(String, chrono::DateTime<chrono::Utc>)> = self
+                .tags
+                .values()
+                .map(|u| (u.tag.clone(), u.updated_at))
+                .collect();
+            tags.sort_by_key(|(_, updated_at)| *updated_at);
+            let excess = tags.len() - SHARD_MAX_TAGS;
+            for (tag, _) in tags.into_iter().take(excess) {
+                self.tags.remove(&tag);
+            }
+        }
+    }
+
+    fn get_trending(
+        &self,
+        window: TrendingWindow,
+        limit: usize,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Vec<TrendingHashtag> {
+        let hashtags = self.tags.values().map(|u| u.trending(now)).collect();
+        top_trending(hashtags, window, limit)
+    }
+}
+
+#[agent_definition]
+trait TrendingAgent {
+    fn new(shard_id: u32) -> Self;
+
+    // Usage of tag (in post) at given time
+    fn record_usage(
+        &mut self,
+        tag: String,
+        used_at: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
+    );
+
+    // Most used tags of shard in window
+    fn get_trending(&mut self, window: TrendingWindow, limit: Option<u32>) -> Vec<TrendingHashtag>;
+}
+
+struct TrendingAgentImpl {
+    shard_id: u32,
+    state: TrendingShardState,
+}
+
+#[agent_implementation]
+impl TrendingAgent for TrendingAgentImpl {
+    fn new(shard_id: u32) -> Self {
+        TrendingAgentImpl {
+            shard_id,
+            state: TrendingShardState::default(),
+        }
+    }
+
+    fn record_usage(
+        &mut self,
+        tag: String,
+        used_at: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
+    ) {
+        trace::init(correlation_id);
+        if get_trending_shard(&tag) != self.shard_id {
+            return;
+        }
+        let recorded = self.state.record_usage(tag.clone(), used_at, clock::now());
+        trace_println!(
+            "record usage - tag: {tag}, used at: {used_at}, shard: {}, recorded: {recorded}",
+            self.shard_id
+        );
+    }
+
+    fn get_trending(&mut self, window: TrendingWindow, limit: Option<u32>) -> Vec<TrendingHashtag> {
+        let now = clock::now();
+        self.state.expire(now);
+        self.state.get_trending(window, trending_limit(limit), now)
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: TrendingShardState = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+// Record usage of tags (from write paths)
+pub fn record_hashtags_usage<'a>(
+    tags: impl IntoIterator<Item = &'a String>,
+    used_at: chrono::DateTime<chrono::Utc>,
+) {
+    for tag in tags {
+        TrendingAgentClient::get(get_trending_shard(tag)).trigger_record_usage(
+            tag.clone(),
+            used_at,
+            trace::propagate(),
+        );
+    }
+}
+
+#[agent_definition(mode = "ephemeral")]
+trait TrendingViewAgent {
+    fn new() -> Self;
+
+    // Most used tags in window (last hour by default), collected from all TrendingAgent shards
+    async fn get_trending(
+        &mut self,
+        window: Option<TrendingWindow>,
+        limit: Option<u32>,
+        correlation_id: Option<String>,
+    ) -> Vec<TrendingHashtag>;
+}
+
+struct TrendingViewAgentImpl {}
+
+#[agent_implementation]
+impl TrendingViewAgent for TrendingViewAgentImpl {
+    fn new() -> Self {
+        Self {}
+    }
+
+    async fn get_trending(
+        &mut self,
+        window: Option<TrendingWindow>,
+        limit: Option<u32>,
+        correlation_id: Option<String>,
+    ) -> Vec<TrendingHashtag> {
+        trace::init(correlation_id);
+        let window = window.unwrap_or_default();
+        let limit = trending_limit(limit);
+        trace_println!("get trending - window: {window:?}, limit: {limit}");
+
+        // tags are disjoint between shards, top tags are among top tags of shards
+        let shard_futures: Vec<_> = (0..TRENDING_SHARDS)
+            .map(|shard_id| async move {
+                TrendingAgentClient::get(shard_id)
+                    .get_trending(window, Some(limit as u32))
+                    .await
+            })
+            .collect();
+        let hashtags = join_all(shard_futures)
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+
+        top_trending(hashtags, window, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    fn time(value: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    #[test]
+    fn test_bucket_start() {
+        assert_eq!(
+            bucket_start(time("2024-01-01T10:07:30Z")),
+            time("2024-01-01T10:05:00Z")
+        );
+        assert_eq!(
+            bucket_start(time("2024-01-01T10:05:00Z")),
+            time("2024-01-01T10:05:00Z")
+        );
+    }
+
+    #[test]
+    fn test_trending_windows() {
+        let mut state = TrendingShardState::default();
+        let now = time("2024-01-02T12:00:00Z");
+        state.record_usage("rust".to_string(), time("2024-01-02T11:50:00Z"), now);
+        state.record_usage("rust".to_string(), time("2024-01-02T11:55:00Z"), now);
+        state.record_usage("golem".to_string(), time("2024-01-02T11:30:00Z"), now);
+        for _ in 0..3 {
+            state.record_usage("wasm".to_string(), time("2024-01-02T08:00:00Z"), now);
+        }
+        // out of day window
+        assert!(!state.record_usage("old".to_string(), time("2024-01-01T11:00:00Z"), now));
+
+        let tags = |hashtags: Vec<TrendingHashtag>| -> Vec<String> {
+            hashtags.into_iter().map(|h| h.tag).collect()
+        };
+        assert_eq!(
+            tags(state.get_trending(TrendingWindow::LastHour, 10, now)),
+            vec!["rust", "golem"]
+        );
+        assert_eq!(
+            tags(state.get_trending(TrendingWindow::LastDay, 10, now)),
+            vec!["wasm", "rust", "golem"]
+        );
+        assert_eq!(
+            tags(state.get_trending(TrendingWindow::LastDay, 1, now)),
+            vec!["wasm"]
+        );
+    }
+
+    #[test]
+    fn test_trending_expiry() {
+        let mut state = TrendingShardState::default();
+        let now = time("2024-01-02T12:00:00Z");
+        state.record_usage("rust".to_string(), time("2024-01-02T11:00:00Z"), now);
+        state.record_usage("golem".to_string(), time("2024-01-02T00:00:00Z"), now);
+
+        state.expire(time("2024-01-03T06:00:00Z"));
+        assert_eq!(state.tags.len(), 1);
+        assert!(state.tags.contains_key("rust"));
+
+        state.expire(time("2024-01-03T12:00:00Z"));
+        assert!(state.tags.is_empty());
+    }
+
+    #[test]
+    fn test_top_trending_merge() {
+        let hashtag = |tag: &str, hour_count: u32| TrendingHashtag {
+            tag: tag.to_string(),
+            hour_count,
+            day_count: hour_count,
+        };
+        let merged = top_trending(
+            vec![
+                hashtag("b", 2),
+                hashtag("a", 2),
+                hashtag("c", 5),
+                hashtag("d", 0),
+            ],
+            TrendingWindow::LastHour,
+            10,
+        );
+        let tags: Vec<&str> = merged.iter().map(|h| h.tag.as_str()).collect();
+        assert_eq!(tags, vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let now = time("2024-01-02T12:00:00Z");
+        let mut state = TrendingShardState::default();
+        state.record_usage("rust".to_string(), time("2024-01-02T11:50:00Z"), now);
+        state.record_usage("golem".to_string(), time("2024-01-02T10:00:00Z"), now);
+        snapshot::assert_round_trip(&state);
+    }
+}
@@ -1,10 +1,30 @@
-use crate::common::{get_shard_number, query, UserConnectionType};
+use crate::abuse::{record_abuse_event, AbuseEventKind};
+use crate::analytics::record_signup;
+use crate::chat::is_valid_url;
+use crate::common::clock;
+use crate::common::diagnostics::AgentDiagnostics;
+use crate::common::namespace::in_namespace;
+use crate::common::schedule::parse_timezone;
+use crate::common::validation::{check_length, ContentField};
+use crate::common::{cursor, get_shard_number, query, trace, SocialNetError, UserConnectionType};
+use crate::invite::{new_invite_code, normalize_invite_code, InviteAgentClient};
+use crate::notification::{notify_user, NotificationKind, NotificationTarget};
+use crate::operations::get_operations_config;
+use crate::trace_println;
+use crate::user_chats::UserChatsAgentClient;
+use crate::user_handle::{
+    normalize_handle, parse_handle, validate_handle, UserHandleAgentClient,
+    HANDLE_ALIASES_MAX_COUNT,
+};
 use email_address::EmailAddress;
 use futures::future::join_all;
 use golem_rust::{agent_definition, agent_implementation, Schema};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 /// Number of shards for UserIndexAgent
 const USER_INDEX_SHARDS: u32 = 8;
@@ -13,40 +33,357 @@ pub fn get_user_index_shard(user_id: &str) -> u32 {
     get_shard_number(user_id.to_string(), USER_INDEX_SHARDS)
 }
 
+/// How long a fetched user status is used by write paths
+const USER_STATUS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Max number of cached user statuses, expired entries are removed when exceeded
+const USER_STATUS_CACHE_MAX_COUNT: usize = 1000;
+
+/// Searchable user fields, see User::matches_query
+const USER_QUERY_FIELDS: &[query::QueryField] = &[
+    query::QueryField {
+        names: &["user-id", "userid"],
+        mode: query::FieldMatchMode::Exact,
+    },
+    query::QueryField {
+        names: &["name"],
+        mode: query::FieldMatchMode::Contains,
+    },
+    query::QueryField {
+        names: &["email"],
+        mode: query::FieldMatchMode::ExactIgnoreCase,
+    },
+    query::QueryField {
+        names: &["handle"],
+        mode: query::FieldMatchMode::ExactIgnoreCase,
+    },
+    query::QueryField {
+        names: &["bio"],
+        mode: query::FieldMatchMode::Contains,
+    },
+    query::QueryField {
+        names: &["location"],
+        mode: query::FieldMatchMode::Contains,
+    },
+    query::QueryField {
+        names: &["connected-users", "connectedusers"],
+        mode: query::FieldMatchMode::Exact,
+    },
+];
+
+const USER_QUERY_TERM_FIELDS: &[&str] = &["user-id", "name", "email"];
+
+/// Max length of note on connected user
+const CONNECTION_NOTE_MAX_LENGTH: usize = 100;
+
+/// Max lengths of profile fields, see also common::validation (bio)
+const PROFILE_LOCATION_MAX_LENGTH: usize = 100;
+
+/// Max number of pending friend requests sent by user
+const OUTGOING_FRIEND_REQUESTS_MAX_COUNT: usize = 500;
+
+const CONNECTIONS_PAGE_DEFAULT_LIMIT: u32 = 50;
+const CONNECTIONS_PAGE_MAX_LIMIT: u32 = 200;
+
 #[derive(Schema, Clone, Serialize, Deserialize)]
 pub struct ConnectedUser {
     pub user_id: String,
     pub connection_types: HashSet<UserConnectionType>,
+    // private note/nickname, visible only to the owner of the connection
+    #[serde(default)]
+    pub note: Option<String>,
+    // interaction strength, weighted count of interactions of the owner with connected user
+    #[serde(default)]
+    pub interaction_score: u32,
+    #[serde(default)]
+    pub last_interaction_at: Option<chrono::DateTime<chrono::Utc>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl ConnectedUser {
     fn new(user_id: String, connection_type: UserConnectionType) -> Self {
-        let now = chrono::Utc::now();
+        let now = clock::now();
         ConnectedUser {
             user_id,
             connection_types: HashSet::from([connection_type]),
+            note: None,
+            interaction_score: 0,
+            last_interaction_at: None,
             created_at: now,
             updated_at: now,
         }
     }
 
+    fn record_interaction(&mut self, kind: &InteractionKind, now: chrono::DateTime<chrono::Utc>) {
+        self.interaction_score = self.interaction_score.saturating_add(kind.weight());
+        self.last_interaction_at = Some(now);
+    }
+
     fn add_connection_type(&mut self, connection_type: UserConnectionType) {
         if self.connection_types.insert(connection_type) {
-            self.updated_at = chrono::Utc::now();
+            self.updated_at = clock::now();
         }
     }
 
     fn remove_connection_type(&mut self, connection_type: &UserConnectionType) {
         if self.connection_types.remove(connection_type) {
-            self.updated_at = chrono::Utc::now();
+            self.updated_at = clock::now();
         }
     }
 
     fn has_connection_type(&self, connection_type: &UserConnectionType) -> bool {
         self.connection_types.contains(connection_type)
     }
+
+    fn set_note(&mut self, note: Option<String>) {
+        self.note = note;
+        self.updated_at = clock::now();
+    }
+}
+
+// Numbers of connected users by connection type, from view of the user
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ConnectionCounts {
+    pub friends: u32,
+    pub followers: u32,
+    pub following: u32,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ConnectionsPage {
+    pub connections: Vec<ConnectedUser>,
+    // cursor of next page, none if this is the last page
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum FriendRequestDirection {
+    // sent by other user, waiting for accept or reject of this user
+    Incoming,
+    // sent to other user
+    Outgoing,
+}
+
+// Pending friend request, removed when accepted or rejected
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FriendRequest {
+    // other user of request
+    pub user_id: String,
+    pub direction: FriendRequestDirection,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl FriendRequest {
+    fn new(user_id: String, direction: FriendRequestDirection) -> Self {
+        FriendRequest {
+            user_id,
+            direction,
+            created_at: clock::now(),
+        }
+    }
+}
+
+// Outcome of friend request received from other user
+#[derive(Debug, PartialEq)]
+enum FriendRequestReceived {
+    Pending,
+    // request crossed with request sent to the other user, users are friends
+    Accepted,
+    // already friends or request already exists
+    Ignored,
+}
+
+// Interaction of user with other user, counted into connection strength
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum InteractionKind {
+    // like of post or comment of other user
+    Like,
+    // comment of post of other user
+    Comment,
+    // chat message to other user
+    Message,
+}
+
+impl InteractionKind {
+    fn weight(&self) -> u32 {
+        match self {
+            InteractionKind::Like => 1,
+            InteractionKind::Comment => 3,
+            InteractionKind::Message => 1,
+        }
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
+pub enum UserStatus {
+    #[default]
+    Active,
+    // writes of the user are rejected
+    Banned,
+    // writes are accepted, but not distributed to other users
+    Shadowbanned,
+}
+
+impl Display for UserStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UserStatus::Active => write!(f, "Active"),
+            UserStatus::Banned => write!(f, "Banned"),
+            UserStatus::Shadowbanned => write!(f, "Shadowbanned"),
+        }
+    }
+}
+
+// Who is allowed to interact (like, comment) with posts of the user
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
+pub enum InteractionAudience {
+    #[default]
+    Everyone,
+    // any connected user (friend, follower, following)
+    Connections,
+    Friends,
+}
+
+impl Display for InteractionAudience {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InteractionAudience::Everyone => write!(f, "Everyone"),
+            InteractionAudience::Connections => write!(f, "Connections"),
+            InteractionAudience::Friends => write!(f, "Friends"),
+        }
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
+pub struct PrivacySettings {
+    pub likes: InteractionAudience,
+    pub comments: InteractionAudience,
+}
+
+// Profile fields set at once, fields not given (or empty) are cleared
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ProfileUpdate {
+    pub bio: Option<String>,
+    // http(s) URL of avatar image
+    pub avatar_url: Option<String>,
+    pub location: Option<String>,
+    // date of birth, YYYY-MM-DD
+    pub birth_date: Option<String>,
+}
+
+impl ProfileUpdate {
+    // Validated profile, with trimmed fields and empty fields removed
+    fn validated(self) -> Result<Self, SocialNetError> {
+        fn trimmed(value: Option<String>) -> Option<String> {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        }
+
+        let bio = trimmed(self.bio);
+        if let Some(bio) = &bio {
+            check_length(ContentField::UserBio, bio)?;
+        }
+        let avatar_url = trimmed(self.avatar_url);
+        if avatar_url.as_ref().is_some_and(|u| !is_valid_url(u)) {
+            return Err(SocialNetError::ValidationFailed(
+                "Invalid avatar URL".to_string(),
+            ));
+        }
+        let location = trimmed(self.location);
+        if location
+            .as_ref()
+            .is_some_and(|l| l.chars().count() > PROFILE_LOCATION_MAX_LENGTH)
+        {
+            return Err(SocialNetError::ValidationFailed(format!(
+                "Location is too long, max length: {PROFILE_LOCATION_MAX_LENGTH}"
+            )));
+        }
+        let birth_date = trimmed(self.birth_date);
+        if let Some(birth_date) = &birth_date {
+            let date = chrono::NaiveDate::parse_from_str(birth_date, "%Y-%m-%d").map_err(|_| {
+                SocialNetError::ValidationFailed(
+                    "Invalid birth date, expected format: YYYY-MM-DD".to_string(),
+                )
+            })?;
+            if date > clock::now().date_naive() {
+                return Err(SocialNetError::ValidationFailed(
+                    "Birth date can not be in the future".to_string(),
+                ));
+            }
+        }
+
+        Ok(ProfileUpdate {
+            bio,
+            avatar_url,
+            location,
+            birth_date,
+        })
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum OnboardingStep {
+    // name and email set
+    ProfileCompleted,
+    FirstPost,
+    FirstConnection,
+    FirstChat,
+}
+
+// Times of first completion of onboarding steps
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct OnboardingProgress {
+    pub profile_completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub first_post_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub first_connection_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub first_chat_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl OnboardingProgress {
+    const STEPS_COUNT: u32 = 4;
+
+    fn step_mut(&mut self, step: &OnboardingStep) -> &mut Option<chrono::DateTime<chrono::Utc>> {
+        match step {
+            OnboardingStep::ProfileCompleted => &mut self.profile_completed_at,
+            OnboardingStep::FirstPost => &mut self.first_post_at,
+            OnboardingStep::FirstConnection => &mut self.first_connection_at,
+            OnboardingStep::FirstChat => &mut self.first_chat_at,
+        }
+    }
+
+    // Mark step as completed, only first completion is recorded
+    fn complete(&mut self, step: &OnboardingStep, at: chrono::DateTime<chrono::Utc>) -> bool {
+        let completed_at = self.step_mut(step);
+        if completed_at.is_some() {
+            false
+        } else {
+            *completed_at = Some(at);
+            true
+        }
+    }
+
+    fn completed_steps(&self) -> u32 {
+        [
+            self.profile_completed_at,
+            self.first_post_at,
+            self.first_connection_at,
+            self.first_chat_at,
+        ]
+        .iter()
+        .filter(|at| at.is_some())
+        .count() as u32
+    }
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct OnboardingStatus {
+    pub user_id: String,
+    pub progress: OnboardingProgress,
+    pub completed_steps: u32,
+    pub total_steps: u32,
+    pub completed: bool,
 }
 
 #[derive(Schema, Clone, Serialize, Deserialize)]
@@ -54,27 +391,167 @@ pub struct User {
     pub user_id: String,
     pub name: Option<String>,
     pub email: Option<String>,
+    // unique handle registered in handle registry
+    #[serde(default)]
+    pub handle: Option<String>,
+    // previous handles (oldest first), registered as aliases redirecting to the user
+    #[serde(default)]
+    pub handle_aliases: Vec<String>,
     pub connected_users: HashMap<String, ConnectedUser>,
+    #[serde(default)]
+    pub status: UserStatus,
+    #[serde(default)]
+    pub privacy: PrivacySettings,
+    #[serde(default)]
+    pub onboarding: OnboardingProgress,
+    // user who issued invite code redeemed on signup
+    #[serde(default)]
+    pub invited_by: Option<String>,
+    // timezone (IANA name) of local times of user, e.g. publish time of scheduled posts
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub bio: Option<String>,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+    // date of birth, YYYY-MM-DD
+    #[serde(default)]
+    pub birth_date: Option<String>,
+    // pending friend requests by other user id
+    #[serde(default)]
+    pub friend_requests: HashMap<String, FriendRequest>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl User {
-    fn new(user_id: String) -> Self {
-        let now = chrono::Utc::now();
+    pub(crate) fn new(user_id: String) -> Self {
+        let now = clock::now();
         User {
             user_id,
             name: None,
             email: None,
+            handle: None,
+            handle_aliases: vec![],
             connected_users: HashMap::new(),
+            status: UserStatus::Active,
+            privacy: PrivacySettings::default(),
+            onboarding: OnboardingProgress::default(),
+            invited_by: None,
+            timezone: None,
+            bio: None,
+            avatar_url: None,
+            location: None,
+            birth_date: None,
+            friend_requests: HashMap::new(),
             created_at: now,
             updated_at: now,
         }
     }
 
-    fn set_name(&mut self, name: Option<String>) {
+    pub(crate) fn set_status(&mut self, status: UserStatus) -> bool {
+        if self.status == status {
+            false
+        } else {
+            self.status = status;
+            self.updated_at = clock::now();
+            true
+        }
+    }
+
+    pub fn is_banned(&self) -> bool {
+        self.status == UserStatus::Banned
+    }
+
+    pub fn is_shadowbanned(&self) -> bool {
+        self.status == UserStatus::Shadowbanned
+    }
+
+    fn set_privacy(&mut self, privacy: PrivacySettings) {
+        self.privacy = privacy;
+        self.updated_at = clock::now();
+    }
+
+    // Check if user is allowed to interact with content of this user
+    pub fn is_allowed(&self, user_id: &str, audience: &InteractionAudience) -> bool {
+        if user_id == self.user_id {
+            true
+        } else {
+            match audience {
+                InteractionAudience::Everyone => true,
+                InteractionAudience::Connections => self.connected_users.contains_key(user_id),
+                InteractionAudience::Friends => self
+                    .connected_users
+                    .get(user_id)
+                    .is_some_and(|c| c.has_connection_type(&UserConnectionType::Friend)),
+            }
+        }
+    }
+
+    pub fn can_like(&self, user_id: &str) -> bool {
+        self.is_allowed(user_id, &self.privacy.likes)
+    }
+
+    pub fn can_comment(&self, user_id: &str) -> bool {
+        self.is_allowed(user_id, &self.privacy.comments)
+    }
+
+    pub(crate) fn set_name(&mut self, name: Option<String>) {
         self.name = name;
-        self.updated_at = chrono::Utc::now();
+        self.updated_at = clock::now();
+        self.update_profile_onboarding();
+    }
+
+    fn update_profile_onboarding(&mut self) {
+        if self.name.is_some() && self.email.is_some() {
+            self.onboarding
+                .complete(&OnboardingStep::ProfileCompleted, self.updated_at);
+        }
+    }
+
+    fn onboarding_status(&self) -> OnboardingStatus {
+        let completed_steps = self.onboarding.completed_steps();
+        OnboardingStatus {
+            user_id: self.user_id.clone(),
+            progress: self.onboarding.clone(),
+            completed_steps,
+            total_steps: OnboardingProgress::STEPS_COUNT,
+            completed: completed_steps == OnboardingProgress::STEPS_COUNT,
+        }
+    }
+
+    fn set_timezone(&mut self, timezone: Option<String>) -> Result<(), String> {
+        self.timezone = timezone
+            .map(|t| parse_timezone(&t).map(|tz| tz.name().to_string()))
+            .transpose()?;
+        self.updated_at = clock::now();
+        Ok(())
+    }
+
+    fn set_profile(&mut self, profile: ProfileUpdate) -> Result<(), SocialNetError> {
+        let profile = profile.validated()?;
+        self.bio = profile.bio;
+        self.avatar_url = profile.avatar_url;
+        self.location = profile.location;
+        self.birth_date = profile.birth_date;
+        self.updated_at = clock::now();
+        Ok(())
+    }
+
+    // Set current handle, previous handle becomes alias, returns aliases over max count to release
+    fn set_handle(&mut self, handle: String) -> Vec<String> {
+        self.handle_aliases.retain(|h| *h != handle);
+        if let Some(previous) = self.handle.replace(handle) {
+            self.handle_aliases.push(previous);
+        }
+        let excess = self
+            .handle_aliases
+            .len()
+            .saturating_sub(HANDLE_ALIASES_MAX_COUNT);
+        self.updated_at = clock::now();
+        self.handle_aliases.drain(..excess).collect()
     }
 
     fn set_email(&mut self, email: Option<String>) -> Result<(), String> {
@@ -83,11 +560,16 @@ impl User {
             EmailAddress::from_str(email_str).map_err(|e| format!("Invalid email: {e}"))?;
         }
         self.email = email;
-        self.updated_at = chrono::Utc::now();
+        self.updated_at = clock::now();
+        self.update_profile_onboarding();
         Ok(())
     }
 
-    fn connect_user(&mut self, user_id: String, connection_type: UserConnectionType) -> bool {
+    pub(crate) fn connect_user(
+        &mut self,
+        user_id: String,
+        connection_type: UserConnectionType,
+    ) -> bool {
         if user_id == self.user_id {
             false
         } else {
@@ -101,13 +583,42 @@ impl User {
                     .entry(user_id.clone())
                     .and_modify(|u| u.add_connection_type(connection_type.clone()))
                     .or_insert(ConnectedUser::new(user_id.clone(), connection_type.clone()));
-                self.updated_at = chrono::Utc::now();
+                self.updated_at = clock::now();
+                self.onboarding
+                    .complete(&OnboardingStep::FirstConnection, self.updated_at);
             }
 
             should_connect
         }
     }
 
+    // Record interaction with other users, only connected users are scored,
+    // returns number of scored users
+    pub(crate) fn record_interactions(
+        &mut self,
+        user_ids: &[String],
+        kind: &InteractionKind,
+    ) -> usize {
+        let now = clock::now();
+        let mut count = 0;
+        for user_id in user_ids {
+            if let Some(connected_user) = self.connected_users.get_mut(user_id) {
+                connected_user.record_interaction(kind, now);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    // Interaction scores of connected users
+    pub(crate) fn connection_scores(&self) -> HashMap<String, u32> {
+        self.connected_users
+            .values()
+            .filter(|c| c.interaction_score > 0)
+            .map(|c| (c.user_id.clone(), c.interaction_score))
+            .collect()
+    }
+
     fn disconnect_user(&mut self, user_id: String, connection_type: UserConnectionType) -> bool {
         if user_id == self.user_id {
             false
@@ -129,24 +640,225 @@ impl User {
                         .entry(user_id.clone())
                         .and_modify(|u| u.remove_connection_type(&connection_type));
                 }
-                self.updated_at = chrono::Utc::now();
+                self.updated_at = clock::now();
             }
 
             should_disconnect
         }
     }
 
+    fn is_friend(&self, user_id: &str) -> bool {
+        self.connected_users
+            .get(user_id)
+            .is_some_and(|c| c.has_connection_type(&UserConnectionType::Friend))
+    }
+
+    fn send_friend_request(&mut self, user_id: &str) -> Result<(), SocialNetError> {
+        if user_id == self.user_id {
+            return Err(SocialNetError::ValidationFailed(
+                "Friend request can not be sent to yourself".to_string(),
+            ));
+        }
+        if self.is_friend(user_id) {
+            return Err(SocialNetError::already_exists("Already friends"));
+        }
+        match self.friend_requests.get(user_id).map(|r| &r.direction) {
+            Some(FriendRequestDirection::Outgoing) => Err(SocialNetError::already_exists(
+                "Friend request already sent",
+            )),
+            Some(FriendRequestDirection::Incoming) => Err(SocialNetError::already_exists(
+                "Friend request of user is pending, accept it instead",
+            )),
+            None => {
+                let outgoing_count = self
+                    .friend_requests
+                    .values()
+                    .filter(|r| r.direction == FriendRequestDirection::Outgoing)
+                    .count();
+                if outgoing_count >= OUTGOING_FRIEND_REQUESTS_MAX_COUNT {
+                    return Err(SocialNetError::LimitExceeded(format!(
+                        "Too many pending friend requests, max count: {OUTGOING_FRIEND_REQUESTS_MAX_COUNT}"
+                    )));
+                }
+                self.friend_requests.insert(
+                    user_id.to_string(),
+                    FriendRequest::new(user_id.to_string(), FriendRequestDirection::Outgoing),
+                );
+                self.updated_at = clock::now();
+                Ok(())
+            }
+        }
+    }
+
+    fn receive_friend_request(&mut self, user_id: &str) -> FriendRequestReceived {
+        if user_id == self.user_id || self.is_friend(user_id) {
+            return FriendRequestReceived::Ignored;
+        }
+        match self.friend_requests.get(user_id).map(|r| &r.direction) {
+            Some(FriendRequestDirection::Outgoing) => {
+                self.friend_requests.remove(user_id);
+                self.connect_user(user_id.to_string(), UserConnectionType::Friend);
+                FriendRequestReceived::Accepted
+            }
+            Some(FriendRequestDirection::Incoming) => FriendRequestReceived::Ignored,
+            None => {
+                self.friend_requests.insert(
+                    user_id.to_string(),
+                    FriendRequest::new(user_id.to_string(), FriendRequestDirection::Incoming),
+                );
+                self.updated_at = clock::now();
+                FriendRequestReceived::Pending
+            }
+        }
+    }
+
+    // Remove incoming request of user, friends are connected if accepted
+    fn resolve_incoming_friend_request(
+        &mut self,
+        user_id: &str,
+        accepted: bool,
+    ) -> Result<(), SocialNetError> {
+        match self.friend_requests.get(user_id) {
+            Some(r) if r.direction == FriendRequestDirection::Incoming => {
+                self.friend_requests.remove(user_id);
+                if accepted {
+                    self.connect_user(user_id.to_string(), UserConnectionType::Friend);
+                }
+                self.updated_at = clock::now();
+                Ok(())
+            }
+            _ => Err(SocialNetError::not_found("Friend request not found")),
+        }
+    }
+
+    // Remove outgoing request to user (resolved by other user), friends are connected if accepted
+    fn resolve_outgoing_friend_request(&mut self, user_id: &str, accepted: bool) -> bool {
+        let removed = self
+            .friend_requests
+            .get(user_id)
+            .is_some_and(|r| r.direction == FriendRequestDirection::Outgoing);
+        if removed {
+            self.friend_requests.remove(user_id);
+            self.updated_at = clock::now();
+        }
+        let connected =
+            accepted && self.connect_user(user_id.to_string(), UserConnectionType::Friend);
+        removed || connected
+    }
+
+    fn connection_counts(&self) -> ConnectionCounts {
+        let mut counts = ConnectionCounts::default();
+        for connection_type in self
+            .connected_users
+            .values()
+            .flat_map(|c| c.connection_types.iter())
+        {
+            let count = match connection_type {
+                UserConnectionType::Friend => &mut counts.friends,
+                UserConnectionType::Follower => &mut counts.followers,
+                UserConnectionType::Following => &mut counts.following,
+            };
+            *count += 1;
+        }
+        counts
+    }
+
+    // Page of connected users (optionally with given connection type) ordered by user id,
+    // cursor is number of connected users in previous pages
+    fn connections_page(
+        &self,
+        connection_type: Option<UserConnectionType>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<ConnectionsPage, SocialNetError> {
+        let offset = cursor::decode_or(cursor, 0)? as usize;
+        let limit = limit
+            .unwrap_or(CONNECTIONS_PAGE_DEFAULT_LIMIT)
+            .clamp(1, CONNECTIONS_PAGE_MAX_LIMIT) as usize;
+
+        let mut connections: Vec<&ConnectedUser> = self
+            .connected_users
+            .values()
+            .filter(|c| {
+                connection_type
+                    .as_ref()
+                    .is_none_or(|t| c.has_connection_type(t))
+            })
+            .collect();
+        connections.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+
+        let has_more = connections.len() > offset + limit;
+        let connections: Vec<ConnectedUser> = connections
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect();
+
+        Ok(ConnectionsPage {
+            connections,
+            next_cursor: has_more.then(|| cursor::encode((offset + limit) as u64)),
+        })
+    }
+
+    // Pending friend requests, newest first
+    fn pending_friend_requests(&self) -> Vec<FriendRequest> {
+        let mut requests: Vec<FriendRequest> = self.friend_requests.values().cloned().collect();
+        requests.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+        requests
+    }
+
+    fn set_connection_note(
+        &mut self,
+        user_id: &str,
+        note: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+        if note
+            .as_ref()
+            .is_some_and(|n| n.chars().count() > CONNECTION_NOTE_MAX_LENGTH)
+        {
+            return Err(SocialNetError::ValidationFailed(format!(
+                "Note is too long, max length: {CONNECTION_NOTE_MAX_LENGTH}"
+            )));
+        }
+        match self.connected_users.get_mut(user_id) {
+            Some(connected_user) => {
+                connected_user.set_note(note);
+                self.updated_at = clock::now();
+                Ok(())
+            }
+            None => Err(SocialNetError::not_found("Connection not found")),
+        }
+    }
+
     fn matches_query(&self, query: &query::Query) -> bool {
         // Check field filters first
         for (field, value) in query.field_filters.iter() {
-            let matches = match field.to_lowercase().as_str() {
-                "user-id" | "userid" => query::text_exact_matches(&self.user_id, value),
+            let field = field.to_lowercase();
+            let mode = query::field_mode(USER_QUERY_FIELDS, &field);
+            let matches = match field.as_str() {
+                "user-id" | "userid" => query::exact_matches(&mode, &self.user_id, value),
                 "name" => query::opt_text_matches(self.name.clone(), value),
-                "email" => query::opt_text_exact_matches(self.email.clone(), value),
+                "email" => query::opt_exact_matches(&mode, self.email.as_deref(), value),
+                "handle" => {
+                    let value = if value == "*" {
+                        value.clone()
+                    } else {
+                        parse_handle(value)
+                    };
+                    query::opt_exact_matches(&mode, self.handle.as_deref(), &value)
+                        || self
+                            .handle_aliases
+                            .iter()
+                            .any(|h| query::exact_matches(&mode, h, &value))
+                }
+                "bio" => query::opt_text_matches(self.bio.clone(), value),
+                "location" => query::opt_text_matches(self.location.clone(), value),
                 "connected-users" | "connectedusers" => self
                     .connected_users
                     .iter()
-                    .any(|(id, _)| query::text_exact_matches(id, value)),
+                    .any(|(id, _)| query::exact_matches(&mode, id, value)),
                 _ => false, // Unknown field
             };
             if !matches {
@@ -170,46 +882,252 @@ trait UserAgent {
 
     fn get_user(&self) -> Option<User>;
 
-    fn set_name(&mut self, name: Option<String>) -> Result<(), String>;
+    // Create new user, redeemed invite code connects user with inviting user as friends
+    async fn create_user(
+        &mut self,
+        name: Option<String>,
+        invite_code: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
 
-    fn set_email(&mut self, email: Option<String>) -> Result<(), String>;
+    // Issue single-use invite code of user
+    async fn create_invite(
+        &mut self,
+        correlation_id: Option<String>,
+    ) -> Result<String, SocialNetError>;
 
+    fn set_name(
+        &mut self,
+        name: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    fn set_email(
+        &mut self,
+        email: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Set (or clear with none) timezone of user (IANA name, e.g. Europe/Berlin)
+    fn set_timezone(
+        &mut self,
+        timezone: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Set bio, avatar URL, location and birth date at once
+    fn set_profile(
+        &mut self,
+        profile: ProfileUpdate,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Change handle, previous handles stay registered as aliases redirecting to the user
+    async fn set_handle(
+        &mut self,
+        handle: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Connect with other user, friend connection sends friend request (see send_friend_request)
     fn connect_user(
         &mut self,
         user_id: String,
         connection_type: UserConnectionType,
-    ) -> Result<(), String>;
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Send friend request, mutual friend connection is created when other user accepts it,
+    // pending friend request of other user is accepted
+    fn send_friend_request(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Friend request sent by other user (send_friend_request of other user)
+    fn receive_friend_request(&mut self, user_id: String, correlation_id: Option<String>);
+
+    fn accept_friend_request(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    fn reject_friend_request(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Friend request sent to other user was accepted or rejected by other user
+    fn friend_request_resolved(
+        &mut self,
+        user_id: String,
+        accepted: bool,
+        correlation_id: Option<String>,
+    );
+
+    // Pending incoming and outgoing friend requests, newest first
+    fn list_pending_requests(&self) -> Vec<FriendRequest>;
+
+    // Opposite side of connection created by other user (connect_user of other user)
+    fn add_connection(
+        &mut self,
+        user_id: String,
+        connection_type: UserConnectionType,
+        correlation_id: Option<String>,
+    );
 
     fn disconnect_user(
         &mut self,
         user_id: String,
         connection_type: UserConnectionType,
-    ) -> Result<(), String>;
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
 
     fn get_user_if_match(&self, query: query::Query) -> Option<User>;
+
+    // Status of user, none if user not exists
+    fn get_status(&self) -> Option<UserStatus>;
+
+    fn set_status(
+        &mut self,
+        status: UserStatus,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    fn set_privacy(
+        &mut self,
+        privacy: PrivacySettings,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Check if user can like content of this user, false if this user not exists
+    fn can_like(&self, user_id: String) -> bool;
+
+    // Check if user can comment content of this user, false if this user not exists
+    fn can_comment(&self, user_id: String) -> bool;
+
+    // Set (or clear with none) note/nickname on connected user
+    fn set_connection_note(
+        &mut self,
+        user_id: String,
+        note: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Onboarding progress of user (for product funnels), none if user not exists
+    fn get_onboarding_status(&self) -> Option<OnboardingStatus>;
+
+    // Onboarding step completed in other agent, ignored if user not exists
+    fn onboarding_step_completed(&mut self, step: OnboardingStep, correlation_id: Option<String>);
+
+    // Interaction of user with other users (in other agents), ignored if user not exists
+    fn record_interactions(
+        &mut self,
+        user_ids: Vec<String>,
+        kind: InteractionKind,
+        correlation_id: Option<String>,
+    );
+
+    // Interaction scores of connected users (only users with interactions)
+    fn get_connection_scores(&self) -> HashMap<String, u32>;
+
+    // Numbers of friends, followers and following, none if user not exists
+    fn get_connection_counts(&self) -> Option<ConnectionCounts>;
+
+    // Page of connected users (optionally with given connection type), ordered by user id
+    fn list_connections(
+        &self,
+        connection_type: Option<UserConnectionType>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<ConnectionsPage>, SocialNetError>;
+
+    // Operation counters of write methods, for admins
+    fn get_diagnostics(&self) -> AgentDiagnostics;
 }
 
 struct UserAgentImpl {
     _id: String,
     state: Option<User>,
+    diagnostics: AgentDiagnostics,
 }
 
 impl UserAgentImpl {
     fn get_state(&mut self) -> &mut User {
         if self.state.is_none() {
-            let user = User::new(self._id.clone());
-            self.state = Some(user);
-
-            // Get the shard for this user and add to the appropriate UserIndexAgent
-            let shard_id = get_user_index_shard(&self._id);
-            UserIndexAgentClient::get(shard_id).trigger_add(self._id.clone());
+            self.create_state(None);
         }
         self.state.as_mut().unwrap()
     }
 
+    fn create_state(&mut self, invited_by: Option<String>) -> &mut User {
+        let mut user = User::new(self._id.clone());
+        user.invited_by = invited_by.clone();
+
+        // Get the shard for this user and add to the appropriate UserIndexAgent
+        let shard_id = get_user_index_shard(&self._id);
+        UserIndexAgentClient::get(shard_id).trigger_add(self._id.clone(), trace::propagate());
+        record_signup(&self._id, invited_by);
+
+        self.state.insert(user)
+    }
+
     fn with_state<T>(&mut self, f: impl FnOnce(&mut User) -> T) -> T {
         f(self.get_state())
     }
+
+    // Send friend request, pending friend request of other user is accepted instead
+    fn request_friendship(&mut self, user_id: String) -> Result<(), SocialNetError> {
+        let state = self.get_state();
+        if state
+            .friend_requests
+            .get(&user_id)
+            .is_some_and(|r| r.direction == FriendRequestDirection::Incoming)
+        {
+            return self.resolve_friend_request(user_id, true);
+        }
+        state.send_friend_request(&user_id)?;
+        trace_println!("send friend request - id: {user_id}");
+        record_abuse_event(&state.user_id, AbuseEventKind::Connect, None);
+        UserAgentClient::get(user_id)
+            .trigger_receive_friend_request(state.user_id.clone(), trace::propagate());
+        Ok(())
+    }
+
+    fn resolve_friend_request(
+        &mut self,
+        user_id: String,
+        accepted: bool,
+    ) -> Result<(), SocialNetError> {
+        let state = self.get_state();
+        state.resolve_incoming_friend_request(&user_id, accepted)?;
+        trace_println!("resolve friend request - id: {user_id}, accepted: {accepted}");
+        UserAgentClient::get(user_id.clone()).trigger_friend_request_resolved(
+            state.user_id.clone(),
+            accepted,
+            trace::propagate(),
+        );
+        if accepted {
+            notify_user(
+                &user_id,
+                NotificationKind::Connection,
+                &state.user_id,
+                NotificationTarget::default(),
+                "accepted your friend request".to_string(),
+            );
+            notify_user(
+                &state.user_id,
+                NotificationKind::Connection,
+                &user_id,
+                NotificationTarget::default(),
+                "is now your friend".to_string(),
+            );
+        }
+        Ok(())
+    }
 }
 
 #[agent_implementation]
@@ -218,6 +1136,7 @@ impl UserAgent for UserAgentImpl {
         UserAgentImpl {
             _id: id,
             state: None,
+            diagnostics: AgentDiagnostics::default(),
         }
     }
 
@@ -225,65 +1144,540 @@ impl UserAgent for UserAgentImpl {
         self.state.clone()
     }
 
-    fn set_name(&mut self, name: Option<String>) -> Result<(), String> {
-        self.with_state(|state| {
-            println!("set name: {}", name.clone().unwrap_or("N/A".to_string()));
-            state.set_name(name);
+    async fn create_user(
+        &mut self,
+        name: Option<String>,
+        invite_code: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result: Result<(), SocialNetError> = async {
+            if self.state.is_some() {
+                return Err(SocialNetError::already_exists("User already exists"));
+            }
+            if let Some(name) = &name {
+                check_length(ContentField::UserName, name)?;
+            }
+            let invite_code = invite_code
+                .map(|c| normalize_invite_code(&c))
+                .filter(|c| !c.is_empty());
+            let invited_by = match invite_code {
+                Some(code) => Some(
+                    InviteAgentClient::get(code)
+                        .redeem(self._id.clone(), trace::propagate())
+                        .await?,
+                ),
+                None => None,
+            };
+
+            trace_println!(
+                "create user - invited by: {}",
+                invited_by.clone().unwrap_or("N/A".to_string())
+            );
+            let state = self.create_state(invited_by.clone());
+            if name.is_some() {
+                state.set_name(name.clone());
+                let shard_id = get_user_index_shard(&state.user_id);
+                UserIndexAgentClient::get(shard_id).trigger_set_name(
+                    state.user_id.clone(),
+                    name,
+                    trace::propagate(),
+                );
+            }
+            if let Some(invited_by) = invited_by {
+                if state.connect_user(invited_by.clone(), UserConnectionType::Friend) {
+                    UserAgentClient::get(invited_by.clone()).trigger_add_connection(
+                        state.user_id.clone(),
+                        UserConnectionType::Friend,
+                        trace::propagate(),
+                    );
+                    notify_user(
+                        &invited_by,
+                        NotificationKind::Connection,
+                        &state.user_id,
+                        NotificationTarget::default(),
+                        "joined with your invite".to_string(),
+                    );
+                }
+            }
             Ok(())
-        })
+        }
+        .await;
+        self.diagnostics.record("create_user", &result);
+        result
     }
 
-    fn set_email(&mut self, email: Option<String>) -> Result<(), String> {
-        self.with_state(|state| {
-            println!("set email: {}", email.clone().unwrap_or("N/A".to_string()));
-            state.set_email(email)
-        })
+    async fn create_invite(
+        &mut self,
+        correlation_id: Option<String>,
+    ) -> Result<String, SocialNetError> {
+        trace::init(correlation_id);
+        let result: Result<String, SocialNetError> = async {
+            match self.state.as_ref() {
+                None => Err(SocialNetError::not_found("User not exists")),
+                Some(state) if state.is_banned() => {
+                    Err(SocialNetError::not_allowed("User is banned"))
+                }
+                Some(_) => {
+                    let code = new_invite_code();
+                    InviteAgentClient::get(code.clone())
+                        .init_invite(self._id.clone(), trace::propagate())
+                        .await?;
+                    trace_println!("create invite - code: {code}");
+                    Ok(code)
+                }
+            }
+        }
+        .await;
+        self.diagnostics.record("create_invite", &result);
+        result
+    }
+
+    fn set_name(
+        &mut self,
+        name: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result: Result<(), SocialNetError> = self.with_state(|state| {
+            trace_println!("set name: {}", name.clone().unwrap_or("N/A".to_string()));
+            if state.is_banned() {
+                Err(SocialNetError::not_allowed("User is banned"))
+            } else {
+                if let Some(name) = &name {
+                    check_length(ContentField::UserName, name)?;
+                }
+                state.set_name(name.clone());
+                let shard_id = get_user_index_shard(&state.user_id);
+                UserIndexAgentClient::get(shard_id).trigger_set_name(
+                    state.user_id.clone(),
+                    name,
+                    trace::propagate(),
+                );
+                Ok(())
+            }
+        });
+        self.diagnostics.record("set_name", &result);
+        result
+    }
+
+    fn set_email(
+        &mut self,
+        email: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result: Result<(), SocialNetError> = self.with_state(|state| {
+            trace_println!("set email: {}", email.clone().unwrap_or("N/A".to_string()));
+            if state.is_banned() {
+                Err(SocialNetError::not_allowed("User is banned"))
+            } else {
+                state
+                    .set_email(email)
+                    .map_err(SocialNetError::ValidationFailed)
+            }
+        });
+        self.diagnostics.record("set_email", &result);
+        result
+    }
+
+    fn set_profile(
+        &mut self,
+        profile: ProfileUpdate,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result: Result<(), SocialNetError> = self.with_state(|state| {
+            trace_println!("set profile");
+            if state.is_banned() {
+                Err(SocialNetError::not_allowed("User is banned"))
+            } else {
+                state.set_profile(profile)
+            }
+        });
+        self.diagnostics.record("set_profile", &result);
+        result
+    }
+
+    fn set_timezone(
+        &mut self,
+        timezone: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result: Result<(), SocialNetError> = self.with_state(|state| {
+            trace_println!(
+                "set timezone: {}",
+                timezone.clone().unwrap_or("N/A".to_string())
+            );
+            if state.is_banned() {
+                Err(SocialNetError::not_allowed("User is banned"))
+            } else {
+                state
+                    .set_timezone(timezone)
+                    .map_err(SocialNetError::ValidationFailed)
+            }
+        });
+        self.diagnostics.record("set_timezone", &result);
+        result
+    }
+
+    async fn set_handle(
+        &mut self,
+        handle: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result: Result<(), SocialNetError> = async {
+            let handle = normalize_handle(&handle);
+            validate_handle(&handle).map_err(SocialNetError::ValidationFailed)?;
+            let user_id = self._id.clone();
+            let (banned, current_handle) = self
+                .state
+                .as_ref()
+                .map(|s| (s.is_banned(), s.handle.clone()))
+                .unwrap_or((false, None));
+
+            if banned {
+                Err(SocialNetError::not_allowed("User is banned"))
+            } else if current_handle.as_ref() == Some(&handle) {
+                Ok(())
+            } else {
+                UserHandleAgentClient::get(handle.clone())
+                    .claim(user_id.clone(), trace::propagate())
+                    .await?;
+                self.with_state(|state| {
+                    trace_println!("set handle: {handle}");
+                    let released = state.set_handle(handle.clone());
+                    // all aliases redirect to the current handle
+                    for alias in state.handle_aliases.iter() {
+                        UserHandleAgentClient::get(alias.clone()).trigger_set_renamed_to(
+                            user_id.clone(),
+                            handle.clone(),
+                            trace::propagate(),
+                        );
+                    }
+                    for alias in released {
+                        UserHandleAgentClient::get(alias)
+                            .trigger_release(user_id.clone(), trace::propagate());
+                    }
+                    Ok(())
+                })
+            }
+        }
+        .await;
+        self.diagnostics.record("set_handle", &result);
+        result
     }
 
     fn connect_user(
         &mut self,
         user_id: String,
         connection_type: UserConnectionType,
-    ) -> Result<(), String> {
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        if connection_type == UserConnectionType::Friend {
+            let result = self.request_friendship(user_id);
+            self.diagnostics.record("connect_user", &result);
+            return result;
+        }
+        let result: Result<(), SocialNetError> = {
+            let state = self.get_state();
+            if state.connect_user(user_id.clone(), connection_type.clone()) {
+                trace_println!("connect user - id: {user_id}, type: {connection_type}");
+                record_abuse_event(&state.user_id, AbuseEventKind::Connect, None);
+
+                let opposite_connection_type = connection_type.get_opposite();
+
+                UserAgentClient::get(user_id.clone()).trigger_add_connection(
+                    state.user_id.clone(),
+                    opposite_connection_type,
+                    trace::propagate(),
+                );
+                notify_user(
+                    &user_id,
+                    NotificationKind::Connection,
+                    &state.user_id,
+                    NotificationTarget::default(),
+                    connection_notification_content(&connection_type).to_string(),
+                );
+            } else {
+                trace_println!(
+                    "connect user - id: {user_id}, type: {connection_type} - connection already exists or invalid"
+                );
+            }
+            Ok(())
+        };
+        self.diagnostics.record("connect_user", &result);
+        result
+    }
+
+    fn send_friend_request(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result = self.request_friendship(user_id);
+        self.diagnostics.record("send_friend_request", &result);
+        result
+    }
+
+    fn receive_friend_request(&mut self, user_id: String, correlation_id: Option<String>) {
+        trace::init(correlation_id);
         let state = self.get_state();
-        if state.connect_user(user_id.clone(), connection_type.clone()) {
-            println!("connect user - id: {user_id}, type: {connection_type}");
+        let received = state.receive_friend_request(&user_id);
+        trace_println!("receive friend request - id: {user_id}, outcome: {received:?}");
+        let content = match received {
+            FriendRequestReceived::Pending => "sent you a friend request",
+            FriendRequestReceived::Accepted => "is now your friend",
+            FriendRequestReceived::Ignored => return,
+        };
+        notify_user(
+            &state.user_id,
+            NotificationKind::Connection,
+            &user_id,
+            NotificationTarget::default(),
+            content.to_string(),
+        );
+    }
+
+    fn accept_friend_request(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result = self.resolve_friend_request(user_id, true);
+        self.diagnostics.record("accept_friend_request", &result);
+        result
+    }
+
+    fn reject_friend_request(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result = self.resolve_friend_request(user_id, false);
+        self.diagnostics.record("reject_friend_request", &result);
+        result
+    }
+
+    fn friend_request_resolved(
+        &mut self,
+        user_id: String,
+        accepted: bool,
+        correlation_id: Option<String>,
+    ) {
+        trace::init(correlation_id);
+        if self
+            .get_state()
+            .resolve_outgoing_friend_request(&user_id, accepted)
+        {
+            trace_println!("friend request resolved - id: {user_id}, accepted: {accepted}");
+        } else {
+            trace_println!(
+                "friend request resolved - id: {user_id}, accepted: {accepted} - request not found"
+            );
+        }
+    }
 
-            let opposite_connection_type = connection_type.get_opposite();
+    fn list_pending_requests(&self) -> Vec<FriendRequest> {
+        self.state
+            .as_ref()
+            .map(|s| s.pending_friend_requests())
+            .unwrap_or_default()
+    }
 
-            UserAgentClient::get(user_id.clone())
-                .trigger_connect_user(state.user_id.clone(), opposite_connection_type);
+    fn add_connection(
+        &mut self,
+        user_id: String,
+        connection_type: UserConnectionType,
+        correlation_id: Option<String>,
+    ) {
+        trace::init(correlation_id);
+        if self
+            .get_state()
+            .connect_user(user_id.clone(), connection_type.clone())
+        {
+            trace_println!("add connection - id: {user_id}, type: {connection_type}");
         } else {
-            println!(
-                "connect user - id: {user_id}, type: {connection_type} - connection already exists or invalid"
+            trace_println!(
+                "add connection - id: {user_id}, type: {connection_type} - connection already exists or invalid"
             );
         }
-        Ok(())
     }
 
     fn disconnect_user(
         &mut self,
         user_id: String,
         connection_type: UserConnectionType,
-    ) -> Result<(), String> {
-        let state = self.get_state();
-        if state.disconnect_user(user_id.clone(), connection_type.clone()) {
-            println!("disconnect user - id: {user_id}, type: {connection_type}");
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result: Result<(), SocialNetError> = {
+            let state = self.get_state();
+            if state.disconnect_user(user_id.clone(), connection_type.clone()) {
+                trace_println!("disconnect user - id: {user_id}, type: {connection_type}");
+
+                let opposite_connection_type = connection_type.get_opposite();
+
+                UserAgentClient::get(user_id.clone()).trigger_disconnect_user(
+                    state.user_id.clone(),
+                    opposite_connection_type,
+                    trace::propagate(),
+                );
+            } else {
+                trace_println!(
+                    "disconnect user - id: {user_id}, type: {connection_type} - connection not found or invalid"
+                );
+            }
+            Ok(())
+        };
+        self.diagnostics.record("disconnect_user", &result);
+        result
+    }
+
+    fn get_user_if_match(&self, query: query::Query) -> Option<User> {
+        self.state.clone().filter(|user| user.matches_query(&query))
+    }
+
+    fn get_status(&self) -> Option<UserStatus> {
+        self.state.as_ref().map(|user| user.status.clone())
+    }
+
+    fn set_status(
+        &mut self,
+        status: UserStatus,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result: Result<(), SocialNetError> = self.with_state(|state| {
+            trace_println!("set status: {status}");
+            if state.set_status(status) && state.is_banned() {
+                // banned user leaves all chats
+                UserChatsAgentClient::get(state.user_id.clone())
+                    .trigger_leave_all_chats(trace::propagate());
+            }
+            Ok(())
+        });
+        self.diagnostics.record("set_status", &result);
+        result
+    }
+
+    fn set_privacy(
+        &mut self,
+        privacy: PrivacySettings,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result: Result<(), SocialNetError> = self.with_state(|state| {
+            trace_println!(
+                "set privacy - likes: {}, comments: {}",
+                privacy.likes,
+                privacy.comments
+            );
+            if state.is_banned() {
+                Err(SocialNetError::not_allowed("User is banned"))
+            } else {
+                state.set_privacy(privacy);
+                Ok(())
+            }
+        });
+        self.diagnostics.record("set_privacy", &result);
+        result
+    }
+
+    fn can_like(&self, user_id: String) -> bool {
+        self.state
+            .as_ref()
+            .is_some_and(|user| user.can_like(&user_id))
+    }
 
-            let opposite_connection_type = connection_type.get_opposite();
+    fn can_comment(&self, user_id: String) -> bool {
+        self.state
+            .as_ref()
+            .is_some_and(|user| user.can_comment(&user_id))
+    }
 
-            UserAgentClient::get(user_id.clone())
-                .trigger_disconnect_user(state.user_id.clone(), opposite_connection_type);
+    fn set_connection_note(
+        &mut self,
+        user_id: String,
+        note: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let result: Result<(), SocialNetError> = if self.state.is_none() {
+            Err(SocialNetError::not_found("User not exists"))
         } else {
-            println!(
-                "disconnect user - id: {user_id}, type: {connection_type} - connection not found or invalid"
+            self.with_state(|state| {
+                trace_println!("set connection note - user id: {user_id}");
+                state.set_connection_note(&user_id, note)
+            })
+        };
+        self.diagnostics.record("set_connection_note", &result);
+        result
+    }
+
+    fn get_onboarding_status(&self) -> Option<OnboardingStatus> {
+        self.state.as_ref().map(|user| user.onboarding_status())
+    }
+
+    fn onboarding_step_completed(&mut self, step: OnboardingStep, correlation_id: Option<String>) {
+        trace::init(correlation_id);
+        self.diagnostics.record_call("onboarding_step_completed");
+        if let Some(state) = self.state.as_mut() {
+            if state.onboarding.complete(&step, clock::now()) {
+                trace_println!("onboarding step completed - step: {step:?}");
+            }
+        }
+    }
+
+    fn record_interactions(
+        &mut self,
+        user_ids: Vec<String>,
+        kind: InteractionKind,
+        correlation_id: Option<String>,
+    ) {
+        trace::init(correlation_id);
+        self.diagnostics.record_call("record_interactions");
+        if let Some(state) = self.state.as_mut() {
+            let count = state.record_interactions(&user_ids, &kind);
+            trace_println!(
+                "record interactions - kind: {kind:?}, users: {user_ids:?}, scored: {count}"
             );
         }
-        Ok(())
     }
 
-    fn get_user_if_match(&self, query: query::Query) -> Option<User> {
-        self.state.clone().filter(|user| user.matches_query(&query))
+    fn get_connection_scores(&self) -> HashMap<String, u32> {
+        self.state
+            .as_ref()
+            .map(|state| state.connection_scores())
+            .unwrap_or_default()
+    }
+
+    fn get_connection_counts(&self) -> Option<ConnectionCounts> {
+        self.state.as_ref().map(|state| state.connection_counts())
+    }
+
+    fn list_connections(
+        &self,
+        connection_type: Option<UserConnectionType>,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Option<ConnectionsPage>, SocialNetError> {
+        match &self.state {
+            Some(state) => state
+                .connections_page(connection_type, cursor, limit)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn get_diagnostics(&self) -> AgentDiagnostics {
+        self.diagnostics.clone()
     }
 
     async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
@@ -297,18 +1691,137 @@ impl UserAgent for UserAgentImpl {
     }
 }
 
+thread_local! {
+    static USER_STATUS_CACHE: RefCell<HashMap<String, (UserStatus, Instant)>> = RefCell::new(HashMap::new());
+}
+
+// Status of user (none if user not exists), only statuses of existing users are cached
+async fn get_cached_user_status(user_id: &str) -> Option<UserStatus> {
+    let cached = USER_STATUS_CACHE.with(|c| {
+        c.borrow()
+            .get(user_id)
+            .filter(|(_, loaded_at)| loaded_at.elapsed() < USER_STATUS_CACHE_TTL)
+            .map(|(status, _)| status.clone())
+    });
+
+    match cached {
+        Some(status) => Some(status),
+        None => {
+            let status = UserAgentClient::get(user_id.to_string()).get_status().await;
+            if let Some(status) = status.clone() {
+                USER_STATUS_CACHE.with(|c| {
+                    let mut cache = c.borrow_mut();
+                    if cache.len() >= USER_STATUS_CACHE_MAX_COUNT {
+                        cache.retain(|_, (_, loaded_at)| {
+                            loaded_at.elapsed() < USER_STATUS_CACHE_TTL
+                        });
+                    }
+                    cache.insert(user_id.to_string(), (status, Instant::now()));
+                });
+            }
+            status
+        }
+    }
+}
+
+// Write of banned user (id) was rejected
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserBanned(pub String);
+
+impl Display for UserBanned {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "User is banned: {}", self.0)
+    }
+}
+
+impl From<UserBanned> for SocialNetError {
+    fn from(e: UserBanned) -> Self {
+        SocialNetError::NotAllowed(e.to_string())
+    }
+}
+
+// Check that user is allowed to write (post, comment, like, message)
+pub async fn check_user_not_banned(user_id: &str) -> Result<(), UserBanned> {
+    if get_cached_user_status(user_id).await == Some(UserStatus::Banned) {
+        trace_println!("user banned - user id: {user_id}");
+        Err(UserBanned(user_id.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+// Check that content of user is not distributed to other users
+pub async fn is_user_shadowbanned(user_id: &str) -> bool {
+    get_cached_user_status(user_id).await == Some(UserStatus::Shadowbanned)
+}
+
+// Content of notification of connected user, connection type is from view of connecting user
+fn connection_notification_content(connection_type: &UserConnectionType) -> &'static str {
+    match connection_type {
+        UserConnectionType::Friend => "connected with you as friend",
+        UserConnectionType::Following => "started following you",
+        UserConnectionType::Follower => "added you as follower",
+    }
+}
+
+// Record interaction of user with other users (from write paths)
+pub fn record_interaction(user_id: &str, other_user_ids: Vec<String>, kind: InteractionKind) {
+    let other_user_ids: Vec<String> = other_user_ids
+        .into_iter()
+        .filter(|id| id != user_id)
+        .collect();
+    if !other_user_ids.is_empty() {
+        UserAgentClient::get(user_id.to_string()).trigger_record_interactions(
+            other_user_ids,
+            kind,
+            trace::propagate(),
+        );
+    }
+}
+
+// Record onboarding step completed by user (from agents of other entities)
+pub fn record_onboarding_step(user_id: &str, step: OnboardingStep) {
+    UserAgentClient::get(user_id.to_string())
+        .trigger_onboarding_step_completed(step, trace::propagate());
+}
+
+// Timezone of user profile, none if not set or user not exists
+pub async fn get_user_timezone(user_id: &str) -> Option<String> {
+    UserAgentClient::get(user_id.to_string())
+        .get_user()
+        .await
+        .and_then(|u| u.timezone)
+}
+
+pub async fn user_exists(user_id: &str) -> bool {
+    get_cached_user_status(user_id).await.is_some()
+}
+
+// User id of registered (normalized) handle, aliases of renamed users resolve to the user
+async fn resolve_handle_user_id(handle: &str) -> Option<String> {
+    UserHandleAgentClient::get(handle.to_string())
+        .get_registration()
+        .await
+        .map(|registration| registration.user_id)
+        .filter(|user_id| in_namespace(user_id))
+}
+
 #[derive(Schema, Clone, Serialize, Deserialize)]
 pub struct UserIndexState {
     pub user_ids: HashSet<String>,
+    // names of users, used as search pre-filter, users indexed before names are not present
+    #[serde(default)]
+    pub user_names: HashMap<String, Option<String>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl UserIndexState {
     fn new() -> Self {
-        let now = chrono::Utc::now();
+        let now = clock::now();
         UserIndexState {
             user_ids: HashSet::new(),
+            user_names: HashMap::new(),
             created_at: now,
             updated_at: now,
         }
@@ -316,21 +1829,53 @@ impl UserIndexState {
 
     fn add_user(&mut self, user_id: String) -> bool {
         if self.user_ids.insert(user_id.clone()) {
-            self.updated_at = chrono::Utc::now();
+            self.user_names.entry(user_id).or_insert(None);
+            self.updated_at = clock::now();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn set_user_name(&mut self, user_id: String, name: Option<String>) -> bool {
+        if self.user_ids.contains(&user_id) {
+            self.user_names.insert(user_id, name);
+            self.updated_at = clock::now();
             true
         } else {
             false
         }
     }
+
+    // Ids of users which may match the query, name filters are checked only for indexed names
+    fn get_matching_user_ids(&self, query: &query::Query) -> Vec<String> {
+        self.user_ids
+            .iter()
+            .filter(|user_id| {
+                index_matches_query(user_id, self.user_names.get(user_id.as_str()), query)
+            })
+            .cloned()
+            .collect()
+    }
 }
 
 #[agent_definition]
 trait UserIndexAgent {
     fn new(shard_id: u32) -> Self;
 
-    fn add(&mut self, user_id: String) -> bool;
+    fn add(&mut self, user_id: String, correlation_id: Option<String>) -> bool;
+
+    fn set_name(
+        &mut self,
+        user_id: String,
+        name: Option<String>,
+        correlation_id: Option<String>,
+    ) -> bool;
 
     fn get_state(&self) -> UserIndexState;
+
+    // Ids of users in shard which may match the query, see index_matches_query
+    fn get_matching_user_ids(&self, query: query::Query) -> Vec<String>;
 }
 
 struct UserIndexAgentImpl {
@@ -347,20 +1892,41 @@ impl UserIndexAgent for UserIndexAgentImpl {
         }
     }
 
-    fn add(&mut self, user_id: String) -> bool {
+    fn add(&mut self, user_id: String, correlation_id: Option<String>) -> bool {
+        trace::init(correlation_id);
         let expected_shard = get_user_index_shard(&user_id);
         if expected_shard == self.shard_id {
-            println!("add - user id: {}, shard: {}", user_id, self.shard_id);
+            trace_println!("add - user id: {}, shard: {}", user_id, self.shard_id);
             self.state.add_user(user_id)
         } else {
             false
         }
     }
 
+    fn set_name(
+        &mut self,
+        user_id: String,
+        name: Option<String>,
+        correlation_id: Option<String>,
+    ) -> bool {
+        trace::init(correlation_id);
+        let expected_shard = get_user_index_shard(&user_id);
+        if expected_shard == self.shard_id {
+            trace_println!("set name - user id: {}, shard: {}", user_id, self.shard_id);
+            self.state.set_user_name(user_id, name)
+        } else {
+            false
+        }
+    }
+
     fn get_state(&self) -> UserIndexState {
         self.state.clone()
     }
 
+    fn get_matching_user_ids(&self, query: query::Query) -> Vec<String> {
+        self.state.get_matching_user_ids(&query)
+    }
+
     async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
         let data: UserIndexState = crate::common::snapshot::deserialize(&bytes)?;
         self.state = data;
@@ -372,14 +1938,11 @@ impl UserIndexAgent for UserIndexAgentImpl {
     }
 }
 
-async fn get_users_filtered(
-    agent_ids: HashSet<String>,
-    query: query::Query,
-) -> Result<Vec<User>, String> {
+async fn get_users_filtered(agent_ids: HashSet<String>, query: query::Query) -> Vec<User> {
     let user_ids: Vec<String> = agent_ids.into_iter().collect();
     let mut result: Vec<User> = Vec::new();
 
-    for chunk in user_ids.chunks(20) {
+    for chunk in user_ids.chunks(get_operations_config().await.user_fetch_chunk_size()) {
         let clients: Vec<UserAgentClient> = chunk
             .iter()
             .map(|agent_id| UserAgentClient::get(agent_id.to_string()))
@@ -397,14 +1960,71 @@ async fn get_users_filtered(
         result.extend(chunk_users);
     }
 
-    Ok(result)
+    result
+}
+
+async fn get_users(user_ids: Vec<String>) -> Vec<User> {
+    let mut result: Vec<User> = Vec::new();
+
+    for chunk in user_ids.chunks(get_operations_config().await.user_fetch_chunk_size()) {
+        let clients: Vec<UserAgentClient> = chunk
+            .iter()
+            .map(|user_id| UserAgentClient::get(user_id.to_string()))
+            .collect();
+
+        let tasks: Vec<_> = clients.iter().map(|client| client.get_user()).collect();
+
+        let responses = join_all(tasks).await;
+
+        result.extend(responses.into_iter().flatten());
+    }
+
+    result
+}
+
+// Names of users (user id -> name), users without name are not included
+pub async fn get_user_names(user_ids: Vec<String>) -> HashMap<String, String> {
+    get_users(user_ids)
+        .await
+        .into_iter()
+        .filter_map(|user| user.name.map(|name| (user.user_id, name)))
+        .collect()
+}
+
+// Connected user with user data
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct UserConnection {
+    pub connection: ConnectedUser,
+    pub user: User,
+}
+
+impl UserConnection {
+    // Note of connection is used as name: override of connected user
+    fn matches_query(&self, query: &query::Query) -> bool {
+        match self.connection.note.clone() {
+            Some(note) => {
+                let mut user = self.user.clone();
+                user.name = Some(note);
+                user.matches_query(query)
+            }
+            None => self.user.matches_query(query),
+        }
+    }
 }
 
-fn matches_query(user_id: String, query: &query::Query) -> bool {
+// Pre-filter of indexed user, name is checked only if it is indexed (Some)
+fn index_matches_query(
+    user_id: &str,
+    user_name: Option<&Option<String>>,
+    query: &query::Query,
+) -> bool {
     for (field, value) in query.field_filters.iter() {
         let matches = match field.as_str() {
-            "user-id" | "userid" => query::text_exact_matches(&user_id, value),
-            "name" | "email" | "connected-users" | "connectedusers" => true,
+            "user-id" | "userid" => {
+                query::exact_matches(&query::field_mode(USER_QUERY_FIELDS, field), user_id, value)
+            }
+            "name" => user_name.is_none_or(|name| query::opt_text_matches(name.clone(), value)),
+            "email" | "handle" | "bio" | "location" | "connected-users" | "connectedusers" => true,
             _ => false, // Unknown field
         };
         if !matches {
@@ -415,11 +2035,84 @@ fn matches_query(user_id: String, query: &query::Query) -> bool {
     true
 }
 
+// All user IDs, collected from all UserIndexAgent shards
+pub async fn get_all_user_ids() -> HashSet<String> {
+    // Query all UserIndexAgent shards in parallel
+    let shard_futures: Vec<_> = (0..USER_INDEX_SHARDS)
+        .map(|shard_id| async move { UserIndexAgentClient::get(shard_id).get_state().await })
+        .collect();
+
+    let shard_states = join_all(shard_futures).await;
+
+    let mut all_user_ids = HashSet::new();
+    for state in shard_states {
+        all_user_ids.extend(state.user_ids.into_iter().filter(|id| in_namespace(id)));
+    }
+    all_user_ids
+}
+
+// User id of exact (non wildcard) user-id filter, if query has any
+// Handle of exact handle filter (@ prefix is optional), resolved by handle registry
+fn get_exact_handle_filter(query: &query::Query) -> Option<String> {
+    query
+        .field_filters
+        .iter()
+        .find(|(field, value)| field == "handle" && value != "*")
+        .map(|(_, value)| parse_handle(value))
+}
+
+fn get_exact_user_id_filter(query: &query::Query) -> Option<String> {
+    query
+        .field_filters
+        .iter()
+        .find(|(field, value)| (field == "user-id" || field == "userid") && value != "*")
+        .map(|(_, value)| value.clone())
+}
+
+// Ids of users which may match the query, collected from all UserIndexAgent shards
+async fn get_matching_user_ids(query: &query::Query) -> HashSet<String> {
+    let shard_futures: Vec<_> = (0..USER_INDEX_SHARDS)
+        .map(|shard_id| async move {
+            UserIndexAgentClient::get(shard_id)
+                .get_matching_user_ids(query.clone())
+                .await
+        })
+        .collect();
+
+    join_all(shard_futures)
+        .await
+        .into_iter()
+        .flatten()
+        .filter(|id| in_namespace(id))
+        .collect()
+}
+
 #[agent_definition(mode = "ephemeral")]
 trait UserSearchAgent {
     fn new() -> Self;
 
-    async fn search(&self, query: String) -> Result<Vec<User>, String>;
+    async fn search(
+        &self,
+        query: String,
+        correlation_id: Option<String>,
+    ) -> Result<Vec<User>, SocialNetError>;
+
+    fn explain_query(&self, query: String) -> query::QueryExplanation;
+
+    // User id of handle (@ prefix is optional), handles of renamed users resolve to the user
+    async fn resolve_handle(
+        &self,
+        handle: String,
+        correlation_id: Option<String>,
+    ) -> Option<String>;
+
+    // Search connections of user, notes of connections are matched as names
+    async fn search_connections(
+        &self,
+        user_id: String,
+        query: String,
+        correlation_id: Option<String>,
+    ) -> Result<Vec<UserConnection>, SocialNetError>;
 }
 
 struct UserSearchAgentImpl;
@@ -430,36 +2123,92 @@ impl UserSearchAgent for UserSearchAgentImpl {
         UserSearchAgentImpl
     }
 
-    async fn search(&self, query: String) -> Result<Vec<User>, String> {
-        println!("searching for users - query: {}", query);
+    async fn search(
+        &self,
+        query: String,
+        correlation_id: Option<String>,
+    ) -> Result<Vec<User>, SocialNetError> {
+        trace::init(correlation_id);
+        trace_println!("searching for users - query: {}", query);
         let query = query::Query::new(&query);
 
-        // Query all UserIndexAgent shards in parallel
-        let shard_futures: Vec<_> = (0..USER_INDEX_SHARDS)
-            .map(|shard_id| async move { UserIndexAgentClient::get(shard_id).get_state().await })
-            .collect();
-
-        let shard_states = join_all(shard_futures).await;
+        // exact user id filter is resolved directly, without scanning of index
+        if let Some(user_id) = get_exact_user_id_filter(&query) {
+            trace_println!("searching for users - user id: {user_id}");
+            if !in_namespace(&user_id) {
+                return Ok(vec![]);
+            }
+            let user = UserAgentClient::get(user_id).get_user_if_match(query).await;
+            return Ok(user.into_iter().collect());
+        }
 
-        // Collect all user IDs from all shards
-        let mut all_user_ids = HashSet::new();
-        for state in shard_states {
-            all_user_ids.extend(state.user_ids);
+        // exact handle filter is resolved by handle registry, without scanning of index
+        if let Some(handle) = get_exact_handle_filter(&query) {
+            trace_println!("searching for users - handle: {handle}");
+            let user = match resolve_handle_user_id(&handle).await {
+                Some(user_id) => UserAgentClient::get(user_id).get_user_if_match(query).await,
+                None => None,
+            };
+            return Ok(user.into_iter().collect());
         }
 
-        let ids = all_user_ids
+        let ids = get_matching_user_ids(&query).await;
+
+        Ok(get_users_filtered(ids, query).await)
+    }
+
+    fn explain_query(&self, query: String) -> query::QueryExplanation {
+        query::Query::new(&query).explain(&query, USER_QUERY_FIELDS, USER_QUERY_TERM_FIELDS)
+    }
+
+    async fn resolve_handle(
+        &self,
+        handle: String,
+        correlation_id: Option<String>,
+    ) -> Option<String> {
+        trace::init(correlation_id);
+        let handle = parse_handle(&handle);
+        trace_println!("resolve handle - handle: {handle}");
+        resolve_handle_user_id(&handle).await
+    }
+
+    async fn search_connections(
+        &self,
+        user_id: String,
+        query: String,
+        correlation_id: Option<String>,
+    ) -> Result<Vec<UserConnection>, SocialNetError> {
+        trace::init(correlation_id);
+        trace_println!("searching for connections - user id: {user_id}, query: {query}");
+        let query = query::Query::new(&query);
+
+        let user = UserAgentClient::get(user_id.clone())
+            .get_user()
+            .await
+            .ok_or(SocialNetError::not_found("User not exists"))?;
+
+        let mut connected_users = user.connected_users;
+        let users = get_users(connected_users.keys().cloned().collect()).await;
+
+        let result = users
             .into_iter()
-            .filter(|id| matches_query(id.clone(), &query))
-            .collect::<HashSet<_>>();
+            .filter_map(|user| {
+                connected_users
+                    .remove(&user.user_id)
+                    .map(|connection| UserConnection { connection, user })
+            })
+            .filter(|connection| connection.matches_query(&query))
+            .collect();
 
-        let users = get_users_filtered(ids, query).await?;
-        Ok(users)
+        Ok(result)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::snapshot;
+    use crate::common::validation;
     use crate::common::UserConnectionType;
 
     #[test]
@@ -515,6 +2264,44 @@ mod tests {
         assert_eq!(user.created_at, user.updated_at);
     }
 
+    #[test]
+    fn test_set_status() {
+        let mut user = create_test_user();
+        assert_eq!(user.status, UserStatus::Active);
+
+        assert!(user.set_status(UserStatus::Banned));
+        assert!(user.is_banned());
+        assert!(!user.set_status(UserStatus::Banned));
+
+        assert!(user.set_status(UserStatus::Shadowbanned));
+        assert!(!user.is_banned());
+        assert!(user.is_shadowbanned());
+    }
+
+    #[test]
+    fn test_privacy_interactions() {
+        let mut user = create_test_user();
+        user.connect_user("friend".to_string(), UserConnectionType::Friend);
+        user.connect_user("follower".to_string(), UserConnectionType::Follower);
+
+        assert!(user.can_like("stranger"));
+        assert!(user.can_comment("stranger"));
+
+        user.set_privacy(PrivacySettings {
+            likes: InteractionAudience::Connections,
+            comments: InteractionAudience::Friends,
+        });
+
+        assert!(user.can_like("friend"));
+        assert!(user.can_like("follower"));
+        assert!(!user.can_like("stranger"));
+
+        assert!(user.can_comment("friend"));
+        assert!(!user.can_comment("follower"));
+        assert!(!user.can_comment("stranger"));
+        assert!(user.can_comment(&user.user_id.clone()));
+    }
+
     #[test]
     fn test_set_name_some() {
         let mut user = create_test_user();
@@ -541,6 +2328,65 @@ mod tests {
         assert!(user.updated_at > initial_updated_at);
     }
 
+    #[test]
+    fn test_onboarding_status() {
+        let mut user = create_test_user();
+        let status = user.onboarding_status();
+        assert_eq!(status.completed_steps, 0);
+        assert_eq!(status.total_steps, 4);
+        assert!(!status.completed);
+
+        user.set_name(Some("John".to_string()));
+        assert!(user.onboarding.profile_completed_at.is_none());
+        user.set_email(Some("john@example.com".to_string()))
+            .unwrap();
+        assert!(user.onboarding.profile_completed_at.is_some());
+
+        user.connect_user("user2".to_string(), UserConnectionType::Friend);
+        let first_connection_at = user.onboarding.first_connection_at;
+        assert!(first_connection_at.is_some());
+        user.connect_user("user3".to_string(), UserConnectionType::Friend);
+        assert_eq!(user.onboarding.first_connection_at, first_connection_at);
+
+        assert!(user
+            .onboarding
+            .complete(&OnboardingStep::FirstPost, clock::now()));
+        assert!(!user
+            .onboarding
+            .complete(&OnboardingStep::FirstPost, clock::now()));
+        user.onboarding
+            .complete(&OnboardingStep::FirstChat, clock::now());
+        let status = user.onboarding_status();
+        assert_eq!(status.completed_steps, 4);
+        assert!(status.completed);
+    }
+
+    #[test]
+    fn test_set_handle() {
+        let mut user = create_test_user();
+        assert!(user.set_handle("john".to_string()).is_empty());
+        assert!(user.set_handle("johnny".to_string()).is_empty());
+        assert_eq!(user.handle, Some("johnny".to_string()));
+        assert_eq!(user.handle_aliases, vec!["john".to_string()]);
+
+        // renaming back to alias
+        user.set_handle("john".to_string());
+        assert_eq!(user.handle, Some("john".to_string()));
+        assert_eq!(user.handle_aliases, vec!["johnny".to_string()]);
+
+        for i in 0..(HANDLE_ALIASES_MAX_COUNT - 1) {
+            assert!(user.set_handle(format!("john{i}")).is_empty());
+        }
+        let released = user.set_handle("john_last".to_string());
+        assert_eq!(released, vec!["johnny".to_string()]);
+        assert_eq!(user.handle_aliases.len(), HANDLE_ALIASES_MAX_COUNT);
+
+        let query = query::Query::new("handle:john0");
+        assert!(user.matches_query(&query));
+        let query = query::Query::new("handle:@John_Last");
+        assert!(user.matches_query(&query));
+    }
+
     #[test]
     fn test_set_email_valid() {
         let mut user = create_test_user();
@@ -553,6 +2399,22 @@ mod tests {
         assert!(user.updated_at > initial_updated_at);
     }
 
+    #[test]
+    fn test_matches_query_email_ignore_case() {
+        let mut user = create_test_user();
+        user.set_email(Some("John.Doe@Example.com".to_string()))
+            .unwrap();
+
+        assert!(user.matches_query(&query::Query::new("email:john.doe@example.com")));
+        assert!(user.matches_query(&query::Query::new("email:JOHN.DOE@EXAMPLE.COM")));
+        assert!(!user.matches_query(&query::Query::new("email:john@example.com")));
+        // user ids are case sensitive
+        assert!(!user.matches_query(&query::Query::new(&format!(
+            "user-id:{}",
+            user.user_id.to_uppercase()
+        ))));
+    }
+
     #[test]
     fn test_set_email_invalid() {
         let mut user = create_test_user();
@@ -584,6 +2446,97 @@ mod tests {
         assert!(user.updated_at > initial_updated_at);
     }
 
+    #[test]
+    fn test_set_profile_bio_too_long() {
+        let mut user = create_test_user();
+        let max_length = crate::common::validation::get_limits().user_bio_max_length;
+
+        let result = user.set_profile(ProfileUpdate {
+            bio: Some("a".repeat(max_length as usize + 1)),
+            avatar_url: None,
+            location: None,
+            birth_date: None,
+        });
+        match result {
+            Err(SocialNetError::ContentTooLong(e)) => {
+                assert_eq!(e.field, ContentField::UserBio);
+                assert_eq!(e.max_length, max_length);
+            }
+            _ => panic!("expected content too long error"),
+        }
+        assert_eq!(user.bio, None);
+    }
+
+    #[test]
+    fn test_set_profile() {
+        let mut user = create_test_user();
+
+        let result = user.set_profile(ProfileUpdate {
+            bio: Some("  Rust developer  ".to_string()),
+            avatar_url: Some("https://example.com/avatar.png".to_string()),
+            location: Some("Prague".to_string()),
+            birth_date: Some("1990-05-17".to_string()),
+        });
+        assert!(result.is_ok());
+        assert_eq!(user.bio, Some("Rust developer".to_string()));
+        assert_eq!(user.location, Some("Prague".to_string()));
+        assert_eq!(user.birth_date, Some("1990-05-17".to_string()));
+
+        let query = query::Query::new("bio:rust location:prague");
+        assert!(user.matches_query(&query));
+        let query = query::Query::new("location:berlin");
+        assert!(!user.matches_query(&query));
+
+        let invalid = [
+            ProfileUpdate {
+                bio: Some("a".repeat(validation::get_limits().user_bio_max_length as usize + 1)),
+                ..ProfileUpdate::default()
+            },
+            ProfileUpdate {
+                avatar_url: Some("ftp://example.com/avatar.png".to_string()),
+                ..ProfileUpdate::default()
+            },
+            ProfileUpdate {
+                birth_date: Some("17.05.1990".to_string()),
+                ..ProfileUpdate::default()
+            },
+            ProfileUpdate {
+                birth_date: Some("2999-01-01".to_string()),
+                ..ProfileUpdate::default()
+            },
+        ];
+        for profile in invalid {
+            assert!(user.set_profile(profile).is_err());
+            assert_eq!(user.location, Some("Prague".to_string()));
+        }
+
+        assert!(user
+            .set_profile(ProfileUpdate {
+                bio: Some(" ".to_string()),
+                ..ProfileUpdate::default()
+            })
+            .is_ok());
+        assert!(user.bio.is_none());
+        assert!(user.avatar_url.is_none());
+        assert!(user.location.is_none());
+        assert!(user.birth_date.is_none());
+    }
+
+    #[test]
+    fn test_set_timezone() {
+        let mut user = create_test_user();
+        assert!(user
+            .set_timezone(Some(" Europe/Prague".to_string()))
+            .is_ok());
+        assert_eq!(user.timezone, Some("Europe/Prague".to_string()));
+
+        assert!(user.set_timezone(Some("UTC+2".to_string())).is_err());
+        assert_eq!(user.timezone, Some("Europe/Prague".to_string()));
+
+        assert!(user.set_timezone(None).is_ok());
+        assert!(user.timezone.is_none());
+    }
+
     #[test]
     fn test_connect_user_success() {
         let mut user = create_test_user();
@@ -600,6 +2553,140 @@ mod tests {
         assert!(user.updated_at > initial_updated_at);
     }
 
+    #[test]
+    fn test_friend_request_accept() {
+        let mut alice = User::new("alice".to_string());
+        let mut bob = User::new("bob".to_string());
+
+        assert!(matches!(
+            alice.send_friend_request("alice"),
+            Err(SocialNetError::ValidationFailed(_))
+        ));
+        assert!(alice.send_friend_request("bob").is_ok());
+        assert!(matches!(
+            alice.send_friend_request("bob"),
+            Err(SocialNetError::AlreadyExists(_))
+        ));
+        assert_eq!(
+            bob.receive_friend_request("alice"),
+            FriendRequestReceived::Pending
+        );
+        assert_eq!(
+            bob.receive_friend_request("alice"),
+            FriendRequestReceived::Ignored
+        );
+        assert!(bob.send_friend_request("alice").is_err());
+        assert!(!alice.is_friend("bob"));
+        assert!(!bob.is_friend("alice"));
+
+        let pending = bob.pending_friend_requests();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].user_id, "alice");
+        assert_eq!(pending[0].direction, FriendRequestDirection::Incoming);
+
+        assert!(matches!(
+            alice.resolve_incoming_friend_request("bob", true),
+            Err(SocialNetError::NotFound(_))
+        ));
+        assert!(bob.resolve_incoming_friend_request("alice", true).is_ok());
+        assert!(alice.resolve_outgoing_friend_request("bob", true));
+
+        assert!(alice.is_friend("bob"));
+        assert!(bob.is_friend("alice"));
+        assert!(alice.pending_friend_requests().is_empty());
+        assert!(bob.pending_friend_requests().is_empty());
+        assert!(alice.send_friend_request("bob").is_err());
+    }
+
+    #[test]
+    fn test_friend_request_reject() {
+        let mut alice = User::new("alice".to_string());
+        let mut bob = User::new("bob".to_string());
+
+        assert!(alice.send_friend_request("bob").is_ok());
+        bob.receive_friend_request("alice");
+        assert!(bob.resolve_incoming_friend_request("alice", false).is_ok());
+        assert!(bob.resolve_incoming_friend_request("alice", false).is_err());
+        assert!(alice.resolve_outgoing_friend_request("bob", false));
+
+        assert!(!alice.is_friend("bob"));
+        assert!(!bob.is_friend("alice"));
+        assert!(alice.pending_friend_requests().is_empty());
+        assert!(bob.pending_friend_requests().is_empty());
+        // can be sent again
+        assert!(alice.send_friend_request("bob").is_ok());
+    }
+
+    #[test]
+    fn test_friend_requests_crossed() {
+        let mut alice = User::new("alice".to_string());
+        let mut bob = User::new("bob".to_string());
+
+        assert!(alice.send_friend_request("bob").is_ok());
+        assert!(bob.send_friend_request("alice").is_ok());
+        assert_eq!(
+            alice.receive_friend_request("bob"),
+            FriendRequestReceived::Accepted
+        );
+        assert_eq!(
+            bob.receive_friend_request("alice"),
+            FriendRequestReceived::Accepted
+        );
+
+        assert!(alice.is_friend("bob"));
+        assert!(bob.is_friend("alice"));
+        assert!(alice.pending_friend_requests().is_empty());
+        assert!(bob.pending_friend_requests().is_empty());
+    }
+
+    #[test]
+    fn test_connection_counts_and_pages() {
+        let mut user = create_test_user();
+        user.connect_user("user3".to_string(), UserConnectionType::Friend);
+        user.connect_user("user3".to_string(), UserConnectionType::Following);
+        user.connect_user("user1".to_string(), UserConnectionType::Follower);
+        user.connect_user("user2".to_string(), UserConnectionType::Friend);
+
+        assert_eq!(
+            user.connection_counts(),
+            ConnectionCounts {
+                friends: 2,
+                followers: 1,
+                following: 1,
+            }
+        );
+
+        let page = user.connections_page(None, None, Some(2)).unwrap();
+        let user_ids: Vec<&str> = page
+            .connections
+            .iter()
+            .map(|c| c.user_id.as_str())
+            .collect();
+        assert_eq!(user_ids, vec!["user1", "user2"]);
+
+        let page = user
+            .connections_page(None, page.next_cursor, Some(2))
+            .unwrap();
+        assert_eq!(page.connections.len(), 1);
+        assert_eq!(page.connections[0].user_id, "user3");
+        assert!(page.next_cursor.is_none());
+
+        let page = user
+            .connections_page(Some(UserConnectionType::Friend), None, None)
+            .unwrap();
+        let user_ids: Vec<&str> = page
+            .connections
+            .iter()
+            .map(|c| c.user_id.as_str())
+            .collect();
+        assert_eq!(user_ids, vec!["user2", "user3"]);
+        assert!(page.next_cursor.is_none());
+
+        assert!(user
+            .connections_page(None, Some("invalid".to_string()), None)
+            .is_err());
+    }
+
     #[test]
     fn test_connect_user_self() {
         let mut user = create_test_user();
@@ -866,6 +2953,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn test_all_connection_types() {
         let mut user = create_test_user();
 
@@ -962,6 +3050,78 @@ mod tests {
         assert_eq!(user.connected_users.len(), 2);
     }
 
+    #[test]
+    fn test_set_connection_note() {
+        let mut user = create_test_user();
+        user.connect_user("user2".to_string(), UserConnectionType::Friend);
+
+        assert!(user
+            .set_connection_note("user2", Some("  Bestie ".to_string()))
+            .is_ok());
+        assert_eq!(
+            user.connected_users.get("user2").unwrap().note,
+            Some("Bestie".to_string())
+        );
+
+        assert!(user
+            .set_connection_note("user2", Some(" ".to_string()))
+            .is_ok());
+        assert!(user.connected_users.get("user2").unwrap().note.is_none());
+
+        assert_eq!(
+            user.set_connection_note("user3", Some("Unknown".to_string())),
+            Err(SocialNetError::not_found("Connection not found"))
+        );
+        assert!(user
+            .set_connection_note("user2", Some("x".repeat(CONNECTION_NOTE_MAX_LENGTH + 1)))
+            .is_err());
+    }
+
+    #[test]
+    fn test_record_interactions() {
+        let mut user = create_test_user();
+        user.connect_user("user2".to_string(), UserConnectionType::Friend);
+        user.connect_user("user3".to_string(), UserConnectionType::Following);
+
+        let user_ids = vec!["user2".to_string(), "stranger".to_string()];
+        assert_eq!(
+            user.record_interactions(&user_ids, &InteractionKind::Comment),
+            1
+        );
+        user.record_interactions(&["user2".to_string()], &InteractionKind::Like);
+        user.record_interactions(&["user3".to_string()], &InteractionKind::Message);
+
+        let connected_user = user.connected_users.get("user2").unwrap();
+        assert_eq!(connected_user.interaction_score, 4);
+        assert!(connected_user.last_interaction_at.is_some());
+        assert_eq!(
+            user.connection_scores(),
+            HashMap::from([("user2".to_string(), 4), ("user3".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn test_user_connection_matches_note_as_name() {
+        let mut owner = create_test_user();
+        owner.connect_user("user2".to_string(), UserConnectionType::Friend);
+        owner
+            .set_connection_note("user2", Some("Bestie".to_string()))
+            .unwrap();
+
+        let mut user = User::new("user2".to_string());
+        user.set_name(Some("John Doe".to_string()));
+
+        let connection = UserConnection {
+            connection: owner.connected_users.get("user2").unwrap().clone(),
+            user,
+        };
+
+        assert!(connection.matches_query(&query::Query::new("name:bestie")));
+        assert!(connection.matches_query(&query::Query::new("bestie")));
+        assert!(!connection.matches_query(&query::Query::new("name:john")));
+        assert!(connection.matches_query(&query::Query::new("user-id:user2")));
+    }
+
     fn create_test_state() -> UserIndexState {
         UserIndexState::new()
     }
@@ -1015,4 +3175,75 @@ mod tests {
         assert!(state.user_ids.contains("user2"));
         assert!(state.user_ids.contains("user3"));
     }
+
+    #[test]
+    fn test_index_matching_user_ids() {
+        let mut state = create_test_state();
+        state.add_user("user1".to_string());
+        state.add_user("user2".to_string());
+        state.add_user("user3".to_string());
+        assert!(state.set_user_name("user1".to_string(), Some("Alice".to_string())));
+        assert!(state.set_user_name("user2".to_string(), Some("Bob".to_string())));
+        assert!(!state.set_user_name("user4".to_string(), Some("Carol".to_string())));
+        // user indexed before names
+        state.user_ids.insert("user5".to_string());
+
+        let mut ids = state.get_matching_user_ids(&query::Query::new("name:ali"));
+        ids.sort();
+        assert_eq!(ids, vec!["user1".to_string(), "user5".to_string()]);
+
+        let ids = state.get_matching_user_ids(&query::Query::new("user-id:user2"));
+        assert_eq!(ids, vec!["user2".to_string()]);
+
+        assert_eq!(
+            state
+                .get_matching_user_ids(&query::Query::new("name:*"))
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_get_exact_user_id_filter() {
+        assert_eq!(
+            get_exact_user_id_filter(&query::Query::new("userid:user1 name:a")),
+            Some("user1".to_string())
+        );
+        assert_eq!(
+            get_exact_user_id_filter(&query::Query::new("user-id:*")),
+            None
+        );
+        assert_eq!(get_exact_user_id_filter(&query::Query::new("alice")), None);
+    }
+
+    #[test]
+    fn test_get_exact_handle_filter() {
+        assert_eq!(
+            get_exact_handle_filter(&query::Query::new("handle:@John name:a")),
+            Some("john".to_string())
+        );
+        assert_eq!(
+            get_exact_handle_filter(&query::Query::new("handle:*")),
+            None
+        );
+        assert_eq!(get_exact_handle_filter(&query::Query::new("john")), None);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut user = User::new("user1".to_string());
+        user.set_name(Some("User 1".to_string()));
+        user.connect_user("user2".to_string(), UserConnectionType::Friend);
+        user.set_profile(ProfileUpdate {
+            bio: Some("Rust developer".to_string()),
+            ..ProfileUpdate::default()
+        })
+        .unwrap();
+        snapshot::assert_round_trip(&Some(user));
+
+        let mut index = UserIndexState::new();
+        index.add_user("user1".to_string());
+        index.set_user_name("user1".to_string(), Some("User 1".to_string()));
+        snapshot::assert_round_trip(&index);
+    }
 }
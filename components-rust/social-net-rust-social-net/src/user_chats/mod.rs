@@ -1,28 +1,69 @@
-use crate::chat::{fetch_chats_by_ids, fetch_chats_by_ids_and_query, Chat, ChatAgentClient};
-use crate::common::{poll_for_updates, query};
+use crate::chat::{
+    check_participants_count, fetch_chat_summaries_by_ids_and_query, fetch_chat_views_by_ids,
+    fetch_chat_views_by_ids_and_query, validate_participants, ChatAgentClient, ChatError,
+    ChatSummary, ChatView, MessagePreview, CHAT_QUERY_FIELDS, CHAT_QUERY_TERM_FIELDS,
+};
+use crate::common::fetch::Fetched;
+use crate::common::{
+    clock, cursor, namespace, poll_for_updates, poll_for_updates_since, query, trace,
+    SocialNetError,
+};
+use crate::operations::{get_operations_config, SlowOperationTimer};
+use crate::trace_println;
+use crate::user::{record_onboarding_step, OnboardingStep};
 use golem_rust::{agent_definition, agent_implementation, Schema};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
+// default and max number of chats returned by chats view page
+const CHATS_VIEW_DEFAULT_LIMIT: u32 = 20;
+const CHATS_VIEW_MAX_LIMIT: u32 = 100;
+
 #[derive(Schema, Clone, Serialize, Deserialize)]
 pub struct ChatRef {
     pub chat_id: String,
     pub created_by: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    // user chats sequence number of the last update
+    #[serde(default)]
+    pub sequence: u64,
+    #[serde(default)]
+    pub last_message: Option<MessagePreview>,
+    #[serde(default)]
+    pub message_count: u32,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub last_read_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl ChatRef {
     fn new(chat_id: String, created_by: String) -> Self {
-        let now = chrono::Utc::now();
+        let now = clock::now();
         ChatRef {
             chat_id,
             created_by,
             created_at: now,
             updated_at: now,
+            sequence: 0,
+            last_message: None,
+            message_count: 0,
+            pinned: false,
+            last_read_at: Some(now),
         }
     }
 
+    // Chat has message from other user which was not read yet
+    pub fn is_unread(&self, user_id: &str) -> bool {
+        self.last_message.as_ref().is_some_and(|m| {
+            m.created_by != user_id
+                && self
+                    .last_read_at
+                    .is_none_or(|read_at| m.created_at > read_at)
+        })
+    }
+
     pub fn matches_query(&self, query: &query::Query) -> bool {
         for (field, value) in query.field_filters.iter() {
             let matches = match field.as_str() {
@@ -43,26 +84,94 @@ impl ChatRef {
 pub struct UserChats {
     pub user_id: String,
     pub chats: Vec<ChatRef>,
+    // monotonically increasing sequence number of updates
+    #[serde(default)]
+    pub sequence: u64,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl UserChats {
     fn new(user_id: String) -> Self {
-        let now = chrono::Utc::now();
+        let now = clock::now();
         UserChats {
             user_id,
             chats: Vec::new(),
+            sequence: 0,
             created_at: now,
             updated_at: now,
         }
     }
+
+    fn next_sequence(&mut self) -> u64 {
+        self.sequence += 1;
+        self.sequence
+    }
+
+    fn set_pinned(&mut self, chat_id: &str, pinned: bool) -> Result<bool, ChatError> {
+        match self.chats.iter().position(|c| c.chat_id == chat_id) {
+            Some(index) if self.chats[index].pinned != pinned => {
+                let sequence = self.next_sequence();
+                let chat = &mut self.chats[index];
+                chat.pinned = pinned;
+                chat.sequence = sequence;
+                self.updated_at = clock::now();
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Err(ChatError::not_found("Chat not found")),
+        }
+    }
+
+    fn mark_read(&mut self, chat_id: &str) -> Result<(), ChatError> {
+        match self.chats.iter().position(|c| c.chat_id == chat_id) {
+            Some(index) => {
+                let sequence = self.next_sequence();
+                let chat = &mut self.chats[index];
+                chat.last_read_at = Some(clock::now());
+                chat.sequence = sequence;
+                Ok(())
+            }
+            None => Err(ChatError::not_found("Chat not found")),
+        }
+    }
+
+    fn remove_chats(&mut self, chat_ids: &HashSet<String>) -> u32 {
+        let count = self.chats.len();
+        self.chats.retain(|c| !chat_ids.contains(&c.chat_id));
+        let removed = (count - self.chats.len()) as u32;
+        if removed > 0 {
+            self.updated_at = clock::now();
+        }
+        removed
+    }
+
+    // Chats ordered by pinned first, then by last update (newest first)
+    fn sort_chats(&mut self) {
+        self.chats.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then_with(|| b.updated_at.cmp(&a.updated_at))
+        });
+    }
 }
 
 #[derive(Schema, Clone, Serialize, Deserialize)]
 pub struct UserChatsUpdates {
     pub user_id: String,
     pub chats: Vec<ChatRef>,
+    // cursor to get next updates
+    pub cursor: String,
+}
+
+impl UserChatsUpdates {
+    fn new(user_id: String, chats: Vec<ChatRef>, cursor_sequence: u64) -> Self {
+        UserChatsUpdates {
+            user_id,
+            chats,
+            cursor: cursor::encode(cursor_sequence),
+        }
+    }
 }
 
 #[agent_definition]
@@ -71,23 +180,65 @@ trait UserChatsAgent {
 
     fn get_chats(&self) -> Option<UserChats>;
 
-    fn create_chat(&mut self, participants_ids: HashSet<String>) -> Result<String, String>;
+    // Participant ids must be unique and non empty, current user is added to participants
+    async fn create_chat(
+        &mut self,
+        participants_ids: Vec<String>,
+        correlation_id: Option<String>,
+    ) -> Result<String, ChatError>;
 
     fn add_chat(
         &mut self,
         chat_id: String,
         created_by: String,
         created_at: chrono::DateTime<chrono::Utc>,
-    ) -> Result<(), String>;
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
 
     fn chat_updated(
         &mut self,
         chat_id: String,
         updated_at: chrono::DateTime<chrono::Utc>,
-    ) -> Result<(), String>;
+        last_message: Option<MessagePreview>,
+        message_count: u32,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
+
+    fn pin_chat(
+        &mut self,
+        chat_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
+
+    fn unpin_chat(
+        &mut self,
+        chat_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
+
+    fn mark_chat_read(
+        &mut self,
+        chat_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError>;
+
+    // Remove chat refs (e.g. refs to not existing chats), returns number of removed refs
+    fn remove_chats(&mut self, chat_ids: HashSet<String>, correlation_id: Option<String>) -> u32;
+
+    // Remove user from participants of all their chats and remove the chats (banned user)
+    fn leave_all_chats(&mut self, correlation_id: Option<String>) -> u32;
+
+    fn get_updates(
+        &self,
+        updates_since: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
+    ) -> Option<UserChatsUpdates>;
 
-    fn get_updates(&self, updates_since: chrono::DateTime<chrono::Utc>)
-        -> Option<UserChatsUpdates>;
+    fn get_updates_by_cursor(
+        &self,
+        cursor: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<UserChatsUpdates>, ChatError>;
 }
 
 struct UserChatsAgentImpl {
@@ -115,31 +266,116 @@ impl UserChatsAgent for UserChatsAgentImpl {
     }
 
     fn get_chats(&self) -> Option<UserChats> {
-        self.state.clone()
+        self.state.clone().map(|mut state| {
+            state.sort_chats();
+            state
+        })
+    }
+
+    fn pin_chat(
+        &mut self,
+        chat_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
+        self.with_state(|state| {
+            trace_println!("pin chat - id: {chat_id}");
+            state.set_pinned(&chat_id, true).map(|_| ())
+        })
+    }
+
+    fn unpin_chat(
+        &mut self,
+        chat_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
+        self.with_state(|state| {
+            trace_println!("unpin chat - id: {chat_id}");
+            state.set_pinned(&chat_id, false).map(|_| ())
+        })
+    }
+
+    fn mark_chat_read(
+        &mut self,
+        chat_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
+        self.with_state(|state| {
+            trace_println!("mark chat read - id: {chat_id}");
+            state.mark_read(&chat_id)
+        })
+    }
+
+    fn remove_chats(&mut self, chat_ids: HashSet<String>, correlation_id: Option<String>) -> u32 {
+        trace::init(correlation_id);
+        match self.state.as_mut() {
+            Some(state) => {
+                let removed = state.remove_chats(&chat_ids);
+                trace_println!(
+                    "remove chats - requested: {}, removed: {removed}",
+                    chat_ids.len()
+                );
+                removed
+            }
+            None => 0,
+        }
+    }
+
+    fn leave_all_chats(&mut self, correlation_id: Option<String>) -> u32 {
+        trace::init(correlation_id);
+        match self.state.as_mut() {
+            Some(state) => {
+                let chat_ids: HashSet<String> =
+                    state.chats.iter().map(|c| c.chat_id.clone()).collect();
+                trace_println!("leave all chats - chats: {}", chat_ids.len());
+                for chat_id in chat_ids.iter() {
+                    ChatAgentClient::get(chat_id.clone())
+                        .trigger_remove_participant(state.user_id.clone(), trace::propagate());
+                }
+                state.remove_chats(&chat_ids)
+            }
+            None => 0,
+        }
     }
 
-    fn create_chat(&mut self, participants_ids: HashSet<String>) -> Result<String, String> {
+    async fn create_chat(
+        &mut self,
+        participants_ids: Vec<String>,
+        correlation_id: Option<String>,
+    ) -> Result<String, ChatError> {
+        trace::init(correlation_id);
+        let participants_ids = validate_participants(participants_ids)?;
+        let max_participants = get_operations_config().await.chat_max_participants;
         self.with_state(|state| {
             let u_id = state.user_id.clone();
-            let participants_ids: HashSet<String> = participants_ids
+            let participants_ids: Vec<String> = participants_ids
                 .into_iter()
                 .filter(|id| id.clone() != u_id)
-                .collect::<HashSet<_>>();
+                .collect();
             if participants_ids.is_empty() {
-                Err("Chat must have at least 2 participants".to_string())
+                Err(ChatError::invalid("Chat must have at least 2 participants"))
             } else {
-                let chat_id = uuid::Uuid::new_v4().to_string();
-                println!("create chat - id: {chat_id}");
+                check_participants_count(participants_ids.len() + 1, max_participants)?;
+                let chat_id = namespace::new_id();
+                trace_println!("create chat - id: {chat_id}");
 
-                let chat_ref = ChatRef::new(chat_id.clone(), u_id);
+                let mut chat_ref = ChatRef::new(chat_id.clone(), u_id);
+                chat_ref.sequence = state.next_sequence();
                 let created_at = chat_ref.created_at;
 
                 ChatAgentClient::get(chat_id.clone()).trigger_init_chat(
                     participants_ids,
                     state.user_id.clone(),
                     created_at,
+                    trace::propagate(),
                 );
 
+                if state.chats.is_empty() {
+                    record_onboarding_step(&state.user_id, OnboardingStep::FirstChat);
+                }
+
                 state.chats.push(chat_ref);
                 state.updated_at = created_at;
 
@@ -153,20 +389,32 @@ impl UserChatsAgent for UserChatsAgentImpl {
         chat_id: String,
         created_by: String,
         created_at: chrono::DateTime<chrono::Utc>,
-    ) -> Result<(), String> {
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
         self.with_state(|state| {
             let u_id = state.user_id.clone();
             if created_by == u_id {
-                Err("Chat created by current user".to_string())
+                Err(ChatError::invalid("Chat created by current user"))
             } else {
                 if !state.chats.iter().any(|c| c.chat_id == chat_id) {
-                    println!("add chat - id: {chat_id}");
+                    trace_println!("add chat - id: {chat_id}");
 
+                    if state.chats.is_empty() {
+                        record_onboarding_step(&u_id, OnboardingStep::FirstChat);
+                    }
+
+                    let sequence = state.next_sequence();
                     state.chats.push(ChatRef {
                         chat_id,
                         created_by,
                         created_at,
                         updated_at: created_at,
+                        sequence,
+                        last_message: None,
+                        message_count: 0,
+                        pinned: false,
+                        last_read_at: None,
                     });
                     if state.updated_at < created_at {
                         state.updated_at = created_at;
@@ -181,28 +429,39 @@ impl UserChatsAgent for UserChatsAgentImpl {
         &mut self,
         chat_id: String,
         updated_at: chrono::DateTime<chrono::Utc>,
-    ) -> Result<(), String> {
-        self.with_state(
-            |state| match state.chats.iter_mut().find(|m| m.chat_id == chat_id) {
-                Some(chat) => {
-                    println!("chat updated - id: {chat_id}");
+        last_message: Option<MessagePreview>,
+        message_count: u32,
+        correlation_id: Option<String>,
+    ) -> Result<(), ChatError> {
+        trace::init(correlation_id);
+        self.with_state(|state| {
+            if state.chats.iter().any(|c| c.chat_id == chat_id) {
+                trace_println!("chat updated - id: {chat_id}");
+                let sequence = state.next_sequence();
+                if let Some(chat) = state.chats.iter_mut().find(|c| c.chat_id == chat_id) {
                     chat.updated_at = updated_at;
-                    if state.updated_at < updated_at {
-                        state.updated_at = updated_at;
-                    }
-                    Ok(())
+                    chat.sequence = sequence;
+                    chat.last_message = last_message;
+                    chat.message_count = message_count;
                 }
-                None => Err("Chat not found".to_string()),
-            },
-        )
+                if state.updated_at < updated_at {
+                    state.updated_at = updated_at;
+                }
+                Ok(())
+            } else {
+                Err(ChatError::not_found("Chat not found"))
+            }
+        })
     }
 
     fn get_updates(
         &self,
         updates_since: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
     ) -> Option<UserChatsUpdates> {
+        trace::init(correlation_id);
         if let Some(state) = &self.state {
-            println!("get updates - updates since: {updates_since}");
+            trace_println!("get updates - updates since: {updates_since}");
 
             let updates = state
                 .chats
@@ -211,15 +470,44 @@ impl UserChatsAgent for UserChatsAgentImpl {
                 .cloned()
                 .collect();
 
-            Some(UserChatsUpdates {
-                user_id: state.user_id.clone(),
-                chats: updates,
-            })
+            Some(UserChatsUpdates::new(
+                state.user_id.clone(),
+                updates,
+                state.sequence,
+            ))
         } else {
             None
         }
     }
 
+    fn get_updates_by_cursor(
+        &self,
+        cursor: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<UserChatsUpdates>, ChatError> {
+        trace::init(correlation_id);
+        if let Some(state) = &self.state {
+            let sequence = cursor::decode_or(cursor, state.sequence)?;
+            trace_println!("get updates by cursor - sequence: {sequence}");
+
+            let updates = state
+                .chats
+                .iter()
+                .filter(|c| c.sequence > sequence)
+                .cloned()
+                .collect();
+
+            Ok(Some(UserChatsUpdates::new(
+                state.user_id.clone(),
+                updates,
+                state.sequence.max(sequence),
+            )))
+        } else {
+            cursor::decode_or(cursor, 0)?;
+            Ok(None)
+        }
+    }
+
     async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
         let data: Option<UserChats> = crate::common::snapshot::deserialize(&bytes)?;
         self.state = data;
@@ -231,17 +519,92 @@ impl UserChatsAgent for UserChatsAgentImpl {
     }
 }
 
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ChatsViewOptions {
+    // cursor returned with previous page, none for first page
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+    pub unread_only: bool,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ChatsView {
+    pub chats: Vec<ChatView>,
+    // cursor of next page, none if there are no more chats
+    pub next_cursor: Option<String>,
+    // some chats were skipped, as they were not fetched in time
+    pub degraded: bool,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct ChatSummariesView {
+    pub chats: Vec<ChatSummary>,
+    // cursor of next page, none if there are no more chats
+    pub next_cursor: Option<String>,
+    // some chats were skipped, as they were not fetched in time
+    pub degraded: bool,
+}
+
+// Page of chat refs matching the query, cursor of chats page is offset in (sorted) filtered chat refs
+fn get_chats_page(
+    user_chats: UserChats,
+    query: &query::Query,
+    options: ChatsViewOptions,
+) -> Result<(Vec<ChatRef>, Option<String>), SocialNetError> {
+    let offset = cursor::decode_or(options.cursor, 0)? as usize;
+    let limit = options
+        .limit
+        .unwrap_or(CHATS_VIEW_DEFAULT_LIMIT)
+        .clamp(1, CHATS_VIEW_MAX_LIMIT) as usize;
+    let user_id = user_chats.user_id;
+
+    let chats: Vec<ChatRef> = user_chats
+        .chats
+        .into_iter()
+        .filter(|c| c.matches_query(query))
+        .filter(|c| !options.unread_only || c.is_unread(&user_id))
+        .collect();
+
+    let count = chats.len();
+    let page: Vec<ChatRef> = chats.into_iter().skip(offset).take(limit).collect();
+    let next_cursor = if offset + page.len() < count {
+        Some(cursor::encode((offset + page.len()) as u64))
+    } else {
+        None
+    };
+
+    Ok((page, next_cursor))
+}
+
 #[agent_definition(mode = "ephemeral")]
 trait UserChatsViewAgent {
     fn new() -> Self;
 
-    async fn get_chats_view(&mut self, user_id: String, query: String) -> Option<Vec<Chat>>;
+    async fn get_chats_view(
+        &mut self,
+        user_id: String,
+        query: String,
+        options: ChatsViewOptions,
+        correlation_id: Option<String>,
+    ) -> Result<Option<ChatsView>, SocialNetError>;
+
+    // Page of chat summaries (without messages) for chat list rendering
+    async fn get_chats_summary_view(
+        &mut self,
+        user_id: String,
+        query: String,
+        options: ChatsViewOptions,
+        correlation_id: Option<String>,
+    ) -> Result<Option<ChatSummariesView>, SocialNetError>;
 
     async fn get_chats_updates_view(
         &mut self,
         user_id: String,
         updates_since: chrono::DateTime<chrono::Utc>,
-    ) -> Option<Vec<Chat>>;
+        correlation_id: Option<String>,
+    ) -> Option<ChatsView>;
+
+    fn explain_query(&self, query: String) -> query::QueryExplanation;
 }
 
 struct UserChatsViewAgentImpl {}
@@ -252,32 +615,96 @@ impl UserChatsViewAgent for UserChatsViewAgentImpl {
         Self {}
     }
 
-    async fn get_chats_view(&mut self, user_id: String, query: String) -> Option<Vec<Chat>> {
+    async fn get_chats_view(
+        &mut self,
+        user_id: String,
+        query: String,
+        options: ChatsViewOptions,
+        correlation_id: Option<String>,
+    ) -> Result<Option<ChatsView>, SocialNetError> {
+        trace::init(correlation_id);
         let user_chats = UserChatsAgentClient::get(user_id.clone()).get_chats().await;
 
-        println!("get chats view - user id: {user_id}, query: {query}");
+        trace_println!(
+            "get chats view - user id: {user_id}, query: {query}, limit: {:?}, unread only: {}",
+            options.limit,
+            options.unread_only
+        );
 
         if let Some(user_chats) = user_chats {
             let query = query::Query::new(&query);
 
-            println!("get chats view - user id: {user_id}, query matcher: {query}");
+            trace_println!("get chats view - user id: {user_id}, query matcher: {query}");
 
-            let chat_ids = user_chats
-                .chats
-                .iter()
-                .filter(|c| c.matches_query(&query))
-                .map(|p| p.chat_id.clone())
-                .collect::<Vec<_>>();
+            let (page, next_cursor) = get_chats_page(user_chats, &query, options)?;
+            let page_ids: Vec<String> = page.into_iter().map(|c| c.chat_id).collect();
 
-            if chat_ids.is_empty() {
-                Some(vec![])
+            if page_ids.is_empty() {
+                Ok(Some(ChatsView {
+                    chats: vec![],
+                    next_cursor,
+                    degraded: false,
+                }))
             } else {
-                let chats = fetch_chats_by_ids_and_query(&chat_ids, query).await;
-
-                Some(chats)
+                let timer = SlowOperationTimer::start("user chats view", user_id.clone());
+                let chats = fetch_chat_views_by_ids_and_query(&page_ids, &user_id, query)
+                    .await
+                    .map(ChatView::with_content_html);
+                timer.finish(page_ids.len()).await;
+
+                Ok(Some(ChatsView {
+                    degraded: chats.is_degraded(),
+                    chats: chats.items,
+                    next_cursor,
+                }))
             }
         } else {
-            None
+            Ok(None)
+        }
+    }
+
+    async fn get_chats_summary_view(
+        &mut self,
+        user_id: String,
+        query: String,
+        options: ChatsViewOptions,
+        correlation_id: Option<String>,
+    ) -> Result<Option<ChatSummariesView>, SocialNetError> {
+        trace::init(correlation_id);
+        let user_chats = UserChatsAgentClient::get(user_id.clone()).get_chats().await;
+
+        trace_println!(
+            "get chats summary view - user id: {user_id}, query: {query}, limit: {:?}, unread only: {}",
+            options.limit,
+            options.unread_only
+        );
+
+        if let Some(user_chats) = user_chats {
+            let query = query::Query::new(&query);
+
+            let (page, next_cursor) = get_chats_page(user_chats, &query, options)?;
+            let page_chats: Vec<_> = page
+                .into_iter()
+                .map(|c| (c.chat_id, c.last_read_at))
+                .collect();
+
+            let chats = if page_chats.is_empty() {
+                Fetched::default()
+            } else {
+                let timer = SlowOperationTimer::start("user chats summary view", user_id.clone());
+                let chats =
+                    fetch_chat_summaries_by_ids_and_query(&page_chats, &user_id, query).await;
+                timer.finish(page_chats.len()).await;
+                chats
+            };
+
+            Ok(Some(ChatSummariesView {
+                degraded: chats.is_degraded(),
+                chats: chats.items,
+                next_cursor,
+            }))
+        } else {
+            Ok(None)
         }
     }
 
@@ -285,31 +712,48 @@ impl UserChatsViewAgent for UserChatsViewAgentImpl {
         &mut self,
         user_id: String,
         updates_since: chrono::DateTime<chrono::Utc>,
-    ) -> Option<Vec<Chat>> {
+        correlation_id: Option<String>,
+    ) -> Option<ChatsView> {
+        trace::init(correlation_id);
         let user_chats_updates = UserChatsAgentClient::get(user_id.clone())
-            .get_updates(updates_since)
+            .get_updates(updates_since, trace::propagate())
             .await;
 
-        println!("get chats updates view - user id: {user_id}, updates since: {updates_since}");
+        trace_println!(
+            "get chats updates view - user id: {user_id}, updates since: {updates_since}"
+        );
 
         if let Some(user_chats_updates) = user_chats_updates {
             let updated_chat_refs = user_chats_updates.chats;
 
-            if updated_chat_refs.is_empty() {
-                Some(vec![])
+            let chats = if updated_chat_refs.is_empty() {
+                Fetched::default()
             } else {
                 let chat_ids: Vec<String> = updated_chat_refs
                     .iter()
                     .map(|p| p.chat_id.clone())
                     .collect();
-                let chats = fetch_chats_by_ids(&chat_ids).await;
+                let timer = SlowOperationTimer::start("user chats updates view", user_id.clone());
+                let chats = fetch_chat_views_by_ids(&chat_ids, &user_id)
+                    .await
+                    .map(ChatView::with_content_html);
+                timer.finish(chat_ids.len()).await;
+                chats
+            };
 
-                Some(chats)
-            }
+            Some(ChatsView {
+                degraded: chats.is_degraded(),
+                chats: chats.items,
+                next_cursor: None,
+            })
         } else {
             None
         }
     }
+
+    fn explain_query(&self, query: String) -> query::QueryExplanation {
+        query::Query::new(&query).explain(&query, CHAT_QUERY_FIELDS, CHAT_QUERY_TERM_FIELDS)
+    }
 }
 
 #[agent_definition(mode = "ephemeral")]
@@ -322,7 +766,17 @@ trait UserChatsUpdatesAgent {
         updates_since: Option<chrono::DateTime<chrono::Utc>>,
         iter_wait_time: Option<u32>,
         max_wait_time: Option<u32>,
+        correlation_id: Option<String>,
     ) -> Option<Vec<ChatRef>>;
+
+    async fn get_chats_updates_by_cursor(
+        &mut self,
+        user_id: String,
+        cursor: Option<String>,
+        iter_wait_time: Option<u32>,
+        max_wait_time: Option<u32>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<UserChatsUpdates>, SocialNetError>;
 }
 
 struct UserChatsUpdatesAgentImpl {}
@@ -339,14 +793,18 @@ impl UserChatsUpdatesAgent for UserChatsUpdatesAgentImpl {
         updates_since: Option<chrono::DateTime<chrono::Utc>>,
         iter_wait_time: Option<u32>,
         max_wait_time: Option<u32>,
+        correlation_id: Option<String>,
     ) -> Option<Vec<ChatRef>> {
+        trace::init(correlation_id);
         poll_for_updates(
             user_id,
             updates_since,
             iter_wait_time,
             max_wait_time,
             |uid, since| async move {
-                let res = UserChatsAgentClient::get(uid).get_updates(since).await;
+                let res = UserChatsAgentClient::get(uid)
+                    .get_updates(since, trace::propagate())
+                    .await;
 
                 res.map(|r| r.chats)
             },
@@ -354,4 +812,50 @@ impl UserChatsUpdatesAgent for UserChatsUpdatesAgentImpl {
         )
         .await
     }
+
+    async fn get_chats_updates_by_cursor(
+        &mut self,
+        user_id: String,
+        cursor: Option<String>,
+        iter_wait_time: Option<u32>,
+        max_wait_time: Option<u32>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<UserChatsUpdates>, SocialNetError> {
+        trace::init(correlation_id);
+        // resolves none cursor to current one and validates provided cursor
+        let current = UserChatsAgentClient::get(user_id.clone())
+            .get_updates_by_cursor(cursor, trace::propagate())
+            .await?;
+
+        match current {
+            Some(current) if current.chats.is_empty() => {
+                let since = current.cursor;
+                let since_sequence = cursor::decode(&since)?;
+                let chats = poll_for_updates_since(
+                    user_id.clone(),
+                    since,
+                    iter_wait_time,
+                    max_wait_time,
+                    |uid, since| async move {
+                        let res = UserChatsAgentClient::get(uid)
+                            .get_updates_by_cursor(Some(since), trace::propagate())
+                            .await;
+                        res.ok().flatten().map(|r| r.chats)
+                    },
+                    "get chats updates by cursor",
+                )
+                .await;
+
+                Ok(chats.map(|chats| {
+                    let sequence = chats
+                        .iter()
+                        .map(|c| c.sequence)
+                        .max()
+                        .unwrap_or(since_sequence);
+                    UserChatsUpdates::new(user_id, chats, sequence)
+                }))
+            }
+            current => Ok(current),
+        }
+    }
 }
@@ -0,0 +1,178 @@
+use crate::chat::{Chat, ChatAgentClient};
+use crate::common::fetch::{join_all_with_timeout, Fetched};
+use crate::common::{clock, trace, SocialNetError};
+use crate::operations::get_operations_config;
+use crate::post::{Post, PostAgentClient};
+use crate::trace_println;
+use crate::user::{User, UserAgentClient};
+use crate::user_chats::{UserChats, UserChatsAgentClient};
+use crate::user_posts::{UserPosts, UserPostsAgentClient};
+use crate::user_timeline::{UserTimeline, UserTimelineAgentClient};
+use golem_rust::{agent_definition, agent_implementation};
+use serde::{Deserialize, Serialize};
+
+// version of export format, increased on incompatible changes
+const EXPORT_FORMAT_VERSION: u32 = 1;
+
+// Data of user for data portability requests: profile and connections, posts of user (with archived
+// comments), timeline and chats of user (with archived messages); comments and likes of user on
+// posts of other users are part of those posts and are not exported
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UserExport {
+    pub version: u32,
+    pub user_id: String,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    pub user: User,
+    pub user_posts: Option<UserPosts>,
+    pub posts: Vec<Post>,
+    pub timeline: Option<UserTimeline>,
+    pub user_chats: Option<UserChats>,
+    pub chats: Vec<Chat>,
+    // some posts or chats were skipped, as they were not fetched in time
+    pub incomplete: bool,
+}
+
+impl UserExport {
+    fn new(user: User) -> Self {
+        UserExport {
+            version: EXPORT_FORMAT_VERSION,
+            user_id: user.user_id.clone(),
+            exported_at: clock::now(),
+            user,
+            user_posts: None,
+            posts: vec![],
+            timeline: None,
+            user_chats: None,
+            chats: vec![],
+            incomplete: false,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, SocialNetError> {
+        serde_json::to_string(self)
+            .map_err(|e| SocialNetError::Internal(format!("Export serialization failed: {e}")))
+    }
+}
+
+async fn fetch_posts_with_archive(post_ids: &[String]) -> Fetched<Post> {
+    let mut result: Fetched<Post> = Fetched::default();
+
+    for chunk in post_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
+        let clients = chunk
+            .iter()
+            .map(|post_id| PostAgentClient::get(post_id.clone()))
+            .collect::<Vec<_>>();
+
+        let tasks: Vec<_> = clients
+            .iter()
+            .map(|client| client.get_post_with_archive())
+            .collect();
+        result.extend(join_all_with_timeout(chunk, tasks).await);
+    }
+
+    result
+}
+
+async fn fetch_chats_with_archive(chat_ids: &[String]) -> Fetched<Chat> {
+    let mut result: Fetched<Chat> = Fetched::default();
+
+    for chunk in chat_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
+        let clients = chunk
+            .iter()
+            .map(|chat_id| ChatAgentClient::get(chat_id.clone()))
+            .collect::<Vec<_>>();
+
+        let tasks: Vec<_> = clients
+            .iter()
+            .map(|client| client.get_chat_with_archive())
+            .collect();
+        result.extend(join_all_with_timeout(chunk, tasks).await);
+    }
+
+    result
+}
+
+#[agent_definition(mode = "ephemeral")]
+trait UserExportAgent {
+    fn new() -> Self;
+
+    // Export of all data of user as versioned JSON document, fails if user not exists
+    async fn export_user(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<String, SocialNetError>;
+}
+
+struct UserExportAgentImpl {}
+
+#[agent_implementation]
+impl UserExportAgent for UserExportAgentImpl {
+    fn new() -> Self {
+        Self {}
+    }
+
+    async fn export_user(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<String, SocialNetError> {
+        trace::init(correlation_id);
+        let user = UserAgentClient::get(user_id.clone())
+            .get_user()
+            .await
+            .ok_or(SocialNetError::not_found("User not exists"))?;
+        let mut export = UserExport::new(user);
+
+        export.user_posts = UserPostsAgentClient::get(user_id.clone()).get_posts().await;
+        if let Some(user_posts) = &export.user_posts {
+            let post_ids: Vec<String> =
+                user_posts.posts.iter().map(|p| p.post_id.clone()).collect();
+            let posts = fetch_posts_with_archive(&post_ids).await;
+            export.incomplete |= posts.is_degraded();
+            export.posts = posts.items;
+        }
+
+        export.timeline = UserTimelineAgentClient::get(user_id.clone())
+            .get_timeline()
+            .await;
+
+        export.user_chats = UserChatsAgentClient::get(user_id.clone()).get_chats().await;
+        if let Some(user_chats) = &export.user_chats {
+            let chat_ids: Vec<String> =
+                user_chats.chats.iter().map(|c| c.chat_id.clone()).collect();
+            let chats = fetch_chats_with_archive(&chat_ids).await;
+            export.incomplete |= chats.is_degraded();
+            export.chats = chats.items;
+        }
+
+        trace_println!(
+            "export user - user id: {user_id}, posts: {}, chats: {}, incomplete: {}",
+            export.posts.len(),
+            export.chats.len(),
+            export.incomplete
+        );
+
+        export.to_json()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_export_json() {
+        let export = UserExport::new(User::new("u1".to_string()));
+        let json: serde_json::Value = serde_json::from_str(&export.to_json().unwrap()).unwrap();
+
+        assert_eq!(json["version"], EXPORT_FORMAT_VERSION);
+        assert_eq!(json["user_id"], "u1");
+        assert_eq!(json["user"]["user_id"], "u1");
+        assert_eq!(json["posts"], serde_json::json!([]));
+        assert_eq!(json["incomplete"], false);
+
+        let parsed: UserExport = serde_json::from_str(&export.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.version, EXPORT_FORMAT_VERSION);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::common::{clock, trace, SocialNetError};
+use crate::trace_println;
+use golem_rust::{agent_definition, agent_implementation, Schema};
+use serde::{Deserialize, Serialize};
+
+const HANDLE_MIN_LENGTH: usize = 3;
+const HANDLE_MAX_LENGTH: usize = 30;
+
+// max number of previous handles of user kept as aliases, older aliases are released
+pub(crate) const HANDLE_ALIASES_MAX_COUNT: usize = 5;
+
+// Registration of handle in handle registry, previous handles of renamed user stay registered as aliases
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct HandleRegistration {
+    pub handle: String,
+    pub user_id: String,
+    // current handle of user, if this handle is alias (user was renamed)
+    pub renamed_to: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl HandleRegistration {
+    fn new(handle: String, user_id: String) -> Self {
+        let now = clock::now();
+        HandleRegistration {
+            handle,
+            user_id,
+            renamed_to: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn is_alias(&self) -> bool {
+        self.renamed_to.is_some()
+    }
+}
+
+// Normalized (trimmed, lowercase) handle
+pub fn normalize_handle(handle: &str) -> String {
+    handle.trim().to_lowercase()
+}
+
+// Normalized handle of @handle mention or search value
+pub fn parse_handle(value: &str) -> String {
+    normalize_handle(value.trim().trim_start_matches('@'))
+}
+
+pub fn validate_handle(handle: &str) -> Result<(), String> {
+    if handle.len() < HANDLE_MIN_LENGTH || handle.len() > HANDLE_MAX_LENGTH {
+        Err(format!(
+            "Handle must have {HANDLE_MIN_LENGTH} - {HANDLE_MAX_LENGTH} characters"
+        ))
+    } else if !handle.starts_with(|c: char| c.is_ascii_lowercase()) {
+        Err("Handle must start with a lowercase letter".to_string())
+    } else if !handle
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        Err("Handle can contain only lowercase letters, digits and '_'".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+#[agent_definition]
+trait UserHandleAgent {
+    fn new(handle: String) -> Self;
+
+    // Registration of handle, user id of alias redirects to the renamed user
+    fn get_registration(&self) -> Option<HandleRegistration>;
+
+    // Register handle as current handle of user, fails if handle is registered by other user
+    fn claim(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Make handle alias of current handle of the same user
+    fn set_renamed_to(
+        &mut self,
+        user_id: String,
+        renamed_to: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Release handle of user, it can be claimed by other users
+    fn release(&mut self, user_id: String, correlation_id: Option<String>) -> bool;
+}
+
+struct UserHandleAgentImpl {
+    _id: String,
+    state: Option<HandleRegistration>,
+}
+
+#[agent_implementation]
+impl UserHandleAgent for UserHandleAgentImpl {
+    fn new(handle: String) -> Self {
+        UserHandleAgentImpl {
+            _id: handle,
+            state: None,
+        }
+    }
+
+    fn get_registration(&self) -> Option<HandleRegistration> {
+        self.state.clone()
+    }
+
+    fn claim(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        validate_handle(&self._id).map_err(SocialNetError::ValidationFailed)?;
+        match self.state.as_mut() {
+            Some(state) if state.user_id != user_id => {
+                trace_println!("claim - user id: {user_id} - handle taken");
+                Err(SocialNetError::already_exists("Handle is already taken"))
+            }
+            Some(state) => {
+                trace_println!("claim - user id: {user_id} - reclaimed");
+                state.renamed_to = None;
+                state.updated_at = clock::now();
+                Ok(())
+            }
+            None => {
+                trace_println!("claim - user id: {user_id}");
+                self.state = Some(HandleRegistration::new(self._id.clone(), user_id));
+                Ok(())
+            }
+        }
+    }
+
+    fn set_renamed_to(
+        &mut self,
+        user_id: String,
+        renamed_to: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        match self.state.as_mut() {
+            Some(state) if state.user_id == user_id => {
+                trace_println!("set renamed to - user id: {user_id}, renamed to: {renamed_to}");
+                state.renamed_to = Some(renamed_to);
+                state.updated_at = clock::now();
+                Ok(())
+            }
+            _ => Err(SocialNetError::not_found("Handle not registered by user")),
+        }
+    }
+
+    fn release(&mut self, user_id: String, correlation_id: Option<String>) -> bool {
+        trace::init(correlation_id);
+        if self.state.as_ref().is_some_and(|s| s.user_id == user_id) {
+            trace_println!("release - user id: {user_id}");
+            self.state = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
+        let data: Option<HandleRegistration> = crate::common::snapshot::deserialize(&bytes)?;
+        self.state = data;
+        Ok(())
+    }
+
+    async fn save_snapshot(&self) -> Result<Vec<u8>, String> {
+        crate::common::snapshot::serialize(&self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::snapshot;
+
+    #[test]
+    fn test_validate_handle() {
+        assert!(validate_handle("john_doe1").is_ok());
+        assert!(validate_handle("jd").is_err());
+        assert!(validate_handle(&"a".repeat(31)).is_err());
+        assert!(validate_handle("1john").is_err());
+        assert!(validate_handle("John").is_err());
+        assert!(validate_handle("john-doe").is_err());
+    }
+
+    #[test]
+    fn test_normalize_handle() {
+        assert_eq!(normalize_handle(" John_Doe "), "john_doe");
+        assert_eq!(parse_handle(" @John_Doe"), "john_doe");
+        assert_eq!(parse_handle("john_doe"), "john_doe");
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        snapshot::assert_round_trip(&Some(HandleRegistration::new(
+            "john_doe".to_string(),
+            "user1".to_string(),
+        )));
+    }
+}
@@ -1,7 +1,28 @@
-use crate::common::query;
-use crate::post::{fetch_posts_by_ids, fetch_posts_by_ids_and_query, Post, PostAgentClient};
+use crate::analytics::record_user_activity;
+use crate::chat::MediaMetadata;
+use crate::common::clients::AgentClients;
+use crate::common::fetch::Fetched;
+use crate::common::geo::Location;
+use crate::common::validation::{check_length, ContentField};
+use crate::common::{clock, content, cursor, namespace, query, trace, SocialNetError};
+use crate::draft::DraftSessionAgentClient;
+use crate::moderation::{moderate_content, ContentRejected};
+use crate::operations::{get_operations_config, SlowOperationTimer};
+use crate::post::{
+    fetch_post_summaries_by_ids, fetch_posts_by_ids, fetch_posts_by_ids_and_query,
+    validate_attachments, Post, PostAgentClient, PostError, PostSummary, POST_QUERY_FIELDS,
+    POST_QUERY_TERM_FIELDS,
+};
+use crate::rate_limit::{check_rate_limit, RateLimitAction, RateLimited};
+use crate::trace_println;
+use crate::user::{
+    check_user_not_banned, get_all_user_ids, record_onboarding_step, OnboardingStep,
+    UserAgentClient, UserBanned,
+};
+use futures::future::join_all;
 use golem_rust::{agent_definition, agent_implementation, Schema};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 #[derive(Schema, Clone, Serialize, Deserialize)]
 pub struct PostRef {
@@ -13,9 +34,18 @@ impl PostRef {
     fn new(post_id: String) -> Self {
         PostRef {
             post_id,
-            created_at: chrono::Utc::now(),
+            created_at: clock::now(),
         }
     }
+
+    fn created_at_micros(&self) -> u64 {
+        self.created_at.timestamp_micros().max(0) as u64
+    }
+
+    // key of posts page cursor, post id breaks ties of posts created in the same microsecond
+    fn cursor_key(&self) -> (u64, String) {
+        (self.created_at_micros(), self.post_id.clone())
+    }
 }
 
 #[derive(Schema, Clone, Serialize, Deserialize)]
@@ -28,7 +58,7 @@ pub struct UserPosts {
 
 impl UserPosts {
     fn new(user_id: String) -> Self {
-        let now = chrono::Utc::now();
+        let now = clock::now();
         UserPosts {
             user_id,
             posts: Vec::new(),
@@ -36,6 +66,131 @@ impl UserPosts {
             updated_at: now,
         }
     }
+
+    fn get_stats(&self) -> UserPostsStats {
+        UserPostsStats {
+            user_id: self.user_id.clone(),
+            post_count: self.posts.len() as u32,
+            first_post_at: self.posts.iter().map(|p| p.created_at).min(),
+            last_post_at: self.posts.iter().map(|p| p.created_at).max(),
+        }
+    }
+
+    // Page of post refs, cursor is creation time (micros) and id of the last post of previous page
+    pub(crate) fn get_page(
+        &self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        order: PostsOrder,
+    ) -> Result<PostRefsPage, PostError> {
+        let after = cursor::decode_key(cursor)?;
+        let limit = limit
+            .unwrap_or(POSTS_PAGE_DEFAULT_LIMIT)
+            .clamp(1, POSTS_PAGE_MAX_LIMIT) as usize;
+
+        let mut posts: Vec<PostRef> = self
+            .posts
+            .iter()
+            .filter(|p| match (&after, &order) {
+                (None, _) => true,
+                (Some(after), PostsOrder::NewestFirst) => &p.cursor_key() < after,
+                (Some(after), PostsOrder::OldestFirst) => &p.cursor_key() > after,
+            })
+            .cloned()
+            .collect();
+
+        match order {
+            PostsOrder::NewestFirst => posts.sort_by_key(|p| std::cmp::Reverse(p.cursor_key())),
+            PostsOrder::OldestFirst => posts.sort_by_key(|p| p.cursor_key()),
+        }
+
+        let has_more = posts.len() > limit;
+        posts.truncate(limit);
+        let next_cursor = if has_more {
+            posts
+                .last()
+                .map(|p| cursor::encode_key(p.created_at_micros(), &p.post_id))
+        } else {
+            None
+        };
+
+        Ok(PostRefsPage {
+            user_id: self.user_id.clone(),
+            posts,
+            next_cursor,
+        })
+    }
+
+    fn remove_posts(&mut self, post_ids: &HashSet<String>) -> u32 {
+        let count = self.posts.len();
+        self.posts.retain(|p| !post_ids.contains(&p.post_id));
+        let removed = (count - self.posts.len()) as u32;
+        if removed > 0 {
+            self.updated_at = clock::now();
+        }
+        removed
+    }
+}
+
+// max number of posts returned by post search
+const POST_SEARCH_MAX_RESULTS: usize = 100;
+
+// default and max number of posts in posts page
+const POSTS_PAGE_DEFAULT_LIMIT: u32 = 20;
+const POSTS_PAGE_MAX_LIMIT: u32 = 100;
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum PostsOrder {
+    NewestFirst,
+    OldestFirst,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct PostRefsPage {
+    pub user_id: String,
+    pub posts: Vec<PostRef>,
+    // cursor of next page, none if this is the last page
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct UserPostsStats {
+    pub user_id: String,
+    pub post_count: u32,
+    pub first_post_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_post_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct PostsViewOptions {
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+    pub order: Option<PostsOrder>,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct PostsView {
+    pub posts: Vec<Post>,
+    pub next_cursor: Option<String>,
+    // some posts were skipped, as they were not fetched in time
+    pub degraded: bool,
+}
+
+// Lightweight posts view, post summaries without comments
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct PostSummariesView {
+    pub posts: Vec<PostSummary>,
+    pub next_cursor: Option<String>,
+    // some posts were skipped, as they were not fetched in time
+    pub degraded: bool,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum PostSearchMode {
+    // posts of all users
+    All,
+    // posts authored by connections (friends, followers, following) of the requesting user
+    Connections,
 }
 
 #[derive(Schema, Clone, Serialize, Deserialize)]
@@ -50,10 +205,59 @@ trait UserPostsAgent {
 
     fn get_posts(&self) -> Option<UserPosts>;
 
-    fn get_updates(&self, updates_since: chrono::DateTime<chrono::Utc>)
-        -> Option<UserPostsUpdates>;
+    fn list_posts(
+        &self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        order: PostsOrder,
+    ) -> Result<Option<PostRefsPage>, PostError>;
+
+    // Post count and first/last post timestamps
+    fn get_post_stats(&self) -> Option<UserPostsStats>;
 
-    fn create_post(&mut self, content: String) -> Result<String, String>;
+    fn get_updates(
+        &self,
+        updates_since: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
+    ) -> Option<UserPostsUpdates>;
+
+    async fn create_post(
+        &mut self,
+        content: String,
+        location: Option<Location>,
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError>;
+
+    // Repost of post (of any user) with optional comment, repost of repost references the original post
+    async fn create_repost(
+        &mut self,
+        post_id: String,
+        comment: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError>;
+
+    // Start multi-step post composition, returns reserved post id (id of draft session)
+    async fn create_draft(&mut self, correlation_id: Option<String>) -> Result<String, PostError>;
+
+    // Create post with reserved id of draft session (called by DraftSessionAgent::publish)
+    async fn publish_draft(
+        &mut self,
+        post_id: String,
+        content: String,
+        location: Option<Location>,
+        attachments: Vec<MediaMetadata>,
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError>;
+
+    // Delete post of the user: removes post ref, tombstones post and evicts it from timelines
+    fn delete_post(
+        &mut self,
+        post_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError>;
+
+    // Remove post refs (e.g. refs to not existing posts), returns number of removed refs
+    fn remove_posts(&mut self, post_ids: HashSet<String>, correlation_id: Option<String>) -> u32;
 }
 
 struct UserPostsAgentImpl {
@@ -61,16 +265,6 @@ struct UserPostsAgentImpl {
     state: Option<UserPosts>,
 }
 
-impl UserPostsAgentImpl {
-    fn get_state(&mut self) -> &mut UserPosts {
-        self.state.get_or_insert(UserPosts::new(self._id.clone()))
-    }
-
-    fn with_state<T>(&mut self, f: impl FnOnce(&mut UserPosts) -> T) -> T {
-        f(self.get_state())
-    }
-}
-
 #[agent_implementation]
 impl UserPostsAgent for UserPostsAgentImpl {
     fn new(id: String) -> Self {
@@ -84,12 +278,30 @@ impl UserPostsAgent for UserPostsAgentImpl {
         self.state.clone()
     }
 
+    fn list_posts(
+        &self,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        order: PostsOrder,
+    ) -> Result<Option<PostRefsPage>, PostError> {
+        match &self.state {
+            Some(state) => state.get_page(cursor, limit, order).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn get_post_stats(&self) -> Option<UserPostsStats> {
+        self.state.as_ref().map(|state| state.get_stats())
+    }
+
     fn get_updates(
         &self,
         updates_since: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
     ) -> Option<UserPostsUpdates> {
+        trace::init(correlation_id);
         if let Some(state) = &self.state {
-            println!("get updates - updates since: {updates_since}");
+            trace_println!("get updates - updates since: {updates_since}");
 
             let updates = state
                 .posts
@@ -107,21 +319,96 @@ impl UserPostsAgent for UserPostsAgentImpl {
         }
     }
 
-    fn create_post(&mut self, content: String) -> Result<String, String> {
-        self.with_state(|state| {
-            let post_id = uuid::Uuid::new_v4().to_string();
+    async fn create_post(
+        &mut self,
+        content: String,
+        location: Option<Location>,
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError> {
+        trace::init(correlation_id);
+        add_post(
+            &AgentClients,
+            &mut self.state,
+            &self._id,
+            namespace::new_id(),
+            content,
+            location,
+            vec![],
+        )
+        .await
+    }
 
-            println!("create post - id: {post_id}");
+    async fn create_draft(&mut self, correlation_id: Option<String>) -> Result<String, PostError> {
+        trace::init(correlation_id);
+        check_user_not_banned(&self._id).await?;
+        let post_id = namespace::new_id();
+        DraftSessionAgentClient::get(post_id.clone())
+            .start(self._id.clone(), trace::propagate())
+            .await?;
+        trace_println!("create draft - post id: {post_id}");
+        Ok(post_id)
+    }
 
-            let post_ref = PostRef::new(post_id.clone());
+    async fn publish_draft(
+        &mut self,
+        post_id: String,
+        content: String,
+        location: Option<Location>,
+        attachments: Vec<MediaMetadata>,
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError> {
+        trace::init(correlation_id);
+        let published = self
+            .state
+            .as_ref()
+            .is_some_and(|s| s.posts.iter().any(|p| p.post_id == post_id));
+        if published {
+            trace_println!("publish draft - post id: {post_id} - already published");
+            Ok(post_id)
+        } else {
+            add_post(
+                &AgentClients,
+                &mut self.state,
+                &self._id,
+                post_id,
+                content,
+                location,
+                attachments,
+            )
+            .await
+        }
+    }
 
-            PostAgentClient::get(post_id.clone()).trigger_init_post(state.user_id.clone(), content);
+    async fn create_repost(
+        &mut self,
+        post_id: String,
+        comment: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<String, PostError> {
+        trace::init(correlation_id);
+        create_repost(
+            &AgentClients,
+            &mut self.state,
+            &self._id,
+            namespace::new_id(),
+            post_id,
+            comment,
+        )
+        .await
+    }
 
-            state.updated_at = post_ref.created_at;
-            state.posts.push(post_ref);
+    fn delete_post(
+        &mut self,
+        post_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), PostError> {
+        trace::init(correlation_id);
+        delete_post(&AgentClients, &mut self.state, post_id)
+    }
 
-            Ok(post_id)
-        })
+    fn remove_posts(&mut self, post_ids: HashSet<String>, correlation_id: Option<String>) -> u32 {
+        trace::init(correlation_id);
+        remove_posts(&mut self.state, &post_ids)
     }
 
     async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
@@ -135,17 +422,279 @@ impl UserPostsAgent for UserPostsAgentImpl {
     }
 }
 
+// Calls of user posts flows to other agents
+pub(crate) trait UserPostsClients {
+    async fn check_user_not_banned(&self, user_id: &str) -> Result<(), UserBanned>;
+
+    async fn check_rate_limit(
+        &self,
+        user_id: &str,
+        action: RateLimitAction,
+    ) -> Result<(), RateLimited>;
+
+    async fn moderate_content(&self, content: String) -> Result<String, ContentRejected>;
+
+    async fn get_post(&self, post_id: &str) -> Option<Post>;
+
+    // Check if other user can like content of user
+    async fn can_like(&self, user_id: &str, other_user_id: &str) -> bool;
+
+    fn init_post(
+        &self,
+        post_id: &str,
+        user_id: &str,
+        content: String,
+        location: Option<Location>,
+        attachments: Vec<MediaMetadata>,
+    );
+
+    fn init_repost(&self, post_id: &str, user_id: &str, reposted_from: String, content: String);
+
+    fn delete_post(&self, post_id: &str, acting_user_id: &str);
+
+    fn record_onboarding_step(&self, user_id: &str, step: OnboardingStep);
+
+    fn record_user_activity(&self, user_id: &str);
+}
+
+impl UserPostsClients for AgentClients {
+    async fn check_user_not_banned(&self, user_id: &str) -> Result<(), UserBanned> {
+        check_user_not_banned(user_id).await
+    }
+
+    async fn check_rate_limit(
+        &self,
+        user_id: &str,
+        action: RateLimitAction,
+    ) -> Result<(), RateLimited> {
+        check_rate_limit(user_id, action).await
+    }
+
+    async fn moderate_content(&self, content: String) -> Result<String, ContentRejected> {
+        moderate_content(content).await
+    }
+
+    async fn get_post(&self, post_id: &str) -> Option<Post> {
+        PostAgentClient::get(post_id.to_string()).get_post().await
+    }
+
+    async fn can_like(&self, user_id: &str, other_user_id: &str) -> bool {
+        UserAgentClient::get(user_id.to_string())
+            .can_like(other_user_id.to_string())
+            .await
+    }
+
+    fn init_post(
+        &self,
+        post_id: &str,
+        user_id: &str,
+        content: String,
+        location: Option<Location>,
+        attachments: Vec<MediaMetadata>,
+    ) {
+        PostAgentClient::get(post_id.to_string()).trigger_init_post(
+            user_id.to_string(),
+            content,
+            location,
+            attachments,
+            trace::propagate(),
+        );
+    }
+
+    fn init_repost(&self, post_id: &str, user_id: &str, reposted_from: String, content: String) {
+        PostAgentClient::get(post_id.to_string()).trigger_init_repost(
+            user_id.to_string(),
+            reposted_from,
+            content,
+            trace::propagate(),
+        );
+    }
+
+    fn delete_post(&self, post_id: &str, acting_user_id: &str) {
+        PostAgentClient::get(post_id.to_string())
+            .trigger_delete_post(acting_user_id.to_string(), trace::propagate());
+    }
+
+    fn record_onboarding_step(&self, user_id: &str, step: OnboardingStep) {
+        record_onboarding_step(user_id, step);
+    }
+
+    fn record_user_activity(&self, user_id: &str) {
+        record_user_activity(user_id);
+    }
+}
+
+// Add post of user (UserPostsAgent::create_post and publish_draft), post is initialized by post agent
+pub(crate) async fn add_post(
+    clients: &impl UserPostsClients,
+    state: &mut Option<UserPosts>,
+    user_id: &str,
+    post_id: String,
+    content: String,
+    location: Option<Location>,
+    attachments: Vec<MediaMetadata>,
+) -> Result<String, PostError> {
+    let content = content::sanitize(&content);
+    if content.is_empty() {
+        return Err(PostError::invalid("Post content is empty"));
+    }
+    // checked before the post ref is added, init of post agent is not awaited
+    check_length(ContentField::PostContent, &content)?;
+    let location = location
+        .map(|l| l.validated())
+        .transpose()
+        .map_err(PostError::Invalid)?;
+    validate_attachments(&attachments)?;
+    clients.check_user_not_banned(user_id).await?;
+    clients
+        .check_rate_limit(user_id, RateLimitAction::Post)
+        .await?;
+    let content = clients.moderate_content(content).await?;
+    let state = state.get_or_insert(UserPosts::new(user_id.to_string()));
+    trace_println!("create post - id: {post_id}");
+
+    let post_ref = PostRef::new(post_id.clone());
+
+    clients.init_post(&post_id, &state.user_id, content, location, attachments);
+
+    if state.posts.is_empty() {
+        clients.record_onboarding_step(&state.user_id, OnboardingStep::FirstPost);
+    }
+
+    state.updated_at = post_ref.created_at;
+    state.posts.push(post_ref);
+
+    clients.record_user_activity(&state.user_id);
+
+    Ok(post_id)
+}
+
+// Add repost of user (UserPostsAgent::create_repost), repost is initialized by post agent
+pub(crate) async fn create_repost(
+    clients: &impl UserPostsClients,
+    state: &mut Option<UserPosts>,
+    user_id: &str,
+    repost_id: String,
+    post_id: String,
+    comment: Option<String>,
+) -> Result<String, PostError> {
+    let comment = content::sanitize(&comment.unwrap_or_default());
+    check_length(ContentField::PostContent, &comment)?;
+    clients.check_user_not_banned(user_id).await?;
+    let mut original = clients
+        .get_post(&post_id)
+        .await
+        .ok_or(PostError::NotFound(format!("Post not exists: {post_id}")))?;
+    // a repost of a repost references the original post
+    if let Some(reposted_from) = original.reposted_from.clone() {
+        original = clients
+            .get_post(&reposted_from)
+            .await
+            .ok_or(PostError::NotFound(format!(
+                "Post not exists: {reposted_from}"
+            )))?;
+    }
+    // privacy settings of original author, as for likes of the post
+    if original.created_by != user_id && !clients.can_like(&original.created_by, user_id).await {
+        trace_println!(
+            "create repost - post id: {} - not allowed",
+            original.post_id
+        );
+        return Err(PostError::NotAllowed(user_id.to_string()));
+    }
+    let reposted_from = original.post_id;
+    clients
+        .check_rate_limit(user_id, RateLimitAction::Post)
+        .await?;
+    let comment = if comment.is_empty() {
+        comment
+    } else {
+        clients.moderate_content(comment).await?
+    };
+    let state = state.get_or_insert(UserPosts::new(user_id.to_string()));
+
+    trace_println!("create repost - id: {repost_id}, reposted from: {reposted_from}");
+
+    let post_ref = PostRef::new(repost_id.clone());
+
+    clients.init_repost(&repost_id, &state.user_id, reposted_from, comment);
+
+    state.updated_at = post_ref.created_at;
+    state.posts.push(post_ref);
+
+    clients.record_user_activity(&state.user_id);
+
+    Ok(repost_id)
+}
+
+// Delete post of user (UserPostsAgent::delete_post)
+pub(crate) fn delete_post(
+    clients: &impl UserPostsClients,
+    state: &mut Option<UserPosts>,
+    post_id: String,
+) -> Result<(), PostError> {
+    match state.as_mut() {
+        Some(state) if state.posts.iter().any(|p| p.post_id == post_id) => {
+            trace_println!("delete post - id: {post_id}");
+
+            state.remove_posts(&HashSet::from([post_id.clone()]));
+
+            // post agent evicts deleted post from timelines
+            clients.delete_post(&post_id, &state.user_id);
+
+            Ok(())
+        }
+        _ => Err(PostError::not_found("Post not found")),
+    }
+}
+
+pub(crate) fn remove_posts(state: &mut Option<UserPosts>, post_ids: &HashSet<String>) -> u32 {
+    match state.as_mut() {
+        Some(state) => {
+            let removed = state.remove_posts(post_ids);
+            trace_println!(
+                "remove posts - requested: {}, removed: {removed}",
+                post_ids.len()
+            );
+            removed
+        }
+        None => 0,
+    }
+}
+
 #[agent_definition(mode = "ephemeral")]
 trait UserPostsViewAgent {
     fn new() -> Self;
 
-    async fn get_posts_view(&mut self, user_id: String, query: String) -> Option<Vec<Post>>;
+    async fn get_posts_view(
+        &mut self,
+        user_id: String,
+        query: String,
+        correlation_id: Option<String>,
+    ) -> Option<PostsView>;
+
+    // Posts page, only posts of the requested page are hydrated
+    async fn get_posts_page_view(
+        &mut self,
+        user_id: String,
+        options: PostsViewOptions,
+        correlation_id: Option<String>,
+    ) -> Result<Option<PostsView>, SocialNetError>;
+
+    // Posts page as post summaries, comments are not fetched
+    async fn get_post_summaries_page_view(
+        &mut self,
+        user_id: String,
+        options: PostsViewOptions,
+        correlation_id: Option<String>,
+    ) -> Result<Option<PostSummariesView>, SocialNetError>;
 
     async fn get_posts_updates_view(
         &mut self,
         user_id: String,
         updates_since: chrono::DateTime<chrono::Utc>,
-    ) -> Option<Vec<Post>>;
+        correlation_id: Option<String>,
+    ) -> Option<PostsView>;
 }
 
 struct UserPostsViewAgentImpl {}
@@ -156,58 +705,268 @@ impl UserPostsViewAgent for UserPostsViewAgentImpl {
         Self {}
     }
 
-    async fn get_posts_view(&mut self, user_id: String, query: String) -> Option<Vec<Post>> {
+    async fn get_posts_view(
+        &mut self,
+        user_id: String,
+        query: String,
+        correlation_id: Option<String>,
+    ) -> Option<PostsView> {
+        trace::init(correlation_id);
         let user_posts = UserPostsAgentClient::get(user_id.clone()).get_posts().await;
 
-        println!("get posts view - user id: {user_id}, query: {query}");
+        trace_println!("get posts view - user id: {user_id}, query: {query}");
 
         if let Some(user_posts) = user_posts {
             let query = query::Query::new(&query);
 
-            println!("get posts view - user id: {user_id}, query matcher: {query}");
+            trace_println!("get posts view - user id: {user_id}, query matcher: {query}");
 
             let user_posts = user_posts.posts;
 
-            if user_posts.is_empty() {
-                Some(vec![])
+            let posts = if user_posts.is_empty() {
+                Fetched::default()
             } else {
                 let post_ids: Vec<String> = user_posts.iter().map(|p| p.post_id.clone()).collect();
-                let posts = fetch_posts_by_ids_and_query(&post_ids, query).await;
-
-                Some(posts)
-            }
+                let timer = SlowOperationTimer::start("user posts view", user_id.clone());
+                let posts = fetch_posts_by_ids_and_query(&post_ids, query)
+                    .await
+                    .map(Post::with_content_html);
+                timer.finish(post_ids.len()).await;
+                posts
+            };
+
+            Some(PostsView {
+                degraded: posts.is_degraded(),
+                posts: posts.items,
+                next_cursor: None,
+            })
         } else {
             None
         }
     }
 
+    async fn get_posts_page_view(
+        &mut self,
+        user_id: String,
+        options: PostsViewOptions,
+        correlation_id: Option<String>,
+    ) -> Result<Option<PostsView>, SocialNetError> {
+        trace::init(correlation_id);
+        let order = options.order.unwrap_or(PostsOrder::NewestFirst);
+
+        trace_println!("get posts page view - user id: {user_id}, order: {order:?}");
+
+        let page = UserPostsAgentClient::get(user_id.clone())
+            .list_posts(options.cursor, options.limit, order)
+            .await?;
+
+        if let Some(page) = page {
+            let post_ids: Vec<String> = page.posts.into_iter().map(|p| p.post_id).collect();
+
+            let posts = if post_ids.is_empty() {
+                Fetched::default()
+            } else {
+                let timer = SlowOperationTimer::start("user posts page view", user_id.clone());
+                let posts = fetch_posts_by_ids(&post_ids)
+                    .await
+                    .map(Post::with_content_html);
+                timer.finish(post_ids.len()).await;
+                posts
+            };
+
+            Ok(Some(PostsView {
+                degraded: posts.is_degraded(),
+                posts: posts.items,
+                next_cursor: page.next_cursor,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_post_summaries_page_view(
+        &mut self,
+        user_id: String,
+        options: PostsViewOptions,
+        correlation_id: Option<String>,
+    ) -> Result<Option<PostSummariesView>, SocialNetError> {
+        trace::init(correlation_id);
+        let order = options.order.unwrap_or(PostsOrder::NewestFirst);
+
+        trace_println!("get post summaries page view - user id: {user_id}, order: {order:?}");
+
+        let page = UserPostsAgentClient::get(user_id.clone())
+            .list_posts(options.cursor, options.limit, order)
+            .await?;
+
+        if let Some(page) = page {
+            let post_ids: Vec<String> = page.posts.into_iter().map(|p| p.post_id).collect();
+
+            let posts = if post_ids.is_empty() {
+                Fetched::default()
+            } else {
+                let timer =
+                    SlowOperationTimer::start("user post summaries page view", user_id.clone());
+                let posts = fetch_post_summaries_by_ids(&post_ids, None)
+                    .await
+                    .map(PostSummary::with_content_html);
+                timer.finish(post_ids.len()).await;
+                posts
+            };
+
+            Ok(Some(PostSummariesView {
+                degraded: posts.is_degraded(),
+                posts: posts.items,
+                next_cursor: page.next_cursor,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
     async fn get_posts_updates_view(
         &mut self,
         user_id: String,
         updates_since: chrono::DateTime<chrono::Utc>,
-    ) -> Option<Vec<Post>> {
+        correlation_id: Option<String>,
+    ) -> Option<PostsView> {
+        trace::init(correlation_id);
         let user_posts_updates = UserPostsAgentClient::get(user_id.clone())
-            .get_updates(updates_since)
+            .get_updates(updates_since, trace::propagate())
             .await;
 
-        println!("get posts updates view - user id: {user_id}, updates since: {updates_since}");
+        trace_println!(
+            "get posts updates view - user id: {user_id}, updates since: {updates_since}"
+        );
 
         if let Some(user_posts_updates) = user_posts_updates {
             let updated_post_refs = user_posts_updates.posts;
 
-            if updated_post_refs.is_empty() {
-                Some(vec![])
+            let posts = if updated_post_refs.is_empty() {
+                Fetched::default()
             } else {
                 let post_ids: Vec<String> = updated_post_refs
                     .iter()
                     .map(|p| p.post_id.clone())
                     .collect();
-                let posts = fetch_posts_by_ids(&post_ids).await;
-
-                Some(posts)
-            }
+                let timer = SlowOperationTimer::start("user posts updates view", user_id.clone());
+                let posts = fetch_posts_by_ids(&post_ids)
+                    .await
+                    .map(Post::with_content_html);
+                timer.finish(post_ids.len()).await;
+                posts
+            };
+
+            Some(PostsView {
+                degraded: posts.is_degraded(),
+                posts: posts.items,
+                next_cursor: None,
+            })
         } else {
             None
         }
     }
 }
+
+// Post IDs of given users, fan out to their UserPostsAgents
+async fn get_users_post_ids(user_ids: Vec<String>) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+
+    for chunk in user_ids.chunks(get_operations_config().await.fetch_chunk_size()) {
+        let clients = chunk
+            .iter()
+            .map(|user_id| UserPostsAgentClient::get(user_id.clone()))
+            .collect::<Vec<_>>();
+
+        let tasks: Vec<_> = clients.iter().map(|client| client.get_posts()).collect();
+        let responses = join_all(tasks).await;
+
+        result.extend(
+            responses
+                .into_iter()
+                .flatten()
+                .flat_map(|user_posts| user_posts.posts.into_iter().map(|p| p.post_id)),
+        );
+    }
+
+    result
+}
+
+#[agent_definition(mode = "ephemeral")]
+trait PostSearchAgent {
+    fn new() -> Self;
+
+    // Search posts (newest first), user id is the requesting user
+    async fn search(
+        &self,
+        user_id: String,
+        query: String,
+        mode: PostSearchMode,
+        correlation_id: Option<String>,
+    ) -> Result<Vec<Post>, SocialNetError>;
+
+    fn explain_query(&self, query: String) -> query::QueryExplanation;
+}
+
+struct PostSearchAgentImpl;
+
+#[agent_implementation]
+impl PostSearchAgent for PostSearchAgentImpl {
+    fn new() -> Self {
+        PostSearchAgentImpl
+    }
+
+    async fn search(
+        &self,
+        user_id: String,
+        query: String,
+        mode: PostSearchMode,
+        correlation_id: Option<String>,
+    ) -> Result<Vec<Post>, SocialNetError> {
+        trace::init(correlation_id);
+        trace_println!("searching for posts - user id: {user_id}, query: {query}, mode: {mode:?}");
+        let timer = SlowOperationTimer::start("posts search", user_id.clone());
+        let query = query::Query::new(&query);
+
+        let author_ids: Vec<String> = match mode {
+            PostSearchMode::All => get_all_user_ids().await.into_iter().collect(),
+            PostSearchMode::Connections => {
+                let user = UserAgentClient::get(user_id.clone())
+                    .get_user()
+                    .await
+                    .ok_or(SocialNetError::not_found("User not found"))?;
+                user.connected_users.into_keys().collect()
+            }
+        };
+
+        trace_println!(
+            "searching for posts - user id: {user_id}, authors: {}",
+            author_ids.len()
+        );
+
+        let authors_count = author_ids.len();
+        let post_ids = get_users_post_ids(author_ids).await;
+
+        let fetched = fetch_posts_by_ids_and_query(&post_ids, query).await;
+        if fetched.is_degraded() {
+            trace_println!(
+                "searching for posts - user id: {user_id}, skipped: {}",
+                fetched.skipped
+            );
+        }
+        let mut posts: Vec<Post> = fetched
+            .items
+            .into_iter()
+            .map(Post::with_content_html)
+            .collect();
+        posts.sort_by_key(|p| std::cmp::Reverse(p.created_at));
+        posts.truncate(POST_SEARCH_MAX_RESULTS);
+
+        timer.finish(authors_count + post_ids.len()).await;
+        Ok(posts)
+    }
+
+    fn explain_query(&self, query: String) -> query::QueryExplanation {
+        query::Query::new(&query).explain(&query, POST_QUERY_FIELDS, POST_QUERY_TERM_FIELDS)
+    }
+}
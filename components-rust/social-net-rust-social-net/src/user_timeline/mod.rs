@@ -1,21 +1,219 @@
+use crate::common::clients::AgentClients;
+use crate::common::clock;
+use crate::common::fetch::Fetched;
 use crate::common::query::Query;
-use crate::common::UserConnectionType;
-use crate::common::{poll_for_updates, query};
-use crate::post::{fetch_posts_by_ids, fetch_posts_by_ids_and_query, Post};
+use crate::common::{cursor, poll_for_updates, poll_for_updates_since, query, trace};
+use crate::common::{SocialNetError, UserConnectionType};
+use crate::operations::SlowOperationTimer;
+use crate::post::{
+    fetch_post_summaries_by_ids, fetch_post_summaries_by_ids_and_query, fetch_posts_by_ids, Post,
+    PostSummary,
+};
+use crate::trace_println;
+use crate::user::UserAgentClient;
 use golem_rust::{agent_definition, agent_implementation, Schema};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 // max number of posts in timeline
 const POSTS_MAX_COUNT: usize = 500;
 
+// max number of (most recent) removed posts kept, so updates can report removals
+const REMOVED_POSTS_MAX_COUNT: usize = 500;
+
+// default and max number of posts in timeline export page
+const EXPORT_PAGE_DEFAULT_SIZE: u32 = 50;
+const EXPORT_PAGE_MAX_SIZE: u32 = 200;
+
+// max number and length of muted keywords
+const MUTED_KEYWORDS_MAX_COUNT: usize = 100;
+const MUTED_KEYWORD_MAX_LENGTH: usize = 50;
+
+// max number of muted users
+const MUTED_USERS_MAX_COUNT: usize = 500;
+
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub enum TimelineSort {
+    // by post creation
+    #[default]
+    NewestFirst,
+    OldestFirst,
+    // by likes and comments
+    MostEngaged,
+}
+
+// Timeline preferences of user, applied by timeline view
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct TimelinePreferences {
+    pub sort: TimelineSort,
+    // posts with content containing any of muted keywords (case insensitive) are hidden
+    pub muted_keywords: Vec<String>,
+    // most engaged posts are boosted by interaction score of user with post author
+    #[serde(default)]
+    pub connection_boost: bool,
+    // posts of muted users are hidden
+    #[serde(default)]
+    pub muted_user_ids: Vec<String>,
+}
+
+impl TimelinePreferences {
+    pub(crate) fn validated(self) -> Result<Self, SocialNetError> {
+        let mut muted_keywords: Vec<String> = vec![];
+        for keyword in self.muted_keywords {
+            let keyword = keyword.trim().to_lowercase();
+            if keyword.chars().count() > MUTED_KEYWORD_MAX_LENGTH {
+                return Err(SocialNetError::ValidationFailed(format!(
+                    "Muted keyword must have at most {MUTED_KEYWORD_MAX_LENGTH} characters"
+                )));
+            }
+            if !keyword.is_empty() && !muted_keywords.contains(&keyword) {
+                muted_keywords.push(keyword);
+            }
+        }
+        if muted_keywords.len() > MUTED_KEYWORDS_MAX_COUNT {
+            return Err(SocialNetError::LimitExceeded(format!(
+                "Max number of muted keywords is {MUTED_KEYWORDS_MAX_COUNT}"
+            )));
+        }
+        let mut muted_user_ids: Vec<String> = vec![];
+        for user_id in self.muted_user_ids {
+            let user_id = user_id.trim().to_string();
+            if !user_id.is_empty() && !muted_user_ids.contains(&user_id) {
+                muted_user_ids.push(user_id);
+            }
+        }
+        if muted_user_ids.len() > MUTED_USERS_MAX_COUNT {
+            return Err(SocialNetError::LimitExceeded(format!(
+                "Max number of muted users is {MUTED_USERS_MAX_COUNT}"
+            )));
+        }
+        Ok(TimelinePreferences {
+            sort: self.sort,
+            muted_keywords,
+            connection_boost: self.connection_boost,
+            muted_user_ids,
+        })
+    }
+
+    pub fn is_user_muted(&self, user_id: &str) -> bool {
+        self.muted_user_ids.iter().any(|id| id == user_id)
+    }
+
+    // Mute user, returns false if user is already muted
+    pub(crate) fn mute_user(&mut self, user_id: String) -> Result<bool, SocialNetError> {
+        let user_id = user_id.trim().to_string();
+        if self.is_user_muted(&user_id) {
+            Ok(false)
+        } else {
+            let mut muted_user_ids = self.muted_user_ids.clone();
+            muted_user_ids.push(user_id);
+            self.set_validated(TimelinePreferences {
+                muted_user_ids,
+                ..self.clone()
+            })
+        }
+    }
+
+    pub(crate) fn unmute_user(&mut self, user_id: &str) -> bool {
+        let count = self.muted_user_ids.len();
+        self.muted_user_ids.retain(|id| id != user_id);
+        self.muted_user_ids.len() != count
+    }
+
+    // Mute keyword, returns false if keyword is already muted
+    pub(crate) fn mute_keyword(&mut self, keyword: String) -> Result<bool, SocialNetError> {
+        let mut muted_keywords = self.muted_keywords.clone();
+        muted_keywords.push(keyword);
+        self.set_validated(TimelinePreferences {
+            muted_keywords,
+            ..self.clone()
+        })
+    }
+
+    pub(crate) fn unmute_keyword(&mut self, keyword: &str) -> bool {
+        let keyword = keyword.trim().to_lowercase();
+        let count = self.muted_keywords.len();
+        self.muted_keywords.retain(|k| *k != keyword);
+        self.muted_keywords.len() != count
+    }
+
+    // Replace preferences with validated ones, returns true if preferences changed
+    fn set_validated(&mut self, preferences: TimelinePreferences) -> Result<bool, SocialNetError> {
+        let preferences = preferences.validated()?;
+        let changed = *self != preferences;
+        *self = preferences;
+        Ok(changed)
+    }
+
+    // Content contains any of muted keywords as whole word or phrase (case insensitive)
+    pub fn is_muted(&self, content: &str) -> bool {
+        if self.muted_keywords.is_empty() {
+            false
+        } else {
+            let content = content.to_lowercase();
+            self.muted_keywords
+                .iter()
+                .any(|k| contains_keyword(&content, k))
+        }
+    }
+
+    // Connection scores (interaction scores of user with post authors) are needed only if
+    // connection boost is used, see uses_connection_scores
+    pub(crate) fn sort_posts(
+        &self,
+        posts: &mut [PostRef],
+        connection_scores: &HashMap<String, u32>,
+    ) {
+        match self.sort {
+            TimelineSort::NewestFirst => posts.sort_by_key(|p| std::cmp::Reverse(p.created_at)),
+            TimelineSort::OldestFirst => posts.sort_by_key(|p| p.created_at),
+            TimelineSort::MostEngaged => posts.sort_by_key(|p| {
+                let boost = if self.connection_boost {
+                    connection_scores.get(&p.created_by).copied().unwrap_or(0)
+                } else {
+                    0
+                };
+                std::cmp::Reverse((p.like_count + p.comment_count + boost, p.created_at))
+            }),
+        }
+    }
+
+    pub(crate) fn uses_connection_scores(&self) -> bool {
+        self.connection_boost && self.sort == TimelineSort::MostEngaged
+    }
+}
+
+// Keyword occurrence not being part of longer word, so 'art' does not match 'party' but matches '#art'
+fn contains_keyword(content: &str, keyword: &str) -> bool {
+    let is_word_char = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric());
+    content.match_indices(keyword).any(|(start, _)| {
+        let before = content[..start].chars().next_back();
+        let after = content[start + keyword.len()..].chars().next();
+        !is_word_char(before) && !is_word_char(after)
+    })
+}
+
 #[derive(Schema, Clone, Serialize, Deserialize)]
 pub struct PostRef {
     pub post_id: String,
     pub created_by: String,
+    // display name of author at the time of the update, for list rendering
+    #[serde(default)]
+    pub created_by_name: Option<String>,
     pub created_by_connection_type: Option<UserConnectionType>,
+    // counts of post likes and comments at the time of the update, for list rendering
+    #[serde(default)]
+    pub like_count: u32,
+    #[serde(default)]
+    pub comment_count: u32,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    // timeline sequence number of the last update
+    #[serde(default)]
+    pub sequence: u64,
+    // beginning of post content sent by fanout for muted keywords pre-filtering, not stored in timeline
+    #[serde(default)]
+    pub content_snippet: Option<String>,
 }
 
 impl PostRef {
@@ -29,9 +227,36 @@ impl PostRef {
         PostRef {
             post_id,
             created_by,
+            created_by_name: None,
             created_by_connection_type,
+            like_count: 0,
+            comment_count: 0,
             created_at,
             updated_at,
+            sequence: 0,
+            content_snippet: None,
+        }
+    }
+
+    pub fn with_counts(self, like_count: u32, comment_count: u32) -> Self {
+        PostRef {
+            like_count,
+            comment_count,
+            ..self
+        }
+    }
+
+    pub fn with_created_by_name(self, created_by_name: Option<String>) -> Self {
+        PostRef {
+            created_by_name,
+            ..self
+        }
+    }
+
+    pub fn with_content_snippet(self, content_snippet: String) -> Self {
+        PostRef {
+            content_snippet: Some(content_snippet),
+            ..self
         }
     }
 
@@ -47,7 +272,7 @@ impl PostRef {
                     value,
                 ),
                 "created-by" | "createdby" => query::text_exact_matches(&self.created_by, value),
-                "content" => true,
+                "content" | "location" | "near" | "language" | "lang" => true,
                 _ => false, // Unknown field
             };
 
@@ -60,39 +285,133 @@ impl PostRef {
     }
 }
 
+// Tombstone of post removed from timeline
+#[derive(Schema, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RemovedPostRef {
+    pub post_id: String,
+    // timeline sequence number of the removal
+    pub sequence: u64,
+    pub removed_at: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Schema, Clone, Serialize, Deserialize)]
 pub struct UserTimeline {
     pub user_id: String,
     pub posts: Vec<PostRef>,
+    // monotonically increasing sequence number of updates
+    #[serde(default)]
+    pub sequence: u64,
+    // recently removed posts (oldest first)
+    #[serde(default)]
+    pub removed_posts: Vec<RemovedPostRef>,
+    #[serde(default)]
+    pub preferences: TimelinePreferences,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl UserTimeline {
-    fn add_or_update_posts(&mut self, posts: Vec<PostRef>) {
+    pub(crate) fn add_or_update_posts(&mut self, posts: Vec<PostRef>) {
         let ids: HashSet<String> = posts.iter().map(|p| p.post_id.clone()).collect();
 
+        self.sequence += 1;
+        let sequence = self.sequence;
+
+        // posts of muted users and posts of other users muted by content snippet are not added
+        // (or removed if updated), view filters by full content, as snippet is only the beginning
+        // of the content
+        let posts = posts.into_iter().filter(|p| {
+            p.created_by == self.user_id
+                || !(self.preferences.is_user_muted(&p.created_by)
+                    || p.content_snippet
+                        .as_ref()
+                        .is_some_and(|s| self.preferences.is_muted(s)))
+        });
+
         self.posts.retain(|p| !ids.contains(&p.post_id));
-        self.posts.extend(posts);
+        self.removed_posts.retain(|p| !ids.contains(&p.post_id));
+        self.posts.extend(posts.map(|p| PostRef {
+            sequence,
+            content_snippet: None,
+            ..p
+        }));
 
-        self.posts
-            .sort_by(|a, b| a.updated_at.cmp(&b.updated_at).reverse());
+        self.sort_posts();
 
         // Keep only the first POSTS_MAX_COUNT elements
         if self.posts.len() > POSTS_MAX_COUNT {
             self.posts.truncate(POSTS_MAX_COUNT);
         }
 
-        self.updated_at = chrono::Utc::now();
+        self.updated_at = clock::now();
+    }
+
+    // Newest posts (by post creation, not by last update) first
+    fn sort_posts(&mut self) {
+        self.posts.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| a.post_id.cmp(&b.post_id))
+        });
+    }
+
+    // Remove posts, removals are reported by updates with new sequence number
+    pub(crate) fn remove_posts(&mut self, post_ids: &HashSet<String>) -> bool {
+        let removed: Vec<String> = self
+            .posts
+            .iter()
+            .filter(|p| post_ids.contains(&p.post_id))
+            .map(|p| p.post_id.clone())
+            .collect();
+        if removed.is_empty() {
+            return false;
+        }
+
+        self.sequence += 1;
+        let now = clock::now();
+        self.posts.retain(|p| !post_ids.contains(&p.post_id));
+        self.removed_posts
+            .extend(removed.into_iter().map(|post_id| RemovedPostRef {
+                post_id,
+                sequence: self.sequence,
+                removed_at: now,
+            }));
+        if self.removed_posts.len() > REMOVED_POSTS_MAX_COUNT {
+            let excess = self.removed_posts.len() - REMOVED_POSTS_MAX_COUNT;
+            self.removed_posts.drain(..excess);
+        }
+        self.updated_at = now;
+        true
+    }
+
+    // Updates after sequence number: updated posts and ids of removed posts
+    pub(crate) fn get_updates_since_sequence(&self, sequence: u64) -> UserTimelineUpdates {
+        UserTimelineUpdates::new(
+            self.user_id.clone(),
+            self.posts
+                .iter()
+                .filter(|p| p.sequence > sequence)
+                .cloned()
+                .collect(),
+            self.removed_posts
+                .iter()
+                .filter(|p| p.sequence > sequence)
+                .map(|p| p.post_id.clone())
+                .collect(),
+            self.sequence.max(sequence),
+        )
     }
 }
 
 impl UserTimeline {
-    fn new(user_id: String) -> Self {
-        let now = chrono::Utc::now();
+    pub(crate) fn new(user_id: String) -> Self {
+        let now = clock::now();
         UserTimeline {
             user_id,
             posts: Vec::new(),
+            sequence: 0,
+            removed_posts: Vec::new(),
+            preferences: TimelinePreferences::default(),
             created_at: now,
             updated_at: now,
         }
@@ -103,6 +422,30 @@ impl UserTimeline {
 pub struct UserTimelineUpdates {
     pub user_id: String,
     pub posts: Vec<PostRef>,
+    // ids of posts removed from timeline (e.g. deleted posts)
+    pub removed_post_ids: Vec<String>,
+    // cursor to get next updates
+    pub cursor: String,
+}
+
+impl UserTimelineUpdates {
+    fn new(
+        user_id: String,
+        posts: Vec<PostRef>,
+        removed_post_ids: Vec<String>,
+        cursor_sequence: u64,
+    ) -> Self {
+        UserTimelineUpdates {
+            user_id,
+            posts,
+            removed_post_ids,
+            cursor: cursor::encode(cursor_sequence),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.posts.is_empty() && self.removed_post_ids.is_empty()
+    }
 }
 
 #[agent_definition]
@@ -111,12 +454,55 @@ trait UserTimelineAgent {
 
     fn get_timeline(&self) -> Option<UserTimeline>;
 
-    fn posts_updated(&mut self, posts: Vec<PostRef>) -> Result<(), String>;
+    fn get_preferences(&self) -> TimelinePreferences;
+
+    // Preferences applied by timeline view, so clients do not need to send them with every request
+    fn set_preferences(
+        &mut self,
+        preferences: TimelinePreferences,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    // Hide posts of user in timeline, new posts of muted user are not added to timeline
+    fn mute_user(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    fn unmute_user(&mut self, user_id: String, correlation_id: Option<String>) -> bool;
+
+    fn mute_keyword(
+        &mut self,
+        keyword: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    fn unmute_keyword(&mut self, keyword: String, correlation_id: Option<String>) -> bool;
+
+    fn posts_updated(
+        &mut self,
+        posts: Vec<PostRef>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
+
+    fn posts_removed(
+        &mut self,
+        post_ids: Vec<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError>;
 
     fn get_updates(
         &self,
         updates_since: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
     ) -> Option<UserTimelineUpdates>;
+
+    fn get_updates_by_cursor(
+        &self,
+        cursor: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<UserTimelineUpdates>, SocialNetError>;
 }
 
 struct UserTimelineAgentImpl {
@@ -148,12 +534,101 @@ impl UserTimelineAgent for UserTimelineAgentImpl {
         self.state.clone()
     }
 
+    fn get_preferences(&self) -> TimelinePreferences {
+        self.state
+            .as_ref()
+            .map(|s| s.preferences.clone())
+            .unwrap_or_default()
+    }
+
+    fn set_preferences(
+        &mut self,
+        preferences: TimelinePreferences,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        let preferences = preferences.validated()?;
+        trace_println!(
+            "set preferences - sort: {:?}, muted keywords: {}",
+            preferences.sort,
+            preferences.muted_keywords.len()
+        );
+        self.with_state(|state| {
+            state.preferences = preferences;
+            state.updated_at = clock::now();
+            Ok(())
+        })
+    }
+
+    fn mute_user(
+        &mut self,
+        user_id: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        if user_id == self._id {
+            return Err(SocialNetError::ValidationFailed(
+                "User can not mute himself".to_string(),
+            ));
+        }
+        self.with_state(|state| {
+            if state.preferences.mute_user(user_id.clone())? {
+                trace_println!("mute user - user id: {user_id}");
+                state.updated_at = clock::now();
+            }
+            Ok(())
+        })
+    }
+
+    fn unmute_user(&mut self, user_id: String, correlation_id: Option<String>) -> bool {
+        trace::init(correlation_id);
+        let Some(state) = self.state.as_mut() else {
+            return false;
+        };
+        let unmuted = state.preferences.unmute_user(&user_id);
+        if unmuted {
+            trace_println!("unmute user - user id: {user_id}");
+            state.updated_at = clock::now();
+        }
+        unmuted
+    }
+
+    fn mute_keyword(
+        &mut self,
+        keyword: String,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        self.with_state(|state| {
+            if state.preferences.mute_keyword(keyword.clone())? {
+                trace_println!("mute keyword - keyword: {keyword}");
+                state.updated_at = clock::now();
+            }
+            Ok(())
+        })
+    }
+
+    fn unmute_keyword(&mut self, keyword: String, correlation_id: Option<String>) -> bool {
+        trace::init(correlation_id);
+        let Some(state) = self.state.as_mut() else {
+            return false;
+        };
+        let unmuted = state.preferences.unmute_keyword(&keyword);
+        if unmuted {
+            trace_println!("unmute keyword - keyword: {keyword}");
+            state.updated_at = clock::now();
+        }
+        unmuted
+    }
+
     fn get_updates(
         &self,
         updates_since: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
     ) -> Option<UserTimelineUpdates> {
+        trace::init(correlation_id);
         if let Some(state) = &self.state {
-            println!("get updates - updates since: {updates_since}");
+            trace_println!("get updates - updates since: {updates_since}");
 
             let updates = state
                 .posts
@@ -161,26 +636,66 @@ impl UserTimelineAgent for UserTimelineAgentImpl {
                 .filter(|p| p.updated_at > updates_since)
                 .cloned()
                 .collect();
+            let removed_post_ids = state
+                .removed_posts
+                .iter()
+                .filter(|p| p.removed_at > updates_since)
+                .map(|p| p.post_id.clone())
+                .collect();
 
-            Some(UserTimelineUpdates {
-                user_id: state.user_id.clone(),
-                posts: updates,
-            })
+            Some(UserTimelineUpdates::new(
+                state.user_id.clone(),
+                updates,
+                removed_post_ids,
+                state.sequence,
+            ))
         } else {
             None
         }
     }
 
-    fn posts_updated(&mut self, posts: Vec<PostRef>) -> Result<(), String> {
-        self.with_state(|state| {
-            println!("posts updated - count: {}", posts.len());
-            state.add_or_update_posts(posts);
-            Ok(())
-        })
+    fn get_updates_by_cursor(
+        &self,
+        cursor: Option<String>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<UserTimelineUpdates>, SocialNetError> {
+        trace::init(correlation_id);
+        if let Some(state) = &self.state {
+            let sequence = cursor::decode_or(cursor, state.sequence)?;
+            trace_println!("get updates by cursor - sequence: {sequence}");
+            Ok(Some(state.get_updates_since_sequence(sequence)))
+        } else {
+            cursor::decode_or(cursor, 0)?;
+            Ok(None)
+        }
+    }
+
+    fn posts_updated(
+        &mut self,
+        posts: Vec<PostRef>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        posts_updated(&mut self.state, &self._id, posts);
+        Ok(())
+    }
+
+    fn posts_removed(
+        &mut self,
+        post_ids: Vec<String>,
+        correlation_id: Option<String>,
+    ) -> Result<(), SocialNetError> {
+        trace::init(correlation_id);
+        posts_removed(&mut self.state, post_ids);
+        Ok(())
     }
 
     async fn load_snapshot(&mut self, bytes: Vec<u8>) -> Result<(), String> {
-        let data: Option<UserTimeline> = crate::common::snapshot::deserialize(&bytes)?;
+        let mut data: Option<UserTimeline> = crate::common::snapshot::deserialize(&bytes)?;
+        // timelines of older snapshots are ordered by last update
+        if let Some(timeline) = data.as_mut() {
+            timeline.sort_posts();
+        }
         self.state = data;
         Ok(())
     }
@@ -190,17 +705,197 @@ impl UserTimelineAgent for UserTimelineAgentImpl {
     }
 }
 
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct TimelineView {
+    pub posts: Vec<PostSummary>,
+    // some posts were skipped, as they were not fetched in time
+    pub degraded: bool,
+}
+
+#[derive(Schema, Clone, Serialize, Deserialize)]
+pub struct TimelineExportPage {
+    // NDJSON, one hydrated post per line
+    pub data: String,
+    pub count: u32,
+    // cursor of next page, none if this is the last page
+    pub next_cursor: Option<String>,
+    // some posts were skipped, as they were not fetched in time
+    pub degraded: bool,
+}
+
+// Refs of export page, ordered by creation (oldest first),
+// cursor is creation time (micros) and id of the last exported post
+pub(crate) fn get_export_page_refs(
+    posts: Vec<PostRef>,
+    after: Option<(u64, String)>,
+    page_size: usize,
+) -> (Vec<PostRef>, Option<String>) {
+    let mut posts: Vec<PostRef> = posts
+        .into_iter()
+        .filter(|p| after.as_ref().is_none_or(|after| &export_key(p) > after))
+        .collect();
+    posts.sort_by_key(export_key);
+
+    let has_more = posts.len() > page_size;
+    posts.truncate(page_size);
+    let next = if has_more {
+        posts
+            .last()
+            .map(|p| cursor::encode_key(created_at_micros(p), &p.post_id))
+    } else {
+        None
+    };
+
+    (posts, next)
+}
+
+fn created_at_micros(post: &PostRef) -> u64 {
+    post.created_at.timestamp_micros().max(0) as u64
+}
+
+fn export_key(post: &PostRef) -> (u64, String) {
+    (created_at_micros(post), post.post_id.clone())
+}
+
+fn to_ndjson(posts: &[Post]) -> Result<String, SocialNetError> {
+    posts
+        .iter()
+        .map(|p| serde_json::to_string(p).map(|line| format!("{line}\n")))
+        .collect::<Result<String, _>>()
+        .map_err(|e| SocialNetError::Internal(e.to_string()))
+}
+
+pub(crate) fn posts_updated(state: &mut Option<UserTimeline>, user_id: &str, posts: Vec<PostRef>) {
+    let state = state.get_or_insert(UserTimeline::new(user_id.to_string()));
+    trace_println!("posts updated - count: {}", posts.len());
+    state.add_or_update_posts(posts);
+}
+
+pub(crate) fn posts_removed(state: &mut Option<UserTimeline>, post_ids: Vec<String>) {
+    if let Some(state) = state.as_mut() {
+        trace_println!("posts removed - count: {}", post_ids.len());
+        state.remove_posts(&post_ids.into_iter().collect());
+    }
+}
+
+// Calls of timeline view flows to other agents
+pub(crate) trait UserTimelineViewClients {
+    async fn get_timeline(&self, user_id: &str) -> Option<UserTimeline>;
+
+    async fn get_connection_scores(&self, user_id: &str) -> HashMap<String, u32>;
+
+    // Summaries of active posts matching the query, with the reaction of given user
+    async fn fetch_post_summaries(
+        &self,
+        post_ids: &[String],
+        user_id: &str,
+        query: Query,
+    ) -> Fetched<PostSummary>;
+}
+
+impl UserTimelineViewClients for AgentClients {
+    async fn get_timeline(&self, user_id: &str) -> Option<UserTimeline> {
+        UserTimelineAgentClient::get(user_id.to_string())
+            .get_timeline()
+            .await
+    }
+
+    async fn get_connection_scores(&self, user_id: &str) -> HashMap<String, u32> {
+        UserAgentClient::get(user_id.to_string())
+            .get_connection_scores()
+            .await
+    }
+
+    async fn fetch_post_summaries(
+        &self,
+        post_ids: &[String],
+        user_id: &str,
+        query: Query,
+    ) -> Fetched<PostSummary> {
+        let timer = SlowOperationTimer::start("timeline posts view", user_id.to_string());
+        let posts =
+            fetch_post_summaries_by_ids_and_query(post_ids, Some(user_id.to_string()), query).await;
+        timer.finish(post_ids.len()).await;
+        posts
+    }
+}
+
+// Posts of timeline matching the query (UserTimelineViewAgent::get_posts_view),
+// without muted users and keywords, ordered by timeline preferences
+pub(crate) async fn get_posts_view(
+    clients: &impl UserTimelineViewClients,
+    user_id: &str,
+    query: &str,
+) -> Option<TimelineView> {
+    let timeline_posts = clients.get_timeline(user_id).await;
+
+    trace_println!("get posts view - user id: {user_id}, query: {query}");
+
+    let timeline_posts = timeline_posts?;
+    let query = query::Query::new(query);
+    let preferences = timeline_posts.preferences;
+
+    trace_println!("get posts view - user id: {user_id}, query matcher: {query}");
+
+    let mut post_refs = timeline_posts
+        .posts
+        .into_iter()
+        .filter(|p| !preferences.is_user_muted(&p.created_by))
+        .filter(|p| p.matches_query(query.clone()))
+        .collect::<Vec<_>>();
+    let connection_scores = if preferences.uses_connection_scores() {
+        clients.get_connection_scores(user_id).await
+    } else {
+        HashMap::new()
+    };
+    preferences.sort_posts(&mut post_refs, &connection_scores);
+    let post_ids = post_refs.into_iter().map(|p| p.post_id).collect::<Vec<_>>();
+
+    let posts = if post_ids.is_empty() {
+        Fetched::default()
+    } else {
+        clients
+            .fetch_post_summaries(&post_ids, user_id, query)
+            .await
+            .map(PostSummary::with_content_html)
+    };
+
+    Some(TimelineView {
+        degraded: posts.is_degraded(),
+        posts: posts
+            .items
+            .into_iter()
+            .filter(|p| p.created_by == user_id || !preferences.is_muted(&p.content))
+            .collect(),
+    })
+}
+
 #[agent_definition(mode = "ephemeral")]
 trait UserTimelineViewAgent {
     fn new() -> Self;
 
-    async fn get_posts_view(&mut self, user_id: String, query: String) -> Option<Vec<Post>>;
+    async fn get_posts_view(
+        &mut self,
+        user_id: String,
+        query: String,
+        correlation_id: Option<String>,
+    ) -> Option<TimelineView>;
 
     async fn get_posts_updates_view(
         &mut self,
         user_id: String,
         updates_since: chrono::DateTime<chrono::Utc>,
-    ) -> Option<Vec<Post>>;
+        correlation_id: Option<String>,
+    ) -> Option<TimelineView>;
+
+    // Export timeline as NDJSON pages of hydrated posts (oldest first)
+    async fn export_timeline(
+        &mut self,
+        user_id: String,
+        cursor: Option<String>,
+        page_size: Option<u32>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<TimelineExportPage>, SocialNetError>;
 }
 
 struct UserTimelineViewAgentImpl {}
@@ -211,64 +906,112 @@ impl UserTimelineViewAgent for UserTimelineViewAgentImpl {
         Self {}
     }
 
-    async fn get_posts_view(&mut self, user_id: String, query: String) -> Option<Vec<Post>> {
-        let timeline_posts = UserTimelineAgentClient::get(user_id.clone())
-            .get_timeline()
-            .await;
-
-        println!("get posts view - user id: {user_id}, query: {query}");
+    async fn get_posts_view(
+        &mut self,
+        user_id: String,
+        query: String,
+        correlation_id: Option<String>,
+    ) -> Option<TimelineView> {
+        trace::init(correlation_id);
+        get_posts_view(&AgentClients, &user_id, &query).await
+    }
 
-        if let Some(timeline_posts) = timeline_posts {
-            let query = query::Query::new(&query);
+    async fn get_posts_updates_view(
+        &mut self,
+        user_id: String,
+        updates_since: chrono::DateTime<chrono::Utc>,
+        correlation_id: Option<String>,
+    ) -> Option<TimelineView> {
+        trace::init(correlation_id);
+        let timeline = UserTimelineAgentClient::get(user_id.clone());
+        let timeline_updates = timeline
+            .get_updates(updates_since, trace::propagate())
+            .await;
 
-            println!("get posts view - user id: {user_id}, query matcher: {query}");
+        trace_println!(
+            "get posts updates view - user id: {user_id}, updates since: {updates_since}"
+        );
 
-            let post_ids = timeline_posts
+        if let Some(timeline_updates) = timeline_updates {
+            // muted users and keywords are filtered as in posts view
+            let preferences = timeline.get_preferences().await;
+            let updated_post_refs: Vec<PostRef> = timeline_updates
                 .posts
                 .into_iter()
-                .filter(|p| p.matches_query(query.clone()))
-                .map(|p| p.post_id)
-                .collect::<Vec<_>>();
+                .filter(|p| !preferences.is_user_muted(&p.created_by))
+                .collect();
 
-            if post_ids.is_empty() {
-                Some(vec![])
+            let posts = if updated_post_refs.is_empty() {
+                Fetched::default()
             } else {
-                let posts = fetch_posts_by_ids_and_query(&post_ids, query).await;
+                let post_ids: Vec<String> = updated_post_refs
+                    .iter()
+                    .map(|p| p.post_id.clone())
+                    .collect();
+                let timer =
+                    SlowOperationTimer::start("timeline posts updates view", user_id.clone());
+                let posts = fetch_post_summaries_by_ids(&post_ids, Some(user_id.clone()))
+                    .await
+                    .map(PostSummary::with_content_html);
+                timer.finish(post_ids.len()).await;
+                posts
+            };
 
-                Some(posts)
-            }
+            Some(TimelineView {
+                degraded: posts.is_degraded(),
+                posts: posts
+                    .items
+                    .into_iter()
+                    .filter(|p| p.created_by == user_id || !preferences.is_muted(&p.content))
+                    .collect(),
+            })
         } else {
             None
         }
     }
 
-    async fn get_posts_updates_view(
+    async fn export_timeline(
         &mut self,
         user_id: String,
-        updates_since: chrono::DateTime<chrono::Utc>,
-    ) -> Option<Vec<Post>> {
-        let timeline_updates = UserTimelineAgentClient::get(user_id.clone())
-            .get_updates(updates_since)
-            .await;
+        cursor: Option<String>,
+        page_size: Option<u32>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<TimelineExportPage>, SocialNetError> {
+        trace::init(correlation_id);
+        let after = cursor::decode_key(cursor)?;
+        let page_size = page_size
+            .unwrap_or(EXPORT_PAGE_DEFAULT_SIZE)
+            .clamp(1, EXPORT_PAGE_MAX_SIZE) as usize;
 
-        println!("get posts updates view - user id: {user_id}, updates since: {updates_since}");
+        trace_println!("export timeline - user id: {user_id}, page size: {page_size}");
 
-        if let Some(timeline_updates) = timeline_updates {
-            let updated_post_refs = timeline_updates.posts;
+        let timeline = UserTimelineAgentClient::get(user_id.clone())
+            .get_timeline()
+            .await;
 
-            if updated_post_refs.is_empty() {
-                Some(vec![])
+        if let Some(timeline) = timeline {
+            let (page_refs, next) = get_export_page_refs(timeline.posts, after, page_size);
+            let post_ids: Vec<String> = page_refs.into_iter().map(|p| p.post_id).collect();
+
+            let posts: Fetched<Post> = if post_ids.is_empty() {
+                Fetched::default()
             } else {
-                let post_ids: Vec<String> = updated_post_refs
-                    .iter()
-                    .map(|p| p.post_id.clone())
-                    .collect();
-                let posts = fetch_posts_by_ids(&post_ids).await;
+                let timer = SlowOperationTimer::start("timeline export", user_id.clone());
+                let posts = fetch_posts_by_ids(&post_ids)
+                    .await
+                    .map(Post::with_content_html);
+                timer.finish(post_ids.len()).await;
+                posts
+            };
 
-                Some(posts)
-            }
+            Ok(Some(TimelineExportPage {
+                data: to_ndjson(&posts.items)?,
+                count: posts.items.len() as u32,
+                next_cursor: next,
+                degraded: posts.is_degraded(),
+            }))
         } else {
-            None
+            Ok(None)
         }
     }
 }
@@ -283,7 +1026,17 @@ trait UserTimelineUpdatesAgent {
         updates_since: Option<chrono::DateTime<chrono::Utc>>,
         iter_wait_time: Option<u32>,
         max_wait_time: Option<u32>,
+        correlation_id: Option<String>,
     ) -> Option<Vec<PostRef>>;
+
+    async fn get_posts_updates_by_cursor(
+        &mut self,
+        user_id: String,
+        cursor: Option<String>,
+        iter_wait_time: Option<u32>,
+        max_wait_time: Option<u32>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<UserTimelineUpdates>, SocialNetError>;
 }
 
 struct UserTimelineUpdatesAgentImpl {}
@@ -300,18 +1053,64 @@ impl UserTimelineUpdatesAgent for UserTimelineUpdatesAgentImpl {
         updates_since: Option<chrono::DateTime<chrono::Utc>>,
         iter_wait_time: Option<u32>,
         max_wait_time: Option<u32>,
+        correlation_id: Option<String>,
     ) -> Option<Vec<PostRef>> {
+        trace::init(correlation_id);
         poll_for_updates(
             user_id,
             updates_since,
             iter_wait_time,
             max_wait_time,
             |uid, since| async move {
-                let res = UserTimelineAgentClient::get(uid).get_updates(since).await;
+                let res = UserTimelineAgentClient::get(uid)
+                    .get_updates(since, trace::propagate())
+                    .await;
                 res.map(|r| r.posts)
             },
             "get posts updates",
         )
         .await
     }
+
+    async fn get_posts_updates_by_cursor(
+        &mut self,
+        user_id: String,
+        cursor: Option<String>,
+        iter_wait_time: Option<u32>,
+        max_wait_time: Option<u32>,
+        correlation_id: Option<String>,
+    ) -> Result<Option<UserTimelineUpdates>, SocialNetError> {
+        trace::init(correlation_id);
+        // resolves none cursor to current one and validates provided cursor
+        let current = UserTimelineAgentClient::get(user_id.clone())
+            .get_updates_by_cursor(cursor, trace::propagate())
+            .await?;
+
+        match current {
+            Some(current) if current.is_empty() => {
+                let since = current.cursor.clone();
+                let updates = poll_for_updates_since(
+                    user_id.clone(),
+                    since,
+                    iter_wait_time,
+                    max_wait_time,
+                    |uid, since| async move {
+                        let res = UserTimelineAgentClient::get(uid)
+                            .get_updates_by_cursor(Some(since), trace::propagate())
+                            .await;
+                        // updates are polled as single item, empty if nothing changed
+                        res.ok()
+                            .flatten()
+                            .map(|r| if r.is_empty() { vec![] } else { vec![r] })
+                    },
+                    "get posts updates by cursor",
+                )
+                .await;
+
+                // no updates in max wait time, current (empty) updates are returned
+                Ok(updates.map(|updates| updates.into_iter().next().unwrap_or(current)))
+            }
+            current => Ok(current),
+        }
+    }
 }
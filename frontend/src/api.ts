@@ -45,6 +45,8 @@ export interface Comment {
     likes?: UserLikeTuple[];
     'created-by': string;
     'created-at': Timestamp;
+    'updated-at'?: Timestamp;
+    edited?: boolean;
 }
 // Comments is a list of tuples: [commentId, Comment]
 export type CommentTuple = [string, Comment];
@@ -53,6 +55,7 @@ export interface Post {
     'post-id': string;
     content: string;
     'created-by': string;
+    'created-by-name'?: string;
     'created-at': Timestamp;
     likes?: UserLikeTuple[];
     comments?: CommentTuple[];
@@ -108,20 +111,20 @@ export const api = {
     addComment: (postId: string, userId: string, content: string, parentCommentId?: string) =>
         apiClient.post(`/posts/${postId}/comments`, { 'user-id': userId, content, 'parent-comment-id': parentCommentId }),
 
-    deleteComment: (postId: string, commentId: string) =>
-        apiClient.delete(`/posts/${postId}/comments/${commentId}`),
+    deleteComment: (postId: string, commentId: string, userId: string) =>
+        apiClient.delete(`/posts/${postId}/comments/${commentId}`, { params: { 'user-id': userId } }),
 
     likePost: (postId: string, userId: string, likeType: LikeType) =>
         apiClient.put(`/posts/${postId}/likes`, { 'user-id': userId, 'like-type': likeType }),
 
     unlikePost: (postId: string, userId: string) =>
-        apiClient.delete(`/posts/${postId}/likes/${userId}`),
+        apiClient.delete(`/posts/${postId}/likes/${userId}`, { params: { 'acting-user-id': userId } }),
 
     likeComment: (postId: string, commentId: string, userId: string, likeType: LikeType) =>
         apiClient.put(`/posts/${postId}/comments/${commentId}/likes`, { 'user-id': userId, 'like-type': likeType }),
 
     unlikeComment: (postId: string, commentId: string, userId: string) =>
-        apiClient.delete(`/posts/${postId}/comments/${commentId}/likes/${userId}`),
+        apiClient.delete(`/posts/${postId}/comments/${commentId}/likes/${userId}`, { params: { 'acting-user-id': userId } }),
 
     // Chat APIs
     createChat: (userId: string, participants: string[]) =>
@@ -136,14 +139,14 @@ export const api = {
     addChatMessage: (chatId: string, userId: string, content: string) =>
         apiClient.post(`/chats/${chatId}/messages`, { 'user-id': userId, content }),
 
-    deleteChatMessage: (chatId: string, messageId: string) =>
-        apiClient.delete(`/chats/${chatId}/messages/${messageId}`),
+    deleteChatMessage: (chatId: string, messageId: string, userId: string) =>
+        apiClient.delete(`/chats/${chatId}/messages/${messageId}`, { params: { 'user-id': userId } }),
 
     likeChatMessage: (chatId: string, messageId: string, userId: string, likeType: LikeType) =>
         apiClient.put(`/chats/${chatId}/messages/${messageId}/likes`, { 'user-id': userId, 'like-type': likeType }),
 
     unlikeChatMessage: (chatId: string, messageId: string, userId: string) =>
-        apiClient.delete(`/chats/${chatId}/messages/${messageId}/likes/${userId}`),
+        apiClient.delete(`/chats/${chatId}/messages/${messageId}/likes/${userId}`, { params: { 'acting-user-id': userId } }),
 
     addChatParticipant: (chatId: string, participants: string[]) =>
         apiClient.patch(`/chats/${chatId}/participants`, { participants }),
@@ -82,9 +82,9 @@ export const useChatStore = defineStore('chat', () => {
     }
 
     async function deleteMessage(messageId: string) {
-        if (!activeChatId.value) return;
+        if (!activeChatId.value || !userStore.userId) return;
         try {
-            await api.deleteChatMessage(activeChatId.value, messageId);
+            await api.deleteChatMessage(activeChatId.value, messageId, userStore.userId);
             await fetchChats(); // Refresh to reflect deletion
         } catch (e: any) {
             error.value = e.message;